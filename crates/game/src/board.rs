@@ -1,4 +1,6 @@
 use macroquad::prelude as mq;
+use simulation::Terrain;
+use util::spatial_grid::SpatialGrid;
 
 use crate::assets::Assets;
 
@@ -7,9 +9,12 @@ pub(super) struct Board<'a> {
     camera: mq::Camera2D,
     world_unit: f32,
     strings: Vec<String>,
+    territories: Vec<Territory>,
     lines: Vec<Line>,
     pawns: Vec<Pawn<'a>>,
     click_boxes: Vec<ClickBox>,
+    // Where the click boxes lie, by their index
+    click_grid: SpatialGrid<usize>,
 }
 
 impl<'a> Board<'a> {
@@ -23,9 +28,11 @@ impl<'a> Board<'a> {
             camera,
             world_unit,
             strings: vec![],
+            territories: vec![],
             lines: vec![],
             pawns: vec![],
             click_boxes: vec![],
+            click_grid: SpatialGrid::new(CLICK_CELL_UNITS * world_unit),
         }
     }
 
@@ -35,9 +42,11 @@ impl<'a> Board<'a> {
 
     pub fn clear(&mut self) {
         self.strings.clear();
+        self.territories.clear();
         self.lines.clear();
         self.pawns.clear();
         self.click_boxes.clear();
+        self.click_grid.clear();
 
         // Ensure there is a valid "zero index" string
         self.push_string("");
@@ -46,9 +55,13 @@ impl<'a> Board<'a> {
     pub fn hovered(&self) -> Option<Handle> {
         let screen_pos = mq::Vec2::from(mq::mouse_position());
         let world_pos = self.camera.screen_to_world(screen_pos);
-        self.click_boxes
-            .iter()
+        let point = (world_pos.x, world_pos.y);
+        // Boxes pushed last are drawn on top, and picked first
+        self.click_grid
+            .query(point, point)
+            .into_iter()
             .rev()
+            .map(|idx| &self.click_boxes[idx])
             .find(|cb| cb.bounds.contains(world_pos))
             .map(|cb| cb.handle)
     }
@@ -94,20 +107,65 @@ impl<'a> Board<'a> {
             stroke,
         });
 
+        self.click_grid.insert(
+            self.click_boxes.len(),
+            (bounds.x, bounds.y),
+            (bounds.right(), bounds.bottom()),
+        );
         self.click_boxes.push(ClickBox { handle, bounds });
     }
 
-    pub fn push_line(&mut self, source: mq::Vec2, destination: mq::Vec2) {
+    pub fn push_line(
+        &mut self,
+        source: mq::Vec2,
+        destination: mq::Vec2,
+        terrain: Terrain,
+        quality: u8,
+    ) {
         let source = source * self.world_unit;
         let destination = destination * self.world_unit;
+        // Lesser ways are drawn thinner, and tinted after the land they cross
+        let (thicknkess, color) = match terrain {
+            Terrain::Road => (6., mq::GRAY),
+            Terrain::Trail => (3., mq::BEIGE),
+            Terrain::Forest => (4., mq::DARKGREEN),
+            Terrain::Ford => (5., mq::SKYBLUE),
+            Terrain::MountainPass => (3., mq::LIGHTGRAY),
+            Terrain::River => (5., mq::BLUE),
+            Terrain::Sea => (3., mq::DARKBLUE),
+        };
+        // Improved roads stand out thicker
+        let thicknkess = thicknkess + 2. * quality as f32;
         self.lines.push(Line {
             source,
             destination,
-            thicknkess: 6.,
-            color: mq::GRAY.with_alpha(0.5),
+            thicknkess,
+            color: color.with_alpha(0.5),
         });
     }
 
+    /// Shades the land around a site in the colour of the faction holding it
+    pub fn push_territory(&mut self, center: mq::Vec2, colour: u8) {
+        const PALETTE: &[mq::Color] =
+            &[mq::RED, mq::BLUE, mq::GOLD, mq::PURPLE, mq::LIME, mq::PINK];
+        self.territories.push(Territory {
+            center: center * self.world_unit,
+            radius: TERRITORY_RADIUS * self.world_unit,
+            color: PALETTE[colour as usize % PALETTE.len()],
+        });
+    }
+
+    pub fn push_route(&mut self, points: &[mq::Vec2]) {
+        for pair in points.windows(2) {
+            self.lines.push(Line {
+                source: pair[0] * self.world_unit,
+                destination: pair[1] * self.world_unit,
+                thicknkess: 4.,
+                color: mq::ORANGE,
+            });
+        }
+    }
+
     fn push_string(&mut self, text: &str) -> StringIdx {
         let id = StringIdx(self.strings.len());
         self.strings.push(String::default());
@@ -124,6 +182,12 @@ impl<'a> Board<'a> {
         mq::push_camera_state();
         mq::set_camera(&self.camera);
 
+        for territory in &self.territories {
+            let (x, y) = (territory.center.x, territory.center.y);
+            mq::draw_circle(x, y, territory.radius, territory.color.with_alpha(0.15));
+            mq::draw_circle_lines(x, y, territory.radius, 2., territory.color.with_alpha(0.5));
+        }
+
         for line in &self.lines {
             mq::draw_line(
                 line.source.x,
@@ -163,6 +227,125 @@ impl<'a> Board<'a> {
         mq::draw_text_ex(text, x, y, params);
     }
 
+    pub fn caption(&self, text: &str) {
+        let font = Some(self.assets.font("board"));
+
+        let font_size = 32;
+        let measure = mq::measure_text(text, font, font_size, 1.0);
+        let x = (mq::screen_width() - measure.width) / 2.;
+        let y = 9. * mq::screen_height() / 10.;
+        let params = mq::TextParams {
+            font,
+            font_size,
+            color: mq::WHITE,
+            ..Default::default()
+        };
+        mq::draw_text_ex(text, x, y, params);
+    }
+
+    // Small box next to the mouse cursor, one line of text per entry
+    pub fn tooltip(&self, lines: &[&str]) {
+        let font = Some(self.assets.font("board"));
+
+        let font_size = 20;
+        let line_height = font_size as f32 * 1.2;
+        let width = lines
+            .iter()
+            .map(|line| mq::measure_text(line, font, font_size, 1.0).width)
+            .fold(0., f32::max);
+        let (mx, my) = mq::mouse_position();
+        let padding = 6.;
+        let rect = mq::Rect::new(
+            mx + 16.,
+            my + 16.,
+            width + 2. * padding,
+            lines.len() as f32 * line_height + 2. * padding,
+        );
+        fill_rect(&rect, mq::Color::new(0., 0., 0., 0.75));
+        for (idx, line) in lines.iter().enumerate() {
+            let params = mq::TextParams {
+                font,
+                font_size,
+                color: mq::WHITE,
+                ..Default::default()
+            };
+            let y = rect.y + padding + (idx + 1) as f32 * line_height - 4.;
+            mq::draw_text_ex(line, rect.x + padding, y, params);
+        }
+    }
+
+    // Draws a grid with lines every `spacing` world units, labelled with their coordinate
+    pub fn draw_grid(&self, spacing: f32) {
+        const MAX_LINES: f32 = 200.;
+
+        let corner1 = self.screen_to_world(mq::Vec2::ZERO);
+        let corner2 = self.screen_to_world(mq::Vec2::new(mq::screen_width(), mq::screen_height()));
+        let min = corner1.min(corner2);
+        let max = corner1.max(corner2);
+        if (max.x - min.x) / spacing > MAX_LINES || (max.y - min.y) / spacing > MAX_LINES {
+            return;
+        }
+
+        let first_x = (min.x / spacing).floor() * spacing;
+        let first_y = (min.y / spacing).floor() * spacing;
+        let xs =
+            std::iter::successors(Some(first_x), |x| Some(x + spacing)).take_while(|&x| x <= max.x);
+        let ys =
+            std::iter::successors(Some(first_y), |y| Some(y + spacing)).take_while(|&y| y <= max.y);
+
+        let color = mq::DARKGRAY.with_alpha(0.3);
+        let unit = self.world_unit;
+
+        mq::push_camera_state();
+        mq::set_camera(&self.camera);
+        for x in xs.clone() {
+            mq::draw_line(x * unit, min.y * unit, x * unit, max.y * unit, 1., color);
+        }
+        for y in ys.clone() {
+            mq::draw_line(min.x * unit, y * unit, max.x * unit, y * unit, 1., color);
+        }
+        mq::pop_camera_state();
+
+        let font = Some(self.assets.font("board"));
+        let params = mq::TextParams {
+            font,
+            font_size: 14,
+            color: mq::DARKGRAY,
+            ..Default::default()
+        };
+        for x in xs {
+            let screen = self.camera.world_to_screen(mq::Vec2::new(x * unit, 0.));
+            mq::draw_text_ex(&format!("{x}"), screen.x + 2., 14., params.clone());
+        }
+        for y in ys {
+            let screen = self.camera.world_to_screen(mq::Vec2::new(0., y * unit));
+            mq::draw_text_ex(&format!("{y}"), 2., screen.y - 2., params.clone());
+        }
+    }
+
+    /// Where the camera stands and how far it is zoomed in
+    pub fn camera_view(&self) -> CameraView {
+        CameraView {
+            target: self.camera.target,
+            zoom: self.camera.zoom,
+        }
+    }
+
+    pub fn set_camera_view(&mut self, view: CameraView) {
+        self.camera.target = view.target;
+        self.camera.zoom = view.zoom;
+    }
+
+    /// The point of the world at the center of the screen
+    pub fn center(&self) -> mq::Vec2 {
+        self.camera.target / self.world_unit
+    }
+
+    /// Centers the camera on a point of the world
+    pub fn focus(&mut self, pos: mq::Vec2) {
+        self.camera.target = pos * self.world_unit;
+    }
+
     pub fn update_camera(&mut self, delta_translation: mq::Vec2, delta_zoom: f32) {
         let dt = mq::get_frame_time();
         self.camera.target += delta_translation * 300. * dt;
@@ -173,6 +356,12 @@ impl<'a> Board<'a> {
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
 pub struct Handle(pub usize);
 
+#[derive(Clone, Copy)]
+pub struct CameraView {
+    target: mq::Vec2,
+    zoom: mq::Vec2,
+}
+
 fn fill_rect(rect: &mq::Rect, color: mq::Color) {
     mq::draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
 }
@@ -230,6 +419,18 @@ fn draw_label(board: &Board, label: &Label, bounds: &mq::Rect, font: Option<&mq:
     );
 }
 
+// Side of the cells click boxes are bucketed by, in world units
+const CLICK_CELL_UNITS: f32 = 4.;
+
+// Reach of the shading around a held site, in world units
+const TERRITORY_RADIUS: f32 = 3.;
+
+struct Territory {
+    center: mq::Vec2,
+    radius: f32,
+    color: mq::Color,
+}
+
 struct Line {
     source: mq::Vec2,
     destination: mq::Vec2,
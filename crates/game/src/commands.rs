@@ -0,0 +1,203 @@
+use macroquad::prelude::KeyCode;
+use simulation::{MapItem, MapItemKind, Object, ObjectId, Stance};
+
+/// What a command does once picked, from its shortcut or the palette
+#[derive(Clone, PartialEq)]
+pub(crate) enum Order {
+    TogglePause,
+    SpeedUp,
+    SlowDown,
+    ToggleAdvisor,
+    ToggleGrid,
+    ToggleMeasure,
+    Quicksave,
+    Quickload,
+    LoadAutosave,
+    NextParty,
+    NextIdleParty,
+    NextPlayer,
+    AddPlayer(ObjectId),
+    // Bound to the number keys, with control held to save
+    SaveBookmark(usize),
+    RecallBookmark(usize),
+    Halt(ObjectId),
+    Patrol(ObjectId),
+    ForcedMarch(ObjectId, bool),
+    SetStance(ObjectId, Stance),
+    // The party joins the group of the other
+    Join(ObjectId, ObjectId),
+    LeaveGroup(ObjectId),
+    Escort(ObjectId, ObjectId),
+    PartWays(ObjectId),
+    StationTroops(ObjectId),
+    Besiege(ObjectId, ObjectId),
+    MoveTo(ObjectId, ObjectId),
+    Build(ObjectId, String),
+}
+
+pub(crate) struct Command {
+    pub label: String,
+    pub shortcut: Option<KeyCode>,
+    pub order: Order,
+}
+
+// Commands available whatever is selected
+const GLOBAL: &[(&str, KeyCode, Order)] = &[
+    ("Pause / resume", KeyCode::Space, Order::TogglePause),
+    ("Speed up", KeyCode::Equal, Order::SpeedUp),
+    ("Slow down", KeyCode::Minus, Order::SlowDown),
+    ("Toggle advisor", KeyCode::V, Order::ToggleAdvisor),
+    ("Toggle grid", KeyCode::G, Order::ToggleGrid),
+    ("Measure distance", KeyCode::M, Order::ToggleMeasure),
+    ("Quicksave", KeyCode::F5, Order::Quicksave),
+    ("Quickload", KeyCode::F9, Order::Quickload),
+    ("Load autosave", KeyCode::F8, Order::LoadAutosave),
+    ("Next party", KeyCode::Tab, Order::NextParty),
+    ("Next idle party", KeyCode::Period, Order::NextIdleParty),
+    ("Pass the controls", KeyCode::F2, Order::NextPlayer),
+];
+
+/// The orders bound to a key
+pub(crate) fn shortcuts() -> impl Iterator<Item = (KeyCode, Order)> {
+    GLOBAL.iter().map(|(_, key, order)| (*key, order.clone()))
+}
+
+/// Every command available for the selection, with orders to the selection
+/// only offered while the player leads a faction
+pub(crate) fn available(
+    selection: Option<(ObjectId, &Object)>,
+    places: &[MapItem],
+    can_order: bool,
+) -> Vec<Command> {
+    let mut out: Vec<_> = GLOBAL
+        .iter()
+        .map(|(label, key, order)| Command {
+            label: label.to_string(),
+            shortcut: Some(*key),
+            order: order.clone(),
+        })
+        .collect();
+
+    if let Some((id, obj)) = selection
+        && obj.flag("playable")
+    {
+        out.push(Command {
+            label: format!("Seat a player for {}", obj.txt("name")),
+            shortcut: None,
+            order: Order::AddPlayer(id),
+        });
+    }
+
+    let Some((id, obj)) = selection.filter(|_| can_order) else {
+        return out;
+    };
+
+    if obj.flag("mobile") {
+        out.push(Command {
+            label: "Halt".to_string(),
+            shortcut: None,
+            order: Order::Halt(id),
+        });
+        out.push(Command {
+            label: "Patrol waypoints".to_string(),
+            shortcut: None,
+            order: Order::Patrol(id),
+        });
+        let forced = obj.flag("forced_march");
+        out.push(Command {
+            label: if forced {
+                "Ease the march"
+            } else {
+                "Force the march"
+            }
+            .to_string(),
+            shortcut: None,
+            order: Order::ForcedMarch(id, !forced),
+        });
+        if let Some(stance) = obj.try_text("stance").and_then(Stance::parse) {
+            let next = stance.next();
+            out.push(Command {
+                label: format!("Turn {}", next.name()),
+                shortcut: None,
+                order: Order::SetStance(id, next),
+            });
+        }
+        if obj.flag("armed") {
+            out.push(Command {
+                label: "Station troops here".to_string(),
+                shortcut: None,
+                order: Order::StationTroops(id),
+            });
+        }
+        if obj.flag("grouped") {
+            out.push(Command {
+                label: "Leave group".to_string(),
+                shortcut: None,
+                order: Order::LeaveGroup(id),
+            });
+        }
+        if obj.flag("escorted") {
+            out.push(Command {
+                label: "Part ways".to_string(),
+                shortcut: None,
+                order: Order::PartWays(id),
+            });
+        }
+        out.extend(
+            places
+                .iter()
+                .filter(|place| place.id != id && !place.name.is_empty())
+                .map(|place| Command {
+                    label: format!("Move to {}", place.name),
+                    shortcut: None,
+                    order: Order::MoveTo(id, place.id),
+                }),
+        );
+        if obj.flag("armed") {
+            out.extend(
+                places
+                    .iter()
+                    .filter(|place| place.kind == MapItemKind::Party && !place.name.is_empty())
+                    // Settlements lie beneath the parties on the move
+                    .filter(|place| place.layer == 0)
+                    .map(|place| Command {
+                        label: format!("Besiege {}", place.name),
+                        shortcut: None,
+                        order: Order::Besiege(id, place.id),
+                    }),
+            );
+        }
+        out.extend(
+            places
+                .iter()
+                .filter(|place| place.id != id && place.kind == MapItemKind::Party)
+                // Settlements lie beneath the parties on the move
+                .filter(|place| place.layer > 0)
+                .map(|place| Command {
+                    label: format!("Join {}", place.name),
+                    shortcut: None,
+                    order: Order::Join(id, place.id),
+                }),
+        );
+        out.extend(
+            places
+                .iter()
+                .filter(|place| place.id != id && place.kind == MapItemKind::Party)
+                .filter(|place| place.layer > 0)
+                .map(|place| Command {
+                    label: format!("Escort {}", place.name),
+                    shortcut: None,
+                    order: Order::Escort(id, place.id),
+                }),
+        );
+    }
+
+    if let Some(location) = obj.try_child("location") {
+        out.extend(location.list("buildable").iter().map(|building| Command {
+            label: format!("Build {}", building.txt("name")),
+            shortcut: None,
+            order: Order::Build(id, building.txt("tag").to_string()),
+        }));
+    }
+    out
+}
@@ -1,28 +1,136 @@
+use anyhow::Context;
 use macroquad::prelude as mq;
 use simulation::*;
 use util::arena::Arena;
 
 use crate::{assets::Assets, gui::WindowKind, *};
 
+// Tag of the faction the player leads at first
+const PLAYER_FACTION: &str = "rheged";
+// Days of simulated time between autosaves, and how many are kept
+const AUTOSAVE_DAYS: u64 = 30;
+const AUTOSAVE_SLOTS: usize = 3;
+// Keys camera bookmarks are saved under, with control held, and recalled with
+const BOOKMARK_KEYS: [mq::KeyCode; 9] = [
+    mq::KeyCode::Key1,
+    mq::KeyCode::Key2,
+    mq::KeyCode::Key3,
+    mq::KeyCode::Key4,
+    mq::KeyCode::Key5,
+    mq::KeyCode::Key6,
+    mq::KeyCode::Key7,
+    mq::KeyCode::Key8,
+    mq::KeyCode::Key9,
+];
+
+// A saved camera position, named after the place it looked at
+#[derive(Clone)]
+struct Bookmark {
+    name: String,
+    view: board::CameraView,
+}
+
+type Bookmarks = [Option<Bookmark>; BOOKMARK_KEYS.len()];
+
 pub fn start() {
+    start_with(None, 0);
+}
+
+/// Starts the game on a procedurally generated map instead of the fixed
+/// scenario, and with years of history behind it
+pub fn start_with(world: Option<WorldGenParams>, history_years: u64) {
     let config = mq::Conf {
         window_width: 1600,
         window_height: 900,
         high_dpi: true,
         ..Default::default()
     };
-    macroquad::Window::from_config(config, amain());
+    macroquad::Window::from_config(config, amain(world, history_years));
+}
+
+// Draws a frame with a bar of how far along loading is
+async fn loading_screen(label: &str, progress: f32) {
+    const BAR_HEIGHT: f32 = 16.;
+    mq::clear_background(mq::DARKGRAY);
+    let width = mq::screen_width() * 0.4;
+    let x = (mq::screen_width() - width) / 2.;
+    let y = mq::screen_height() / 2.;
+    mq::draw_text(label, x, y - BAR_HEIGHT, 24., mq::WHITE);
+    mq::draw_rectangle_lines(x, y, width, BAR_HEIGHT, 2., mq::WHITE);
+    mq::draw_rectangle(x, y, width * progress.clamp(0., 1.), BAR_HEIGHT, mq::WHITE);
+    mq::next_frame().await;
+}
+
+// Reads the `.template` files of a directory, in name order
+fn load_templates(loader: &mut simulation::Loader, path: &str) -> anyhow::Result<()> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Ok(());
+    };
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "template") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    for path in paths {
+        let source = std::fs::read_to_string(&path)?;
+        loader
+            .load_templates(&source)
+            .with_context(|| format!("in {}", path.display()))?;
+    }
+    Ok(())
+}
+
+// Reads the factions of the scenario from a file, if there is one
+fn load_factions(loader: &mut simulation::Loader, path: &str) -> anyhow::Result<()> {
+    let Ok(source) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    loader
+        .load_factions(&source)
+        .with_context(|| format!("in {path}"))
 }
 
-async fn amain() {
+async fn amain(world: Option<WorldGenParams>, history_years: u64) {
+    loading_screen("Loading assets", 0.).await;
     let assets = Assets::load().await.unwrap();
 
     let mut frame_arena = Arena::default();
 
-    let mut sim = Simulation::new();
-    init_sim(&mut sim, &frame_arena);
+    let mut loader = simulation::Loader::new(world, history_years);
+    if let Err(err) = load_templates(&mut loader, "assets/templates") {
+        println!("Failed to load templates: {err:#}");
+    }
+    if let Err(err) = load_factions(&mut loader, "assets/scenario/factions.scenario") {
+        println!("Failed to load factions: {err:#}");
+    }
+    let mut sim = loop {
+        loading_screen(&loader.describe(), loader.progress()).await;
+        frame_arena.reset();
+        if let Some(sim) = loader.step(&mut frame_arena) {
+            break sim;
+        }
+    };
+
+    if let Some(faction) = sim.object_with_tag(PLAYER_FACTION) {
+        sim.add_player(faction);
+    }
+    let mut game_over = false;
+
+    loading_screen("Reading scripts and layouts", 1.).await;
+    let mut scripts = scripting::Scripts::new();
+    if let Err(err) = scripts.load_dir("assets/scripts") {
+        println!("Failed to load scripts: {err}");
+    }
 
-    let mut gui = gui::Gui::new();
+    let mut layouts = layout::Layouts::default();
+    if let Err(err) = layouts.load_dir("assets/layouts") {
+        println!("Failed to load layouts: {err:#}");
+    }
+
+    let mut gui = gui::Gui::new(layouts);
     egui_macroquad::cfg(|ctx| gui.setup(ctx));
 
     let mut board = board::Board::new(20., &assets);
@@ -32,14 +140,57 @@ async fn amain() {
     // Pre-records the kind of windows the matching requested objects are
     let mut window_kinds = vec![];
     let mut is_paused = true;
+    // Bookmarks are saved and loaded along with the quicksave
+    let mut quicksave: Option<(Checkpoint, Bookmarks)> = None;
+    let mut bookmarks = Bookmarks::default();
+    let mut autosaves = Autosaves::new(AUTOSAVE_DAYS, AUTOSAVE_SLOTS);
+    let mut show_grid = false;
+    let mut show_advisor = false;
+    // Faction whose ledger is open
+    let mut ledger: Option<ObjectId> = None;
+    // Battle whose report is open
+    let mut battle: Option<ObjectId> = None;
+    // Endpoints picked with the measuring tool, when active
+    let mut measuring: Option<(Option<ObjectId>, Option<ObjectId>)> = None;
 
     loop {
         frame_arena.reset();
+        // The faction of the player at the controls, if any is left
+        let player = sim.player();
         if mq::is_key_pressed(mq::KeyCode::Escape) {
-            break;
+            if !gui.is_palette_open() {
+                break;
+            }
+            gui.close_palette();
+        }
+        if mq::is_key_down(mq::KeyCode::LeftControl) && mq::is_key_pressed(mq::KeyCode::P) {
+            gui.open_palette();
         }
 
+        // Orders picked this frame, from the palette or their shortcut
+        let mut orders = vec![];
+        let selection = window_kinds
+            .iter()
+            .zip(&view.objects)
+            .find(|(kind, _)| **kind == WindowKind::Entity)
+            .and_then(|(_, obj)| selected_entity.zip(obj.as_ref()));
+        let available = commands::available(selection, &view.map_items, player.is_some());
+        gui.set_palette_entries(
+            available
+                .iter()
+                .map(|command| {
+                    let shortcut = command.shortcut.map(|key| format!("{key:?}"));
+                    (command.label.clone(), shortcut)
+                })
+                .collect(),
+        );
+
+        let script_commands = scripts.take_commands();
         let mut request = TickRequest::default();
+        for command in &script_commands {
+            command.issue(&mut request.commands);
+        }
+        request.commands.as_player(true);
 
         let mut is_mouse_over_ui = false;
         let mut is_keyboard_taken_by_ui = false;
@@ -54,23 +205,102 @@ async fn amain() {
             is_keyboard_taken_by_ui = ctx.wants_keyboard_input();
         });
 
+        let mut quit = false;
+        for action in gui.take_actions() {
+            match action {
+                gui::GuiAction::Rename(id, name) => {
+                    request.commands.rename(id, frame_arena.alloc_str(&name))
+                }
+                gui::GuiAction::SetNote(id, note) => {
+                    request.commands.set_note(id, frame_arena.alloc_str(&note))
+                }
+                gui::GuiAction::SetAutonomy(id, managed) => {
+                    if player.is_some() {
+                        request.commands.set_autonomy(id, managed)
+                    }
+                }
+                gui::GuiAction::SetStockpile(id, good, target) => {
+                    if player.is_some() {
+                        request.commands.set_stockpile_policy(
+                            id,
+                            frame_arena.alloc_str(&good),
+                            target,
+                        )
+                    }
+                }
+                gui::GuiAction::Transfer(id, good, amount) => {
+                    if player.is_some() {
+                        request.commands.transfer_goods(TransferParams {
+                            party: id,
+                            good: frame_arena.alloc_str(&good),
+                            amount,
+                        })
+                    }
+                }
+                gui::GuiAction::Observe => {
+                    game_over = false;
+                    gui.hide_game_over();
+                }
+                gui::GuiAction::ShowLedger(faction) => ledger = faction,
+                gui::GuiAction::ShowBattle(report) => battle = report,
+                gui::GuiAction::RunCommand(idx) => {
+                    orders.extend(available.get(idx).map(|command| command.order.clone()))
+                }
+                gui::GuiAction::Quit => quit = true,
+            }
+        }
+        if quit {
+            break;
+        }
+
         let map_item_ids: Vec<_> = view.map_items.iter().map(|x| x.id).collect();
         populate_board(&mut board, &view, selected_entity);
 
+        let measurement = match measuring {
+            Some((Some(from), Some(to))) => sim.measure_route(from, to),
+            _ => None,
+        };
+        if let Some(measurement) = &measurement {
+            let points: Vec<_> = measurement
+                .waypoints
+                .iter()
+                .map(|p| mq::Vec2::new(p.x, p.y))
+                .collect();
+            board.push_route(&points);
+        }
+
         if !is_mouse_over_ui {
             if mq::is_mouse_button_pressed(mq::MouseButton::Left) {
-                selected_entity = board
+                let hovered = board
                     .hovered()
                     .and_then(|handle| map_item_ids.get(handle.0))
                     .copied();
+                match &mut measuring {
+                    Some((from, to)) => {
+                        if from.is_none() || to.is_some() {
+                            *from = hovered;
+                            *to = None;
+                        } else {
+                            *to = hovered;
+                        }
+                    }
+                    None => selected_entity = hovered,
+                }
             }
             if mq::is_mouse_button_pressed(mq::MouseButton::Right) {
                 let target = board
                     .hovered()
                     .and_then(|handle| map_item_ids.get(handle.0))
                     .copied();
-                if let (Some(subject), Some(target)) = (selected_entity, target) {
-                    request.commands.issue_move_to_object(subject, target);
+                if let (Some(subject), Some(target)) = (selected_entity, target)
+                    && player.is_some()
+                {
+                    // With shift held the target is queued after the others
+                    if mq::is_key_down(mq::KeyCode::LeftShift) {
+                        request.commands.queue_move_to_object(subject, target);
+                    } else {
+                        request.commands.issue_move_to_object(subject, target);
+                    }
                 }
             }
         }
@@ -78,28 +308,198 @@ async fn amain() {
         if !is_keyboard_taken_by_ui {
             update_camera_from_keyboard(&mut board);
 
-            if mq::is_key_pressed(mq::KeyCode::Space) {
-                is_paused = !is_paused;
+            for (key, order) in commands::shortcuts() {
+                if mq::is_key_pressed(key) {
+                    orders.push(order);
+                }
+            }
+            let saving = mq::is_key_down(mq::KeyCode::LeftControl);
+            for (idx, &key) in BOOKMARK_KEYS.iter().enumerate() {
+                if mq::is_key_pressed(key) {
+                    orders.push(if saving {
+                        commands::Order::SaveBookmark(idx)
+                    } else {
+                        commands::Order::RecallBookmark(idx)
+                    });
+                }
+            }
+        }
+
+        for order in orders {
+            match order {
+                commands::Order::TogglePause => {
+                    if !game_over {
+                        is_paused = !is_paused;
+                    }
+                }
+                commands::Order::SpeedUp => {
+                    let speed = sim.schedule.ticks_per_frame();
+                    sim.schedule.set_ticks_per_frame((speed * 2).min(64));
+                }
+                commands::Order::SlowDown => {
+                    let speed = sim.schedule.ticks_per_frame();
+                    sim.schedule.set_ticks_per_frame((speed / 2).max(1));
+                }
+                commands::Order::ToggleAdvisor => show_advisor = !show_advisor,
+                commands::Order::ToggleGrid => show_grid = !show_grid,
+                commands::Order::ToggleMeasure => {
+                    measuring = match measuring {
+                        Some(_) => None,
+                        None => Some((None, None)),
+                    };
+                }
+                commands::Order::Quicksave => {
+                    quicksave = Some((sim.checkpoint(), bookmarks.clone()));
+                }
+                commands::Order::Quickload => {
+                    if let Some((checkpoint, saved)) = &quicksave {
+                        sim.restore(checkpoint);
+                        bookmarks = saved.clone();
+                    }
+                }
+                commands::Order::SaveBookmark(idx) => {
+                    let center = board.center();
+                    let name = view
+                        .map_items
+                        .iter()
+                        .filter(|item| !item.name.is_empty())
+                        .min_by(|a, b| {
+                            let distance = |pos: V2| center.distance(mq::Vec2::new(pos.x, pos.y));
+                            distance(a.pos).total_cmp(&distance(b.pos))
+                        })
+                        .map_or("open country".to_string(), |item| item.name.clone());
+                    gui.add_notice(&format!("Bookmark {} saved near {name}", idx + 1));
+                    bookmarks[idx] = Some(Bookmark {
+                        name,
+                        view: board.camera_view(),
+                    });
+                }
+                commands::Order::RecallBookmark(idx) => match &bookmarks[idx] {
+                    Some(bookmark) => {
+                        board.set_camera_view(bookmark.view);
+                        gui.add_notice(&format!("Near {}", bookmark.name));
+                    }
+                    None => gui.add_notice(&format!("No bookmark on {}", idx + 1)),
+                },
+                commands::Order::LoadAutosave => match autosaves.latest() {
+                    Some(checkpoint) => sim.restore(checkpoint),
+                    None => gui.add_notice("No autosave yet"),
+                },
+                commands::Order::NextParty | commands::Order::NextIdleParty => {
+                    let idle_only = order == commands::Order::NextIdleParty;
+                    let parties: Vec<_> = player
+                        .map(|faction| sim.parties_of(faction))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|party| party.idle || !idle_only)
+                        .collect();
+                    // The one after the selection, wrapping around
+                    let current = parties
+                        .iter()
+                        .position(|party| Some(party.id) == selected_entity);
+                    let next = current.map_or(0, |idx| idx + 1) % parties.len().max(1);
+                    match parties.get(next) {
+                        Some(party) => {
+                            selected_entity = Some(party.id);
+                            board.focus(mq::Vec2::new(party.pos.x, party.pos.y));
+                        }
+                        None if idle_only => gui.add_notice("No party is idle"),
+                        None => {}
+                    }
+                }
+                commands::Order::NextPlayer => match sim.next_player() {
+                    Some(faction) if Some(faction) != player => {
+                        selected_entity = Some(faction);
+                        gui.add_notice("The controls pass to the next player");
+                    }
+                    Some(_) => gui.add_notice("No other player is seated"),
+                    None => {}
+                },
+                commands::Order::AddPlayer(faction) => {
+                    if sim.add_player(faction) {
+                        gui.add_notice("A player takes a seat, pass the controls with F2");
+                    }
+                }
+                commands::Order::Halt(subject) => request.commands.halt(subject),
+                commands::Order::Patrol(subject) => request.commands.patrol(subject),
+                commands::Order::ForcedMarch(subject, on) => {
+                    request.commands.forced_march(subject, on)
+                }
+                commands::Order::SetStance(subject, stance) => {
+                    request.commands.set_stance(subject, stance)
+                }
+                commands::Order::Join(party, leader) => request.commands.merge_party(leader, party),
+                commands::Order::LeaveGroup(party) => request.commands.detach_party(party),
+                commands::Order::Escort(escort, party) => {
+                    request.commands.escort_party(escort, party)
+                }
+                commands::Order::PartWays(party) => request.commands.release_party(party),
+                commands::Order::StationTroops(party) => request.commands.station_troops(party),
+                commands::Order::Besiege(party, settlement) => {
+                    request.commands.besiege(party, settlement)
+                }
+                commands::Order::MoveTo(subject, target) => {
+                    request.commands.issue_move_to_object(subject, target)
+                }
+                commands::Order::Build(location, building) => request
+                    .commands
+                    .construct_building(location, frame_arena.alloc_str(&building)),
             }
         }
 
         mq::clear_background(mq::LIGHTGRAY);
+        if show_grid {
+            board.draw_grid(5.);
+        }
         board.draw();
         if is_paused {
             board.billboard("Paused");
         }
+        if !is_mouse_over_ui
+            && let Some(item) = board
+                .hovered()
+                .and_then(|handle| view.map_items.get(handle.0))
+            && !item.name.is_empty()
+        {
+            let mut lines = vec![item.name.as_str()];
+            lines.extend(item.note.lines());
+            // How long the selected party would take to get there if ordered to
+            let eta = selected_entity
+                .filter(|&subject| subject != item.id && player.is_some() && measuring.is_none())
+                .and_then(|subject| sim.estimate_travel(subject, item.id))
+                .map(|eta| format!("{:1.1} days away", eta.days()));
+            lines.extend(eta.as_deref());
+            board.tooltip(&lines);
+        }
+        if player.is_none() && measuring.is_none() {
+            board.caption("Observing");
+        }
+        if measuring.is_some() {
+            let caption = match &measurement {
+                Some(m) => format!(
+                    "Distance {:1.1} - {:1.1} days on foot",
+                    m.distance, m.days_on_foot
+                ),
+                None => "Measuring: pick two points".to_string(),
+            };
+            board.caption(&caption);
+        }
         egui_macroquad::draw();
 
         request.num_ticks = if is_paused {
             0
         } else {
+            let speed = sim.schedule.ticks_per_frame();
             if mq::is_key_down(mq::KeyCode::LeftControl) {
-                10
+                speed * 10
             } else {
-                1
+                speed
             }
         };
 
+        request.map_filter = gui.map_filter();
+        // Players only see what their faction does
+        request.observer = sim.player();
         request.map_viewport = {
             let convert = |v: mq::Vec2| V2::new(v.x, v.y);
             let top_left = convert(board.screen_to_world(mq::Vec2::ZERO));
@@ -119,11 +519,48 @@ async fn amain() {
             request.objects_to_extract.push(ObjectId::global());
             window_kinds.push(WindowKind::TopStrip);
 
+            if show_advisor {
+                request.objects_to_extract.push(ObjectId::advisor());
+                window_kinds.push(WindowKind::Advisor);
+            }
+
+            request.objects_to_extract.extend(ledger);
+            window_kinds.extend(ledger.map(|_| WindowKind::Ledger));
+            request.objects_to_extract.extend(battle);
+            window_kinds.extend(battle.map(|_| WindowKind::Battle));
+
             request.objects_to_extract.extend(selected_entity);
             window_kinds.extend(selected_entity.map(|_| WindowKind::Entity));
         }
 
+        let players = sim.players();
         view = sim.tick(request, &frame_arena);
+        autosaves.update(&sim);
+        scripts.handle_events(&view.events);
+        gui.add_warnings(&view.warnings);
+        for event in &view.events {
+            if let SimEvent::PathBlocked { party } = event
+                && Some(*party) == selected_entity
+            {
+                gui.add_notice("The party cannot reach its destination");
+            }
+            if let SimEvent::Battle { winner, loser, .. } = event
+                && (Some(*winner) == selected_entity || Some(*loser) == selected_entity)
+            {
+                gui.add_notice("The party fought a battle");
+            }
+            if let SimEvent::FactionEliminated { faction } = event
+                && players.contains(faction)
+            {
+                if sim.player().is_some() {
+                    gui.add_notice("A player's realm fell, the controls pass on");
+                    continue;
+                }
+                game_over = true;
+                is_paused = true;
+                gui.show_game_over(&sim.statistics());
+            }
+        }
         mq::next_frame().await;
     }
 }
@@ -132,10 +569,19 @@ fn populate_board(board: &mut board::Board, view: &SimView, selected_entity: Opt
     board.clear();
     let mut ids = Vec::with_capacity(view.map_items.len());
     // Lines
-    for (source, dest) in &view.map_lines {
+    for &(source, dest, terrain, quality) in &view.map_lines {
         board.push_line(
             mq::Vec2::new(source.x, source.y),
             mq::Vec2::new(dest.x, dest.y),
+            terrain,
+            quality,
+        );
+    }
+    // Territory
+    for territory in &view.map_territory {
+        board.push_territory(
+            mq::Vec2::new(territory.pos.x, territory.pos.y),
+            territory.colour,
         );
     }
     // Pawns
@@ -161,6 +607,13 @@ fn populate_board(board: &mut board::Board, view: &SimView, selected_entity: Opt
             (mq::BLACK, mq::WHITE)
         };
 
+        // What is out of sight is drawn faded, as last seen
+        let fill_color = if item.remembered {
+            fill_color.with_alpha(0.4)
+        } else {
+            fill_color
+        };
+
         let show_name = is_selected || is_big;
         let name = if show_name { item.name.as_str() } else { "" };
         let pos = mq::Vec2::new(item.pos.x, item.pos.y);
@@ -170,7 +623,7 @@ fn populate_board(board: &mut board::Board, view: &SimView, selected_entity: Opt
         let texture = if item.image.is_empty() {
             None
         } else {
-            Some(board.assets.texture(item.image))
+            Some(board.assets.texture(&item.image))
         };
 
         board.push_pawn(
@@ -214,127 +667,3 @@ fn update_camera_from_keyboard(board: &mut board::Board) {
 
     board.update_camera(dtranslate, dzoom);
 }
-
-fn init_sim(sim: &mut Simulation, arena: &Arena) {
-    struct Desc<'a> {
-        name: &'a str,
-        site: &'a str,
-        kind: &'a str,
-    }
-
-    let descs = [
-        Desc {
-            name: "Caer Ligualid",
-            site: "caer_ligualid",
-            kind: "town",
-        },
-        Desc {
-            name: "Anava",
-            site: "anava",
-            kind: "village",
-        },
-        Desc {
-            name: "Din Drust",
-            site: "din_drust",
-            kind: "hillfort",
-        },
-        Desc {
-            name: "Llan Heledd",
-            site: "llan_heledd",
-            kind: "village",
-        },
-        Desc {
-            name: "Isura",
-            site: "isura",
-            kind: "village",
-        },
-        Desc {
-            name: "Ad Candidam Casam",
-            site: "ad_candidam_casam",
-            kind: "village",
-        },
-        Desc {
-            name: "Din Rheged",
-            site: "din_rheged",
-            kind: "hillfort",
-        },
-    ];
-
-    let mut request = TickRequest::default();
-    request.commands.create_faction(CreateFactionParams {
-        tag: "rheged",
-        name: "Rheged",
-    });
-    sim.tick(request, arena);
-
-    let mut request = TickRequest::default();
-    for desc in descs {
-        let prosperity = match desc.kind {
-            "town" => 0.4,
-            "hillfort" => 0.3,
-            "village" => 0.3,
-            _ => panic!(),
-        };
-
-        let tokens: &[CreateToken] = match desc.kind {
-            "village" => &[CreateToken {
-                tag: "paesants",
-                size: 5_000,
-            }],
-            "hillfort" => &[
-                CreateToken {
-                    tag: "paesants",
-                    size: 5_000,
-                },
-                CreateToken {
-                    tag: "artisans",
-                    size: 100,
-                },
-                CreateToken {
-                    tag: "nobles",
-                    size: 50,
-                },
-            ],
-            "town" => &[
-                CreateToken {
-                    tag: "paesants",
-                    size: 7_500,
-                },
-                CreateToken {
-                    tag: "artisans",
-                    size: 1_000,
-                },
-                CreateToken {
-                    tag: "nobles",
-                    size: 200,
-                },
-                CreateToken {
-                    tag: "toolmaker",
-                    size: 1,
-                },
-            ],
-            _ => &[],
-        };
-
-        request.commands.create_location(CreateLocationParams {
-            name: desc.name,
-            site: desc.site,
-            settlement_kind: desc.kind,
-            faction: "rheged",
-            prosperity,
-            tokens,
-        });
-    }
-
-    request.commands.create_person(CreatePersonParams {
-        name: "Federico",
-        site: "caer_ligualid",
-        faction: "rheged",
-    });
-    request.commands.create_person(CreatePersonParams {
-        name: "Test",
-        site: "din_drust",
-        faction: "rheged",
-    });
-    sim.tick(request, arena);
-}
@@ -1,13 +1,72 @@
-use simulation::Object;
+use simulation::{MapFilter, Object, ObjectId, SimWarning, Statistics};
+
+use crate::layout::Layouts;
 
 #[derive(Default)]
 pub(crate) struct Gui {
     objects: Vec<(WindowKind, Object)>,
+    edit: Option<EntityEdit>,
+    actions: Vec<GuiAction>,
+    warnings: Vec<String>,
+    // Closing statistics, while the end screen is up
+    game_over: Option<Vec<(String, String)>>,
+    palette: Option<Palette>,
+    entity_tab: EntityTab,
+    layouts: Layouts,
+    // Parties drawn on the map, as toggled in the top strip
+    map_filter: MapFilter,
+}
+
+// Page shown in entity windows
+#[derive(Default, Clone, Copy, PartialEq)]
+enum EntityTab {
+    #[default]
+    Overview,
+    History,
+}
+
+// The command palette, while open
+#[derive(Default)]
+struct Palette {
+    query: String,
+    // Position of the highlighted command among those matching the query
+    highlighted: usize,
+    // Label and shortcut of each command, in registry order
+    entries: Vec<(String, Option<String>)>,
+}
+
+// Name and note being edited in an entity window
+struct EntityEdit {
+    id: ObjectId,
+    name: String,
+    note: String,
+}
+
+pub(crate) enum GuiAction {
+    Rename(ObjectId, String),
+    SetNote(ObjectId, String),
+    SetAutonomy(ObjectId, bool),
+    // Have a location keep this much of the good with the tag
+    SetStockpile(ObjectId, String, f64),
+    // Unload this much of the good with the tag, or load it when negative
+    Transfer(ObjectId, String, f64),
+    // Open the ledger of a faction, or close it with none
+    ShowLedger(Option<ObjectId>),
+    // Open the report of a battle, or close it with none
+    ShowBattle(Option<ObjectId>),
+    // Run the command at this position in the registry
+    RunCommand(usize),
+    // Keep watching the world after the player faction fell
+    Observe,
+    Quit,
 }
 
 impl Gui {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(layouts: Layouts) -> Self {
+        Self {
+            layouts,
+            ..Self::default()
+        }
     }
 
     pub fn setup(&mut self, ctx: &egui::Context) {
@@ -21,10 +80,83 @@ impl Gui {
     pub fn tick(&mut self, ctx: &egui::Context) {
         for (window_idx, (kind, obj)) in self.objects.drain(..).enumerate() {
             match kind {
-                WindowKind::TopStrip => top_strip(ctx, &obj),
-                WindowKind::Entity => object_ui(ctx, window_idx, &obj),
+                WindowKind::TopStrip => top_strip(ctx, &obj, &mut self.map_filter),
+                WindowKind::Advisor => advisor_ui(ctx, &self.layouts, &obj),
+                WindowKind::Ledger => ledger_ui(ctx, &obj, &mut self.actions),
+                WindowKind::Battle => battle_ui(ctx, &obj, &mut self.actions),
+                WindowKind::Entity => {
+                    let (edit, tab) = (&mut self.edit, &mut self.entity_tab);
+                    object_ui(
+                        ctx,
+                        window_idx,
+                        &self.layouts,
+                        &obj,
+                        edit,
+                        tab,
+                        &mut self.actions,
+                    )
+                }
             }
         }
+        warnings_ui(ctx, &mut self.warnings);
+        if let Some(palette) = &mut self.palette
+            && palette_ui(ctx, palette, &mut self.actions)
+        {
+            self.palette = None;
+        }
+        if let Some(statistics) = &self.game_over {
+            game_over_ui(ctx, statistics, &mut self.actions);
+        }
+    }
+
+    pub fn show_game_over(&mut self, statistics: &Statistics) {
+        self.game_over = Some(statistics.fields());
+    }
+
+    pub fn hide_game_over(&mut self) {
+        self.game_over = None;
+    }
+
+    pub fn add_warnings(&mut self, warnings: &[SimWarning]) {
+        self.add_messages(warnings.iter().map(|warning| warning.to_string()));
+    }
+
+    fn add_messages(&mut self, messages: impl Iterator<Item = String>) {
+        const MAX_WARNINGS: usize = 20;
+        self.warnings.extend(messages);
+        let excess = self.warnings.len().saturating_sub(MAX_WARNINGS);
+        self.warnings.drain(..excess);
+    }
+
+    pub fn add_notice(&mut self, notice: &str) {
+        self.add_messages(std::iter::once(notice.to_string()));
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette = Some(Palette::default());
+    }
+
+    pub fn close_palette(&mut self) {
+        self.palette = None;
+    }
+
+    pub fn is_palette_open(&self) -> bool {
+        self.palette.is_some()
+    }
+
+    /// Commands the open palette lists, as label and shortcut
+    pub fn set_palette_entries(&mut self, entries: Vec<(String, Option<String>)>) {
+        if let Some(palette) = &mut self.palette {
+            palette.entries = entries;
+        }
+    }
+
+    pub fn take_actions(&mut self) -> Vec<GuiAction> {
+        std::mem::take(&mut self.actions)
+    }
+
+    pub fn map_filter(&self) -> MapFilter {
+        self.map_filter
     }
 }
 
@@ -32,17 +164,252 @@ impl Gui {
 pub(crate) enum WindowKind {
     TopStrip,
     Entity,
+    Advisor,
+    Ledger,
+    Battle,
 }
 
-fn top_strip(ctx: &egui::Context, obj: &Object) {
+fn top_strip(ctx: &egui::Context, obj: &Object, filter: &mut MapFilter) {
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
         ui.horizontal_centered(|ui| {
             ui.label(obj.txt("date"));
+            ui.separator();
+            if let Some(player) = obj.try_text("player") {
+                ui.label(format!("Playing {player}"));
+                ui.separator();
+            }
+            ui.label(format!("Money {}", obj.txt("money_supply")));
+            ui.label(format!("Prices x{}", obj.txt("inflation")));
+            ui.separator();
+            ui.checkbox(&mut filter.traders, "Traders");
+            ui.checkbox(&mut filter.civilians, "Civilians");
+            ui.checkbox(&mut filter.foreign, "Foreign")
+                .on_hover_text("Parties of other factions than yours");
+        });
+    });
+}
+
+fn warnings_ui(ctx: &egui::Context, warnings: &mut Vec<String>) {
+    if warnings.is_empty() {
+        return;
+    }
+    egui::Window::new("Warnings")
+        .collapsible(true)
+        .resizable(false)
+        .show(ctx, |ui| {
+            for warning in warnings.iter() {
+                ui.label(warning);
+            }
+            ui.separator();
+            if ui.button("Clear").clicked() {
+                warnings.clear();
+            }
+        });
+}
+
+fn game_over_ui(
+    ctx: &egui::Context,
+    statistics: &[(String, String)],
+    actions: &mut Vec<GuiAction>,
+) {
+    egui::Window::new("Your realm has fallen")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Grid::new("game-over-grid").show(ui, |ui| {
+                for (name, value) in statistics {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Observe").clicked() {
+                    actions.push(GuiAction::Observe);
+                }
+                if ui.button("Quit").clicked() {
+                    actions.push(GuiAction::Quit);
+                }
+            });
         });
+}
+
+// Returns whether a command was picked, and the palette is done
+fn palette_ui(ctx: &egui::Context, palette: &mut Palette, actions: &mut Vec<GuiAction>) -> bool {
+    let query = palette.query.to_lowercase();
+    let matches: Vec<_> = palette
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, (label, _))| {
+            let label = label.to_lowercase();
+            query.split_whitespace().all(|word| label.contains(word))
+        })
+        .collect();
+
+    let (down, up, enter) = ctx.input(|input| {
+        (
+            input.key_pressed(egui::Key::ArrowDown),
+            input.key_pressed(egui::Key::ArrowUp),
+            input.key_pressed(egui::Key::Enter),
+        )
     });
+    if down {
+        palette.highlighted += 1;
+    }
+    if up {
+        palette.highlighted = palette.highlighted.saturating_sub(1);
+    }
+    palette.highlighted = palette.highlighted.min(matches.len().saturating_sub(1));
+
+    let mut picked = enter
+        .then(|| matches.get(palette.highlighted).map(|(idx, _)| *idx))
+        .flatten();
+
+    const MAX_SHOWN: usize = 12;
+    egui::Window::new("Commands")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 60.))
+        .show(ctx, |ui| {
+            ui.set_min_width(320.);
+            let search = ui.text_edit_singleline(&mut palette.query);
+            search.request_focus();
+            if search.changed() {
+                palette.highlighted = 0;
+            }
+            ui.separator();
+            if matches.is_empty() {
+                ui.weak("No matching command");
+            }
+            // Keep the highlighted command in view
+            let first = palette.highlighted.saturating_sub(MAX_SHOWN - 1);
+            for (pos, &(idx, (label, shortcut))) in
+                matches.iter().enumerate().skip(first).take(MAX_SHOWN)
+            {
+                ui.horizontal(|ui| {
+                    let highlighted = pos == palette.highlighted;
+                    if ui.selectable_label(highlighted, label).clicked() {
+                        picked = Some(idx);
+                    }
+                    if let Some(shortcut) = shortcut {
+                        ui.weak(shortcut);
+                    }
+                });
+            }
+        });
+
+    if let Some(idx) = picked {
+        actions.push(GuiAction::RunCommand(idx));
+    }
+    picked.is_some()
 }
 
-fn object_ui(ctx: &egui::Context, obj_idx: usize, obj: &Object) {
+fn advisor_ui(ctx: &egui::Context, layouts: &Layouts, obj: &Object) {
+    egui::Window::new("Advisor")
+        .collapsible(true)
+        .resizable(false)
+        .show(ctx, |ui| {
+            rows_table(ui, layouts, "advisor.suggestions", obj.list("suggestions"));
+        });
+}
+
+fn ledger_ui(ctx: &egui::Context, obj: &Object, actions: &mut Vec<GuiAction>) {
+    egui::Window::new(format!("Ledger of {}", obj.txt("name")))
+        .id("ledger_window".into())
+        .collapsible(true)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let rows = [
+                ("Population", "population", "population_history"),
+                ("Settlements", "settlements", ""),
+                ("Output", "gdp", "gdp_history"),
+                ("Treasury", "treasury", "treasury_history"),
+                ("Trade balance", "trade_balance", "trade_balance_history"),
+            ];
+            egui::Grid::new("ledger-grid").striped(true).show(ui, |ui| {
+                for (label, field, history) in rows {
+                    ui.label(label);
+                    ui.label(obj.txt(field));
+                    if history.is_empty() {
+                        ui.label("");
+                    } else {
+                        sparkline(ui, obj.series(history));
+                    }
+                    ui.end_row();
+                }
+            });
+            if ui.button("Close").clicked() {
+                actions.push(GuiAction::ShowLedger(None));
+            }
+        });
+}
+
+fn battle_ui(ctx: &egui::Context, obj: &Object, actions: &mut Vec<GuiAction>) {
+    egui::Window::new(obj.txt("name"))
+        .id("battle_window".into())
+        .collapsible(true)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.weak(obj.txt("date"));
+            ui.label(format!("{} carried the day", obj.txt("winner")));
+            ui.separator();
+            egui::Grid::new("battle-sides")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (side, modifiers) in [
+                        ("attacker", "attacker_modifiers"),
+                        ("defender", "defender_modifiers"),
+                    ] {
+                        ui.label(obj.txt(side));
+                        let modifiers: Vec<_> =
+                            obj.list(modifiers).iter().map(|x| x.txt("text")).collect();
+                        if modifiers.is_empty() {
+                            ui.weak("no modifiers");
+                        } else {
+                            ui.label(modifiers.join(", "));
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            egui::Grid::new("battle-rounds")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in ["Round", "Attacker", "Losses", "Defender", "Losses"] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+                    for round in obj.list("rounds") {
+                        for field in [
+                            "round",
+                            "attacker_strength",
+                            "attacker_losses",
+                            "defender_strength",
+                            "defender_losses",
+                        ] {
+                            ui.label(round.txt(field));
+                        }
+                        ui.end_row();
+                    }
+                });
+            if ui.button("Close").clicked() {
+                actions.push(GuiAction::ShowBattle(None));
+            }
+        });
+}
+
+fn object_ui(
+    ctx: &egui::Context,
+    obj_idx: usize,
+    layouts: &Layouts,
+    obj: &Object,
+    edit: &mut Option<EntityEdit>,
+    tab: &mut EntityTab,
+    actions: &mut Vec<GuiAction>,
+) {
     let window_id = format!("object_window_{obj_idx}");
     egui::Window::new(obj.txt("name"))
         .id(window_id.into())
@@ -51,193 +418,531 @@ fn object_ui(ctx: &egui::Context, obj_idx: usize, obj: &Object) {
         .show(ctx, |ui| {
             ui.set_min_width(250.);
 
+            if let Some(history) = obj.try_list("history") {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(tab, EntityTab::Overview, "Overview");
+                    ui.selectable_value(tab, EntityTab::History, "History");
+                });
+                ui.separator();
+                if *tab == EntityTab::History {
+                    history_ui(ui, history, actions);
+                    return;
+                }
+            }
+
             ui.horizontal(|ui| {
                 ui.vertical(|ui| {
                     ui.heading("Overview");
-                    let table = [
-                        ("Name", "name"),
-                        ("Kind", "kind"),
-                        ("Cash", "cash"),
-                        ("Faction", "faction"),
-                        ("Country", "country"),
-                    ];
-                    field_table(ui, "overview-table", &table, obj);
+                    field_table(ui, layouts, "entity", obj);
+                    let ledger = obj.id("ledger");
+                    if ledger != ObjectId::default() && ui.button("Ledger").clicked() {
+                        actions.push(GuiAction::ShowLedger(Some(ledger)));
+                    }
                 });
 
                 if let Some(list) = obj.try_list("good_stock") {
                     ui.vertical(|ui| {
                         ui.heading("Stock");
-                        let table = [
-                            Row {
-                                label: "Name",
-                                primary: "name",
-                                tooltip: &[],
-                            },
-                            Row {
-                                label: "Amount",
-                                primary: "amount",
-                                tooltip: &[],
-                            },
-                        ];
-                        rows_table(ui, "good_stock", &table, list);
+                        rows_table(ui, layouts, "entity.good_stock", list);
                     });
                 }
 
                 ui.vertical(|ui| {
                     if let Some(obj) = obj.try_child("pressure_agent") {
                         ui.heading("Pressures");
-                        let table = [
-                            Row {
-                                label: "Name",
-                                primary: "name",
-                                tooltip: &[],
-                            },
-                            Row {
-                                label: "Value",
-                                primary: "amount",
-                                tooltip: &[],
-                            },
-                        ];
-                        rows_table(ui, "pressure_agent_table", &table, obj.list("current"));
+                        rows_table(
+                            ui,
+                            layouts,
+                            "entity.pressure_agent.current",
+                            obj.list("current"),
+                        );
                     }
                 })
             });
 
+            if let (Some(opinions), Some(intel)) = (obj.try_list("opinions"), obj.try_list("intel"))
+            {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Opinions");
+                        rows_table(ui, layouts, "entity.opinions", opinions);
+                    });
+                    ui.vertical(|ui| {
+                        ui.heading("Intel");
+                        rows_table(ui, layouts, "entity.intel", intel);
+                    });
+                    if let Some(embargoes) = obj.try_list("embargoes") {
+                        ui.vertical(|ui| {
+                            ui.heading("Embargoes");
+                            rows_table(ui, layouts, "entity.embargoes", embargoes);
+                        });
+                    }
+                    if let Some(edicts) = obj.try_list("edicts") {
+                        ui.vertical(|ui| {
+                            ui.heading("Edicts");
+                            rows_table(ui, layouts, "entity.edicts", edicts);
+                        });
+                    }
+                    if let Some(passages) = obj.try_list("passages") {
+                        ui.vertical(|ui| {
+                            ui.heading("Passages");
+                            rows_table(ui, layouts, "entity.passages", passages);
+                        });
+                    }
+                });
+            }
+
+            if let Some(transfer) = obj.try_child("transfer") {
+                ui.separator();
+                transfer_ui(ui, obj.id("id"), transfer, actions);
+            }
+
+            if let Some(lore) = obj.try_child("lore") {
+                ui.separator();
+                lore_ui(ui, lore);
+            }
+
+            ui.separator();
+            notes_ui(ui, obj, edit, actions);
+
+            let id = obj.id("id");
             if let Some(obj) = obj.try_child("location") {
                 ui.separator();
                 ui.heading("Location");
-                let table = [
-                    ("Population", "population"),
-                    ("Prosperity", "prosperity"),
-                    ("Food", "food"),
-                    ("Income", "income"),
-                ];
-                field_table(ui, "location-table", &table, obj);
-
+                let mut managed = obj.flag("managed");
+                if ui
+                    .checkbox(&mut managed, "Managed by its council")
+                    .on_hover_text("Builds and sends for food on its own")
+                    .changed()
                 {
-                    let tokens_table = [
-                        Row {
-                            label: "Name",
-                            primary: "name",
-                            tooltip: &[],
-                        },
-                        Row {
-                            label: "Size",
-                            primary: "size",
-                            tooltip: &[],
-                        },
-                    ];
+                    actions.push(GuiAction::SetAutonomy(id, managed));
+                }
+                field_table(ui, layouts, "entity.location", obj);
 
-                    ui.separator();
-                    ui.horizontal(|ui| {
-                        ui.vertical(|ui| {
-                            ui.heading("Pops");
-                            rows_table(ui, "pop_grid", &tokens_table, obj.list("pops"));
-                        });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Pops");
+                        rows_table(ui, layouts, "entity.location.pops", obj.list("pops"));
+                    });
 
-                        ui.vertical(|ui| {
-                            ui.heading("Buildings");
-                            rows_table(ui, "building_grid", &tokens_table, obj.list("buildings"));
-                        });
+                    ui.vertical(|ui| {
+                        ui.heading("Buildings");
+                        rows_table(
+                            ui,
+                            layouts,
+                            "entity.location.buildings",
+                            obj.list("buildings"),
+                        );
+                    });
 
-                        ui.vertical(|ui| {
-                            ui.heading("Influences");
-                            let table = [
-                                Row {
-                                    label: "Kind",
-                                    primary: "kind",
-                                    tooltip: &[],
-                                },
-                                Row {
-                                    label: "Source",
-                                    primary: "source",
-                                    tooltip: &[],
-                                },
-                                Row {
-                                    label: "Amount",
-                                    primary: "amount",
-                                    tooltip: &[],
-                                },
-                            ];
-                            rows_table(ui, "influence_grid", &table, obj.list("influences"));
-                        })
+                    ui.vertical(|ui| {
+                        ui.heading("Influences");
+                        rows_table(
+                            ui,
+                            layouts,
+                            "entity.location.influences",
+                            obj.list("influences"),
+                        );
+                    })
+                });
+
+                if let Some(demography) = obj.try_child("demography") {
+                    ui.separator();
+                    ui.heading("Demography");
+                    egui::Grid::new("demography-grid").show(ui, |ui| {
+                        for (label, field) in [
+                            ("Class", "classes"),
+                            ("Age", "ages"),
+                            ("Culture", "cultures"),
+                        ] {
+                            if let Some(breakdown) = demography.try_child(field) {
+                                ui.label(label);
+                                stacked_bar(ui, breakdown);
+                                ui.end_row();
+                            }
+                        }
                     });
                 }
 
                 ui.separator();
                 ui.heading("Market");
-                let table = [
-                    Row {
-                        label: "Name",
-                        primary: "name",
-                        tooltip: &[],
-                    },
-                    Row {
-                        label: "Stock",
-                        primary: "stock",
-                        tooltip: &[("Change", "stock_delta")],
-                    },
-                    Row {
-                        label: "Supply",
-                        primary: "supply_effective",
-                        tooltip: &[
-                            ("Base", "supply_base"),
-                            ("From stockpile", "supply_from_stock"),
-                        ],
-                    },
-                    Row {
-                        label: "Demand",
-                        primary: "demand_effective",
-                        tooltip: &[("Base", "demand_base"), ("Satisfaction", "satisfaction")],
-                    },
-                    Row {
-                        label: "Price",
-                        primary: "price",
-                        tooltip: &[("Target", "target_price")],
-                    },
-                ];
-                rows_table(ui, "market-grid", &table, obj.list("market_goods"));
+                rows_table(
+                    ui,
+                    layouts,
+                    "entity.location.market_goods",
+                    obj.list("market_goods"),
+                );
+                rows_table(
+                    ui,
+                    layouts,
+                    "entity.location.market_categories",
+                    obj.list("market_categories"),
+                );
+
+                ui.separator();
+                ui.heading("Stockpiles");
+                egui::Grid::new("stockpile-grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for good in obj.list("market_goods") {
+                            let tag = good.txt("tag");
+                            let target: f64 = good.txt("stockpile").parse().unwrap_or(0.);
+                            ui.label(good.txt("name"));
+                            ui.label(good.txt("stockpile"));
+                            if ui.button("-").clicked() {
+                                let target = (target - STOCKPILE_STEP).max(0.);
+                                actions.push(GuiAction::SetStockpile(id, tag.to_string(), target));
+                            }
+                            if ui.button("+").clicked() {
+                                let target = target + STOCKPILE_STEP;
+                                actions.push(GuiAction::SetStockpile(id, tag.to_string(), target));
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.heading("Price history");
+                egui::Grid::new("price-history-grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for good in obj.list("market_goods") {
+                            ui.label(good.txt("name"));
+                            sparkline(ui, good.series("price_history"));
+                            ui.label(good.txt("trend"));
+                            ui.end_row();
+                        }
+                    });
+
+                let routes = obj.list("trade_routes");
+                if !routes.is_empty() {
+                    ui.separator();
+                    ui.heading("Trade routes");
+                    rows_table(ui, layouts, "entity.location.trade_routes", routes);
+                }
+
+                let constructions = obj.list("constructions");
+                if !constructions.is_empty() {
+                    ui.separator();
+                    ui.heading("Construction");
+                    rows_table(ui, layouts, "entity.location.constructions", constructions);
+                }
+
+                let contracts = obj.list("contracts");
+                if !contracts.is_empty() {
+                    ui.separator();
+                    ui.heading("Delivery orders");
+                    rows_table(ui, layouts, "entity.location.contracts", contracts);
+                }
+
+                deposits_ui(
+                    ui,
+                    layouts,
+                    "entity.location.deposits",
+                    obj.list("deposits"),
+                );
+            }
+            if let Some(deposits) = obj.try_list("deposits") {
+                deposits_ui(ui, layouts, "entity.deposits", deposits);
             }
         });
 }
 
-fn field_table(ui: &mut egui::Ui, grid_id: &str, table: &[(&str, &str)], obj: &Object) {
-    egui::Grid::new(grid_id).show(ui, |ui| {
-        for &(label, field) in table {
-            if let Some(txt) = obj.try_text(field) {
-                ui.label(label);
+fn deposits_ui(ui: &mut egui::Ui, layouts: &Layouts, section: &str, deposits: &[Object]) {
+    if deposits.is_empty() {
+        return;
+    }
+    ui.separator();
+    ui.heading("Deposits");
+    rows_table(ui, layouts, section, deposits);
+}
+
+fn notes_ui(
+    ui: &mut egui::Ui,
+    obj: &Object,
+    edit: &mut Option<EntityEdit>,
+    actions: &mut Vec<GuiAction>,
+) {
+    let id = obj.id("id");
+    match edit {
+        Some(state) if state.id == id => {
+            ui.heading("Edit");
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut state.name);
+            });
+            ui.label("Note");
+            ui.text_edit_multiline(&mut state.note);
+            let mut done = false;
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    actions.push(GuiAction::Rename(id, state.name.clone()));
+                    actions.push(GuiAction::SetNote(id, state.note.clone()));
+                    done = true;
+                } else if ui.button("Cancel").clicked() {
+                    done = true;
+                }
+            });
+            if done {
+                *edit = None;
+            }
+        }
+        _ => {
+            ui.horizontal(|ui| {
+                ui.heading("Notes");
+                if ui.button("Edit").clicked() {
+                    *edit = Some(EntityEdit {
+                        id,
+                        name: obj.txt("name").to_string(),
+                        note: obj.txt("note").to_string(),
+                    });
+                }
+            });
+            match obj.try_text("note") {
+                Some(note) if !note.is_empty() => ui.label(note),
+                _ => ui.weak("No notes"),
+            };
+        }
+    }
+}
+
+fn history_ui(ui: &mut egui::Ui, history: &[Object], actions: &mut Vec<GuiAction>) {
+    if history.is_empty() {
+        ui.weak("Nothing of note yet");
+        return;
+    }
+    egui::ScrollArea::vertical()
+        .max_height(300.)
+        .show(ui, |ui| {
+            egui::Grid::new("history-grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for entry in history {
+                        ui.weak(entry.txt("date"));
+                        ui.label(entry.txt("text"));
+                        let battle = entry.id("battle");
+                        if battle != ObjectId::default() && ui.small_button("Report").clicked() {
+                            actions.push(GuiAction::ShowBattle(Some(battle)));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+// Amount moved by each press of the +/- buttons
+const TRANSFER_STEP: f64 = 10.;
+// Change in a stockpile target for each press of its +/- buttons
+const STOCKPILE_STEP: f64 = 50.;
+
+// A good dragged between the panes of the transfer window
+struct DraggedGood {
+    tag: String,
+    from_cargo: bool,
+}
+
+fn transfer_ui(ui: &mut egui::Ui, id: ObjectId, transfer: &Object, actions: &mut Vec<GuiAction>) {
+    ui.heading(format!("Transfer at {}", transfer.txt("location")));
+    let loadable = transfer.flag("loadable");
+    let goods = transfer.list("goods");
+    let mut dropped = vec![];
+
+    ui.horizontal_top(|ui| {
+        let (_, payload) =
+            ui.dnd_drop_zone::<DraggedGood, _>(egui::Frame::group(ui.style()), |ui| {
+                ui.label("Cargo");
+                egui::Grid::new("transfer-cargo").show(ui, |ui| {
+                    for good in goods {
+                        let tag = good.txt("tag");
+                        let label = format!("{} {}", good.txt("name"), good.txt("carried"));
+                        let payload = DraggedGood {
+                            tag: tag.to_string(),
+                            from_cargo: true,
+                        };
+                        ui.dnd_drag_source(egui::Id::new(("cargo", tag)), payload, |ui| {
+                            ui.label(label)
+                        });
+                        if ui.button("-").clicked() {
+                            actions.push(GuiAction::Transfer(id, tag.to_string(), TRANSFER_STEP));
+                        }
+                        if ui.add_enabled(loadable, egui::Button::new("+")).clicked() {
+                            actions.push(GuiAction::Transfer(id, tag.to_string(), -TRANSFER_STEP));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        dropped.extend(payload.filter(|good| !good.from_cargo));
+
+        let (_, payload) =
+            ui.dnd_drop_zone::<DraggedGood, _>(egui::Frame::group(ui.style()), |ui| {
+                ui.label("Market");
+                for good in goods {
+                    let tag = good.txt("tag");
+                    let label = format!("{} {}", good.txt("name"), good.txt("stock"));
+                    if !loadable {
+                        ui.weak(label);
+                        continue;
+                    }
+                    let payload = DraggedGood {
+                        tag: tag.to_string(),
+                        from_cargo: false,
+                    };
+                    ui.dnd_drag_source(egui::Id::new(("market", tag)), payload, |ui| {
+                        ui.label(label)
+                    });
+                }
+            });
+        dropped.extend(payload.filter(|good| good.from_cargo));
+    });
+
+    // Dropping a good in the other pane moves all of it that can be moved
+    for good in dropped {
+        let amount = if good.from_cargo {
+            f64::INFINITY
+        } else {
+            f64::NEG_INFINITY
+        };
+        actions.push(GuiAction::Transfer(id, good.tag.clone(), amount));
+    }
+    if !loadable {
+        ui.weak("Goods can only be loaded in the settlements of your realm");
+    }
+}
+
+fn lore_ui(ui: &mut egui::Ui, lore: &Object) {
+    ui.heading("Lore");
+    for field in ["etymology", "founding"] {
+        if let Some(text) = lore.try_text(field) {
+            ui.label(egui::RichText::new(text).italics());
+        }
+    }
+}
+
+fn field_table(ui: &mut egui::Ui, layouts: &Layouts, section: &str, obj: &Object) {
+    let layout = layouts.get(section);
+    egui::Grid::new(section).show(ui, |ui| {
+        for row in &layout.rows {
+            if let Some(txt) = obj.try_text(&row.field) {
+                ui.label(&row.label);
                 ui.label(txt);
                 ui.end_row();
             }
         }
+        if layout.rest {
+            for (field, txt) in obj.texts() {
+                if !layout.names(field) {
+                    ui.label(field_label(field));
+                    ui.label(txt);
+                    ui.end_row();
+                }
+            }
+        }
     });
 }
 
-struct Row<'a> {
-    label: &'a str,
-    primary: &'a str,
-    tooltip: &'a [(&'a str, &'a str)],
+// The label of a field no layout names, as in "market_access" to "Market access"
+fn field_label(field: &str) -> String {
+    let mut label = field.replace('_', " ");
+    if let Some(first) = label.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    label
 }
 
-fn rows_table(ui: &mut egui::Ui, grid_id: &str, table: &[Row], list: &[Object]) {
-    egui::Grid::new(grid_id).striped(true).show(ui, |ui| {
+fn sparkline(ui: &mut egui::Ui, values: &[f32]) {
+    let size = egui::vec2(160., 24.);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    if values.len() < 2 {
+        return;
+    }
+
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+    let points: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(idx, &value)| {
+            let x = rect.left() + rect.width() * idx as f32 / (values.len() - 1) as f32;
+            let y = rect.bottom() - rect.height() * (value - min) / range;
+            egui::pos2(x, y)
+        })
+        .collect();
+
+    let stroke = ui.visuals().widgets.inactive.fg_stroke;
+    ui.painter().add(egui::Shape::line(points, stroke));
+    response.on_hover_text(format!("{min:1.2}$ - {max:1.2}$"));
+}
+
+// One bar split by the shares of the parts of a whole, listed on hover
+fn stacked_bar(ui: &mut egui::Ui, breakdown: &Object) {
+    const COLORS: [egui::Color32; 6] = [
+        egui::Color32::from_rgb(86, 130, 172),
+        egui::Color32::from_rgb(196, 142, 72),
+        egui::Color32::from_rgb(110, 160, 96),
+        egui::Color32::from_rgb(170, 88, 88),
+        egui::Color32::from_rgb(140, 110, 170),
+        egui::Color32::from_rgb(150, 150, 150),
+    ];
+    let size = egui::vec2(200., 14.);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let parts = breakdown.list("parts");
+
+    let mut left = rect.left();
+    for (idx, share) in breakdown.series("shares").iter().enumerate() {
+        let width = rect.width() * share.clamp(0., 1.);
+        let segment = egui::Rect::from_min_size(
+            egui::pos2(left, rect.top()),
+            egui::vec2(width, rect.height()),
+        );
+        ui.painter()
+            .rect_filled(segment, 0., COLORS[idx % COLORS.len()]);
+        left += width;
+    }
+
+    response.on_hover_ui(|ui| {
+        egui::Grid::new("stacked-bar-hover").show(ui, |ui| {
+            for (idx, part) in parts.iter().enumerate() {
+                ui.colored_label(COLORS[idx % COLORS.len()], "■");
+                ui.label(part.txt("name"));
+                ui.label(part.txt("share"));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn rows_table(ui: &mut egui::Ui, layouts: &Layouts, section: &str, list: &[Object]) {
+    let table = &layouts.get(section).rows;
+    egui::Grid::new(section).striped(true).show(ui, |ui| {
         if list.is_empty() {
             ui.label("Empty...");
             return;
         }
         for row in table {
-            ui.label(row.label);
+            ui.label(&row.label);
         }
         ui.end_row();
         for obj in list {
             for row in table {
-                let primary = obj.txt(row.primary);
+                let primary = obj.txt(&row.field);
                 let response = ui.label(primary);
                 if !row.tooltip.is_empty() {
                     response.on_hover_ui(|ui| {
                         ui.heading(format!("{} {}", row.label, primary));
                         ui.separator();
-                        field_table(ui, "hover-grid", row.tooltip, obj);
+                        egui::Grid::new("hover-grid").show(ui, |ui| {
+                            for (label, field) in &row.tooltip {
+                                if let Some(txt) = obj.try_text(field) {
+                                    ui.label(label);
+                                    ui.label(txt);
+                                    ui.end_row();
+                                }
+                            }
+                        });
                     });
                 }
             }
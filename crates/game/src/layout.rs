@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, bail};
+
+/// The tables of the object windows, read from layout files so they can be
+/// changed, or extended to new fields, without rebuilding the game.
+/// Sections are named after the path of the object they lay out, as in
+/// `entity.location.market_goods`.
+#[derive(Default)]
+pub(crate) struct Layouts {
+    sections: BTreeMap<String, Layout>,
+}
+
+#[derive(Default)]
+pub(crate) struct Layout {
+    pub rows: Vec<Field>,
+    // Whether text fields not named in the rows are shown too
+    pub rest: bool,
+    // Fields left out of the rest
+    pub hidden: BTreeSet<String>,
+}
+
+pub(crate) struct Field {
+    pub label: String,
+    pub field: String,
+    // Shown when hovering over the cell of a list
+    pub tooltip: Vec<(String, String)>,
+}
+
+impl Layout {
+    const EMPTY: &Layout = &Layout {
+        rows: vec![],
+        rest: false,
+        hidden: BTreeSet::new(),
+    };
+
+    pub fn names(&self, field: &str) -> bool {
+        self.hidden.contains(field) || self.rows.iter().any(|row| row.field == field)
+    }
+}
+
+impl Layouts {
+    /// The layout of a section, or an empty one if no file defines it
+    pub fn get(&self, section: &str) -> &Layout {
+        self.sections.get(section).unwrap_or(Layout::EMPTY)
+    }
+
+    /// Reads the `.layout` files of a directory, in name order
+    pub fn load_dir(&mut self, path: &str) -> anyhow::Result<()> {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return Ok(());
+        };
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "layout") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            let source = std::fs::read_to_string(&path)?;
+            self.load(&source)
+                .with_context(|| format!("in {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&mut self, source: &str) -> anyhow::Result<()> {
+        let mut current: Option<(String, Layout)> = None;
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let Some(name) = name.strip_suffix(']') else {
+                    bail!("line {}: unclosed section name", idx + 1);
+                };
+                if let Some((name, layout)) = current.take() {
+                    self.sections.insert(name, layout);
+                }
+                current = Some((name.trim().to_string(), Layout::default()));
+                continue;
+            }
+            let Some((_, layout)) = &mut current else {
+                bail!("line {}: field outside of a section", idx + 1);
+            };
+            if line == "*" {
+                layout.rest = true;
+            } else if let Some(field) = line.strip_prefix('!') {
+                layout.hidden.insert(field.trim().to_string());
+            } else {
+                let mut pairs = line.split(';').map(|pair| {
+                    pair.split_once('=')
+                        .map(|(label, field)| (label.trim().to_string(), field.trim().to_string()))
+                        .with_context(|| format!("line {}: expected `Label = field`", idx + 1))
+                });
+                let (label, field) = pairs.next().unwrap()?;
+                let tooltip = pairs.collect::<anyhow::Result<_>>()?;
+                layout.rows.push(Field {
+                    label,
+                    field,
+                    tooltip,
+                });
+            }
+        }
+        if let Some((name, layout)) = current {
+            self.sections.insert(name, layout);
+        }
+        Ok(())
+    }
+}
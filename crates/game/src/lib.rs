@@ -1,6 +1,9 @@
 mod game;
-pub use game::start;
+pub use game::{start, start_with};
+pub use simulation::WorldGenParams;
 
 mod assets;
 mod board;
+mod commands;
 mod gui;
+mod layout;
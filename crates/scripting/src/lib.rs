@@ -0,0 +1,704 @@
+//! Rhai scripting hooks for the simulation.
+//!
+//! Scripts define any of the following callbacks, which are invoked with the
+//! events reported in each `SimView`:
+//!
+//! ```rhai
+//! fn on_new_day() { }
+//! fn on_entity_created(id) { }
+//! fn on_trade_resolved(party, location) { }
+//! fn on_covert_action(actor, target, succeeded, discovered) { }
+//! fn on_famine(location, starving) { }
+//! fn on_path_blocked(party) { }
+//! fn on_faction_eliminated(faction) { }
+//! fn on_battle(winner, loser, destroyed) { }
+//! ```
+//!
+//! From inside a callback, scripts issue orders with `move_to(subject, target)`,
+//! `move_to_tag(subject, tag)`, `queue_move(subject, target)`, which adds a
+//! waypoint after those already ordered, `patrol(subject)`, which sends the
+//! party round its waypoints, `forced_march(subject, on)`, which has it travel
+//! faster than its usual pace or back at it, `set_stance(subject, stance)`,
+//! which has it meet hostile parties as passive, defensive, aggressive or
+//! evasive, `merge(leader, party)`, which has a party travel
+//! with the group of another standing where it is, `detach(party)`,
+//! `escort(escort, party)`, which has a party take another standing where it
+//! is along wherever it goes, `release(party)`, which lets it go its own way,
+//! `station_troops(party)`, which leaves its troops in the garrison of the
+//! settlement of its faction it stands in,
+//! `despawn(subject)`,
+//! `create_person(name, site, faction)`, `spawn(template, site, faction)`, which
+//! spawns a party from the entity template with the given tag, and
+//! `covert_action(faction, target, action)`,
+//! where action is one of "scout", "steal" or "incite". Famines are relieved with
+//! `ship_goods(from, to, good, amount)` and `set_edict(faction, edict, active)`,
+//! where edict is one of "rationing" or "price_ceiling", and provoked with
+//! `blight(site, severity, seasons)`. Passing effects on a pop or building of
+//! a location, like a plague or a festival, are put with
+//! `modify_token(site, token, name, rgo_points, demand, supply, days)`, the
+//! factors multiplying what the token works, demands and supplies. Factions trade under
+//! `embargo(imposer, target, good, active)`, where an empty target stands for
+//! every other faction and an empty good for all trade, and tax their
+//! settlements with `set_tax_rate(faction, rate)` and foreign traders with
+//! `set_toll_rate(faction, rate)`, and pay to improve the road between two
+//! sites with `invest_in_road(faction, from, to, cash)`. A faction takes a
+//! site by force with `conquer(faction, site)`. Buildings are raised with
+//! `construct_building(location, building)`, or left to the settlement itself
+//! with `set_autonomy(location, managed)`. The orders are queued and
+//! applied to the next `TickRequest`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rhai::{AST, Engine, Scope};
+use simulation::*;
+
+pub enum ScriptCommand {
+    MoveTo {
+        subject: ObjectId,
+        target: ObjectId,
+    },
+    MoveToTag {
+        subject: ObjectId,
+        tag: String,
+    },
+    QueueMove {
+        subject: ObjectId,
+        target: ObjectId,
+    },
+    Patrol {
+        subject: ObjectId,
+    },
+    ForcedMarch {
+        subject: ObjectId,
+        on: bool,
+    },
+    SetStance {
+        subject: ObjectId,
+        stance: Stance,
+    },
+    Merge {
+        leader: ObjectId,
+        party: ObjectId,
+    },
+    Detach {
+        party: ObjectId,
+    },
+    Escort {
+        escort: ObjectId,
+        party: ObjectId,
+    },
+    Release {
+        party: ObjectId,
+    },
+    StationTroops {
+        party: ObjectId,
+    },
+    Despawn {
+        subject: ObjectId,
+    },
+    CreatePerson {
+        name: String,
+        site: String,
+        faction: String,
+    },
+    Spawn {
+        template: String,
+        site: String,
+        faction: String,
+    },
+    Covert {
+        actor: String,
+        target: ObjectId,
+        action: CovertAction,
+    },
+    Ship {
+        from: ObjectId,
+        to: ObjectId,
+        good: String,
+        amount: f64,
+    },
+    Edict {
+        faction: String,
+        edict: Edict,
+        active: bool,
+    },
+    Embargo {
+        imposer: String,
+        target: String,
+        good: String,
+        active: bool,
+    },
+    Blight {
+        site: String,
+        severity: f64,
+        seasons: u64,
+    },
+    ModifyToken {
+        site: String,
+        token: String,
+        name: String,
+        rgo_points: f64,
+        demand: f64,
+        supply: f64,
+        days: u64,
+    },
+    TaxRate {
+        faction: String,
+        rate: f64,
+    },
+    TollRate {
+        faction: String,
+        rate: f64,
+    },
+    RoadWorks {
+        faction: String,
+        from: String,
+        to: String,
+        cash: f64,
+    },
+    Conquer {
+        faction: String,
+        site: String,
+    },
+    Construct {
+        location: ObjectId,
+        building: String,
+    },
+    Autonomy {
+        location: ObjectId,
+        managed: bool,
+    },
+}
+
+impl ScriptCommand {
+    pub fn issue<'a>(&'a self, commands: &mut TickCommands<'a>) {
+        match self {
+            Self::MoveTo { subject, target } => commands.issue_move_to_object(*subject, *target),
+            Self::MoveToTag { subject, tag } => commands.issue_move_to_tag(*subject, tag),
+            Self::QueueMove { subject, target } => commands.queue_move_to_object(*subject, *target),
+            Self::Patrol { subject } => commands.patrol(*subject),
+            Self::ForcedMarch { subject, on } => commands.forced_march(*subject, *on),
+            Self::SetStance { subject, stance } => commands.set_stance(*subject, *stance),
+            Self::Merge { leader, party } => commands.merge_party(*leader, *party),
+            Self::Detach { party } => commands.detach_party(*party),
+            Self::Escort { escort, party } => commands.escort_party(*escort, *party),
+            Self::Release { party } => commands.release_party(*party),
+            Self::StationTroops { party } => commands.station_troops(*party),
+            Self::Despawn { subject } => commands.despawn(*subject),
+            Self::CreatePerson {
+                name,
+                site,
+                faction,
+            } => commands.create_person(CreatePersonParams {
+                name,
+                site,
+                faction,
+            }),
+            Self::Spawn {
+                template,
+                site,
+                faction,
+            } => commands.spawn(SpawnParams {
+                template,
+                name: None,
+                site,
+                faction,
+            }),
+            Self::Covert {
+                actor,
+                target,
+                action,
+            } => commands.covert_action(CovertActionParams {
+                actor,
+                target: *target,
+                action: *action,
+            }),
+            Self::Ship {
+                from,
+                to,
+                good,
+                amount,
+            } => commands.ship_goods(ShipmentParams {
+                from: *from,
+                to: *to,
+                good,
+                amount: *amount,
+            }),
+            Self::Edict {
+                faction,
+                edict,
+                active,
+            } => commands.set_edict(EdictParams {
+                faction,
+                edict: *edict,
+                active: *active,
+            }),
+            Self::Embargo {
+                imposer,
+                target,
+                good,
+                active,
+            } => commands.embargo(EmbargoParams {
+                imposer,
+                target: Some(target.as_str()).filter(|x| !x.is_empty()),
+                good: Some(good.as_str()).filter(|x| !x.is_empty()),
+                active: *active,
+            }),
+            Self::Blight {
+                site,
+                severity,
+                seasons,
+            } => commands.blight(BlightParams {
+                site,
+                severity: *severity,
+                seasons: *seasons,
+            }),
+            Self::ModifyToken {
+                site,
+                token,
+                name,
+                rgo_points,
+                demand,
+                supply,
+                days,
+            } => commands.modify_token(TokenModifierParams {
+                site,
+                token,
+                name,
+                rgo_points: *rgo_points,
+                demand: *demand,
+                supply: *supply,
+                days: *days,
+            }),
+            Self::TaxRate { faction, rate } => commands.set_tax_rate(TaxRateParams {
+                faction,
+                rate: *rate,
+            }),
+            Self::TollRate { faction, rate } => commands.set_toll_rate(TollRateParams {
+                faction,
+                rate: *rate,
+            }),
+            Self::RoadWorks {
+                faction,
+                from,
+                to,
+                cash,
+            } => commands.invest_in_road(RoadWorksParams {
+                faction,
+                from,
+                to,
+                cash: *cash,
+            }),
+            Self::Conquer { faction, site } => commands.conquer(ConquerParams { faction, site }),
+            Self::Construct { location, building } => {
+                commands.construct_building(*location, building)
+            }
+            Self::Autonomy { location, managed } => commands.set_autonomy(*location, *managed),
+        }
+    }
+}
+
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<(String, AST)>,
+    pending: Rc<RefCell<Vec<ScriptCommand>>>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        let pending: Rc<RefCell<Vec<ScriptCommand>>> = Rc::default();
+
+        engine.register_type_with_name::<ObjectId>("ObjectId");
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("move_to", move |subject: ObjectId, target: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::MoveTo { subject, target });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("move_to_tag", move |subject: ObjectId, tag: &str| {
+                pending.borrow_mut().push(ScriptCommand::MoveToTag {
+                    subject,
+                    tag: tag.to_string(),
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("queue_move", move |subject: ObjectId, target: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::QueueMove { subject, target });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("patrol", move |subject: ObjectId| {
+                pending.borrow_mut().push(ScriptCommand::Patrol { subject });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("forced_march", move |subject: ObjectId, on: bool| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::ForcedMarch { subject, on });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("set_stance", move |subject: ObjectId, stance: &str| {
+                if let Some(stance) = Stance::parse(stance) {
+                    pending
+                        .borrow_mut()
+                        .push(ScriptCommand::SetStance { subject, stance });
+                }
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("merge", move |leader: ObjectId, party: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::Merge { leader, party });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("detach", move |party: ObjectId| {
+                pending.borrow_mut().push(ScriptCommand::Detach { party });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("escort", move |escort: ObjectId, party: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::Escort { escort, party });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("release", move |party: ObjectId| {
+                pending.borrow_mut().push(ScriptCommand::Release { party });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("station_troops", move |party: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::StationTroops { party });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("despawn", move |subject: ObjectId| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::Despawn { subject });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "create_person",
+                move |name: &str, site: &str, faction: &str| {
+                    pending.borrow_mut().push(ScriptCommand::CreatePerson {
+                        name: name.to_string(),
+                        site: site.to_string(),
+                        faction: faction.to_string(),
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("spawn", move |template: &str, site: &str, faction: &str| {
+                pending.borrow_mut().push(ScriptCommand::Spawn {
+                    template: template.to_string(),
+                    site: site.to_string(),
+                    faction: faction.to_string(),
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "covert_action",
+                move |actor: &str, target: ObjectId, action: &str| {
+                    let action = match action {
+                        "scout" => CovertAction::ScoutMarket,
+                        "steal" => CovertAction::StealTreasury,
+                        "incite" => CovertAction::InciteUnrest,
+                        _ => return,
+                    };
+                    pending.borrow_mut().push(ScriptCommand::Covert {
+                        actor: actor.to_string(),
+                        target,
+                        action,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "ship_goods",
+                move |from: ObjectId, to: ObjectId, good: &str, amount: f64| {
+                    pending.borrow_mut().push(ScriptCommand::Ship {
+                        from,
+                        to,
+                        good: good.to_string(),
+                        amount,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "set_edict",
+                move |faction: &str, edict: &str, active: bool| {
+                    let edict = match edict {
+                        "rationing" => Edict::Rationing,
+                        "price_ceiling" => Edict::PriceCeiling,
+                        _ => return,
+                    };
+                    pending.borrow_mut().push(ScriptCommand::Edict {
+                        faction: faction.to_string(),
+                        edict,
+                        active,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "embargo",
+                move |imposer: &str, target: &str, good: &str, active: bool| {
+                    pending.borrow_mut().push(ScriptCommand::Embargo {
+                        imposer: imposer.to_string(),
+                        target: target.to_string(),
+                        good: good.to_string(),
+                        active,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("blight", move |site: &str, severity: f64, seasons: i64| {
+                pending.borrow_mut().push(ScriptCommand::Blight {
+                    site: site.to_string(),
+                    severity,
+                    seasons: seasons.max(0) as u64,
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "modify_token",
+                move |site: &str,
+                      token: &str,
+                      name: &str,
+                      rgo_points: f64,
+                      demand: f64,
+                      supply: f64,
+                      days: i64| {
+                    pending.borrow_mut().push(ScriptCommand::ModifyToken {
+                        site: site.to_string(),
+                        token: token.to_string(),
+                        name: name.to_string(),
+                        rgo_points,
+                        demand,
+                        supply,
+                        days: days.max(0) as u64,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("set_tax_rate", move |faction: &str, rate: f64| {
+                pending.borrow_mut().push(ScriptCommand::TaxRate {
+                    faction: faction.to_string(),
+                    rate,
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("set_toll_rate", move |faction: &str, rate: f64| {
+                pending.borrow_mut().push(ScriptCommand::TollRate {
+                    faction: faction.to_string(),
+                    rate,
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "invest_in_road",
+                move |faction: &str, from: &str, to: &str, cash: f64| {
+                    pending.borrow_mut().push(ScriptCommand::RoadWorks {
+                        faction: faction.to_string(),
+                        from: from.to_string(),
+                        to: to.to_string(),
+                        cash,
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("conquer", move |faction: &str, site: &str| {
+                pending.borrow_mut().push(ScriptCommand::Conquer {
+                    faction: faction.to_string(),
+                    site: site.to_string(),
+                });
+            });
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn(
+                "construct_building",
+                move |location: ObjectId, building: &str| {
+                    pending.borrow_mut().push(ScriptCommand::Construct {
+                        location,
+                        building: building.to_string(),
+                    });
+                },
+            );
+        }
+
+        {
+            let pending = pending.clone();
+            engine.register_fn("set_autonomy", move |location: ObjectId, managed: bool| {
+                pending
+                    .borrow_mut()
+                    .push(ScriptCommand::Autonomy { location, managed });
+            });
+        }
+
+        Self {
+            engine,
+            scripts: vec![],
+            pending,
+        }
+    }
+
+    pub fn load(&mut self, name: impl Into<String>, source: &str) -> anyhow::Result<()> {
+        let ast = self.engine.compile(source)?;
+        self.scripts.push((name.into(), ast));
+        Ok(())
+    }
+
+    /// Loads every `.rhai` file in a directory. A missing directory is not an error.
+    pub fn load_dir(&mut self, path: &str) -> anyhow::Result<()> {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return Ok(());
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "rhai") {
+                let source = std::fs::read_to_string(&path)?;
+                self.load(path.to_string_lossy(), &source)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_events(&mut self, events: &[SimEvent]) {
+        for event in events {
+            match event {
+                SimEvent::NewDay => self.call("on_new_day", ()),
+                SimEvent::EntityCreated { id } => self.call("on_entity_created", (*id,)),
+                SimEvent::TradeResolved { party, location } => {
+                    self.call("on_trade_resolved", (*party, *location))
+                }
+                SimEvent::CovertActionResolved {
+                    actor,
+                    target,
+                    succeeded,
+                    discovered,
+                    ..
+                } => self.call(
+                    "on_covert_action",
+                    (*actor, *target, *succeeded, *discovered),
+                ),
+                SimEvent::FamineChanged { location, starving } => {
+                    self.call("on_famine", (*location, *starving))
+                }
+                SimEvent::PathBlocked { party } => self.call("on_path_blocked", (*party,)),
+                SimEvent::FactionEliminated { faction } => {
+                    self.call("on_faction_eliminated", (*faction,))
+                }
+                SimEvent::Battle {
+                    winner,
+                    loser,
+                    destroyed,
+                } => self.call("on_battle", (*winner, *loser, *destroyed)),
+            }
+        }
+    }
+
+    /// Orders issued by scripts since the last call
+    pub fn take_commands(&mut self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut self.pending.borrow_mut())
+    }
+
+    fn call(&self, callback: &str, args: impl rhai::FuncArgs + Clone) {
+        for (name, ast) in &self.scripts {
+            let defined = ast.iter_functions().any(|f| f.name == callback);
+            if !defined {
+                continue;
+            }
+            let mut scope = Scope::new();
+            let result =
+                self.engine
+                    .call_fn::<rhai::Dynamic>(&mut scope, ast, callback, args.clone());
+            if let Err(err) = result {
+                println!("Script '{name}' failed in '{callback}': {err}");
+            }
+        }
+    }
+}
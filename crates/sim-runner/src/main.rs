@@ -0,0 +1,242 @@
+use std::io::Write;
+
+use anyhow::{Context, bail};
+use simulation::*;
+use util::arena::Arena;
+
+// One in-game day
+const DEFAULT_SAMPLE_EVERY: usize = 72;
+
+struct Args {
+    num_ticks: usize,
+    sample_every: usize,
+    output: Option<String>,
+    chronicle: Option<String>,
+    world: Option<WorldGenParams>,
+    congestion: bool,
+    // How many times slower off road than on one, if not the default
+    off_road: Option<f32>,
+    slices: usize,
+    snapshot: Option<String>,
+    // Day, month and year the simulation starts from
+    start: Option<(u64, u64, u64)>,
+    // Years run before sampling starts
+    history: u64,
+    // Template file read over the built-in entity templates
+    templates: Option<String>,
+    // Scenario file read over the built-in factions
+    factions: Option<String>,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut num_ticks = None;
+    let mut sample_every = DEFAULT_SAMPLE_EVERY;
+    let mut output = None;
+    let mut chronicle = None;
+    let mut world: Option<WorldGenParams> = None;
+    let mut congestion = false;
+    let mut off_road = None;
+    let mut slices = 1;
+    let mut snapshot = None;
+    let mut start = None;
+    let mut history = 0;
+    let mut templates = None;
+    let mut factions = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--every" => {
+                let value = args.next().context("--every requires a value")?;
+                sample_every = value.parse().context("invalid --every")?;
+            }
+            "--out" => {
+                output = Some(args.next().context("--out requires a path")?);
+            }
+            "--chronicle" => {
+                chronicle = Some(args.next().context("--chronicle requires a path")?);
+            }
+            "--seed" => {
+                let value = args.next().context("--seed requires a value")?;
+                world.get_or_insert_default().seed = value.parse().context("invalid --seed")?;
+            }
+            "--size" => {
+                let value = args.next().context("--size requires a value")?;
+                world.get_or_insert_default().size = value.parse().context("invalid --size")?;
+            }
+            "--density" => {
+                let value = args.next().context("--density requires a value")?;
+                world.get_or_insert_default().density =
+                    value.parse().context("invalid --density")?;
+            }
+            "--congestion" => congestion = true,
+            "--off-road" => {
+                let value = args.next().context("--off-road requires a slowdown")?;
+                off_road = Some(value.parse().context("invalid --off-road")?);
+            }
+            "--slices" => {
+                let value = args.next().context("--slices requires a value")?;
+                slices = value.parse().context("invalid --slices")?;
+            }
+            "--snapshot" => {
+                snapshot = Some(args.next().context("--snapshot requires a path")?);
+            }
+            "--start" => {
+                let value = args.next().context("--start requires a date")?;
+                let parts: Vec<u64> = value
+                    .split('/')
+                    .map(|part| part.parse())
+                    .collect::<Result<_, _>>()
+                    .context("invalid --start")?;
+                let [day, month, year] = parts[..] else {
+                    bail!("--start takes a date as day/month/year");
+                };
+                start = Some((day, month, year));
+            }
+            "--templates" => {
+                templates = Some(args.next().context("--templates requires a path")?);
+            }
+            "--factions" => {
+                factions = Some(args.next().context("--factions requires a path")?);
+            }
+            "--history" => {
+                let value = args
+                    .next()
+                    .context("--history requires a number of years")?;
+                history = value.parse().context("invalid --history")?;
+            }
+            _ => num_ticks = Some(arg.parse().context("invalid number of ticks")?),
+        }
+    }
+
+    let Some(num_ticks) = num_ticks else {
+        bail!(
+            "usage: sim-runner <num_ticks> [--every <ticks>] [--out <file.csv>] [--chronicle <file.txt>] [--seed <n>] [--size <units>] [--density <sites>] [--congestion] [--off-road <slowdown>] [--slices <ticks>] [--snapshot <file.txt>] [--start <day/month/year>] [--history <years>] [--templates <file.template>] [--factions <file.scenario>]\n       sim-runner diff <snapshot.txt> <snapshot.txt>"
+        );
+    };
+    if sample_every == 0 {
+        bail!("--every must be greater than zero");
+    }
+
+    Ok(Args {
+        num_ticks,
+        sample_every,
+        output,
+        chronicle,
+        world,
+        congestion,
+        off_road,
+        slices,
+        snapshot,
+        start,
+        history,
+        templates,
+        factions,
+    })
+}
+
+// Reports the first field where two snapshots part ways
+fn diff(left: &str, right: &str) -> anyhow::Result<()> {
+    let read = |path: &str| -> anyhow::Result<Snapshot> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+        Ok(Snapshot::from_text(&text))
+    };
+    match read(left)?.first_divergence(&read(right)?) {
+        Some(divergence) => {
+            println!("snapshots diverge at {divergence}");
+            std::process::exit(1);
+        }
+        None => {
+            println!("snapshots are identical");
+            Ok(())
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut raw = std::env::args().skip(1);
+    if raw.next().as_deref() == Some("diff") {
+        let (Some(left), Some(right)) = (raw.next(), raw.next()) else {
+            bail!("usage: sim-runner diff <snapshot.txt> <snapshot.txt>");
+        };
+        return diff(&left, &right);
+    }
+
+    let args = parse_args()?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    let mut arena = Arena::default();
+    let mut sim = match &args.world {
+        Some(params) => Simulation::generated(params),
+        None => Simulation::new(),
+    };
+    if let Some(path) = &args.templates {
+        let source = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+        sim.load_templates(&source)
+            .with_context(|| format!("in {path}"))?;
+    }
+    if let Some(path) = &args.factions {
+        let source = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+        sim.load_factions(&source)
+            .with_context(|| format!("in {path}"))?;
+    }
+    match &args.world {
+        Some(params) => init_generated_scenario(&mut sim, &arena, params),
+        None => init_scenario(&mut sim, &arena),
+    }
+    sim.road_congestion = args.congestion;
+    if let Some(slowdown) = args.off_road {
+        sim.off_road_slowdown = slowdown;
+    }
+    for system in [System::Influences, System::Market] {
+        sim.schedule.set_slices(system, args.slices);
+    }
+    if let Some((day, month, year)) = args.start {
+        sim.set_start_date(day, month, year);
+    }
+    if args.history > 0 {
+        sim.generate_history(args.history, &mut arena);
+    }
+
+    let stats = sim.statistics();
+    writeln!(out, "{}", stats.csv_header())?;
+    writeln!(out, "{}", stats.csv_row())?;
+
+    // Nothing is on screen, so ask for an empty viewport
+    let empty_viewport = Extents {
+        top_left: V2::MAX,
+        bottom_right: V2::MIN,
+    };
+
+    let mut remaining = args.num_ticks;
+    while remaining > 0 {
+        arena.reset();
+        let num_ticks = remaining.min(args.sample_every);
+        let request = TickRequest {
+            num_ticks,
+            map_viewport: empty_viewport,
+            ..Default::default()
+        };
+        let view = sim.tick(request, &arena);
+        for warning in &view.warnings {
+            eprintln!("warning: {warning}");
+        }
+        remaining -= num_ticks;
+
+        writeln!(out, "{}", sim.statistics().csv_row())?;
+    }
+
+    if let Some(path) = &args.snapshot {
+        std::fs::write(path, sim.snapshot().to_text())?;
+    }
+
+    if let Some(path) = &args.chronicle {
+        std::fs::write(path, sim.export_chronicle(None))?;
+    }
+
+    Ok(())
+}
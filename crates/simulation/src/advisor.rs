@@ -0,0 +1,260 @@
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+use crate::tokens::*;
+
+const MAX_SUGGESTIONS: usize = 8;
+// Markets with a satisfaction below this are considered to be lacking a good
+const LACKING_SATISFACTION: f64 = 0.5;
+// A route is only worth suggesting if selling fetches this much more than buying
+pub(crate) const ROUTE_MIN_MARGIN: f64 = 1.25;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SuggestionKind {
+    Route,
+    Building,
+    Smuggling,
+}
+
+impl SuggestionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Route => "Route",
+            Self::Building => "Building",
+            Self::Smuggling => "Smuggling",
+        }
+    }
+}
+
+pub(crate) struct Suggestion {
+    pub kind: SuggestionKind,
+    pub text: String,
+    pub profit: f64,
+}
+
+/// Most profitable trade routes and buildings given the current markets
+pub(crate) fn suggestions(sim: &Simulation) -> Vec<Suggestion> {
+    let mut out = vec![];
+    route_suggestions(sim, &mut out);
+    building_suggestions(sim, &mut out);
+    smuggling_suggestions(sim, &mut out);
+    out.sort_by(|a, b| b.profit.total_cmp(&a.profit));
+    out.truncate(MAX_SUGGESTIONS);
+    out
+}
+
+fn route_suggestions(sim: &Simulation, out: &mut Vec<Suggestion>) {
+    for (good_id, good) in sim.good_types.iter() {
+        let source = sim
+            .locations
+            .values()
+            .filter(|location| location.market.goods[good_id].stock > 0.)
+            .min_by(|a, b| {
+                let a = a.market.goods[good_id].price;
+                let b = b.market.goods[good_id].price;
+                a.total_cmp(&b)
+            });
+        let destination = sim.locations.values().max_by(|a, b| {
+            let a = a.market.goods[good_id].price;
+            let b = b.market.goods[good_id].price;
+            a.total_cmp(&b)
+        });
+        let (Some(source), Some(destination)) = (source, destination) else {
+            continue;
+        };
+
+        let buy = &source.market.goods[good_id];
+        let sell = &destination.market.goods[good_id];
+        if sell.price < buy.price * ROUTE_MIN_MARGIN {
+            continue;
+        }
+
+        let amount = buy.stock.min(sell.demand_base);
+        let source_name = &sim.entities[source.entity].name;
+        let destination_name = &sim.entities[destination.entity].name;
+        out.push(Suggestion {
+            kind: SuggestionKind::Route,
+            text: format!(
+                "{} is cheap in {source_name} ({:1.2}$) and dear in {destination_name} ({:1.2}$, {})",
+                good.name,
+                buy.price,
+                sell.price,
+                trend_text(sell.price_trend())
+            ),
+            profit: (sell.price - buy.price) * amount,
+        });
+    }
+}
+
+// Embargoed goods fetch a premium in the markets of the faction banning them
+fn smuggling_suggestions(sim: &Simulation, out: &mut Vec<Suggestion>) {
+    let faction_at = |location: &LocationData| {
+        sim.entities[location.entity]
+            .agent
+            .and_then(|agent| faction_of(&sim.agents, agent))
+    };
+
+    for faction in sim.agents.entries.keys() {
+        for embargo in sim.relations.embargoes_by(faction) {
+            let Some(good_id) = embargo.good else {
+                continue;
+            };
+            let price = |location: &&LocationData| location.market.goods[good_id].price;
+
+            let destination = sim
+                .locations
+                .values()
+                .filter(|location| faction_at(location) == Some(faction))
+                .max_by(|a, b| price(a).total_cmp(&price(b)));
+            let source = sim
+                .locations
+                .values()
+                .filter(|location| {
+                    let owner = faction_at(location);
+                    owner != Some(faction) && embargo.target.is_none_or(|x| owner == Some(x))
+                })
+                .filter(|location| location.market.goods[good_id].stock > 0.)
+                .min_by(|a, b| price(a).total_cmp(&price(b)));
+            let (Some(source), Some(destination)) = (source, destination) else {
+                continue;
+            };
+
+            let buy = &source.market.goods[good_id];
+            let sell = &destination.market.goods[good_id];
+            if sell.price < buy.price * ROUTE_MIN_MARGIN {
+                continue;
+            }
+
+            let amount = buy.stock.min(sell.demand_base);
+            out.push(Suggestion {
+                kind: SuggestionKind::Smuggling,
+                text: format!(
+                    "{} is embargoed in {} ({:1.2}$), but could be smuggled from {} ({:1.2}$)",
+                    sim.good_types[good_id].name,
+                    sim.entities[destination.entity].name,
+                    sell.price,
+                    sim.entities[source.entity].name,
+                    buy.price
+                ),
+                profit: (sell.price - buy.price) * amount,
+            });
+        }
+    }
+}
+
+fn trend_text(trend: f64) -> String {
+    // Changes smaller than this are noise
+    const STEADY: f64 = 0.02;
+    if trend > STEADY {
+        format!("rising {:1.0}%", trend * 100.)
+    } else if trend < -STEADY {
+        format!("falling {:1.0}%", -trend * 100.)
+    } else {
+        "steady".to_string()
+    }
+}
+
+fn building_suggestions(sim: &Simulation, out: &mut Vec<Suggestion>) {
+    let buildings: Vec<_> = sim
+        .tokens
+        .types
+        .values()
+        .filter(|typ| typ.category == TokenCategory::Building)
+        .collect();
+
+    for (lacking, good_id) in lacking_goods(sim) {
+        let good = &sim.good_types[good_id];
+        let best = buildings
+            .iter()
+            .filter(|typ| typ.supply.get(good_id).is_some_and(|&amt| amt > 0.))
+            .flat_map(|typ| sim.locations.values().map(move |site| (*typ, site)))
+            .map(|(typ, site)| (typ, site, building_profit(typ, site, lacking)))
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        let Some((typ, site, profit)) = best else {
+            continue;
+        };
+        if profit <= 0. {
+            continue;
+        }
+
+        let lacking_name = &sim.entities[lacking.entity].name;
+        let site_name = &sim.entities[site.entity].name;
+        out.push(Suggestion {
+            kind: SuggestionKind::Building,
+            text: format!(
+                "{lacking_name} lacks {}; a {} in {site_name} would profit",
+                good.name.to_lowercase(),
+                typ.name.to_lowercase()
+            ),
+            profit,
+        });
+    }
+}
+
+// Goods each market is short of
+fn lacking_goods(sim: &Simulation) -> impl Iterator<Item = (&LocationData, GoodId)> {
+    sim.locations.values().flat_map(move |lacking| {
+        sim.good_types.iter().filter_map(move |(good_id, _)| {
+            let market_good = &lacking.market.goods[good_id];
+            (market_good.demand_base > 0. && market_good.satisfaction < LACKING_SATISFACTION)
+                .then_some((lacking, good_id))
+        })
+    })
+}
+
+// Outputs are sold where they are lacking, inputs bought where the building is
+fn building_profit(typ: &TokenType, site: &LocationData, lacking: &LocationData) -> f64 {
+    let income: f64 = typ
+        .supply
+        .iter()
+        .map(|(id, amt)| amt * lacking.market.goods[id].price)
+        .sum();
+    let expenses: f64 = typ
+        .demand
+        .iter()
+        .map(|(id, amt)| amt * site.market.goods[id].price)
+        .sum();
+    income - expenses
+}
+
+// Buildings putting up travellers earn the fees of those seen on the last day
+fn hospitality_profits(
+    sim: &Simulation,
+    location: LocationId,
+) -> impl Iterator<Item = (TokenTypeId, f64)> + '_ {
+    let site = &sim.locations[location];
+    let travellers = sim.hospitality.travellers(location);
+    sim.tokens
+        .types
+        .iter()
+        .filter(|(_, typ)| typ.construction.is_some() && typ.hospitality > 0.)
+        .map(move |(id, typ)| {
+            let expenses: f64 = typ
+                .demand
+                .iter()
+                .map(|(good, amt)| amt * site.market.goods[good].price)
+                .sum();
+            (id, travellers as f64 * typ.hospitality - expenses)
+        })
+}
+
+/// The most profitable building that can be raised at a location, judged as
+/// the building suggestions are
+pub(crate) fn best_building(sim: &Simulation, location: LocationId) -> Option<(TokenTypeId, f64)> {
+    let site = sim.locations.get(location)?;
+    lacking_goods(sim)
+        .flat_map(|(lacking, good_id)| {
+            sim.tokens
+                .types
+                .iter()
+                .filter(move |(_, typ)| {
+                    typ.category == TokenCategory::Building
+                        && typ.construction.is_some()
+                        && typ.supply.get(good_id).is_some_and(|&amt| amt > 0.)
+                })
+                .map(move |(id, typ)| (id, building_profit(typ, site, lacking)))
+        })
+        .chain(hospitality_profits(sim, location))
+        .filter(|&(_, profit)| profit > 0.)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
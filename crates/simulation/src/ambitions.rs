@@ -0,0 +1,334 @@
+use rand::Rng;
+use slotmap::SecondaryMap;
+
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+use crate::sites::SiteId;
+
+// Daily chance for a character without an ambition to take one up
+const AMBITION_CHANCE: f64 = 1. / 30.;
+// Days a character pursues an ambition before giving up on it
+const AMBITION_PATIENCE: u64 = 360;
+// Cash a character must hold to be satisfied with their wealth
+const FORTUNE: f64 = 2_000.;
+// Cash spent by a character to found a settlement
+const FOUNDING_COST: f64 = 1_000.;
+// Chance of unseating a lord with as much cash as the schemer
+const OUST_CHANCE: f64 = 0.3;
+// Share of their cash schemers lose when their plot is exposed
+const EXPOSURE_FINE: f64 = 0.5;
+// Share of the market income of a location paid daily to its lord
+const LORD_TITHE: f64 = 0.001;
+
+/// What a character strives for, pursued through their behavior
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Ambition {
+    /// Become lord of a settlement of their faction, by grant or by plot
+    Title { location: LocationId },
+    /// Peddle between settlements until rich
+    Wealth,
+    /// Settle an empty site in the name of their faction
+    Settlement { site: SiteId },
+}
+
+impl Ambition {
+    pub fn describe(&self, sim: &Simulation) -> String {
+        match *self {
+            Self::Title { location } => match sim.locations.get(location) {
+                Some(location) => format!("the lordship of {}", sim.entities[location.entity].name),
+                None => "a lordship".to_string(),
+            },
+            Self::Wealth => "wealth".to_string(),
+            Self::Settlement { .. } => "founding a settlement".to_string(),
+        }
+    }
+}
+
+/// Lordships held by characters
+#[derive(Default, Clone)]
+pub(crate) struct Ambitions {
+    lords: SecondaryMap<LocationId, AgentId>,
+}
+
+impl Ambitions {
+    pub fn lord_of(&self, agents: &Agents, location: LocationId) -> Option<AgentId> {
+        self.lords
+            .get(location)
+            .copied()
+            .filter(|&agent| agents.entries.contains_key(agent))
+    }
+
+    /// Hands the lordships of a character over to their heir
+    pub fn pass_titles(&mut self, from: AgentId, to: AgentId) {
+        for lord in self.lords.values_mut() {
+            if *lord == from {
+                *lord = to;
+            }
+        }
+    }
+
+    pub fn titles_of(&self, agent: AgentId) -> impl Iterator<Item = LocationId> + '_ {
+        self.lords
+            .iter()
+            .filter(move |&(_, &lord)| lord == agent)
+            .map(|(location, _)| location)
+    }
+}
+
+/// A character arrived where their ambition is to be fulfilled
+#[derive(Clone, Copy)]
+pub(crate) struct Event {
+    pub entity: EntityId,
+    pub ambition: Ambition,
+}
+
+/// A settlement to be founded by a character
+pub(crate) struct Foundation {
+    pub founder: EntityId,
+    pub site: SiteId,
+}
+
+/// Daily upkeep: lords collect their tithe, characters take up, fulfill or
+/// give up on their ambitions
+pub(crate) fn tick(sim: &mut Simulation) {
+    for (location, &lord) in sim.ambitions.lords.iter() {
+        if let Some(lord) = sim.agents.entries.get_mut(lord)
+            && let Some(location) = sim.locations.get(location)
+        {
+            lord.cash += location.market.income.max(0.) * LORD_TITHE;
+        }
+    }
+
+    let characters: Vec<_> = sim
+        .beahviors
+        .iter()
+        .filter_map(|(id, behavior)| {
+            let agent = sim.entities[behavior.entity].agent?;
+            // Children wait until they come of age
+            (sim.agents[agent].flags.get(AgentFlag::IsCharacter)
+                && !sim.characters.is_minor(sim.date, agent))
+            .then_some((id, agent))
+        })
+        .collect();
+
+    for (id, agent) in characters {
+        let entity = sim.beahviors[id].entity;
+        match sim.beahviors[id].goal {
+            Goal::Idle => {
+                if !sim.rng.0.gen_bool(AMBITION_CHANCE) {
+                    continue;
+                }
+                if let Some(ambition) = choose_ambition(sim, agent) {
+                    let text = format!(
+                        "{} set their sights on {}",
+                        sim.entities[entity].name,
+                        ambition.describe(sim)
+                    );
+                    sim.record_chronicle(entity, text);
+                    let behavior = &mut sim.beahviors[id];
+                    behavior.goal = Goal::Ambition {
+                        ambition,
+                        since: sim.date,
+                    };
+                    behavior.plan.clear();
+                }
+            }
+            Goal::Ambition { ambition, since } => {
+                if ambition == Ambition::Wealth && sim.agents[agent].cash >= FORTUNE {
+                    let text = format!("{} made their fortune", sim.entities[entity].name);
+                    sim.record_chronicle(entity, text);
+                    settle_down(sim, id);
+                } else if !is_feasible(sim, agent, ambition)
+                    || since.add_days(AMBITION_PATIENCE) <= sim.date
+                {
+                    let text = format!(
+                        "{} gave up on {}",
+                        sim.entities[entity].name,
+                        ambition.describe(sim)
+                    );
+                    sim.record_chronicle(entity, text);
+                    settle_down(sim, id);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn settle_down(sim: &mut Simulation, id: BehaviorId) {
+    let behavior = &mut sim.beahviors[id];
+    behavior.goal = Goal::Idle;
+    behavior.plan.clear();
+    // Stop wherever the ambition led them
+    if let Some(party) = sim.entities[behavior.entity].party {
+        sim.parties[party].movement.target = None;
+    }
+}
+
+fn is_feasible(sim: &Simulation, agent: AgentId, ambition: Ambition) -> bool {
+    match ambition {
+        Ambition::Title { location } => {
+            sim.locations.contains_key(location)
+                && sim.ambitions.lord_of(&sim.agents, location) != Some(agent)
+        }
+        Ambition::Wealth => true,
+        Ambition::Settlement { site } => {
+            is_free(sim, site) && sim.agents[agent].cash >= FOUNDING_COST
+        }
+    }
+}
+
+// Unsettled, and not crowding a settlement next door
+fn is_free(sim: &Simulation, site: SiteId) -> bool {
+    sim.sites[site].location.is_none()
+        && sim
+            .sites
+            .neighbours(site)
+            .iter()
+            .all(|&(neighbour, _, _)| sim.sites[neighbour].location.is_none())
+}
+
+fn choose_ambition(sim: &mut Simulation, agent: AgentId) -> Option<Ambition> {
+    let personality = personality_of(&sim.agents, agent);
+    let faction = faction_of(&sim.agents, agent);
+    let party = sim.entities[sim.agents[agent].entity].party?;
+    let here = sim.parties[party].position.closest_endpoint();
+
+    // Settlements of the faction not already held by the character, the
+    // unclaimed ones first
+    let mut titles: Vec<_> = sim
+        .locations
+        .iter()
+        .filter(|&(_, location)| {
+            let owner = sim.entities[location.entity].agent;
+            owner.and_then(|owner| faction_of(&sim.agents, owner)) == faction
+        })
+        .map(|(id, _)| (id, sim.ambitions.lord_of(&sim.agents, id)))
+        .filter(|&(_, lord)| lord != Some(agent))
+        .collect();
+    if titles.iter().any(|(_, lord)| lord.is_none()) {
+        titles.retain(|(_, lord)| lord.is_none());
+    }
+
+    let wheat = sim.tags.goods.lookup("wheat");
+    let site = sim
+        .sites
+        .iter()
+        .filter(|&(id, site)| {
+            is_free(sim, id) && wheat.is_some_and(|good| site.rgo.rate_of(good) > 0.)
+        })
+        .map(|(id, _)| id)
+        .min_by(|&a, &b| {
+            let a = sim.sites.distance(here, a);
+            let b = sim.sites.distance(here, b);
+            a.total_cmp(&b)
+        });
+
+    let cash = sim.agents[agent].cash;
+    let weights = [
+        if titles.is_empty() {
+            0.
+        } else {
+            personality.aggressiveness * sim.characters.courage(agent)
+        },
+        if cash < FORTUNE {
+            personality.trade_focus * sim.characters.greed(agent)
+        } else {
+            0.
+        },
+        if site.is_none() || cash < FOUNDING_COST {
+            0.
+        } else {
+            personality.expansion_appetite * 0.5
+        },
+    ];
+    let total: f64 = weights.iter().sum();
+    if total <= 0. {
+        return None;
+    }
+
+    let mut roll = sim.rng.0.gen_range(0. ..total);
+    let choice = weights.iter().position(|&weight| {
+        roll -= weight;
+        roll < 0.
+    })?;
+    Some(match choice {
+        0 => {
+            let idx = sim.rng.0.gen_range(0..titles.len());
+            Ambition::Title {
+                location: titles[idx].0,
+            }
+        }
+        2 => Ambition::Settlement { site: site? },
+        _ => Ambition::Wealth,
+    })
+}
+
+/// Acts on the ambitions of characters that reached their goal. Returns the
+/// settlements to be founded.
+pub(crate) fn resolve(sim: &mut Simulation, events: Vec<Event>) -> Vec<Foundation> {
+    let mut foundations = vec![];
+    for event in events {
+        let Some(agent) = sim
+            .entities
+            .get(event.entity)
+            .and_then(|entity| entity.agent)
+        else {
+            continue;
+        };
+        match event.ambition {
+            Ambition::Title { location } => claim_title(sim, event.entity, agent, location),
+            Ambition::Settlement { site } => {
+                if is_free(sim, site) && sim.agents[agent].cash >= FOUNDING_COST {
+                    sim.agents[agent].cash -= FOUNDING_COST;
+                    foundations.push(Foundation {
+                        founder: event.entity,
+                        site,
+                    });
+                }
+            }
+            Ambition::Wealth => continue,
+        }
+
+        if let Some(behavior) = sim.entities[event.entity].behavior {
+            settle_down(sim, behavior);
+        }
+    }
+    foundations
+}
+
+fn claim_title(sim: &mut Simulation, entity: EntityId, agent: AgentId, location: LocationId) {
+    let Some(location_data) = sim.locations.get(location) else {
+        return;
+    };
+    let schemer = sim.entities[entity].name.clone();
+    let settlement = sim.entities[location_data.entity].name.clone();
+
+    let Some(lord) = sim.ambitions.lord_of(&sim.agents, location) else {
+        sim.ambitions.lords.insert(location, agent);
+        let text = format!("{schemer} was made lord of {settlement}");
+        sim.record_chronicle(entity, text);
+        return;
+    };
+    if lord == agent {
+        return;
+    }
+
+    // Cash buys followers, on both sides of the plot
+    let cash = sim.agents[agent].cash;
+    let lord_cash = sim.agents[lord].cash;
+    let share = if cash + lord_cash > 0. {
+        cash / (cash + lord_cash)
+    } else {
+        0.5
+    };
+    let lord_name = sim.entities[sim.agents[lord].entity].name.clone();
+    let text = if sim.rng.0.gen_bool((OUST_CHANCE * 2. * share).clamp(0., 1.)) {
+        sim.ambitions.lords.insert(location, agent);
+        format!("{schemer} ousted {lord_name} as lord of {settlement}")
+    } else {
+        sim.agents[agent].cash *= 1. - EXPOSURE_FINE;
+        format!("the plot of {schemer} against {lord_name}, lord of {settlement}, was exposed")
+    };
+    sim.record_chronicle(entity, text);
+}
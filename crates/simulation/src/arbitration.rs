@@ -0,0 +1,282 @@
+use crate::combat::{faction_of_party, hostile, refuge, strength};
+use crate::date::Date;
+use crate::migration::Migration;
+use crate::pace::Pace;
+use crate::simulation::*;
+use crate::sites::{InfluenceKind, SiteId};
+use crate::tick::BASE_SPEED;
+
+// How much better another goal must score before a party turns to it
+const SWITCH_MARGIN: f64 = 0.2;
+// Cash at which a party is half as keen to go on with its errand
+const COMFORTABLE_CASH: f64 = 200.;
+// Appeal of trading with no spread to trade on at all, as a share of that of
+// the best trade
+const TRADE_FLOOR: f64 = 0.5;
+// Spread between two markets, as a share of the price at the cheaper, that
+// makes the trip between them as good as it gets
+const FULL_SPREAD: f64 = 0.5;
+// Days of travel that halve the appeal of a market
+const HALF_APPEAL_DAYS: f64 = 10.;
+// Resolve of migrants and rebels to see their errand through
+const RESOLVE: f64 = 0.6;
+// Pull of home on a party with money enough
+const HOMESICKNESS: f64 = 0.25;
+// Pull of home that a party without any money left feels besides
+const HARDSHIP: f64 = 0.55;
+// How far off parties reckon with hostile parties
+const DANGER_RANGE: f32 = 6.;
+
+/// Daily reckoning: each party with an errand weighs going on with it against
+/// going home and running from the enemies about, and turns to whichever
+/// suits its situation clearly better than what it does
+pub(crate) fn tick(sim: &mut Simulation) {
+    let switches: Vec<(BehaviorId, Goal)> = sim
+        .beahviors
+        .iter()
+        .filter_map(|(id, behavior)| {
+            let party = sim.entities[behavior.entity].party?;
+            let danger = danger(sim, party);
+            let current = utility(sim, behavior, party, danger, &behavior.goal);
+            let (best, score) = candidates(sim, behavior, party)
+                .into_iter()
+                .map(|goal| {
+                    let score = utility(sim, behavior, party, danger, &goal);
+                    (goal, score)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            (best != behavior.goal && score > current + SWITCH_MARGIN).then_some((id, best))
+        })
+        .collect();
+
+    for (id, goal) in switches {
+        let entity = sim.beahviors[id].entity;
+        if let Some(text) = describe_switch(sim, entity, &sim.beahviors[id], &goal) {
+            sim.record_chronicle(entity, text);
+        }
+        let behavior = &mut sim.beahviors[id];
+        let resumed = goal == behavior.vocation;
+        behavior.goal = goal;
+        behavior.plan.clear();
+        // Back at its errand, a party sees to the order it took up first
+        if resumed
+            && let Some(contract) = behavior.contract
+            && let Some(delivery) = crate::contracts::delivery(sim, contract)
+        {
+            sim.beahviors[id].plan.push_back(delivery);
+        }
+    }
+}
+
+// The goals a party could turn to. Characters and the parties of the player
+// have goals of their own making, and those that cannot pay their way go
+// where their upkeep sends them, so they weigh none.
+fn candidates(sim: &Simulation, behavior: &Behavior, party: PartyId) -> Vec<Goal> {
+    if matches!(behavior.vocation, Goal::Idle | Goal::Ambition { .. }) || sim.upkeep.stranded(party)
+    {
+        return vec![];
+    }
+    let mut candidates = vec![behavior.vocation.clone()];
+    candidates.extend(homecoming(sim, &behavior.vocation));
+    candidates.extend(refuge(sim, party).map(|refuge| Goal::Flee { refuge }));
+    candidates
+}
+
+// Going back where the party came from
+fn homecoming(sim: &Simulation, vocation: &Goal) -> Option<Goal> {
+    match *vocation {
+        Goal::LocalTrade { base }
+        | Goal::Smuggle { base }
+        | Goal::ArbitrageTrade { base }
+        | Goal::Rebel { base } => sim
+            .parties
+            .contains_key(base)
+            .then_some(Goal::ReturnHome { base }),
+        // Migrants take their pops back home with them
+        Goal::Migrate(migration) => Some(Goal::Migrate(Migration {
+            origin: migration.destination,
+            destination: migration.origin,
+            ..migration
+        })),
+        _ => None,
+    }
+}
+
+// How much a goal suits a party, from nothing to all it could want. Running
+// away is worth as much as the danger the party is in, and anything else that
+// much less.
+fn utility(sim: &Simulation, behavior: &Behavior, party: PartyId, danger: f64, goal: &Goal) -> f64 {
+    let data = &sim.parties[party];
+    let appeal = match *goal {
+        Goal::Flee { .. } => return danger,
+        Goal::LocalTrade { .. }
+        | Goal::Smuggle { .. }
+        | Goal::ArbitrageTrade { .. }
+        | Goal::RouteTrade { .. } => {
+            let appeal = TRADE_FLOOR + (1. - TRADE_FLOOR) * trade_appeal(sim, party, goal);
+            solvency(sim, behavior, data) * appeal
+        }
+        Goal::Rebel { .. } => RESOLVE,
+        Goal::Migrate(migration) if Goal::Migrate(migration) == behavior.vocation => {
+            if sim.locations.contains_key(migration.destination) {
+                RESOLVE
+            } else {
+                0.
+            }
+        }
+        // Migrants heading back home
+        Goal::Migrate(migration) => {
+            if sim.locations.contains_key(migration.destination) {
+                HOMESICKNESS
+            } else {
+                0.
+            }
+        }
+        Goal::ReturnHome { .. } => HOMESICKNESS + HARDSHIP * (1. - solvency(sim, behavior, data)),
+        Goal::Idle | Goal::Ambition { .. } => 0.,
+    };
+    (1. - danger) * appeal
+}
+
+// How well off a party is, from broke to at ease, counting its cash and the
+// goods it carries at what they fetch at home
+fn solvency(sim: &Simulation, behavior: &Behavior, party: &PartyData) -> f64 {
+    let cash = sim.entities[party.entity]
+        .agent
+        .map_or(0., |agent| sim.agents[agent].cash.max(0.));
+    let home = match behavior.vocation {
+        Goal::LocalTrade { base }
+        | Goal::Smuggle { base }
+        | Goal::ArbitrageTrade { base }
+        | Goal::Rebel { base } => sim.parties.get(base).and_then(|data| data.location),
+        Goal::RouteTrade { route } => sim.routes.routes.get(route).map(|route| route.source),
+        _ => None,
+    };
+    let cargo = home
+        .and_then(|home| sim.locations.get(home))
+        .map_or(0., |home| {
+            party
+                .good_stock
+                .amount
+                .iter()
+                .map(|(good, &amount)| amount * home.market.goods[good].price)
+                .sum()
+        });
+    let worth = cash + cargo;
+    worth / (worth + COMFORTABLE_CASH)
+}
+
+// How good the best trade open to a trading goal is, by the spread between
+// the markets it trades between and the length of the trip
+fn trade_appeal(sim: &Simulation, party: PartyId, goal: &Goal) -> f64 {
+    let location_of = |party: PartyId| sim.parties.get(party).and_then(|data| data.location);
+    let trips: Vec<(LocationId, LocationId)> = match *goal {
+        Goal::LocalTrade { base } | Goal::Smuggle { base } => {
+            let market = sim
+                .parties
+                .get(base)
+                .and_then(|data| data.position.as_site())
+                .and_then(|site| sim.sites[site].influences.top_source(InfluenceKind::Market))
+                .and_then(location_of);
+            location_of(base).zip(market).into_iter().collect()
+        }
+        Goal::ArbitrageTrade { base } => location_of(base)
+            .into_iter()
+            .flat_map(|home| sim.locations.keys().map(move |market| (home, market)))
+            .collect(),
+        Goal::RouteTrade { route } => sim
+            .routes
+            .routes
+            .get(route)
+            .map(|route| {
+                vec![
+                    (route.source, route.destination),
+                    (route.destination, route.source),
+                ]
+            })
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+    let speed = Pace::of(sim).cruising_speed(party);
+    trips
+        .into_iter()
+        .filter(|(home, market)| home != market)
+        .map(|(home, market)| {
+            let (home, market) = (&sim.locations[home], &sim.locations[market]);
+            let days = days_between(sim, home.site, market.site, speed);
+            let spread = sim
+                .good_types
+                .iter()
+                .map(|(good, _)| {
+                    let bought = home.market.goods[good].price;
+                    let sold = market.market.goods[good].price;
+                    if bought > 0. {
+                        (sold - bought) / bought
+                    } else {
+                        0.
+                    }
+                })
+                .fold(0., f64::max);
+            (spread / FULL_SPREAD).min(1.) / (1. + days / HALF_APPEAL_DAYS)
+        })
+        .fold(0., f64::max)
+}
+
+// Days a party takes to cover the distance between two sites as the crow
+// flies, at the pace it keeps with its group
+fn days_between(sim: &Simulation, a: SiteId, b: SiteId, speed: f32) -> f64 {
+    if speed <= 0. {
+        return f64::INFINITY;
+    }
+    let distance = sim.sites[a].pos.distance(sim.sites[b].pos);
+    let ticks = distance / (speed * BASE_SPEED);
+    ticks as f64 / Date::TICKS_IN_DAY as f64
+}
+
+// How much a party stands to fear from the hostile parties about, from
+// nothing to being overwhelmed. Closer enemies count for more.
+fn danger(sim: &Simulation, party: PartyId) -> f64 {
+    let party = &sim.parties[party];
+    let faction = faction_of_party(sim, party);
+    let threat: f64 = sim
+        .parties_near(party.pos, DANGER_RANGE)
+        .map(|other| &sim.parties[other])
+        .filter(|other| other.location.is_none() && other.movement_speed > 0.)
+        .filter(|other| hostile(sim, faction, faction_of_party(sim, other)))
+        .filter_map(|other| {
+            let distance = other.pos.distance(party.pos);
+            let closeness = 1. - distance / DANGER_RANGE;
+            (closeness > 0.).then(|| strength(sim, other) * closeness as f64)
+        })
+        .sum();
+    if threat > 0. {
+        threat / (threat + strength(sim, party))
+    } else {
+        0.
+    }
+}
+
+// What the chronicle makes of a party turning to another goal
+fn describe_switch(
+    sim: &Simulation,
+    entity: EntityId,
+    behavior: &Behavior,
+    goal: &Goal,
+) -> Option<String> {
+    let name = &sim.entities[entity].name;
+    let name_of = |party: PartyId| {
+        sim.parties
+            .get(party)
+            .map(|data| sim.entities[data.entity].name.as_str())
+    };
+    Some(match *goal {
+        _ if *goal == behavior.vocation => format!("{name} took to the roads again"),
+        Goal::Flee { refuge } => format!("{name} fled to {}", name_of(refuge)?),
+        Goal::ReturnHome { base } => format!("{name} turned back for {}", name_of(base)?),
+        Goal::Migrate(migration) => format!(
+            "{name} turned back for {}",
+            sim.entities[sim.locations.get(migration.destination)?.entity].name
+        ),
+        _ => return None,
+    })
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+
+// Below this food satisfaction, a managed settlement sends for food
+const RELIEF_SATISFACTION: f64 = 0.8;
+// A settlement must be this well fed to spare food for another
+const DONOR_SATISFACTION: f64 = 0.95;
+// Days of demand asked for in a relief shipment
+const RELIEF_DAYS: f64 = 30.;
+// Most of its stock a donor parts with at once
+const MAX_DONATION_SHARE: f64 = 0.5;
+// Treasuries keep this many times the cash of a building in reserve
+const BUILDING_RESERVE: f64 = 2.;
+
+/// Settlements left to manage themselves, rather than awaiting orders
+#[derive(Default, Clone)]
+pub(crate) struct Autonomy {
+    managed: BTreeSet<LocationId>,
+}
+
+impl Autonomy {
+    pub fn is_managed(&self, location: LocationId) -> bool {
+        self.managed.contains(&location)
+    }
+
+    pub fn set_managed(&mut self, location: LocationId, managed: bool) {
+        if managed {
+            self.managed.insert(location);
+        } else {
+            self.managed.remove(&location);
+        }
+    }
+}
+
+/// Once a month, managed settlements raise the building the advisor rates the
+/// highest, and send for food from their realm when going hungry
+pub(crate) fn tick(sim: &mut Simulation) {
+    if !sim.date.is_new_month() {
+        return;
+    }
+    sim.autonomy
+        .managed
+        .retain(|&location| sim.locations.contains_key(location));
+
+    let managed: Vec<_> = sim.autonomy.managed.iter().copied().collect();
+    for location in managed {
+        build(sim, location);
+        send_for_food(sim, location);
+    }
+}
+
+pub(crate) fn treasury_of(sim: &Simulation, location: LocationId) -> Option<AgentId> {
+    let owner = sim.entities[sim.locations[location].entity].agent?;
+    faction_of(&sim.agents, owner)
+}
+
+fn build(sim: &mut Simulation, location: LocationId) {
+    if sim.constructions.at(location).next().is_some() {
+        return;
+    }
+    let Some((typ, _)) = crate::advisor::best_building(sim, location) else {
+        return;
+    };
+    let Some(treasury) = treasury_of(sim, location) else {
+        return;
+    };
+    let cash = sim.tokens.types[typ]
+        .construction
+        .as_ref()
+        .map_or(0., |cost| cost.cash);
+    if sim.agents[treasury].cash < cash * BUILDING_RESERVE {
+        return;
+    }
+
+    crate::construction::start(sim, location, typ);
+    let entity = sim.locations[location].entity;
+    let text = format!(
+        "{} began raising a {}",
+        sim.entities[entity].name,
+        sim.tokens.types[typ].name.to_lowercase()
+    );
+    sim.record_chronicle(entity, text);
+}
+
+fn send_for_food(sim: &mut Simulation, location: LocationId) {
+    let hungry = &sim.locations[location];
+    if hungry.market.food_satisfaction() >= RELIEF_SATISFACTION {
+        return;
+    }
+    let faction = treasury_of(sim, location);
+
+    // The best stocked food at the best fed settlement of the realm
+    let donation = sim
+        .locations
+        .iter()
+        .filter(|&(id, donor)| {
+            id != location
+                && faction.is_some()
+                && treasury_of(sim, id) == faction
+                && donor.market.food_satisfaction() >= DONOR_SATISFACTION
+        })
+        .flat_map(|(id, donor)| {
+            sim.good_types
+                .iter()
+                .filter(|(_, good)| good.food_rate > 0.)
+                .map(move |(good, _)| (id, good, donor.market.goods[good].stock))
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2));
+    let Some((donor, good, stock)) = donation else {
+        return;
+    };
+
+    let wanted = hungry.market.goods[good].demand_base * RELIEF_DAYS;
+    let amount = wanted.min(stock * MAX_DONATION_SHARE);
+    if amount > 0. {
+        crate::famine::ship(sim, donor, location, good, amount);
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+use crate::date::Date;
+use crate::simulation::{Checkpoint, Simulation};
+
+/// Checkpoints taken at regular intervals of simulated time. They are spaced
+/// by the date of the simulation rather than by how often it is ticked, so
+/// running it faster or slower does not change when they are taken.
+pub struct Autosaves {
+    interval: u64,
+    capacity: usize,
+    // Oldest first
+    slots: VecDeque<Checkpoint>,
+}
+
+impl Autosaves {
+    /// Keeps up to `capacity` checkpoints, taken `interval_days` apart
+    pub fn new(interval_days: u64, capacity: usize) -> Self {
+        Self {
+            interval: Date::TICKS_IN_DAY * interval_days.max(1),
+            capacity: capacity.max(1),
+            slots: VecDeque::new(),
+        }
+    }
+
+    /// Takes a checkpoint once the interval has passed since the last one.
+    /// After a rollback, the checkpoints from past the current date are dropped.
+    pub fn update(&mut self, sim: &Simulation) {
+        let now = sim.date.epoch();
+        while self.slots.back().is_some_and(|slot| slot.epoch() > now) {
+            self.slots.pop_back();
+        }
+        let due = self
+            .slots
+            .back()
+            .is_none_or(|slot| now >= slot.epoch() + self.interval);
+        if !due {
+            return;
+        }
+        if self.slots.len() == self.capacity {
+            self.slots.pop_front();
+        }
+        self.slots.push_back(sim.checkpoint());
+    }
+
+    pub fn latest(&self) -> Option<&Checkpoint> {
+        self.slots.back()
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Checkpoint> {
+        self.slots.iter()
+    }
+}
@@ -0,0 +1,273 @@
+use std::ops::RangeInclusive;
+
+use rand::{Rng, RngCore};
+use slotmap::SecondaryMap;
+use util::arena::Arena;
+use util::hierarchy::Hierarchy;
+
+use crate::date::Date;
+use crate::scenario::NameGenerator;
+use crate::simulation::*;
+use crate::sites::SiteId;
+
+// Age at which characters come of age, and take up ambitions of their own
+const ADULTHOOD: u64 = 16;
+// Ages at which characters have children
+const CHILDBEARING: RangeInclusive<u64> = 18..=45;
+// Yearly chance for a character of an age to have children to have one
+const BIRTH_CHANCE: f64 = 0.15;
+// Children a character has at most
+const MAX_CHILDREN: usize = 3;
+// Yearly chance of dying at thirty, which doubles every few years after
+const MORTALITY_AT_THIRTY: f64 = 0.005;
+const MORTALITY_DOUBLING_YEARS: f64 = 8.;
+// Ages of the characters there from the start
+const FOUNDER_AGES: RangeInclusive<u64> = 20..=40;
+// Most skill the characters there from the start have
+const FOUNDER_SKILL: f64 = 0.5;
+// Chance of having each trait, and of taking after a parent who has it
+const TRAIT_CHANCE: f64 = 0.3;
+const INHERITED_TRAIT_CHANCE: f64 = 0.5;
+// Skill gained with each market traded at, and with each day on the road
+const TRADE_PRACTICE: f64 = 0.01;
+const TRAVEL_PRACTICE: f64 = 1. / 720.;
+// Share more a master trader gets for their sales
+const TRADE_MASTERY: f64 = 0.1;
+// Share faster a master wayfarer travels
+const TRAVEL_MASTERY: f64 = 0.15;
+// Times more of their cash greedy characters spend on goods, and times
+// keener they are on wealth
+const GREED: f64 = 1.25;
+// Times their own strength brave characters reckon themselves, and times
+// keener they are on titles
+const BRAVERY: f64 = 1.5;
+
+/// What a character is like, from birth
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Trait {
+    /// Spends more on goods, and sets their sights on wealth
+    Greedy,
+    /// Stands up to stronger enemies, and sets their sights on titles
+    Brave,
+}
+
+impl Trait {
+    const ALL: [Self; 2] = [Self::Greedy, Self::Brave];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Greedy => "greedy",
+            Self::Brave => "brave",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Character {
+    pub born: Date,
+    pub traits: Vec<Trait>,
+    /// How well they drive a bargain, from a novice to a master
+    pub trade: f64,
+    /// How well they find their way and keep the pace
+    pub travel: f64,
+}
+
+impl Character {
+    pub fn has(&self, trait_: Trait) -> bool {
+        self.traits.contains(&trait_)
+    }
+}
+
+/// The people among the agents, with what they are like and their families
+#[derive(Default, Clone)]
+pub(crate) struct Characters {
+    entries: SecondaryMap<AgentId, Character>,
+    // Parents to their children
+    family: Hierarchy<AgentId, AgentId>,
+}
+
+impl Characters {
+    pub fn get(&self, agent: AgentId) -> Option<&Character> {
+        self.entries.get(agent)
+    }
+
+    pub fn age(&self, date: Date, agent: AgentId) -> Option<u64> {
+        self.get(agent)
+            .map(|character| date.years_since(character.born))
+    }
+
+    pub fn is_minor(&self, date: Date, agent: AgentId) -> bool {
+        self.age(date, agent).is_some_and(|age| age < ADULTHOOD)
+    }
+
+    pub fn parent(&self, agent: AgentId) -> Option<AgentId> {
+        self.family.parent(agent)
+    }
+
+    pub fn children(&self, agent: AgentId) -> impl Iterator<Item = AgentId> + '_ {
+        self.family.children(agent)
+    }
+
+    /// Multiplier of what an agent gets for their sales
+    pub fn bargaining(&self, agent: AgentId) -> f64 {
+        self.get(agent)
+            .map_or(1., |character| 1. + TRADE_MASTERY * character.trade)
+    }
+
+    /// Multiplier of the speed of the party of an agent, for characters only
+    pub fn wayfaring(&self, agent: AgentId) -> Option<f32> {
+        self.get(agent)
+            .map(|character| (1. + TRAVEL_MASTERY * character.travel) as f32)
+    }
+
+    /// Multiplier of the share of their cash an agent spends on goods
+    pub fn greed(&self, agent: AgentId) -> f64 {
+        match self.get(agent) {
+            Some(character) if character.has(Trait::Greedy) => GREED,
+            _ => 1.,
+        }
+    }
+
+    /// Multiplier of the strength an agent reckons themselves to have
+    pub fn courage(&self, agent: AgentId) -> f64 {
+        match self.get(agent) {
+            Some(character) if character.has(Trait::Brave) => BRAVERY,
+            _ => 1.,
+        }
+    }
+
+    pub fn practise_trade(&mut self, agent: AgentId) {
+        if let Some(character) = self.entries.get_mut(agent) {
+            character.trade = (character.trade + TRADE_PRACTICE).min(1.);
+        }
+    }
+
+    pub fn forget(&mut self, arena: &Arena, agent: AgentId) {
+        self.entries.remove(agent);
+        self.family.remove_child(agent);
+        self.family.remove_parents(arena, &[agent]);
+    }
+}
+
+/// A child born to a character where they stay
+pub(crate) struct Birth {
+    pub parent: AgentId,
+    pub name: String,
+    pub site: SiteId,
+}
+
+/// Gives a newly created character their age, traits and skills. Children
+/// are newborns that may take after their parent, the others are grown up.
+pub(crate) fn register(sim: &mut Simulation, agent: AgentId, parent: Option<AgentId>) {
+    let rng = &mut sim.rng.0;
+    let parent_data = parent.and_then(|parent| sim.characters.entries.get(parent));
+    let traits = Trait::ALL
+        .into_iter()
+        .filter(|&trait_| {
+            let chance = match parent_data {
+                Some(parent) if parent.has(trait_) => INHERITED_TRAIT_CHANCE,
+                _ => TRAIT_CHANCE,
+            };
+            rng.gen_bool(chance)
+        })
+        .collect();
+    let character = match parent_data {
+        Some(_) => Character {
+            born: sim.date,
+            traits,
+            trade: 0.,
+            travel: 0.,
+        },
+        None => Character {
+            born: sim.date.years_before(rng.gen_range(FOUNDER_AGES)),
+            traits,
+            trade: rng.gen_range(0. ..FOUNDER_SKILL),
+            travel: rng.gen_range(0. ..FOUNDER_SKILL),
+        },
+    };
+    sim.characters.entries.insert(agent, character);
+    if let Some(parent) = parent.filter(|&parent| sim.characters.entries.contains_key(parent)) {
+        sim.characters.family.insert(parent, agent);
+    }
+}
+
+/// Daily: characters grow practised on the road, have children while staying
+/// in a settlement, and die, the more likely the older they are. Returns the
+/// entities of those who died and the children born.
+pub(crate) fn tick(sim: &mut Simulation) -> (Vec<EntityId>, Vec<Birth>) {
+    let days_in_year = (Date::TICKS_IN_YEAR / Date::TICKS_IN_DAY) as f64;
+    let mut deaths = vec![];
+    let mut births = vec![];
+    let agents: Vec<_> = sim.characters.entries.keys().collect();
+    for agent in agents {
+        let Some(agent_data) = sim.agents.entries.get(agent) else {
+            continue;
+        };
+        let entity = agent_data.entity;
+        let age = sim.date.years_since(sim.characters.entries[agent].born);
+        let mortality =
+            MORTALITY_AT_THIRTY * 2_f64.powf((age as f64 - 30.) / MORTALITY_DOUBLING_YEARS);
+        if sim.rng.0.gen_bool((mortality / days_in_year).min(1.)) {
+            deaths.push(entity);
+            continue;
+        }
+
+        let Some(party) = sim.entities[entity]
+            .party
+            .and_then(|party| sim.parties.get(party))
+        else {
+            continue;
+        };
+        if !party.movement.path.is_empty() {
+            let character = &mut sim.characters.entries[agent];
+            character.travel = (character.travel + TRAVEL_PRACTICE).min(1.);
+        }
+        let home = party
+            .position
+            .as_site()
+            .filter(|&site| sim.sites[site].location.is_some());
+        if let Some(site) = home
+            && CHILDBEARING.contains(&age)
+            && sim.characters.children(agent).count() < MAX_CHILDREN
+            && sim.rng.0.gen_bool(BIRTH_CHANCE / days_in_year)
+        {
+            // Children go by their own name and that of their parent
+            let given = NameGenerator::new(sim.rng.0.next_u64()).given_name();
+            let parent_name = &sim.entities[entity].name;
+            let parent_given = parent_name.split_whitespace().next().unwrap_or(parent_name);
+            births.push(Birth {
+                parent: agent,
+                name: format!("{given} ap {parent_given}"),
+                site,
+            });
+        }
+    }
+    (deaths, births)
+}
+
+/// Hands what a character leaves behind over to their eldest child, their
+/// purse and their lordships alike. Returns what the chronicle makes of it.
+pub(crate) fn pass_away(sim: &mut Simulation, agent: AgentId) -> String {
+    let name = &sim.entities[sim.agents[agent].entity].name;
+    let mut text = match sim.characters.age(sim.date, agent) {
+        Some(age) => format!("{name} died at the age of {age}"),
+        None => format!("{name} died"),
+    };
+    let heir = sim
+        .characters
+        .children(agent)
+        .filter_map(|child| Some((child, sim.characters.get(child)?.born)))
+        .filter(|&(child, _)| sim.agents.entries.contains_key(child))
+        .min_by_key(|&(_, born)| born)
+        .map(|(child, _)| child);
+    if let Some(heir) = heir {
+        text = format!(
+            "{text}, leaving all to {}",
+            sim.entities[sim.agents[heir].entity].name
+        );
+        let cash = std::mem::take(&mut sim.agents[agent].cash).max(0.);
+        sim.agents[heir].cash += cash;
+        sim.ambitions.pass_titles(agent, heir);
+    }
+    text
+}
@@ -0,0 +1,139 @@
+use crate::combat::BattleReport;
+use crate::date::Date;
+use crate::simulation::*;
+
+#[derive(Clone)]
+pub(crate) struct ChronicleEntry {
+    pub date: Date,
+    pub faction: Option<AgentId>,
+    // Entities whose history the entry is part of
+    pub entities: Vec<EntityId>,
+    // Only kept for the history of its entities, and left out of the chronicle
+    pub personal: bool,
+    // Rendered when recorded, since the subject may not outlive the entry
+    pub text: String,
+    // Report of the battle the entry tells of, if any
+    pub battle: Option<usize>,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Chronicle {
+    entries: Vec<ChronicleEntry>,
+    battles: Vec<BattleReport>,
+}
+
+impl Chronicle {
+    pub fn record(&mut self, date: Date, faction: Option<AgentId>, text: impl Into<String>) {
+        self.entries.push(ChronicleEntry {
+            date,
+            faction,
+            entities: vec![],
+            personal: false,
+            text: text.into(),
+            battle: None,
+        });
+    }
+
+    pub fn battle(&self, id: usize) -> Option<&BattleReport> {
+        self.battles.get(id)
+    }
+
+    /// Entries about the entity, oldest first
+    pub fn history_of(&self, entity: EntityId) -> impl DoubleEndedIterator<Item = &ChronicleEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.entities.contains(&entity))
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &ChronicleEntry> {
+        self.entries.iter()
+    }
+}
+
+// The faction an agent belongs to, or the agent itself if it is one
+pub(crate) fn faction_of(agents: &Agents, agent: AgentId) -> Option<AgentId> {
+    if agents[agent].flags.get(AgentFlag::IsFaction) {
+        return Some(agent);
+    }
+    query_related_agent(agents, agent, RelatedAgent::Faction).map(|(id, _)| id)
+}
+
+impl Simulation {
+    pub(crate) fn record_chronicle(&mut self, subject: EntityId, text: impl Into<String>) {
+        self.record_chronicle_about(&[subject], text);
+    }
+
+    /// Records an entry in the history of all the entities, and in the
+    /// chronicle of the faction of the first
+    pub(crate) fn record_chronicle_about(
+        &mut self,
+        entities: &[EntityId],
+        text: impl Into<String>,
+    ) {
+        self.push_entry(entities, false, text.into(), None);
+    }
+
+    /// Records a battle like `record_chronicle_about`, along with its report
+    pub(crate) fn record_battle(
+        &mut self,
+        entities: &[EntityId],
+        text: impl Into<String>,
+        report: BattleReport,
+    ) {
+        let battle = self.chronicle.battles.len();
+        self.chronicle.battles.push(report);
+        self.push_entry(entities, false, text.into(), Some(battle));
+    }
+
+    /// Records an event only worth remembering in the history of the subject
+    pub(crate) fn record_history(&mut self, subject: EntityId, text: impl Into<String>) {
+        self.push_entry(&[subject], true, text.into(), None);
+    }
+
+    fn push_entry(
+        &mut self,
+        entities: &[EntityId],
+        personal: bool,
+        text: String,
+        battle: Option<usize>,
+    ) {
+        let faction = entities
+            .first()
+            .and_then(|&subject| self.entities[subject].agent)
+            .and_then(|agent| faction_of(&self.agents, agent));
+        self.chronicle.entries.push(ChronicleEntry {
+            date: self.date,
+            faction,
+            entities: entities.to_vec(),
+            personal,
+            text,
+            battle,
+        });
+    }
+
+    /// Renders the chronicle as a readable timeline, either for the whole world
+    /// or only for the faction with the given tag.
+    pub fn export_chronicle(&self, faction: Option<&str>) -> String {
+        let faction = match faction {
+            Some(tag) => match self.tags.agents.lookup(tag) {
+                Some(id) => Some(id),
+                None => return String::new(),
+            },
+            None => None,
+        };
+
+        let mut out = String::new();
+        for entry in self.chronicle.iter() {
+            if entry.personal || (faction.is_some() && entry.faction != faction) {
+                continue;
+            }
+            out.push_str(&format!(
+                "In {} of year {}, {}.\n",
+                entry.date.season().name(),
+                entry.date.calendar_year(),
+                entry.text
+            ));
+        }
+        out
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::Rng;
+
+use crate::chronicle::faction_of;
+use crate::date::Date;
+use crate::object::*;
+use crate::simulation::*;
+use crate::stances::Reaction;
+use crate::tick::pos_of_grid_coordinate;
+use crate::tokens::{TokenCategory, TokenContainerId, TokenId};
+use crate::view::SimEvent;
+
+// Share of its troops the losing side loses against an enemy as strong, over
+// a whole battle
+const LOSER_CASUALTIES: f64 = 0.5;
+// Share of its troops the winning side loses against an enemy as strong, over
+// a whole battle
+const WINNER_CASUALTIES: f64 = 0.15;
+// Rounds a battle is fought over at most, before the side that lost the
+// larger share of its strength gives way
+const MAX_ROUNDS: usize = 3;
+// Share of its strength a side can lose before it breaks
+const BREAKING_LOSS: f64 = 0.5;
+// How many times stronger a garrison fights from behind its walls
+const WALLS_BONUS: f64 = 1.25;
+// How many times weaker a party fights when worn by a forced march
+const FORCED_MARCH_WEARINESS: f64 = 0.85;
+// Days a party that fled a battle is left alone to get away
+const RETREAT_DAYS: u64 = 3;
+
+/// Something that makes a side fight better or worse than its troops alone
+#[derive(Clone, Copy)]
+pub(crate) struct Modifier {
+    pub name: &'static str,
+    /// Multiplier of the strength of the side
+    pub factor: f64,
+}
+
+/// A side of a battle, as it went into it
+#[derive(Clone)]
+pub(crate) struct Side {
+    // Rendered when fought, since the party may not outlive the report
+    pub name: String,
+    pub modifiers: Vec<Modifier>,
+}
+
+/// How a round of a battle went, for the attacker and the defender
+#[derive(Clone, Copy)]
+pub(crate) struct Round {
+    /// Strength of each side going into the round, modifiers included
+    pub strengths: [f64; 2],
+    pub casualties: [i64; 2],
+}
+
+/// Round by round account of a battle, kept by the chronicle
+#[derive(Clone)]
+pub(crate) struct BattleReport {
+    pub date: Date,
+    /// The attacker, then the defender
+    pub sides: [Side; 2],
+    pub rounds: Vec<Round>,
+    pub attacker_won: bool,
+}
+
+/// The parties that fled a battle, each left alone until it is clear of it
+#[derive(Default, Clone)]
+pub(crate) struct Combat {
+    retreating: BTreeMap<PartyId, Date>,
+    // Settlements parties were ordered to take, assaulted once they get there
+    sieges: BTreeMap<PartyId, LocationId>,
+}
+
+impl Combat {
+    /// Has a party assault a settlement as soon as it stands there
+    pub fn besiege(&mut self, party: PartyId, location: LocationId) {
+        self.sieges.insert(party, location);
+    }
+
+    /// Calls off the siege a party was ordered to lay, if any
+    pub fn lift_siege(&mut self, party: PartyId) {
+        self.sieges.remove(&party);
+    }
+
+    pub fn forget_party(&mut self, party: PartyId) {
+        self.retreating.remove(&party);
+        self.sieges.remove(&party);
+    }
+}
+
+/// Fighting strength of a party, from its troops
+pub(crate) fn strength(sim: &Simulation, party: &PartyData) -> f64 {
+    party
+        .troops
+        .map_or(0., |troops| troops_strength(sim, troops))
+}
+
+/// Fighting strength of the garrison of a settlement, the troops among its
+/// tokens
+pub(crate) fn garrison_strength(sim: &Simulation, location: LocationId) -> f64 {
+    troops_strength(sim, sim.locations[location].tokens)
+}
+
+fn troops_strength(sim: &Simulation, troops: TokenContainerId) -> f64 {
+    sim.tokens
+        .all_tokens_of_category(troops, TokenCategory::Military)
+        .map(|token| token.data.size as f64 * token.typ.strength)
+        .sum()
+}
+
+/// Whether parties of two factions fight on meeting: factions that think
+/// little enough of either, and those answering to no faction with all
+pub(crate) fn hostile(sim: &Simulation, a: Option<AgentId>, b: Option<AgentId>) -> bool {
+    let hostile_opinion = crate::modifiers::HOSTILE_OPINION;
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a != b
+                && (sim.relations.opinion(a, b) <= hostile_opinion
+                    || sim.relations.opinion(b, a) <= hostile_opinion)
+        }
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+pub(crate) fn faction_of_party(sim: &Simulation, party: &PartyData) -> Option<AgentId> {
+    sim.entities[party.entity]
+        .agent
+        .and_then(|agent| faction_of(&sim.agents, agent))
+}
+
+// Settlements fight with their garrisons, not as parties, and escorted
+// parties are kept out of the way
+fn in_field(sim: &Simulation, id: PartyId, party: &PartyData) -> bool {
+    party.location.is_none() && party.movement_speed > 0. && sim.escorts.escort_of(id).is_none()
+}
+
+/// Fights a battle for each armed party in contact with a hostile one,
+/// neither having fought already this tick, and has those left standing in
+/// the hostile settlement they were ordered to besiege assault it. Returns
+/// the entities of the parties destroyed.
+pub(crate) fn tick(sim: &mut Simulation) -> Vec<EntityId> {
+    let date = sim.date;
+    sim.combat.retreating.retain(|_, until| date < *until);
+
+    let armed: Vec<PartyId> = sim
+        .parties
+        .iter()
+        .filter(|&(id, party)| in_field(sim, id, party) && strength(sim, party) > 0.)
+        .map(|(id, _)| id)
+        .collect();
+
+    let mut fought = BTreeSet::new();
+    let mut destroyed = vec![];
+    for attacker in armed {
+        if fought.contains(&attacker) || sim.combat.retreating.contains_key(&attacker) {
+            continue;
+        }
+        let attacker_data = &sim.parties[attacker];
+        let faction = faction_of_party(sim, attacker_data);
+        let defender = sim
+            .parties_near(attacker_data.pos, crate::interception::CATCH_RADIUS)
+            .map(|id| (id, &sim.parties[id]))
+            .filter(|&(id, party)| id != attacker && in_field(sim, id, party))
+            .filter(|&(id, _)| !fought.contains(&id) && !sim.combat.retreating.contains_key(&id))
+            .filter(|&(_, party)| crate::interception::in_contact(&sim.sites, attacker_data, party))
+            .find(|&(_, party)| hostile(sim, faction, faction_of_party(sim, party)))
+            .map(|(id, _)| id);
+        let Some(defender) = defender else {
+            continue;
+        };
+        fought.insert(attacker);
+        fought.insert(defender);
+        if let Some(loser) = battle(sim, attacker, defender) {
+            destroyed.push(sim.parties[loser].entity);
+        }
+    }
+
+    let besiegers: Vec<(PartyId, AgentId, LocationId)> = sim
+        .combat
+        .sieges
+        .iter()
+        .map(|(&id, &location)| (id, &sim.parties[id], location))
+        .filter(|&(id, party, _)| {
+            in_field(sim, id, party)
+                && !fought.contains(&id)
+                && !sim.combat.retreating.contains_key(&id)
+                && strength(sim, party) > 0.
+        })
+        // The settlement may have been abandoned on the way
+        .filter(|&(_, party, location)| {
+            sim.locations
+                .get(location)
+                .is_some_and(|data| party.position.as_site() == Some(data.site))
+        })
+        .filter_map(|(id, party, location)| {
+            let faction = faction_of_party(sim, party)?;
+            let holder = faction_of_party(sim, &sim.parties[sim.locations[location].party]);
+            hostile(sim, Some(faction), holder).then_some((id, faction, location))
+        })
+        .collect();
+    for (party, faction, location) in besiegers {
+        sim.combat.lift_siege(party);
+        match assault(sim, party, location) {
+            Assault::Taken => capture(sim, party, faction, location),
+            Assault::Repelled => retreat(sim, party),
+            Assault::Destroyed => destroyed.push(sim.parties[party].entity),
+        }
+    }
+    destroyed
+}
+
+/// What came of a party attacking a settlement
+pub(crate) enum Assault {
+    /// The garrison was beaten, or there was none to stop the party
+    Taken,
+    /// The garrison held, and the party fled
+    Repelled,
+    /// The garrison held, and the party was destroyed
+    Destroyed,
+}
+
+/// A party attacks the garrison of a settlement, if it has one. The party is
+/// left for the caller to send off or despawn.
+pub(crate) fn assault(sim: &mut Simulation, party: PartyId, location: LocationId) -> Assault {
+    let garrison = sim.locations[location].tokens;
+    if troops_strength(sim, garrison) <= 0. {
+        return Assault::Taken;
+    }
+    let troops = [sim.parties[party].troops, Some(garrison)];
+    let sides = [party_side(sim, party), garrison_side(sim, location)];
+    let report = fight(sim, troops, sides);
+    let taken = report.attacker_won;
+
+    let outcome = if taken {
+        Assault::Taken
+    } else if strength(sim, &sim.parties[party]) <= 0. {
+        Assault::Destroyed
+    } else {
+        Assault::Repelled
+    };
+    let party_entity = sim.parties[party].entity;
+    let town_entity = sim.locations[location].entity;
+    let (winner, loser) = if taken {
+        (party_entity, town_entity)
+    } else {
+        (town_entity, party_entity)
+    };
+    sim.events.push(SimEvent::Battle {
+        winner: ObjectId(ObjectHandle::Entity(winner)),
+        loser: ObjectId(ObjectHandle::Entity(loser)),
+        destroyed: matches!(outcome, Assault::Destroyed),
+    });
+    let (party_name, town) = (
+        &sim.entities[party_entity].name,
+        &sim.entities[town_entity].name,
+    );
+    let text = if taken {
+        format!("{party_name} stormed the walls of {town}")
+    } else {
+        format!("the garrison of {town} threw back {party_name}")
+    };
+    sim.record_battle(&[town_entity, party_entity], text, report);
+    outcome
+}
+
+// Hands a settlement a party took to the faction of the party, as its
+// overlord and the holder of its site
+fn capture(sim: &mut Simulation, party: PartyId, faction: AgentId, location: LocationId) {
+    let town_entity = sim.locations[location].entity;
+    let Some(town) = sim.entities[town_entity].agent else {
+        return;
+    };
+    sim.agents.political_hierarchy.insert(faction, town);
+    let site = sim.locations[location].site;
+    sim.sites.set_conqueror(site, faction);
+    crate::control::update(sim);
+    sim.view_revision += 1;
+    let text = format!(
+        "{} captured {} for {}",
+        sim.entities[sim.parties[party].entity].name,
+        sim.entities[town_entity].name,
+        sim.entities[sim.agents[faction].entity].name
+    );
+    let about = [town_entity, sim.agents[faction].entity];
+    sim.record_chronicle_about(&about, text);
+}
+
+// A party fights under its own name, worn out if it marched hard
+fn party_side(sim: &Simulation, party: PartyId) -> Side {
+    let data = &sim.parties[party];
+    let mut modifiers = vec![];
+    if data.forced_march {
+        modifiers.push(Modifier {
+            name: "forced march",
+            factor: FORCED_MARCH_WEARINESS,
+        });
+    }
+    Side {
+        name: sim.entities[data.entity].name.clone(),
+        modifiers,
+    }
+}
+
+// A garrison fights from behind the walls of its settlement
+fn garrison_side(sim: &Simulation, location: LocationId) -> Side {
+    let town = &sim.entities[sim.locations[location].entity].name;
+    Side {
+        name: format!("The garrison of {town}"),
+        modifiers: vec![Modifier {
+            name: "walls",
+            factor: WALLS_BONUS,
+        }],
+    }
+}
+
+/// The modifiers of a side, with how they bear on its strength
+pub(crate) fn describe(modifiers: &[Modifier]) -> Vec<String> {
+    modifiers
+        .iter()
+        .map(|modifier| format!("{} {:+1.0}%", modifier.name, (modifier.factor - 1.) * 100.))
+        .collect()
+}
+
+// Fights a battle between an attacker and a defender over rounds, the odds of
+// winning each going with strength, and the loser of each losing more of its
+// troops. A side breaks once it has lost too much of its strength, and if
+// neither does, the one that lost the larger share of it gives way.
+fn fight(
+    sim: &mut Simulation,
+    troops: [Option<TokenContainerId>; 2],
+    sides: [Side; 2],
+) -> BattleReport {
+    let strengths = |sim: &Simulation| {
+        [0, 1].map(|idx| {
+            let factor: f64 = sides[idx].modifiers.iter().map(|m| m.factor).product();
+            troops[idx].map_or(0., |troops| troops_strength(sim, troops)) * factor
+        })
+    };
+    let start = strengths(sim);
+    let lost = |current: [f64; 2], idx: usize| {
+        if start[idx] > 0. {
+            1. - current[idx] / start[idx]
+        } else {
+            1.
+        }
+    };
+
+    let mut rounds = vec![];
+    let mut current = start;
+    while rounds.len() < MAX_ROUNDS
+        && (0..2).all(|idx| current[idx] > 0. && lost(current, idx) < BREAKING_LOSS)
+    {
+        let [attack, defence] = current;
+        let attacker_wins = sim.rng.0.gen_range(0. ..attack + defence) < attack;
+        let (won, lost) = if attacker_wins {
+            (attack, defence)
+        } else {
+            (defence, attack)
+        };
+        let per_round = MAX_ROUNDS as f64;
+        let winner_share = (WINNER_CASUALTIES / per_round * lost / won).min(1.);
+        let loser_share = (LOSER_CASUALTIES / per_round * won / lost).min(1.);
+        let shares = if attacker_wins {
+            [winner_share, loser_share]
+        } else {
+            [loser_share, winner_share]
+        };
+        let casualties = [0, 1]
+            .map(|idx| troops[idx].map_or(0, |troops| take_casualties(sim, troops, shares[idx])));
+        rounds.push(Round {
+            strengths: current,
+            casualties,
+        });
+        current = strengths(sim);
+    }
+
+    // Troops died, and histories gained an entry
+    sim.view_revision += 1;
+    BattleReport {
+        date: sim.date,
+        attacker_won: current[1] <= 0. || lost(current, 0) < lost(current, 1),
+        sides,
+        rounds,
+    }
+}
+
+// Resolves a battle, the odds of winning it going with strength. The loser
+// flees, robbed if it had no troops, or is destroyed if it lost all it had,
+// in which case it is returned.
+fn battle(sim: &mut Simulation, attacker: PartyId, defender: PartyId) -> Option<PartyId> {
+    let armed = [attacker, defender].map(|party| strength(sim, &sim.parties[party]) > 0.);
+    let troops = [sim.parties[attacker].troops, sim.parties[defender].troops];
+    let sides = [party_side(sim, attacker), party_side(sim, defender)];
+    let report = fight(sim, troops, sides);
+    let attacker_wins = report.attacker_won;
+    let (winner, loser, loser_armed) = if attacker_wins {
+        (attacker, defender, armed[1])
+    } else {
+        (defender, attacker, armed[0])
+    };
+    // Only soldiers fight to the last man. Those with none to lose give up
+    // what they carry and run.
+    let is_destroyed = loser_armed && strength(sim, &sim.parties[loser]) <= 0.;
+    let is_robbed = !loser_armed && rob(sim, winner, loser);
+    if !is_destroyed {
+        retreat(sim, loser);
+    }
+
+    let winner_entity = sim.parties[winner].entity;
+    let loser_entity = sim.parties[loser].entity;
+    sim.events.push(SimEvent::Battle {
+        winner: ObjectId(ObjectHandle::Entity(winner_entity)),
+        loser: ObjectId(ObjectHandle::Entity(loser_entity)),
+        destroyed: is_destroyed,
+    });
+    let verb = if is_destroyed {
+        "destroyed"
+    } else if is_robbed {
+        "robbed"
+    } else {
+        "routed"
+    };
+    let text = format!(
+        "{} {verb} {} in battle",
+        sim.entities[winner_entity].name, sim.entities[loser_entity].name
+    );
+    sim.record_battle(&[winner_entity, loser_entity], text, report);
+    is_destroyed.then_some(loser)
+}
+
+// The winner of a battle takes the cash of the loser, and as much of its
+// cargo as it has room for. Returns whether anything was taken.
+fn rob(sim: &mut Simulation, winner: PartyId, loser: PartyId) -> bool {
+    let mut robbed = false;
+    let agents = [winner, loser].map(|party| sim.entities[sim.parties[party].entity].agent);
+    if let [Some(robber), Some(victim)] = agents
+        && sim.agents[victim].cash > 0.
+    {
+        let cash = std::mem::take(&mut sim.agents[victim].cash);
+        sim.agents[robber].cash += cash;
+        robbed = true;
+    }
+
+    let data = &sim.parties[winner];
+    let mut room = (data.carry_capacity - data.good_stock.weight(&sim.good_types)).max(0.);
+    let cargo: Vec<(GoodId, f64)> = sim.parties[loser]
+        .good_stock
+        .amount
+        .iter()
+        .filter(|&(_, &amount)| amount > 0.)
+        .map(|(good, &amount)| (good, amount))
+        .collect();
+    for (good, amount) in cargo {
+        let weight = sim.good_types[good].weight;
+        let taken = if weight > 0. {
+            amount.min(room / weight)
+        } else {
+            amount
+        };
+        if taken <= 0. {
+            continue;
+        }
+        room -= taken * weight;
+        sim.parties[loser].good_stock[good] -= taken;
+        sim.parties[winner].good_stock[good] += taken;
+        robbed = true;
+    }
+    robbed
+}
+
+// Kills a share of each kind of troops in a container, rounding up, and
+// returns how many died
+fn take_casualties(sim: &mut Simulation, troops: TokenContainerId, share: f64) -> i64 {
+    let tokens: Vec<(TokenId, i64)> = sim
+        .tokens
+        .all_tokens_of_category(troops, TokenCategory::Military)
+        .map(|token| (token.id, token.data.size))
+        .collect();
+    let mut total = 0;
+    for (token, size) in tokens {
+        let dead = ((size as f64 * share).ceil() as i64).min(size);
+        if sim.tokens.remove_size(token, dead).is_ok() {
+            total += dead;
+        }
+    }
+    total
+}
+
+/// A party hands all its troops to the garrison of the settlement it stands
+/// in, if one of its own faction
+pub(crate) fn station(sim: &mut Simulation, party: PartyId) {
+    let data = &sim.parties[party];
+    let Some(troops) = data.troops else {
+        return;
+    };
+    let Some(location) = data
+        .position
+        .as_site()
+        .and_then(|site| sim.sites[site].location)
+    else {
+        return;
+    };
+    let holder = faction_of_party(sim, &sim.parties[sim.locations[location].party]);
+    if holder.is_none() || holder != faction_of_party(sim, data) {
+        return;
+    }
+    let garrison = sim.locations[location].tokens;
+    let tokens: Vec<(TokenId, i64)> = sim
+        .tokens
+        .all_tokens_of_category(troops, TokenCategory::Military)
+        .map(|token| (token.id, token.data.size))
+        .collect();
+    for (token, size) in tokens {
+        let _ = sim.tokens.transfer(troops, garrison, token, size);
+    }
+}
+
+// Sends a beaten party back to the nearest settlement of its faction, or
+// halts it where there is none
+pub(crate) fn retreat(sim: &mut Simulation, party: PartyId) {
+    let refuge = refuge(sim, party);
+    let movement = &mut sim.parties[party].movement;
+    movement.clear_orders();
+    movement.path.clear();
+    movement.target = refuge.map(MovementTarget::Party);
+    movement.reaction = refuge.map(|target| Reaction {
+        target,
+        fleeing: true,
+        resume: None,
+    });
+    sim.combat
+        .retreating
+        .insert(party, sim.date.add_days(RETREAT_DAYS));
+}
+
+/// The nearest settlement of the faction of a party, where it is safe
+pub(crate) fn refuge(sim: &Simulation, party: PartyId) -> Option<PartyId> {
+    let faction = faction_of_party(sim, &sim.parties[party]);
+    let from = pos_of_grid_coordinate(&sim.sites, sim.parties[party].position);
+    sim.locations
+        .values()
+        .filter(|location| {
+            let agent = sim.entities[location.entity].agent;
+            faction.is_some() && agent.and_then(|agent| faction_of(&sim.agents, agent)) == faction
+        })
+        .min_by(|a, b| {
+            let a = sim.sites[a.site].pos.distance(from);
+            let b = sim.sites[b.site].pos.distance(from);
+            a.total_cmp(&b)
+        })
+        .map(|location| location.party)
+}
@@ -0,0 +1,102 @@
+use crate::chronicle::faction_of;
+use crate::date::Date;
+use crate::simulation::*;
+use crate::tokens::*;
+
+#[derive(Clone)]
+pub(crate) struct Construction {
+    pub location: LocationId,
+    pub typ: TokenTypeId,
+    pub started: Date,
+    /// Days of work paid for so far
+    pub days_done: u32,
+    /// Whether the last day of work went unpaid
+    pub stalled: bool,
+}
+
+/// Buildings being raised at locations, paid for a day of work at a time
+#[derive(Default, Clone)]
+pub(crate) struct Constructions {
+    projects: Vec<Construction>,
+}
+
+impl Constructions {
+    pub fn at(&self, location: LocationId) -> impl Iterator<Item = &Construction> + '_ {
+        self.projects
+            .iter()
+            .filter(move |project| project.location == location)
+    }
+}
+
+pub(crate) fn start(sim: &mut Simulation, location: LocationId, typ: TokenTypeId) {
+    sim.constructions.projects.push(Construction {
+        location,
+        typ,
+        started: sim.date,
+        days_done: 0,
+        stalled: false,
+    });
+}
+
+/// Daily work on the constructions. Each day takes its share of the goods from
+/// the market of the location and of the cash from the treasury of its faction,
+/// or stalls if either falls short.
+pub(crate) fn tick(sim: &mut Simulation) {
+    let mut projects = std::mem::take(&mut sim.constructions.projects);
+    projects.retain(|project| sim.locations.contains_key(project.location));
+
+    for project in &mut projects {
+        let Some(cost) = &sim.tokens.types[project.typ].construction else {
+            continue;
+        };
+        let days = cost.days.max(1) as f64;
+        let location = &sim.locations[project.location];
+        let treasury = sim.entities[location.entity]
+            .agent
+            .and_then(|agent| faction_of(&sim.agents, agent));
+
+        let cash = cost.cash / days;
+        let affordable = treasury.is_some_and(|id| sim.agents[id].cash >= cash)
+            && cost
+                .goods
+                .iter()
+                .all(|(good, &amount)| location.market.goods[good].stock >= amount / days);
+        project.stalled = !affordable;
+        if !affordable {
+            continue;
+        }
+
+        if let Some(treasury) = treasury {
+            sim.agents[treasury].cash -= cash;
+        }
+        let market = &mut sim.locations[project.location].market;
+        for (good, &amount) in &cost.goods {
+            market.goods[good].stock -= amount / days;
+            market.goods[good].stock_delta -= amount / days;
+        }
+
+        project.days_done += 1;
+    }
+
+    let types = &sim.tokens.types;
+    let (completed, ongoing): (Vec<_>, Vec<_>) = projects.into_iter().partition(|project| {
+        types[project.typ]
+            .construction
+            .as_ref()
+            .is_some_and(|cost| project.days_done >= cost.days)
+    });
+    sim.constructions.projects = ongoing;
+
+    for project in completed {
+        let location = &sim.locations[project.location];
+        sim.tokens.add_token(location.tokens, project.typ, 1);
+
+        let entity = location.entity;
+        let text = format!(
+            "a {} was built at {}",
+            sim.tokens.types[project.typ].name.to_lowercase(),
+            sim.entities[entity].name
+        );
+        sim.record_chronicle(entity, text);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+
+use slotmap::{SlotMap, new_key_type};
+
+use crate::simulation::*;
+
+new_key_type! { pub(crate) struct ContractId; }
+
+// Below this satisfaction, a good is short at a location
+const SHORTAGE_SATISFACTION: f64 = 0.8;
+// Days a shortage must last before a delivery order is posted for it
+const SHORTAGE_DAYS: u32 = 15;
+// Days of demand a delivery order asks for
+const ORDER_DAYS_OF_DEMAND: f64 = 15.;
+// Paid on top of the local price of the goods ordered
+const REWARD_PREMIUM: f64 = 0.5;
+// Days an order stays up, carried or not
+const ORDER_EXPIRY_DAYS: u32 = 60;
+
+/// A delivery order posted by a location short of a good. The reward is put
+/// aside from the treasury of its realm when posted.
+#[derive(Clone)]
+pub(crate) struct Contract {
+    pub location: LocationId,
+    pub good: GoodId,
+    pub quantity: f64,
+    pub reward: f64,
+    pub treasury: AgentId,
+    // Entity of the party that took up the order
+    pub carrier: Option<EntityId>,
+    pub days_left: u32,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Contracts {
+    pub entries: SlotMap<ContractId, Contract>,
+    // Consecutive days each good has been short at each location
+    shortages: BTreeMap<(LocationId, GoodId), u32>,
+}
+
+impl Contracts {
+    pub fn posted_by(&self, location: LocationId) -> impl Iterator<Item = &Contract> {
+        self.entries
+            .values()
+            .filter(move |contract| contract.location == location)
+    }
+
+    /// Cash set aside for rewards
+    pub fn escrow(&self) -> f64 {
+        self.entries.values().map(|contract| contract.reward).sum()
+    }
+}
+
+/// A carrier that reached the location that posted its order
+pub(crate) struct Delivery {
+    pub entity: EntityId,
+    pub contract: ContractId,
+}
+
+/// Tracks shortages, posts delivery orders for the lasting ones, and takes
+/// down the orders that expired
+pub(crate) fn tick(sim: &mut Simulation) {
+    let contracts = &mut sim.contracts;
+    contracts
+        .shortages
+        .retain(|(location, _), _| sim.locations.contains_key(*location));
+    for (id, location) in &sim.locations {
+        for (good, data) in location.market.goods.iter() {
+            let short = data.demand_base > 0. && data.satisfaction < SHORTAGE_SATISFACTION;
+            if short {
+                *contracts.shortages.entry((id, good)).or_default() += 1;
+            } else {
+                contracts.shortages.remove(&(id, good));
+            }
+        }
+    }
+
+    let expired: Vec<_> = sim
+        .contracts
+        .entries
+        .iter_mut()
+        .filter_map(|(id, contract)| {
+            contract.days_left = contract.days_left.saturating_sub(1);
+            let gone = !sim.locations.contains_key(contract.location);
+            (contract.days_left == 0 || gone).then_some(id)
+        })
+        .collect();
+    for id in expired {
+        close(sim, id);
+    }
+
+    let due: Vec<_> = sim
+        .contracts
+        .shortages
+        .iter()
+        .filter(|&(_, &days)| days >= SHORTAGE_DAYS)
+        .map(|(&key, _)| key)
+        .collect();
+    for (location, good) in due {
+        post(sim, location, good);
+    }
+}
+
+fn post(sim: &mut Simulation, location: LocationId, good: GoodId) {
+    let already_posted = sim
+        .contracts
+        .posted_by(location)
+        .any(|contract| contract.good == good);
+    if already_posted {
+        return;
+    }
+    let Some(treasury) = crate::autonomy::treasury_of(sim, location) else {
+        return;
+    };
+
+    let market = &sim.locations[location].market.goods[good];
+    let quantity = market.demand_base * ORDER_DAYS_OF_DEMAND;
+    let reward = quantity * market.price * (1. + REWARD_PREMIUM);
+    let purse = &mut sim.agents[treasury].cash;
+    if quantity <= 0. || *purse < reward {
+        return;
+    }
+    *purse -= reward;
+    sim.contracts.entries.insert(Contract {
+        location,
+        good,
+        quantity,
+        reward,
+        treasury,
+        carrier: None,
+        days_left: ORDER_EXPIRY_DAYS,
+    });
+
+    let entity = sim.locations[location].entity;
+    let text = format!(
+        "{} posted an order for {:1.0} {}",
+        sim.entities[entity].name,
+        quantity,
+        sim.good_types[good].name.to_lowercase()
+    );
+    sim.record_chronicle(entity, text);
+}
+
+// Takes down an order, giving back what is left of the reward
+fn close(sim: &mut Simulation, id: ContractId) {
+    let Some(contract) = sim.contracts.entries.remove(id) else {
+        return;
+    };
+    if let Some(agent) = sim.agents.entries.get_mut(contract.treasury) {
+        agent.cash += contract.reward;
+    }
+    let behavior = contract
+        .carrier
+        .and_then(|carrier| sim.entities.get(carrier))
+        .and_then(|entity| entity.behavior);
+    if let Some(behavior) = behavior.and_then(|id| sim.beahviors.get_mut(id))
+        && behavior.contract == Some(id)
+    {
+        behavior.contract = None;
+        behavior.plan.retain(|task| task.action != Action::Deliver);
+    }
+}
+
+/// Carrying the goods of an order to the location that posted it
+pub(crate) fn delivery(sim: &Simulation, id: ContractId) -> Option<Task> {
+    let location = sim.contracts.entries.get(id)?.location;
+    Some(Task::visit(
+        sim.locations.get(location)?.party,
+        Action::Deliver,
+    ))
+}
+
+/// Traders that just traded take up an open order for a good sold where they
+/// stand, buying as much of it as they can carry and afford
+pub(crate) fn take_up(
+    sim: &mut Simulation,
+    traders: impl IntoIterator<Item = (EntityId, LocationId)>,
+) {
+    for (entity, location) in traders {
+        let Some(data) = sim.entities.get(entity) else {
+            continue;
+        };
+        let (Some(party), Some(agent), Some(behavior)) = (data.party, data.agent, data.behavior)
+        else {
+            continue;
+        };
+        let behavior_data = &sim.beahviors[behavior];
+        let trades = matches!(
+            behavior_data.goal,
+            Goal::LocalTrade { .. }
+                | Goal::ArbitrageTrade { .. }
+                | Goal::Ambition {
+                    ambition: crate::ambitions::Ambition::Wealth,
+                    ..
+                }
+        );
+        if !trades || behavior_data.contract.is_some() {
+            continue;
+        }
+
+        let market = &sim.locations[location].market;
+        let offer = sim
+            .contracts
+            .entries
+            .iter()
+            .filter(|(_, contract)| contract.carrier.is_none() && contract.location != location)
+            .find(|(_, contract)| market.goods[contract.good].stock > 0.)
+            .map(|(id, contract)| (id, contract.good, contract.quantity));
+        let Some((id, good, quantity)) = offer else {
+            continue;
+        };
+
+        let party_data = &sim.parties[party];
+        let weight = sim.good_types[good].weight;
+        let room = party_data.carry_capacity - party_data.good_stock.weight(&sim.good_types);
+        let price = market.goods[good].price;
+        let cash = sim.agents[agent].cash;
+        let mut amount = quantity.min(market.goods[good].stock);
+        if weight > 0. {
+            amount = amount.min(room / weight);
+        }
+        if price > 0. {
+            amount = amount.min(cash / price);
+        }
+        if amount <= 0. {
+            continue;
+        }
+
+        let in_market = &mut sim.locations[location].market.goods[good];
+        in_market.stock -= amount;
+        in_market.stock_delta -= amount;
+        sim.agents[agent].cash -= amount * price;
+        sim.parties[party].good_stock[good] += amount;
+        sim.contracts.entries[id].carrier = Some(entity);
+        // Head for the location that posted the order before going on with
+        // the plan where it stood
+        let delivery = delivery(sim, id);
+        let behavior_data = &mut sim.beahviors[behavior];
+        behavior_data.contract = Some(id);
+        if let Some(delivery) = delivery {
+            behavior_data.plan.push_front(delivery);
+        }
+
+        let destination = sim.locations[sim.contracts.entries[id].location].entity;
+        let text = format!(
+            "{} took up the order of {}",
+            sim.entities[entity].name, sim.entities[destination].name
+        );
+        sim.record_history(entity, text);
+    }
+}
+
+/// Hands over the goods of carriers that reached their destination, and pays
+/// them their share of the reward
+pub(crate) fn deliver(sim: &mut Simulation, deliveries: Vec<Delivery>) {
+    for Delivery { entity, contract } in deliveries {
+        let Some(data) = sim.contracts.entries.get(contract) else {
+            continue;
+        };
+        let (location, good, quantity) = (data.location, data.good, data.quantity);
+        let entity_data = &sim.entities[entity];
+        let (Some(party), Some(agent)) = (entity_data.party, entity_data.agent) else {
+            continue;
+        };
+
+        let carried = sim.parties[party].good_stock[good];
+        let delivered = carried.min(quantity);
+        sim.parties[party].good_stock[good] -= delivered;
+        let in_market = &mut sim.locations[location].market.goods[good];
+        in_market.stock += delivered;
+        in_market.stock_delta += delivered;
+
+        let paid = sim.contracts.entries[contract].reward * delivered / quantity.max(1.);
+        sim.contracts.entries[contract].reward -= paid;
+        sim.agents[agent].cash += paid;
+
+        let town = sim.locations[location].entity;
+        let text = format!(
+            "{} delivered {:1.0} {} to {} for {:1.0}$",
+            sim.entities[entity].name,
+            delivered,
+            sim.good_types[good].name.to_lowercase(),
+            sim.entities[town].name,
+            paid
+        );
+        sim.record_chronicle_about(&[entity, town], text);
+        close(sim, contract);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+use crate::sites::{InfluenceKind, SiteId};
+
+// Weight of security over market influence in deciding who holds a site
+const SECURITY_WEIGHT: f64 = 2.;
+
+/// Hands each site to the faction whose influence over it is strongest,
+/// unless another holds it by conquest. Sites where no faction prevails are
+/// held by none. Returns whether any site changed hands.
+pub(crate) fn update(sim: &mut Simulation) -> bool {
+    let holders: Vec<(SiteId, Option<AgentId>)> = sim
+        .sites
+        .iter()
+        .map(|(id, site)| {
+            let conqueror = site
+                .conqueror
+                .filter(|&faction| sim.agents.entries.contains_key(faction));
+            (id, conqueror.or_else(|| dominant_faction(sim, id)))
+        })
+        .collect();
+    let mut changed = false;
+    for (id, holder) in holders {
+        changed |= sim.sites.set_controller(id, holder);
+    }
+    changed
+}
+
+// The faction with the most weight of influence over a site, if one prevails
+fn dominant_faction(sim: &Simulation, site: SiteId) -> Option<AgentId> {
+    let mut weights: BTreeMap<AgentId, f64> = BTreeMap::new();
+    for &(typ, amount) in sim.sites[site].influences.iter() {
+        let faction = sim
+            .parties
+            .get(typ.source)
+            .and_then(|party| sim.entities[party.entity].agent)
+            .and_then(|agent| faction_of(&sim.agents, agent));
+        let Some(faction) = faction else {
+            continue;
+        };
+        let weight = match typ.kind {
+            InfluenceKind::Market => 1.,
+            InfluenceKind::Security => SECURITY_WEIGHT,
+        };
+        *weights.entry(faction).or_default() += weight * amount as f64;
+    }
+    let mut ranked: Vec<(AgentId, f64)> = weights.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    match ranked.as_slice() {
+        [] => None,
+        // Contested sites are held by none
+        [first, second, ..] if first.1 == second.1 => None,
+        [first, ..] => Some(first.0),
+    }
+}
+
+/// A faction takes a site by force, and holds it whatever the influences
+/// over it until another conquers it in turn
+pub(crate) fn conquer(sim: &mut Simulation, faction: AgentId, site: SiteId) {
+    let before = sim.sites[site].controller;
+    sim.sites.set_conqueror(site, faction);
+    update(sim);
+    if sim.sites[site].controller == before {
+        return;
+    }
+    let entity = sim.agents[faction].entity;
+    let place = match sim.sites[site].location {
+        Some(location) => sim.entities[sim.locations[location].entity].name.clone(),
+        None => sim.sites[site].tag.clone(),
+    };
+    let text = format!("{} seized {place}", sim.entities[entity].name);
+    sim.record_chronicle(entity, text);
+}
@@ -0,0 +1,78 @@
+use crate::simulation::*;
+
+// Daily share of their treasury realms spend on their courts
+const COURT_UPKEEP_RATE: f64 = 0.01;
+// Cash per head the base prices of goods were set for
+const REFERENCE_CASH_PER_CAPITA: f64 = 10.;
+// How strongly prices follow the money supply
+const INFLATION_ELASTICITY: f64 = 0.25;
+// Daily share of the way the index covers towards the money supply
+const INFLATION_SMOOTHING: f64 = 0.02;
+
+/// Accounting of the money in circulation. Markets settle trades on the spot
+/// and hold no cash, so all of it sits with agents or is set aside for the
+/// rewards of delivery orders.
+#[derive(Clone)]
+pub(crate) struct Currency {
+    supply: f64,
+    // Cash taken out of circulation on the last day
+    drained: f64,
+    // Multiplier of the base price of all goods
+    index: f64,
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self {
+            supply: 0.,
+            drained: 0.,
+            index: 1.,
+        }
+    }
+}
+
+impl Currency {
+    pub fn supply(&self) -> f64 {
+        self.supply
+    }
+
+    pub fn drained(&self) -> f64 {
+        self.drained
+    }
+
+    pub fn index(&self) -> f64 {
+        self.index
+    }
+}
+
+/// Charges the daily upkeep of parties and courts, then takes stock of the
+/// money supply and moves the inflation index after it
+pub(crate) fn tick(sim: &mut Simulation) {
+    let mut drained = crate::upkeep::tick(sim);
+    for data in sim.agents.entries.values_mut() {
+        if data.flags.get(AgentFlag::IsFaction) {
+            let paid = (data.cash * COURT_UPKEEP_RATE).max(0.);
+            data.cash -= paid;
+            drained += paid;
+        }
+    }
+
+    let currency = &mut sim.currency;
+    currency.drained = drained;
+    currency.supply = sim
+        .agents
+        .entries
+        .values()
+        .map(|agent| agent.cash)
+        .sum::<f64>()
+        + sim.contracts.escrow();
+
+    let population: i64 = sim.locations.values().map(|x| x.population).sum();
+    if population > 0 {
+        let per_capita = currency.supply / population as f64;
+        let target = (per_capita / REFERENCE_CASH_PER_CAPITA)
+            .max(1.)
+            .powf(INFLATION_ELASTICITY);
+        currency.index += (target - currency.index) * INFLATION_SMOOTHING;
+    }
+}
@@ -3,13 +3,13 @@ pub struct Date(u64);
 
 impl Date {
     const TICKS_IN_HOUR: u64 = 3;
-    const HOURS_IN_DAY: u64 = 24;
-    const DAYS_IN_MONTH: u64 = 30;
+    pub(crate) const HOURS_IN_DAY: u64 = 24;
+    pub(crate) const DAYS_IN_MONTH: u64 = 30;
     const MONTHS_IN_YEAR: u64 = 12;
 
-    const TICKS_IN_DAY: u64 = Self::TICKS_IN_HOUR * Self::HOURS_IN_DAY;
+    pub(crate) const TICKS_IN_DAY: u64 = Self::TICKS_IN_HOUR * Self::HOURS_IN_DAY;
     const TICKS_IN_MONTH: u64 = Self::TICKS_IN_DAY * Self::DAYS_IN_MONTH;
-    const TICKS_IN_YEAR: u64 = Self::TICKS_IN_MONTH * Self::MONTHS_IN_YEAR;
+    pub(crate) const TICKS_IN_YEAR: u64 = Self::TICKS_IN_MONTH * Self::MONTHS_IN_YEAR;
 
     pub fn epoch(self) -> u64 {
         self.0
@@ -29,10 +29,36 @@ impl Date {
         self.0 += 1;
     }
 
+    pub(crate) fn add_days(self, days: u64) -> Self {
+        Self(self.0 + days * Self::TICKS_IN_DAY)
+    }
+
+    pub(crate) fn years_before(self, years: u64) -> Self {
+        Self(self.0.saturating_sub(years * Self::TICKS_IN_YEAR))
+    }
+
+    /// Whole years gone by since an earlier date
+    pub(crate) fn years_since(self, earlier: Date) -> u64 {
+        self.0.saturating_sub(earlier.0) / Self::TICKS_IN_YEAR
+    }
+
+    pub fn is_new_hour(&self) -> bool {
+        self.0.is_multiple_of(Self::TICKS_IN_HOUR)
+    }
+
     pub fn is_new_day(&self) -> bool {
         self.0 % Self::TICKS_IN_DAY == 0
     }
 
+    pub fn is_new_month(&self) -> bool {
+        self.0.is_multiple_of(Self::TICKS_IN_MONTH)
+    }
+
+    // Seasons start in the third month of each quarter, see `season`
+    pub fn is_new_season(&self) -> bool {
+        self.is_new_month() && self.calendar_month().is_multiple_of(3)
+    }
+
     pub fn calendar_day(&self) -> u64 {
         (self.0 / Self::TICKS_IN_DAY) % Self::DAYS_IN_MONTH + 1
     }
@@ -45,3 +71,51 @@ impl Date {
         self.0 / Self::TICKS_IN_YEAR + 1
     }
 }
+
+/// A span of time, counted in ticks
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Duration(u64);
+
+impl Duration {
+    pub(crate) fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    pub fn ticks(self) -> u64 {
+        self.0
+    }
+
+    pub fn days(self) -> f32 {
+        self.0 as f32 / Date::TICKS_IN_DAY as f32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spring => "spring",
+            Self::Summer => "summer",
+            Self::Autumn => "autumn",
+            Self::Winter => "winter",
+        }
+    }
+}
+
+impl Date {
+    pub fn season(&self) -> Season {
+        match self.calendar_month() {
+            3..=5 => Season::Spring,
+            6..=8 => Season::Summer,
+            9..=11 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+}
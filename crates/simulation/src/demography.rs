@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use slotmap::SecondaryMap;
+
+use crate::simulation::*;
+use crate::tokens::TokenCategory;
+
+// Average food satisfaction over a month above which pops grow
+const GROWTH_THRESHOLD: f64 = 0.95;
+// Monthly growth of well fed pops
+const GROWTH_RATE: f64 = 0.004;
+// Average food satisfaction over a month below which pops dwindle, if the
+// stockpiles are empty too
+const DECLINE_THRESHOLD: f64 = 0.9;
+// Monthly share of the pops lost at zero food satisfaction
+const DECLINE_RATE: f64 = 0.05;
+
+pub(crate) const AGE_GROUPS: [&str; 3] = ["Young", "Adults", "Elders"];
+// Age structure of the pops of a newly tracked location
+const INITIAL_AGES: [f64; 3] = [0.35, 0.5, 0.15];
+// Monthly shares of the young coming of age, of adults growing old, and of
+// elders passing away. The dead are replaced by births.
+const COMING_OF_AGE_RATE: f64 = 1. / (14. * 12.);
+const AGEING_RATE: f64 = 1. / (30. * 12.);
+const ELDER_MORTALITY: f64 = 1. / (12. * 12.);
+
+#[derive(Default, Clone, Copy)]
+struct FoodRecord {
+    satisfaction: f64,
+    days: u32,
+}
+
+/// Population growth and decline, following the food balance of each location
+#[derive(Default, Clone)]
+pub(crate) struct Demography {
+    // Food satisfaction accumulated over the current month
+    records: SecondaryMap<LocationId, FoodRecord>,
+    // Relative change of the population at the last monthly update
+    changes: SecondaryMap<LocationId, f64>,
+    profiles: SecondaryMap<LocationId, Profile>,
+}
+
+/// Make-up of the pops of a location, as shares of its population
+#[derive(Clone)]
+pub(crate) struct Profile {
+    pub ages: [f64; 3],
+    // By the name of the realm the pops were raised in
+    pub cultures: BTreeMap<String, f64>,
+}
+
+impl Demography {
+    pub fn last_change(&self, location: LocationId) -> Option<f64> {
+        self.changes.get(location).copied()
+    }
+
+    pub fn profile(&self, location: LocationId) -> Option<&Profile> {
+        self.profiles.get(location)
+    }
+}
+
+// Pops take up the culture of the realm ruling the place they are raised in
+fn culture_of(sim: &Simulation, location: LocationId) -> String {
+    match crate::autonomy::treasury_of(sim, location) {
+        Some(realm) => sim.entities[sim.agents[realm].entity].name.clone(),
+        None => sim.entities[sim.locations[location].entity].name.clone(),
+    }
+}
+
+fn ensure_profile(sim: &mut Simulation, location: LocationId) {
+    if sim.demography.profiles.contains_key(location) {
+        return;
+    }
+    let profile = Profile {
+        ages: INITIAL_AGES,
+        cultures: BTreeMap::from([(culture_of(sim, location), 1.)]),
+    };
+    sim.demography.profiles.insert(location, profile);
+}
+
+// Moves the age structure on by a month, given the relative change of the
+// population over it
+fn age(ages: &mut [f64; 3], rate: f64) {
+    let [young, adults, elders] = *ages;
+    let matured = young * COMING_OF_AGE_RATE;
+    let aged = adults * AGEING_RATE;
+    let died = elders * ELDER_MORTALITY;
+    let born = died + rate.max(0.);
+    *ages = [
+        young + born - matured,
+        adults + matured - aged,
+        elders + aged - died,
+    ];
+    let total: f64 = ages.iter().sum();
+    if total > 0. {
+        ages.iter_mut().for_each(|share| *share /= total);
+    }
+}
+
+/// Blends the culture of migrants from one location into the pops of another,
+/// before they are added to its population
+pub(crate) fn settle(sim: &mut Simulation, origin: LocationId, destination: LocationId, size: i64) {
+    if !sim.locations.contains_key(destination) {
+        return;
+    }
+    ensure_profile(sim, destination);
+    let incoming = match sim.demography.profiles.get(origin) {
+        Some(profile) => profile.cultures.clone(),
+        None if sim.locations.contains_key(origin) => {
+            BTreeMap::from([(culture_of(sim, origin), 1.)])
+        }
+        None => return,
+    };
+    let residents = sim.locations[destination].population.max(0) as f64;
+    let size = size.max(0) as f64;
+    if residents + size <= 0. {
+        return;
+    }
+    let cultures = &mut sim.demography.profiles[destination].cultures;
+    for share in cultures.values_mut() {
+        *share *= residents / (residents + size);
+    }
+    for (culture, share) in incoming {
+        *cultures.entry(culture).or_default() += share * size / (residents + size);
+    }
+}
+
+/// Samples the food balance every day, and resizes the pops once a month.
+/// Starving locations are left to the famine, which takes its own toll.
+pub(crate) fn tick(sim: &mut Simulation) {
+    for (id, location) in &sim.locations {
+        let record = sim.demography.records.entry(id).unwrap().or_default();
+        record.satisfaction += location.market.food_satisfaction();
+        record.days += 1;
+    }
+
+    if !sim.date.is_new_month() {
+        return;
+    }
+
+    let records = std::mem::take(&mut sim.demography.records);
+    sim.demography.changes.clear();
+    sim.demography
+        .profiles
+        .retain(|id, _| sim.locations.contains_key(id));
+    for (id, record) in records {
+        if !sim.locations.contains_key(id) {
+            continue;
+        }
+        ensure_profile(sim, id);
+        let location = &sim.locations[id];
+        let fed = record.satisfaction / record.days.max(1) as f64;
+        let rate = if fed >= GROWTH_THRESHOLD {
+            GROWTH_RATE
+        } else if fed < DECLINE_THRESHOLD
+            && location.market.food_stockpile <= 0.
+            && !sim.famines.is_starving(id)
+        {
+            -DECLINE_RATE * (1. - fed)
+        } else {
+            0.
+        };
+
+        let pops: Vec<_> = sim
+            .tokens
+            .all_tokens_of_category(location.tokens, TokenCategory::Pop)
+            .map(|tok| (tok.id, tok.data.size))
+            .collect();
+        for (tok, size) in pops {
+            let change = (size as f64 * rate).round() as i64;
+            sim.tokens.tokens[tok].size = (size + change).max(0);
+        }
+        sim.demography.changes.insert(id, rate);
+        age(&mut sim.demography.profiles[id].ages, rate);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::simulation::{AgentId, GoodId};
+
+// Share of the proceeds from selling an embargoed good seized at customs
+pub(crate) const EMBARGO_TARIFF: f64 = 0.5;
+// Chance of a trader slipping past customs, halved by each point of security
+pub(crate) const SMUGGLING_CHANCE: f64 = 0.3;
+
+/// A faction's refusal to trade with a faction, or in a good
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) struct Embargo {
+    pub imposer: AgentId,
+    /// Embargoed faction, everyone else if missing
+    pub target: Option<AgentId>,
+    /// Embargoed good, all goods if missing
+    pub good: Option<GoodId>,
+}
+
+impl Embargo {
+    fn applies_to(&self, faction: Option<AgentId>) -> bool {
+        faction != Some(self.imposer) && self.target.is_none_or(|target| faction == Some(target))
+    }
+}
+
+/// What agents think of each other, and the embargoes between factions.
+/// Opinions start neutral at zero.
+#[derive(Default, Clone)]
+pub(crate) struct Relations {
+    opinions: BTreeMap<(AgentId, AgentId), f64>,
+    embargoes: BTreeSet<Embargo>,
+}
+
+impl Relations {
+    pub fn adjust(&mut self, of: AgentId, towards: AgentId, delta: f64) {
+        const LIMIT: f64 = 100.;
+        let opinion = self.opinions.entry((of, towards)).or_default();
+        *opinion = (*opinion + delta).clamp(-LIMIT, LIMIT);
+    }
+
+    pub fn opinion(&self, of: AgentId, towards: AgentId) -> f64 {
+        self.opinions.get(&(of, towards)).copied().unwrap_or(0.)
+    }
+
+    /// Opinions held by an agent
+    pub fn held_by(&self, of: AgentId) -> impl Iterator<Item = (AgentId, f64)> + '_ {
+        self.opinions
+            .iter()
+            .filter(move |((x, _), _)| *x == of)
+            .map(|(&(_, towards), &opinion)| (towards, opinion))
+    }
+
+    /// Imposes or lifts an embargo, returning whether anything changed
+    pub fn set_embargo(&mut self, embargo: Embargo, active: bool) -> bool {
+        if active {
+            self.embargoes.insert(embargo)
+        } else {
+            self.embargoes.remove(&embargo)
+        }
+    }
+
+    pub fn embargoes_by(&self, imposer: AgentId) -> impl Iterator<Item = &Embargo> + '_ {
+        self.embargoes.iter().filter(move |x| x.imposer == imposer)
+    }
+
+    /// Whether the markets of a faction hold back any trade of another faction
+    pub fn embargoes_any(&self, imposer: AgentId, trader: Option<AgentId>) -> bool {
+        self.embargoes_by(imposer).any(|x| x.applies_to(trader))
+    }
+
+    /// Whether the markets of a faction turn away traders of another faction
+    pub fn refuses(&self, imposer: AgentId, trader: Option<AgentId>) -> bool {
+        self.embargoes_by(imposer)
+            .any(|x| x.good.is_none() && x.applies_to(trader))
+    }
+
+    /// Whether a good is embargoed for traders of a faction
+    pub fn embargoes_good(&self, imposer: AgentId, trader: Option<AgentId>, good: GoodId) -> bool {
+        self.embargoes_by(imposer)
+            .any(|x| x.good == Some(good) && x.applies_to(trader))
+    }
+
+    pub fn forget(&mut self, agent: AgentId) {
+        self.opinions
+            .retain(|&(of, towards), _| of != agent && towards != agent);
+        self.embargoes
+            .retain(|x| x.imposer != agent && x.target != Some(agent));
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeSet;
+
+use crate::chronicle::faction_of;
+use crate::object::*;
+use crate::simulation::*;
+use crate::view::SimEvent;
+
+/// Factions that hold locations or parties. A faction that loses the last of
+/// them is eliminated.
+#[derive(Default, Clone)]
+pub(crate) struct Realms {
+    standing: BTreeSet<AgentId>,
+}
+
+pub(crate) fn tick(sim: &mut Simulation) {
+    let holding: BTreeSet<AgentId> = sim
+        .entities
+        .values()
+        .filter(|entity| entity.location.is_some() || entity.party.is_some())
+        .filter_map(|entity| faction_of(&sim.agents, entity.agent?))
+        .collect();
+
+    let fallen: Vec<_> = sim.realms.standing.difference(&holding).copied().collect();
+    sim.realms.standing = holding;
+
+    for faction in fallen {
+        let Some(agent) = sim.agents.entries.get(faction) else {
+            continue;
+        };
+        let entity = agent.entity;
+        sim.remove_player(ObjectId(ObjectHandle::Entity(entity)));
+        let text = format!("the realm of {} fell", sim.entities[entity].name);
+        sim.record_chronicle(entity, text);
+        sim.events.push(SimEvent::FactionEliminated {
+            faction: ObjectId(ObjectHandle::Entity(entity)),
+        });
+        sim.view_revision += 1;
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use crate::simulation::*;
+
+/// Parties travelling under the escort of another, such as guards seeing a
+/// caravan along or an army taking a person with it. Escorted parties go
+/// wherever their escort takes them, keeping their own orders for when they
+/// are let go, and are kept out of battles while the escort stands.
+#[derive(Default, Clone)]
+pub(crate) struct Escorts {
+    // The parties each escort takes along
+    passengers: BTreeMap<PartyId, Vec<PartyId>>,
+    escorts: BTreeMap<PartyId, PartyId>,
+}
+
+impl Escorts {
+    pub fn escort_of(&self, party: PartyId) -> Option<PartyId> {
+        self.escorts.get(&party).copied()
+    }
+
+    pub fn passengers(&self, escort: PartyId) -> &[PartyId] {
+        self.passengers.get(&escort).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// A party takes another standing where it is under its escort, as ships
+/// take passengers aboard. Ships, parties already escorted or escorting
+/// others, and those travelling in a group cannot be taken along. Returns whether the escort took it.
+pub(crate) fn attach(sim: &mut Simulation, escort: PartyId, party: PartyId) -> bool {
+    let (Some(escort_data), Some(party_data)) = (sim.parties.get(escort), sim.parties.get(party))
+    else {
+        return false;
+    };
+    if escort == party
+        || sim.escorts.escort_of(escort).is_some()
+        || sim.escorts.escort_of(party).is_some()
+        || !sim.escorts.passengers(party).is_empty()
+        || sim.groups.leader_of(party).is_some()
+        || !sim.groups.members(party).is_empty()
+        || escort_data.movement_speed == 0.
+        || party_data.movement_speed == 0.
+        || party_data.ship
+        || escort_data.position != party_data.position
+    {
+        return false;
+    }
+    sim.escorts
+        .passengers
+        .entry(escort)
+        .or_default()
+        .push(party);
+    sim.escorts.escorts.insert(party, escort);
+    sim.parties[party].movement.path.clear();
+    true
+}
+
+/// Lets a party go its own way where it stands: an escorted party leaves its
+/// escort, and an escort lets go of all those it takes along
+pub(crate) fn release(sim: &mut Simulation, party: PartyId) {
+    if let Some(escort) = sim.escorts.escorts.remove(&party) {
+        let passengers = sim.escorts.passengers.entry(escort).or_default();
+        passengers.retain(|&passenger| passenger != party);
+        if passengers.is_empty() {
+            sim.escorts.passengers.remove(&escort);
+        }
+    }
+    for passenger in sim.escorts.passengers.remove(&party).unwrap_or_default() {
+        sim.escorts.escorts.remove(&passenger);
+    }
+}
+
+/// Moves the escorted parties to where their escort stands, after the
+/// escorts and the groups they travel in have moved
+pub(crate) fn carry(sim: &mut Simulation) {
+    for (&passenger, &escort) in sim.escorts.escorts.iter() {
+        let Some(&PartyData { position, pos, .. }) = sim.parties.get(escort) else {
+            continue;
+        };
+        let Some(data) = sim.parties.get_mut(passenger) else {
+            continue;
+        };
+        data.position = position;
+        data.pos = pos;
+        sim.party_grid.insert_point(passenger, (pos.x, pos.y));
+    }
+}
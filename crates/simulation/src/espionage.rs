@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::chronicle::faction_of;
+use crate::date::Date;
+use crate::object::*;
+use crate::simulation::*;
+use crate::sites::{InfluenceKind, SiteId};
+use crate::view::SimEvent;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CovertAction {
+    /// Learn the state of the target market
+    ScoutMarket,
+    /// Take a share of the cash of the target faction
+    StealTreasury,
+    /// Lower the prosperity of the target location
+    InciteUnrest,
+}
+
+impl CovertAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ScoutMarket => "scouting",
+            Self::StealTreasury => "theft",
+            Self::InciteUnrest => "incitement",
+        }
+    }
+}
+
+struct ActionDesc {
+    base_chance: f64,
+    // Opinion lost by the victim towards the perpetrator when the plot is discovered
+    fallout: f64,
+}
+
+fn desc(action: CovertAction) -> ActionDesc {
+    match action {
+        CovertAction::ScoutMarket => ActionDesc {
+            base_chance: 0.8,
+            fallout: 10.,
+        },
+        CovertAction::StealTreasury => ActionDesc {
+            base_chance: 0.4,
+            fallout: 30.,
+        },
+        CovertAction::InciteUnrest => ActionDesc {
+            base_chance: 0.5,
+            fallout: 20.,
+        },
+    }
+}
+
+// Security influence at which the chance of success is halved
+const SECURITY_SCALE: f64 = 1000.;
+const DISCOVERY_ON_SUCCESS: f64 = 0.1;
+const STOLEN_SHARE: f64 = 0.2;
+const UNREST_PROSPERITY_LOSS: f64 = 0.05;
+
+/// Locations each faction has scouted, and when
+#[derive(Default, Clone)]
+pub(crate) struct Intel {
+    reports: BTreeMap<(AgentId, LocationId), Date>,
+}
+
+impl Intel {
+    pub fn reports_of(&self, faction: AgentId) -> impl Iterator<Item = (LocationId, Date)> + '_ {
+        self.reports
+            .iter()
+            .filter(move |((x, _), _)| *x == faction)
+            .map(|(&(_, location), &date)| (location, date))
+    }
+
+    pub fn forget_location(&mut self, location: LocationId) {
+        self.reports.retain(|&(_, x), _| x != location);
+    }
+
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        self.reports.retain(|&(x, _), _| x != faction);
+    }
+}
+
+/// Security a faction enjoys at a site, where 1 halves the odds of plotters
+/// and smugglers. Only the faction's own strongholds protect it.
+pub(crate) fn security_of(sim: &Simulation, site: SiteId, faction: AgentId) -> f64 {
+    let amounts = sim.sites[site]
+        .influences
+        .iter()
+        .filter(|(typ, _)| typ.kind == InfluenceKind::Security)
+        .filter(|(typ, _)| {
+            let entity = sim.parties.get(typ.source).map(|party| party.entity);
+            let owner = entity
+                .and_then(|entity| sim.entities[entity].agent)
+                .and_then(|agent| faction_of(&sim.agents, agent));
+            owner == Some(faction)
+        })
+        .map(|&(_, amount)| amount as f64);
+    let blend = sim.influence_blends.get(InfluenceKind::Security);
+    blend.combine(amounts) / SECURITY_SCALE
+}
+
+pub(crate) fn resolve(
+    sim: &mut Simulation,
+    actor: AgentId,
+    target: LocationId,
+    action: CovertAction,
+) {
+    let Some(location) = sim.locations.get(target) else {
+        return;
+    };
+    let location_entity = location.entity;
+    let site = location.site;
+    let Some(victim) = sim.entities[location_entity]
+        .agent
+        .and_then(|agent| faction_of(&sim.agents, agent))
+    else {
+        return;
+    };
+    if victim == actor {
+        return;
+    }
+
+    let security = security_of(sim, site, victim);
+
+    let desc = desc(action);
+    let chance = desc.base_chance / (1. + security);
+    let succeeded = sim.rng.0.gen_bool(chance.clamp(0., 1.));
+    let discovery = if succeeded {
+        DISCOVERY_ON_SUCCESS
+    } else {
+        0.5 + 0.5 * security.min(1.)
+    };
+    let discovered = sim.rng.0.gen_bool(discovery.clamp(0., 1.));
+
+    if succeeded {
+        match action {
+            CovertAction::ScoutMarket => {
+                sim.intel.reports.insert((actor, target), sim.date);
+            }
+            CovertAction::StealTreasury => {
+                let stolen = sim.agents[victim].cash * STOLEN_SHARE;
+                sim.agents[victim].cash -= stolen;
+                sim.agents[actor].cash += stolen;
+            }
+            CovertAction::InciteUnrest => {
+                let location = &mut sim.locations[target];
+                location.prosperity = (location.prosperity - UNREST_PROSPERITY_LOSS).max(0.);
+            }
+        }
+    }
+
+    if discovered {
+        sim.relations.adjust(victim, actor, -desc.fallout);
+        let actor_name = &sim.entities[sim.agents[actor].entity].name;
+        let location_name = &sim.entities[location_entity].name;
+        let text = format!(
+            "agents of {actor_name} were caught plotting {} in {location_name}",
+            action.name()
+        );
+        let victim_entity = sim.agents[victim].entity;
+        sim.record_chronicle(victim_entity, text);
+    }
+
+    sim.events.push(SimEvent::CovertActionResolved {
+        actor: ObjectId(ObjectHandle::Entity(sim.agents[actor].entity)),
+        target: ObjectId(ObjectHandle::Entity(location_entity)),
+        action,
+        succeeded,
+        discovered,
+    });
+}
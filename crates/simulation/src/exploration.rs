@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::Rng;
+use slotmap::SecondaryMap;
+
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+use crate::sites::{EdgeKind, PassageKind, SiteId};
+
+// Passages hidden in a map, at most
+const MAX_PASSAGES: usize = 4;
+// Only sites closer than this many times the average road length can be
+// joined by a passage
+const MAX_PASSAGE_LENGTH: f32 = 1.5;
+// Passages save at least this share of the trip between their ends by road
+const MIN_SHORTCUT: f32 = 0.4;
+// Daily chance of a character standing at one end of a passage finding it
+const DISCOVERY_CHANCE: f64 = 0.05;
+
+/// Passages each faction has found
+#[derive(Default, Clone)]
+pub(crate) struct Exploration {
+    known: BTreeMap<AgentId, BTreeSet<(SiteId, SiteId)>>,
+}
+
+impl Exploration {
+    pub fn known_by(&self, faction: AgentId) -> impl Iterator<Item = (SiteId, SiteId)> + '_ {
+        self.known.get(&faction).into_iter().flatten().copied()
+    }
+
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        self.known.remove(&faction);
+    }
+}
+
+/// Hides the passages of a newly built map between the pairs of sites they
+/// shorten the trip between the most
+pub(crate) fn hide_passages(sim: &mut Simulation) {
+    let sites = &sim.sites;
+    // Rivers say nothing of how far apart the roads leave sites
+    let roads: Vec<f32> = sites
+        .iter()
+        .flat_map(|(id, _)| sites.neighbours(id))
+        .filter(|&&(_, _, kind)| kind == EdgeKind::Land)
+        .map(|&(_, distance, _)| distance)
+        .collect();
+    if roads.is_empty() {
+        return;
+    }
+    let reach = MAX_PASSAGE_LENGTH * roads.iter().sum::<f32>() / roads.len() as f32;
+
+    let ids: Vec<_> = sites.iter().map(|(id, _)| id).collect();
+    let mut candidates = vec![];
+    for (idx, &a) in ids.iter().enumerate() {
+        for &b in &ids[idx + 1..] {
+            let direct = sites[a].pos.distance(sites[b].pos);
+            let connected = sites.neighbours(a).iter().any(|&(x, _, _)| x == b);
+            if direct > reach || connected {
+                continue;
+            }
+            let Some((_, by_road)) = sites.astar(a, b) else {
+                continue;
+            };
+            let saving = 1. - direct / by_road;
+            if saving >= MIN_SHORTCUT {
+                candidates.push((a, b, saving));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| y.2.total_cmp(&x.2));
+
+    let mut used = BTreeSet::new();
+    let mut hidden = 0;
+    for (a, b, _) in candidates {
+        if hidden == MAX_PASSAGES {
+            break;
+        }
+        // One passage per site, so that they are spread over the map
+        if used.contains(&a) || used.contains(&b) {
+            continue;
+        }
+        let kind = if hidden % 2 == 0 {
+            PassageKind::Ford
+        } else {
+            PassageKind::MountainPass
+        };
+        sim.sites.add_passage(a, b, kind);
+        used.extend([a, b]);
+        hidden += 1;
+    }
+}
+
+/// Characters standing at one end of a passage their faction does not know
+/// may find it
+pub(crate) fn tick(sim: &mut Simulation) {
+    let passages: Vec<_> = sim.sites.passages().collect();
+    if passages.is_empty() {
+        return;
+    }
+
+    let explorers: Vec<_> = sim
+        .entities
+        .iter()
+        .filter_map(|(entity, data)| {
+            let agent = data.agent?;
+            if !sim.agents[agent].flags.get(AgentFlag::IsCharacter) {
+                return None;
+            }
+            let faction = faction_of(&sim.agents, agent)?;
+            let site = sim.parties.get(data.party?)?.position.as_site()?;
+            Some((entity, faction, site))
+        })
+        .collect();
+
+    for (entity, faction, site) in explorers {
+        for &((a, b), kind) in &passages {
+            let known = sim
+                .exploration
+                .known
+                .get(&faction)
+                .is_some_and(|known| known.contains(&(a, b)));
+            if known || (site != a && site != b) || !sim.rng.0.gen_bool(DISCOVERY_CHANCE) {
+                continue;
+            }
+            sim.exploration
+                .known
+                .entry(faction)
+                .or_default()
+                .insert((a, b));
+            let text = format!(
+                "{} found a {} {}",
+                sim.entities[entity].name,
+                kind.name(),
+                whereabouts(sim, a, b)
+            );
+            sim.record_chronicle(entity, text);
+        }
+    }
+}
+
+// Where a passage lies, by the settlements at its ends
+pub(crate) fn whereabouts(sim: &Simulation, a: SiteId, b: SiteId) -> String {
+    let name = |site: SiteId| {
+        let location = sim.sites.get(site)?.location?;
+        Some(
+            sim.entities[sim.locations.get(location)?.entity]
+                .name
+                .as_str(),
+        )
+    };
+    match (name(a), name(b)) {
+        (Some(a), Some(b)) => format!("between {a} and {b}"),
+        (Some(x), None) | (None, Some(x)) => format!("near {x}"),
+        (None, None) => "in the wilds".to_string(),
+    }
+}
+
+/// Passages known to the faction of each party, for the parties that know any
+pub(crate) fn known_by_party(
+    sim: &Simulation,
+) -> SecondaryMap<PartyId, BTreeSet<(SiteId, SiteId)>> {
+    sim.parties
+        .keys()
+        .filter_map(|id| Some((id, known_to(sim, id)?.clone())))
+        .collect()
+}
+
+/// Passages known to the faction of a party, if it knows any
+pub(crate) fn known_to(sim: &Simulation, party: PartyId) -> Option<&BTreeSet<(SiteId, SiteId)>> {
+    let agent = sim.entities.get(sim.parties.get(party)?.entity)?.agent?;
+    let faction = faction_of(&sim.agents, agent)?;
+    sim.exploration.known.get(&faction)
+}
@@ -0,0 +1,443 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::Rng;
+use slotmap::SecondaryMap;
+
+use crate::chronicle::faction_of;
+use crate::date::{Date, Season};
+use crate::object::*;
+use crate::simulation::*;
+use crate::sites::SiteId;
+use crate::tokens::TokenCategory;
+use crate::view::SimEvent;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Edict {
+    /// Cuts the food demand of the population, at the cost of prosperity
+    Rationing,
+    /// Keeps food prices from climbing far above their base price
+    PriceCeiling,
+}
+
+impl Edict {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rationing => "rationing",
+            Self::PriceCeiling => "a ceiling on food prices",
+        }
+    }
+}
+
+// Chance of a blight striking somewhere at the start of each season
+const BLIGHT_CHANCE: f64 = 0.25;
+const DAYS_IN_SEASON: u64 = 90;
+// Share of the severity felt by the sites around the stricken one
+const BLIGHT_SPREAD: f64 = 0.5;
+
+// Food satisfaction under which a location counts as hungry
+const HUNGER_THRESHOLD: f64 = 0.8;
+// Hungry days, net of fed ones, before a location starves. A starving location
+// recovers once the count is back to zero.
+const FAMINE_ONSET_DAYS: u32 = 10;
+// Daily share of the population lost at zero food satisfaction
+const FAMINE_MORTALITY: f64 = 0.002;
+const FAMINE_PROSPERITY_LOSS: f64 = 0.005;
+
+pub(crate) const RATIONING_DEMAND: f64 = 0.75;
+const RATIONING_PROSPERITY_COST: f64 = 0.001;
+// How far above their base price food prices may go under a price ceiling
+pub(crate) const PRICE_CEILING_MARGIN: f64 = 0.25;
+// Extra target price of food goods at zero food satisfaction
+pub(crate) const HUNGER_PRICE_SHOCK: f64 = 2.;
+
+#[derive(Clone)]
+struct Blight {
+    site: SiteId,
+    severity: f64,
+    until: Date,
+}
+
+#[derive(Clone)]
+struct Shipment {
+    to: LocationId,
+    good: GoodId,
+    amount: f64,
+    arrival: Date,
+}
+
+#[derive(Clone)]
+pub(crate) struct Famine {
+    pub started: Date,
+    pub faction: Option<AgentId>,
+    pub locations: BTreeSet<LocationId>,
+    pub deaths: i64,
+}
+
+/// Failed harvests, the famines they cause and the relief sent their way
+#[derive(Default, Clone)]
+pub(crate) struct Famines {
+    blights: Vec<Blight>,
+    shipments: Vec<Shipment>,
+    edicts: BTreeSet<(AgentId, Edict)>,
+    hungry_days: BTreeMap<LocationId, u32>,
+    starving: BTreeSet<LocationId>,
+    active: Vec<Famine>,
+}
+
+impl Famines {
+    pub fn active(&self) -> &[Famine] {
+        &self.active
+    }
+
+    pub fn edicts_of(&self, faction: AgentId) -> impl Iterator<Item = Edict> + '_ {
+        self.edicts
+            .iter()
+            .filter(move |(x, _)| *x == faction)
+            .map(|&(_, edict)| edict)
+    }
+
+    pub fn is_starving(&self, location: LocationId) -> bool {
+        self.starving.contains(&location)
+    }
+
+    // Share of the usual food output that a site still yields
+    pub fn harvest(&self, site: SiteId) -> f64 {
+        self.blights
+            .iter()
+            .filter(|blight| blight.site == site)
+            .map(|blight| 1. - blight.severity)
+            .fold(1., f64::min)
+    }
+
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        self.edicts.retain(|&(x, _)| x != faction);
+    }
+}
+
+/// What the famine systems, the season and wars change about the economy of a
+/// location
+#[derive(Clone, Copy)]
+pub(crate) struct LocalConditions {
+    pub harvest: f64,
+    pub rationing: bool,
+    pub price_ceiling: bool,
+    pub season: Season,
+    pub at_war: bool,
+}
+
+impl Default for LocalConditions {
+    fn default() -> Self {
+        Self {
+            harvest: 1.,
+            rationing: false,
+            price_ceiling: false,
+            season: Season::Spring,
+            at_war: false,
+        }
+    }
+}
+
+pub(crate) fn local_conditions(sim: &Simulation) -> SecondaryMap<LocationId, LocalConditions> {
+    sim.locations
+        .iter()
+        .map(|(id, location)| (id, conditions_at(sim, location)))
+        .collect()
+}
+
+pub(crate) fn conditions_at(sim: &Simulation, location: &LocationData) -> LocalConditions {
+    let faction = sim.entities[location.entity]
+        .agent
+        .and_then(|agent| faction_of(&sim.agents, agent));
+    let decreed =
+        |edict| faction.is_some_and(|faction| sim.famines.edicts.contains(&(faction, edict)));
+    LocalConditions {
+        harvest: sim.famines.harvest(location.site),
+        rationing: decreed(Edict::Rationing),
+        price_ceiling: decreed(Edict::PriceCeiling),
+        season: sim.date.season(),
+        at_war: faction.is_some_and(|faction| crate::modifiers::at_war(sim, faction)),
+    }
+}
+
+/// Daily update of harvests, relief shipments and hunger
+pub(crate) fn tick(sim: &mut Simulation) {
+    let date = sim.date;
+    if date.is_new_season() {
+        sim.famines.blights.retain(|blight| blight.until > date);
+        roll_blight(sim);
+    }
+
+    deliver_shipments(sim);
+    update_hunger(sim);
+}
+
+fn roll_blight(sim: &mut Simulation) {
+    if sim.locations.is_empty() || !sim.rng.0.gen_bool(BLIGHT_CHANCE) {
+        return;
+    }
+    let idx = sim.rng.0.gen_range(0..sim.locations.len());
+    let Some((_, location)) = sim.locations.iter().nth(idx) else {
+        return;
+    };
+    let entity = location.entity;
+    let site = location.site;
+    let severity = sim.rng.0.gen_range(0.5..0.9);
+    let seasons = sim.rng.0.gen_range(1..=2);
+    strike(sim, site, severity, seasons);
+
+    let text = format!(
+        "blight ruined the harvest around {}",
+        sim.entities[entity].name
+    );
+    sim.record_chronicle(entity, text);
+}
+
+pub(crate) fn strike(sim: &mut Simulation, site: SiteId, severity: f64, seasons: u64) {
+    let until = sim.date.add_days(seasons * DAYS_IN_SEASON);
+    let severity = severity.clamp(0., 1.);
+    sim.famines.blights.push(Blight {
+        site,
+        severity,
+        until,
+    });
+    for &(neighbour, _, _) in sim.sites.neighbours(site) {
+        sim.famines.blights.push(Blight {
+            site: neighbour,
+            severity: severity * BLIGHT_SPREAD,
+            until,
+        });
+    }
+}
+
+pub(crate) fn set_edict(sim: &mut Simulation, faction: AgentId, edict: Edict, active: bool) {
+    let changed = if active {
+        sim.famines.edicts.insert((faction, edict))
+    } else {
+        sim.famines.edicts.remove(&(faction, edict))
+    };
+    if !changed {
+        return;
+    }
+    let entity = sim.agents[faction].entity;
+    let verb = if active { "decreed" } else { "lifted" };
+    let text = format!("{} {verb} {}", sim.entities[entity].name, edict.name());
+    sim.record_chronicle(entity, text);
+}
+
+/// Sends goods from the stock of one market to another. The receiving faction
+/// pays the sending one at the source price.
+pub(crate) fn ship(
+    sim: &mut Simulation,
+    from: LocationId,
+    to: LocationId,
+    good: GoodId,
+    amount: f64,
+) {
+    if from == to || !sim.locations.contains_key(to) {
+        return;
+    }
+    let Some(source) = sim.locations.get(from) else {
+        return;
+    };
+    let faction_at = |location: &LocationData| {
+        sim.entities[location.entity]
+            .agent
+            .and_then(|agent| faction_of(&sim.agents, agent))
+    };
+    let seller = faction_at(source);
+    let buyer = faction_at(&sim.locations[to]);
+    let price = source.market.goods[good].price;
+
+    let mut amount = amount.min(source.market.goods[good].stock).max(0.);
+    let mut payment = None;
+    if let (Some(seller), Some(buyer)) = (seller, buyer)
+        && seller != buyer
+    {
+        amount = amount.min(sim.agents[buyer].cash / price.max(0.01));
+        payment = Some((seller, buyer));
+    }
+    if amount <= 0. {
+        return;
+    }
+
+    let from_id = ObjectId(ObjectHandle::Entity(source.entity));
+    let to_id = ObjectId(ObjectHandle::Entity(sim.locations[to].entity));
+    let Some(route) = sim.measure_route(from_id, to_id) else {
+        return;
+    };
+
+    if let Some((seller, buyer)) = payment {
+        sim.agents[buyer].cash -= amount * price;
+        sim.agents[seller].cash += amount * price;
+    }
+    sim.locations[from].market.goods[good].stock -= amount;
+    sim.famines.shipments.push(Shipment {
+        to,
+        good,
+        amount,
+        arrival: sim.date.add_days(route.days_on_foot.ceil() as u64),
+    });
+}
+
+fn deliver_shipments(sim: &mut Simulation) {
+    let date = sim.date;
+    let (arrived, in_transit) = std::mem::take(&mut sim.famines.shipments)
+        .into_iter()
+        .partition(|shipment| shipment.arrival <= date);
+    sim.famines.shipments = in_transit;
+
+    for shipment in arrived {
+        let Some(location) = sim.locations.get_mut(shipment.to) else {
+            continue;
+        };
+        location.market.goods[shipment.good].stock += shipment.amount;
+        let entity = location.entity;
+        let text = format!(
+            "a shipment of {:1.0} {} reached {}",
+            shipment.amount,
+            sim.good_types[shipment.good].name.to_lowercase(),
+            sim.entities[entity].name
+        );
+        sim.record_chronicle(entity, text);
+    }
+}
+
+fn update_hunger(sim: &mut Simulation) {
+    let conditions = local_conditions(sim);
+    let famines = &mut sim.famines;
+    famines
+        .hungry_days
+        .retain(|&id, _| sim.locations.contains_key(id));
+    famines
+        .starving
+        .retain(|&id| sim.locations.contains_key(id));
+
+    let mut deaths = vec![];
+    for (id, location) in sim.locations.iter_mut() {
+        let satisfaction = location.market.food_satisfaction();
+        let days = famines.hungry_days.entry(id).or_default();
+        if satisfaction < HUNGER_THRESHOLD {
+            *days = (*days + 1).min(FAMINE_ONSET_DAYS);
+        } else {
+            *days = days.saturating_sub(1);
+        }
+        match *days {
+            0 => {
+                famines.hungry_days.remove(&id);
+                famines.starving.remove(&id);
+            }
+            FAMINE_ONSET_DAYS => {
+                famines.starving.insert(id);
+            }
+            _ => {}
+        }
+
+        if conditions[id].rationing {
+            location.prosperity = (location.prosperity - RATIONING_PROSPERITY_COST).max(0.);
+        }
+
+        if famines.is_starving(id) {
+            let hunger = 1. - satisfaction;
+            location.prosperity = (location.prosperity - FAMINE_PROSPERITY_LOSS * hunger).max(0.);
+
+            let pops: Vec<_> = sim
+                .tokens
+                .all_tokens_of_category(location.tokens, TokenCategory::Pop)
+                .map(|tok| (tok.id, tok.data.size))
+                .collect();
+            let mut dead = 0;
+            for (tok, size) in pops {
+                let lost = ((size as f64 * FAMINE_MORTALITY * hunger).round() as i64).min(size);
+                if sim.tokens.remove_size(tok, lost).is_ok() {
+                    dead += lost;
+                }
+            }
+            deaths.push((id, dead));
+        }
+    }
+
+    // Locations that recovered, or no longer exist, leave their famine
+    let mut recovered = vec![];
+    for famine in &mut famines.active {
+        famine.locations.retain(|&id| {
+            let keep = famines.starving.contains(&id);
+            if !keep {
+                recovered.push(id);
+            }
+            keep
+        });
+    }
+    for id in recovered {
+        if let Some(location) = sim.locations.get(id) {
+            sim.events.push(SimEvent::FamineChanged {
+                location: ObjectId(ObjectHandle::Entity(location.entity)),
+                starving: false,
+            });
+        }
+    }
+
+    // Newly starving locations join a famine next door, or start their own
+    for (id, dead) in deaths {
+        let location = &sim.locations[id];
+        let entity = location.entity;
+        let site = location.site;
+
+        if let Some(famine) = sim
+            .famines
+            .active
+            .iter_mut()
+            .find(|famine| famine.locations.contains(&id))
+        {
+            famine.deaths += dead;
+            continue;
+        }
+
+        let neighbours = sim.sites.neighbours(site);
+        let nearby = sim.famines.active.iter_mut().find(|famine| {
+            famine.locations.iter().any(|&other| {
+                let other = sim.locations[other].site;
+                neighbours.iter().any(|&(x, _, _)| x == other)
+            })
+        });
+        let name = &sim.entities[entity].name;
+        let text = match nearby {
+            Some(famine) => {
+                famine.locations.insert(id);
+                famine.deaths += dead;
+                format!("the famine spread to {name}")
+            }
+            None => {
+                let faction = sim.entities[entity]
+                    .agent
+                    .and_then(|agent| faction_of(&sim.agents, agent));
+                sim.famines.active.push(Famine {
+                    started: sim.date,
+                    faction,
+                    locations: BTreeSet::from([id]),
+                    deaths: dead,
+                });
+                format!("famine struck {name}")
+            }
+        };
+        sim.record_chronicle(entity, text);
+        sim.events.push(SimEvent::FamineChanged {
+            location: ObjectId(ObjectHandle::Entity(entity)),
+            starving: true,
+        });
+    }
+
+    let (ended, active) = std::mem::take(&mut sim.famines.active)
+        .into_iter()
+        .partition(|famine| famine.locations.is_empty());
+    sim.famines.active = active;
+    let ended: Vec<Famine> = ended;
+    for famine in ended {
+        let text = format!(
+            "the famine that began in {} of year {} came to an end, having claimed {} lives",
+            famine.started.season().name(),
+            famine.started.calendar_year(),
+            famine.deaths
+        );
+        sim.chronicle.record(sim.date, famine.faction, text);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use crate::simulation::*;
+use crate::sites::Mobility;
+
+/// Parties travelling together as one, behind the leader of each group,
+/// which goes where the group is ordered at the pace of its slowest member
+#[derive(Default, Clone)]
+pub(crate) struct Groups {
+    // The other members of each group, by its leader
+    members: BTreeMap<PartyId, Vec<PartyId>>,
+    leaders: BTreeMap<PartyId, PartyId>,
+}
+
+impl Groups {
+    pub fn leader_of(&self, party: PartyId) -> Option<PartyId> {
+        self.leaders.get(&party).copied()
+    }
+
+    pub fn members(&self, leader: PartyId) -> &[PartyId] {
+        self.members.get(&leader).map_or(&[], Vec::as_slice)
+    }
+
+    /// The ways all of the group a party leads can travel
+    pub fn mobility(&self, parties: &Parties, leader: PartyId) -> Mobility {
+        self.members(leader)
+            .iter()
+            .filter_map(|&member| parties.get(member))
+            .fold(parties[leader].mobility(), |acc, member| Mobility {
+                boats: acc.boats && member.boats,
+                off_road: acc.off_road && member.off_road,
+                ship: acc.ship && member.ship,
+            })
+    }
+
+    fn join(&mut self, leader: PartyId, party: PartyId) {
+        self.members.entry(leader).or_default().push(party);
+        self.leaders.insert(party, leader);
+    }
+}
+
+/// A party joins the group of another standing where it is, bringing along
+/// any it led. Parties that do not move stay apart. Returns whether it joined.
+pub(crate) fn merge(sim: &mut Simulation, leader: PartyId, party: PartyId) -> bool {
+    let leader = sim.groups.leader_of(leader).unwrap_or(leader);
+    let (Some(leader_data), Some(party_data)) = (sim.parties.get(leader), sim.parties.get(party))
+    else {
+        return false;
+    };
+    if leader == party
+        || sim.groups.leader_of(party).is_some()
+        || sim.escorts.escort_of(leader).is_some()
+        || sim.escorts.escort_of(party).is_some()
+        || leader_data.movement_speed == 0.
+        || party_data.movement_speed == 0.
+        || leader_data.position != party_data.position
+        // Ships and those on land go separate ways
+        || leader_data.ship != party_data.ship
+    {
+        return false;
+    }
+    let followers = sim.groups.members.remove(&party).unwrap_or_default();
+    for member in std::iter::once(party).chain(followers) {
+        sim.groups.join(leader, member);
+        sim.parties[member].movement.clear_orders();
+        sim.parties[member].movement.path.clear();
+    }
+    true
+}
+
+/// A party leaves its group where it stands. A leader that leaves hands the
+/// group to the first of the others.
+pub(crate) fn detach(sim: &mut Simulation, party: PartyId) {
+    if let Some(leader) = sim.groups.leaders.remove(&party) {
+        let members = sim.groups.members.entry(leader).or_default();
+        members.retain(|&member| member != party);
+        if members.is_empty() {
+            sim.groups.members.remove(&leader);
+        }
+        return;
+    }
+    let Some(mut members) = sim.groups.members.remove(&party) else {
+        return;
+    };
+    let heir = members.remove(0);
+    sim.groups.leaders.remove(&heir);
+    if let Some(data) = sim.parties.get(party) {
+        let movement = data.movement.clone();
+        if let Some(heir) = sim.parties.get_mut(heir) {
+            heir.movement = movement;
+        }
+    }
+    for member in members {
+        sim.groups.leaders.remove(&member);
+        sim.groups.join(heir, member);
+    }
+}
+
+/// Moves the members of every group to where their leader stands
+pub(crate) fn follow(sim: &mut Simulation) {
+    for (&member, &leader) in sim.groups.leaders.iter() {
+        let Some(&PartyData { position, pos, .. }) = sim.parties.get(leader) else {
+            continue;
+        };
+        let Some(data) = sim.parties.get_mut(member) else {
+            continue;
+        };
+        data.position = position;
+        data.pos = pos;
+        sim.party_grid.insert_point(member, (pos.x, pos.y));
+    }
+}
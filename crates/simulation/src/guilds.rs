@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+
+use slotmap::SecondaryMap;
+
+use crate::date::Date;
+use crate::simulation::*;
+use crate::sites::InfluenceKind;
+
+// Towns of a faction it takes to charter a guild
+const CHARTER_TOWNS: usize = 1;
+// Purse a guild keeps for each of its towns before easing its dues
+const RESERVE_PER_TOWN: f64 = 2000.;
+const MIN_DUES: f64 = 0.01;
+const MAX_DUES: f64 = 0.1;
+// Change in dues each month the purse is off its reserve
+const DUES_STEP: f64 = 0.01;
+// Least profit members look for on a run while the purse is short
+const CAUTIOUS_PROFIT: f64 = 200.;
+// Members short of this much cash are staked from the purse
+const STAKE: f64 = 1000.;
+// Days a toll privilege lasts
+const PRIVILEGE_DAYS: u64 = 360;
+// Share of a year of tolls a faction asks to waive them, and the share the
+// guild bids at first, raised each month the faction turns it down
+const PRIVILEGE_ASK: f64 = 0.7;
+const OPENING_BID: f64 = 0.4;
+const BID_STEP: f64 = 0.05;
+
+/// How the members of a guild trade
+#[derive(Clone, Copy)]
+pub(crate) struct GuildPolicy {
+    /// Share of the proceeds of their sales members pay into the purse
+    pub dues: f64,
+    /// Least profit, net of the journey, a run must promise for members to set out
+    pub min_profit: f64,
+}
+
+/// An association of the merchants of the towns of a faction. It pools their
+/// capital in a purse, funded by dues and what disbanded members leave, and
+/// buys them freedom from the tolls of foreign factions.
+#[derive(Clone)]
+pub(crate) struct Guild {
+    pub faction: AgentId,
+    pub policy: GuildPolicy,
+    // Factions that waive their tolls for members, until the given date
+    pub privileges: BTreeMap<AgentId, Date>,
+    // Tolls members paid to each faction since the month began
+    tolls: BTreeMap<AgentId, f64>,
+    // Share of a year of tolls the guild offers each faction for a privilege
+    bids: BTreeMap<AgentId, f64>,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Guilds {
+    // Keyed by the agent of the guild
+    pub entries: BTreeMap<AgentId, Guild>,
+    // Guild of each town
+    towns: SecondaryMap<LocationId, AgentId>,
+}
+
+impl Guilds {
+    pub fn of_town(&self, location: LocationId) -> Option<AgentId> {
+        self.towns.get(location).copied()
+    }
+
+    pub fn towns_of(&self, guild: AgentId) -> impl Iterator<Item = LocationId> + '_ {
+        self.towns
+            .iter()
+            .filter(move |&(_, &of)| of == guild)
+            .map(|(location, _)| location)
+    }
+
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        for guild in self.entries.values_mut() {
+            guild.privileges.remove(&faction);
+            guild.tolls.remove(&faction);
+            guild.bids.remove(&faction);
+        }
+        self.entries.retain(|_, guild| guild.faction != faction);
+        let entries = &self.entries;
+        self.towns.retain(|_, guild| entries.contains_key(guild));
+    }
+}
+
+/// The guild a merchant belongs to, that of the town it sets out from
+pub(crate) fn member_of(sim: &Simulation, agent: AgentId) -> Option<AgentId> {
+    let entity = &sim.entities[sim.agents.entries.get(agent)?.entity];
+    let behavior = sim.beahviors.get(entity.behavior?)?;
+    let Goal::ArbitrageTrade { base } = behavior.vocation else {
+        return None;
+    };
+    let town = sim.parties.get(base)?.location?;
+    sim.guilds.of_town(town)
+}
+
+/// The least profit a run from a town must promise for its merchants
+pub(crate) fn min_profit(sim: &Simulation, town: LocationId) -> f64 {
+    sim.guilds
+        .of_town(town)
+        .and_then(|guild| sim.guilds.entries.get(&guild))
+        .map_or(0., |guild| guild.policy.min_profit)
+}
+
+/// Whether a faction waives its tolls for a trader
+pub(crate) fn exempts(sim: &Simulation, agent: AgentId, faction: AgentId) -> bool {
+    member_of(sim, agent)
+        .and_then(|guild| sim.guilds.entries.get(&guild))
+        .and_then(|guild| guild.privileges.get(&faction))
+        .is_some_and(|&until| sim.date < until)
+}
+
+/// Collects the dues of a member on the proceeds of its sales, and keeps
+/// count of the tolls it paid to foreign factions
+pub(crate) fn settle_trade(
+    sim: &mut Simulation,
+    agent: AgentId,
+    sales: f64,
+    tolls: Option<(AgentId, f64)>,
+) {
+    let Some(id) = member_of(sim, agent) else {
+        return;
+    };
+    let Some(guild) = sim.guilds.entries.get_mut(&id) else {
+        return;
+    };
+    if let Some((collector, paid)) = tolls.filter(|&(_, paid)| paid > 0.) {
+        *guild.tolls.entry(collector).or_default() += paid;
+    }
+    let dues = (sales * guild.policy.dues).min(sim.agents[agent].cash.max(0.));
+    sim.agents[agent].cash -= dues;
+    sim.agents[id].cash += dues;
+}
+
+/// Hands the purse of a disbanding member over to its guild
+pub(crate) fn bequeath(sim: &mut Simulation, entity: EntityId) {
+    let Some(agent) = sim.entities.get(entity).and_then(|entity| entity.agent) else {
+        return;
+    };
+    let Some(guild) = member_of(sim, agent) else {
+        return;
+    };
+    let cash = std::mem::take(&mut sim.agents[agent].cash).max(0.);
+    sim.agents[guild].cash += cash;
+}
+
+/// Draws the capital of a new member from the purse of the guild of its town,
+/// as far as it goes
+pub(crate) fn put_up(sim: &mut Simulation, town: LocationId, capital: f64) {
+    if let Some(guild) = sim.guilds.of_town(town) {
+        let purse = &mut sim.agents[guild].cash;
+        *purse -= capital.min(*purse).max(0.);
+    }
+}
+
+/// Stakes the members that run short of cash out of the purse of their guild
+pub(crate) fn stake(sim: &mut Simulation) {
+    let members: Vec<_> = sim
+        .beahviors
+        .values()
+        .filter_map(|behavior| sim.entities[behavior.entity].agent)
+        .filter_map(|agent| Some((agent, member_of(sim, agent)?)))
+        .collect();
+    for (agent, guild) in members {
+        let short = STAKE - sim.agents[agent].cash;
+        let stake = short.min(sim.agents[guild].cash);
+        if stake > 0. {
+            sim.agents[guild].cash -= stake;
+            sim.agents[agent].cash += stake;
+        }
+    }
+}
+
+pub(crate) fn tick(sim: &mut Simulation) {
+    if !sim.date.is_new_month() {
+        return;
+    }
+    charter(sim);
+    set_policies(sim);
+    negotiate(sim);
+}
+
+// Binds the towns of each faction to its guild, chartering one once the
+// faction holds enough towns
+fn charter(sim: &mut Simulation) {
+    let mut towns: BTreeMap<AgentId, Vec<LocationId>> = BTreeMap::new();
+    for (id, location) in &sim.locations {
+        let is_town = location
+            .influence_sources
+            .iter()
+            .any(|source| source.kind == InfluenceKind::Market);
+        let faction = crate::autonomy::treasury_of(sim, id);
+        if let (true, Some(faction)) = (is_town, faction) {
+            towns.entry(faction).or_default().push(id);
+        }
+    }
+
+    sim.guilds.towns.clear();
+    for (faction, towns) in towns {
+        let guild = sim
+            .guilds
+            .entries
+            .iter()
+            .find(|(_, guild)| guild.faction == faction)
+            .map(|(&id, _)| id);
+        let guild = match guild {
+            Some(guild) => guild,
+            None if towns.len() >= CHARTER_TOWNS => {
+                found(sim, faction, &towns);
+                continue;
+            }
+            None => continue,
+        };
+        for town in towns {
+            sim.guilds.towns.insert(town, guild);
+        }
+    }
+}
+
+fn found(sim: &mut Simulation, faction: AgentId, towns: &[LocationId]) {
+    let seat = towns
+        .iter()
+        .copied()
+        .max_by_key(|&town| sim.locations[town].population)
+        .unwrap();
+    let name = format!("Guild of {}", sim.entities[sim.locations[seat].entity].name);
+    let entity = sim.entities.insert(EntityData {
+        name,
+        kind_name: "Guild",
+        ..Default::default()
+    });
+    let agent = sim.agents.insert(AgentData {
+        entity,
+        flags: AgentFlags::default(),
+        cash: 0.,
+        personality: sim.agents[faction].personality,
+        tax_rate: 0.,
+        tax_revenue: 0.,
+        toll_rate: 0.,
+        toll_revenue: 0.,
+    });
+    sim.entities[entity].agent = Some(agent);
+    sim.agents.political_hierarchy.insert(faction, agent);
+
+    let guild = Guild {
+        faction,
+        policy: GuildPolicy {
+            dues: MIN_DUES,
+            min_profit: 0.,
+        },
+        privileges: BTreeMap::new(),
+        tolls: BTreeMap::new(),
+        bids: BTreeMap::new(),
+    };
+    sim.guilds.entries.insert(agent, guild);
+    for &town in towns {
+        sim.guilds.towns.insert(town, agent);
+    }
+
+    let faction_entity = sim.agents[faction].entity;
+    let names: Vec<_> = towns
+        .iter()
+        .map(|&town| sim.entities[sim.locations[town].entity].name.as_str())
+        .collect();
+    let text = format!(
+        "the merchants of {} were chartered as the {}",
+        names.join(", "),
+        sim.entities[entity].name
+    );
+    sim.record_chronicle_about(&[entity, faction_entity], text);
+    sim.view_revision += 1;
+}
+
+// Dues rise while the purse is short of its reserve, and members only set
+// out on surer runs, and both ease once it is full
+fn set_policies(sim: &mut Simulation) {
+    let ids: Vec<_> = sim.guilds.entries.keys().copied().collect();
+    for id in ids {
+        let towns = sim.guilds.towns_of(id).count();
+        let short = sim.agents[id].cash < RESERVE_PER_TOWN * towns as f64;
+        let policy = &mut sim.guilds.entries.get_mut(&id).unwrap().policy;
+        policy.dues = if short {
+            (policy.dues + DUES_STEP).min(MAX_DUES)
+        } else {
+            (policy.dues - DUES_STEP).max(MIN_DUES)
+        };
+        policy.min_profit = if short { CAUTIOUS_PROFIT } else { 0. };
+    }
+}
+
+// Guilds buy a year free of the tolls of the factions their members paid
+// the most to, raising their bid each month they are turned down
+fn negotiate(sim: &mut Simulation) {
+    let date = sim.date;
+    let ids: Vec<_> = sim.guilds.entries.keys().copied().collect();
+    for id in ids {
+        let guild = sim.guilds.entries.get_mut(&id).unwrap();
+        guild.privileges.retain(|_, until| date < *until);
+        let tolls = std::mem::take(&mut guild.tolls);
+
+        for (faction, paid) in tolls {
+            let guild = &sim.guilds.entries[&id];
+            if guild.privileges.contains_key(&faction) || !sim.agents.entries.contains_key(faction)
+            {
+                continue;
+            }
+            let yearly = paid * 12.;
+            let bid = guild.bids.get(&faction).copied().unwrap_or(OPENING_BID);
+            let price = yearly * bid;
+            let guild = sim.guilds.entries.get_mut(&id).unwrap();
+            if bid < PRIVILEGE_ASK || sim.agents[id].cash < price {
+                guild
+                    .bids
+                    .insert(faction, (bid + BID_STEP).min(PRIVILEGE_ASK));
+                continue;
+            }
+            guild.bids.remove(&faction);
+            guild
+                .privileges
+                .insert(faction, date.add_days(PRIVILEGE_DAYS));
+            sim.agents[id].cash -= price;
+            sim.agents[faction].cash += price;
+
+            let (guild_entity, faction_entity) =
+                (sim.agents[id].entity, sim.agents[faction].entity);
+            let text = format!(
+                "the {} bought a year free of the tolls of {} for {price:1.0}$",
+                sim.entities[guild_entity].name, sim.entities[faction_entity].name
+            );
+            sim.record_chronicle_about(&[guild_entity, faction_entity], text);
+        }
+    }
+}
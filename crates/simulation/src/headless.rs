@@ -0,0 +1,114 @@
+use crate::simulation::*;
+
+/// Aggregate figures sampled from a running simulation, used to balance
+/// the economy without launching the game.
+pub struct Statistics {
+    pub epoch: u64,
+    pub date: String,
+    pub num_locations: usize,
+    pub num_parties: usize,
+    pub total_population: i64,
+    pub total_cash: f64,
+    // Cash taken out of circulation by upkeep on the last day
+    pub cash_drained: f64,
+    // Multiplier of the base prices of goods
+    pub inflation: f64,
+    pub total_income: f64,
+    pub total_food_stockpile: f64,
+    pub starving_locations: usize,
+    pub active_famines: usize,
+    // Average price of each good across all markets
+    pub average_prices: Vec<(&'static str, f64)>,
+}
+
+impl Statistics {
+    /// Name and formatted value of each figure, in column order
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            ("epoch".to_string(), self.epoch.to_string()),
+            ("date".to_string(), self.date.clone()),
+            ("locations".to_string(), self.num_locations.to_string()),
+            ("parties".to_string(), self.num_parties.to_string()),
+            ("population".to_string(), self.total_population.to_string()),
+            ("cash".to_string(), format!("{:.2}", self.total_cash)),
+            ("upkeep".to_string(), format!("{:.2}", self.cash_drained)),
+            ("inflation".to_string(), format!("{:.3}", self.inflation)),
+            ("income".to_string(), format!("{:.2}", self.total_income)),
+            (
+                "food_stockpile".to_string(),
+                format!("{:.2}", self.total_food_stockpile),
+            ),
+            ("starving".to_string(), self.starving_locations.to_string()),
+            ("famines".to_string(), self.active_famines.to_string()),
+        ];
+        fields.extend(
+            self.average_prices
+                .iter()
+                .map(|(tag, price)| (format!("price_{tag}"), format!("{price:.3}"))),
+        );
+        fields
+    }
+
+    pub fn csv_header(&self) -> String {
+        let names: Vec<_> = self.fields().into_iter().map(|(name, _)| name).collect();
+        names.join(",")
+    }
+
+    pub fn csv_row(&self) -> String {
+        let values: Vec<_> = self.fields().into_iter().map(|(_, value)| value).collect();
+        values.join(",")
+    }
+}
+
+impl Simulation {
+    pub fn statistics(&self) -> Statistics {
+        let num_locations = self.locations.len();
+
+        let average_prices = self
+            .good_types
+            .iter()
+            .map(|(good_id, good)| {
+                let total: f64 = self
+                    .locations
+                    .values()
+                    .map(|location| location.market.goods[good_id].price)
+                    .sum();
+                let average = if num_locations == 0 {
+                    good.price
+                } else {
+                    total / num_locations as f64
+                };
+                (good.tag, average)
+            })
+            .collect();
+
+        Statistics {
+            epoch: self.date.epoch(),
+            date: format!(
+                "{}/{}/{}",
+                self.date.calendar_day(),
+                self.date.calendar_month(),
+                self.date.calendar_year()
+            ),
+            num_locations,
+            num_parties: self.parties.len(),
+            total_population: self.locations.values().map(|x| x.population).sum(),
+            total_cash: self.agents.entries.values().map(|x| x.cash).sum(),
+            cash_drained: self.currency.drained(),
+            inflation: self.currency.index(),
+            total_income: self.locations.values().map(|x| x.market.income).sum(),
+            total_food_stockpile: self
+                .locations
+                .values()
+                .map(|x| x.market.food_stockpile)
+                .sum(),
+            starving_locations: self
+                .locations
+                .keys()
+                .filter(|&id| self.famines.is_starving(id))
+                .count(),
+            active_famines: self.famines.active().len(),
+            average_prices,
+        }
+    }
+}
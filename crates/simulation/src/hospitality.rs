@@ -0,0 +1,89 @@
+use std::collections::BTreeSet;
+
+use slotmap::SecondaryMap;
+
+use crate::simulation::*;
+use crate::tokens::TokenCategory;
+
+/// Travellers stopping at locations on their way, and what the inns and
+/// caravansaries there earn from them
+#[derive(Default, Clone)]
+pub(crate) struct Hospitality {
+    // Travellers seen at each location since the last daily update
+    visitors: BTreeSet<(LocationId, PartyId)>,
+    // Travellers and fees of the last day
+    travellers: SecondaryMap<LocationId, u32>,
+    earnings: SecondaryMap<LocationId, f64>,
+}
+
+impl Hospitality {
+    pub fn travellers(&self, location: LocationId) -> u32 {
+        self.travellers.get(location).copied().unwrap_or(0)
+    }
+
+    pub fn earnings(&self, location: LocationId) -> f64 {
+        self.earnings.get(location).copied().unwrap_or(0.)
+    }
+}
+
+/// Notes the travellers passing through or resting at a location
+pub(crate) fn observe(sim: &mut Simulation) {
+    for (id, party) in &sim.parties {
+        if party.location.is_some() || party.movement_speed <= 0. {
+            continue;
+        }
+        let location = party
+            .position
+            .as_site()
+            .and_then(|site| sim.sites.get(site))
+            .and_then(|site| site.location);
+        if let Some(location) = location {
+            sim.hospitality.visitors.insert((location, id));
+        }
+    }
+}
+
+// Daily fee a traveller pays at a location
+pub(crate) fn fee_at(sim: &Simulation, location: LocationId) -> f64 {
+    sim.tokens
+        .all_tokens_of_category(sim.locations[location].tokens, TokenCategory::Building)
+        .map(|tok| tok.data.size as f64 * tok.typ.hospitality)
+        .sum()
+}
+
+/// Charges the travellers of the day for their stay, paying the locations
+/// that put them up
+pub(crate) fn tick(sim: &mut Simulation) {
+    let visitors = std::mem::take(&mut sim.hospitality.visitors);
+    sim.hospitality.travellers.clear();
+    sim.hospitality.earnings.clear();
+    for (location, party) in visitors {
+        let (Some(location_data), Some(party_data)) =
+            (sim.locations.get(location), sim.parties.get(party))
+        else {
+            continue;
+        };
+        *sim.hospitality
+            .travellers
+            .entry(location)
+            .unwrap()
+            .or_default() += 1;
+
+        let host = sim.entities[location_data.entity].agent;
+        let guest = sim.entities[party_data.entity].agent;
+        let (Some(host), Some(guest)) = (host, guest) else {
+            continue;
+        };
+        let paid = fee_at(sim, location).min(sim.agents[guest].cash).max(0.);
+        if paid <= 0. {
+            continue;
+        }
+        sim.agents[guest].cash -= paid;
+        sim.agents[host].cash += paid;
+        *sim.hospitality
+            .earnings
+            .entry(location)
+            .unwrap()
+            .or_default() += paid;
+    }
+}
@@ -0,0 +1,73 @@
+use crate::simulation::*;
+use crate::sites::Sites;
+use crate::tick::pos_of_grid_coordinate;
+
+/// Parties this close are in contact, even when they passed each other
+/// between two ticks
+pub(crate) const CATCH_RADIUS: f32 = 0.1;
+// Times the meeting point is refined, each from the last guess
+const PREDICTION_ROUNDS: usize = 3;
+
+pub(crate) fn in_contact(sites: &Sites, a: &PartyData, b: &PartyData) -> bool {
+    a.position == b.position
+        || pos_of_grid_coordinate(sites, a.position)
+            .distance(pos_of_grid_coordinate(sites, b.position))
+            <= CATCH_RADIUS
+}
+
+/// Where a party chasing another should head to meet it: as far ahead along
+/// the path of its quarry as the quarry goes while the pursuer gets there
+pub(crate) fn intercept(sim: &Simulation, pursuer: PartyId, quarry: PartyId) -> GridCoord {
+    // Those escorted or in a group are where their escort or leader takes them
+    let quarry = sim.escorts.escort_of(quarry).unwrap_or(quarry);
+    let quarry = sim.groups.leader_of(quarry).unwrap_or(quarry);
+    let quarry_data = &sim.parties[quarry];
+    // At the pace they actually travel, save that the ways ahead of the
+    // quarry are weighed along its path
+    let pace = crate::pace::Pace::of(sim);
+    let quarry_speed = pace.cruising_speed(quarry);
+    let pursuer_speed = pace.speed(pursuer);
+    if quarry_speed == 0. || pursuer_speed == 0. || quarry_data.movement.path.is_empty() {
+        return quarry_data.position;
+    }
+    let mobility = sim.groups.mobility(&sim.parties, quarry);
+    let from = pos_of_grid_coordinate(&sim.sites, sim.parties[pursuer].position);
+    let mut aim = quarry_data.position;
+    for _ in 0..PREDICTION_ROUNDS {
+        let distance = from.distance(pos_of_grid_coordinate(&sim.sites, aim));
+        let ahead = distance / pursuer_speed * quarry_speed;
+        aim = along_path(&sim.sites, quarry_data, mobility, ahead);
+    }
+    aim
+}
+
+// Where a party will be once it has gone some distance along its path, the
+// slower stretches counting as longer
+fn along_path(
+    sites: &Sites,
+    party: &PartyData,
+    mobility: crate::sites::Mobility,
+    distance: f32,
+) -> GridCoord {
+    let mut at = party.position;
+    let mut left = distance;
+    for step in party.movement.path.iter() {
+        let Some(pair) = GridCoord::as_colinear(at, step) else {
+            break;
+        };
+        if left <= 0. {
+            break;
+        }
+        let span = pair.t2 - pair.t1;
+        let length = sites.way_length(pair.start, pair.end)
+            * span.abs()
+            * sites.slowdown(pair.start, pair.end, mobility);
+        if left < length {
+            let t = (pair.t1 + span * left / length).clamp(0., 1.);
+            return GridCoord::with_triple(pair.start, pair.end, t);
+        }
+        left -= length;
+        at = step;
+    }
+    at
+}
@@ -0,0 +1,91 @@
+use slotmap::SecondaryMap;
+use util::ring_buffer::RingBuffer;
+
+use crate::simulation::*;
+
+// Months of statistics kept for each faction
+const HISTORY_MONTHS: usize = 24;
+
+/// The economy of a faction, as it stood at the end of a month
+#[derive(Default, Clone, Copy)]
+pub(crate) struct FactionStats {
+    pub population: i64,
+    // Sum of the incomes of the markets of its settlements
+    pub gdp: f64,
+    pub treasury: f64,
+    // Value of what its parties sold over the month, less what they bought
+    pub trade_balance: f64,
+    pub settlements: usize,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Ledger {
+    entries: SecondaryMap<AgentId, RingBuffer<FactionStats>>,
+    // Sales and purchases of the parties of each faction since the month began
+    trade: SecondaryMap<AgentId, (f64, f64)>,
+}
+
+impl Ledger {
+    pub fn latest(&self, faction: AgentId) -> Option<&FactionStats> {
+        self.entries
+            .get(faction)
+            .and_then(|history| history.newest())
+    }
+
+    pub fn history(&self, faction: AgentId) -> impl Iterator<Item = &FactionStats> {
+        self.entries.get(faction).into_iter().flat_map(|x| x.iter())
+    }
+
+    /// Counts a trade towards the balance of the faction of the trader
+    pub fn record_trade(&mut self, faction: AgentId, sold: f64, bought: f64) {
+        let entry = self.trade.entry(faction).unwrap().or_default();
+        entry.0 += sold;
+        entry.1 += bought;
+    }
+}
+
+/// Once a month, takes stock of the economy of each faction
+pub(crate) fn tick(sim: &mut Simulation) {
+    if !sim.date.is_new_month() {
+        return;
+    }
+
+    let mut stats: SecondaryMap<AgentId, FactionStats> = sim
+        .agents
+        .entries
+        .iter()
+        .filter(|(_, agent)| agent.flags.get(AgentFlag::IsFaction))
+        .map(|(id, agent)| {
+            let (sold, bought) = sim.ledger.trade.get(id).copied().unwrap_or_default();
+            let stats = FactionStats {
+                treasury: agent.cash,
+                trade_balance: sold - bought,
+                ..Default::default()
+            };
+            (id, stats)
+        })
+        .collect();
+    for (id, location) in &sim.locations {
+        let Some(faction) = crate::autonomy::treasury_of(sim, id) else {
+            continue;
+        };
+        let Some(entry) = stats.get_mut(faction) else {
+            continue;
+        };
+        entry.population += location.population;
+        entry.gdp += location.market.income;
+        entry.settlements += 1;
+    }
+
+    let ledger = &mut sim.ledger;
+    ledger.trade.clear();
+    ledger.entries.retain(|id, _| stats.contains_key(id));
+    for (id, entry) in stats {
+        ledger
+            .entries
+            .entry(id)
+            .unwrap()
+            .or_insert_with(|| RingBuffer::new(HISTORY_MONTHS))
+            .push(entry);
+    }
+}
@@ -1,17 +1,130 @@
 mod simulation;
 pub use simulation::*;
 
+mod advisor;
+
+mod ambitions;
+
+mod arbitration;
+
+mod autonomy;
+
+mod autosave;
+pub use autosave::Autosaves;
+
+mod characters;
+
+mod chronicle;
+
+mod combat;
+
+mod construction;
+
+mod contracts;
+
+mod control;
+
+mod currency;
+
 mod date;
+pub use date::Duration;
+
+mod demography;
+
+mod diplomacy;
+
+mod elimination;
+
+mod escorts;
+
+mod espionage;
+pub use espionage::CovertAction;
+
+mod exploration;
+
+mod famine;
+pub use famine::Edict;
+
+mod groups;
+
+mod guilds;
+
+mod headless;
+pub use headless::Statistics;
+
+mod hospitality;
+
+mod interception;
+
+mod loading;
+pub use loading::Loader;
+
+mod lore;
+
+mod ledger;
+
+mod migration;
+
+mod modifiers;
 
 mod object;
 pub use object::{Object, ObjectId};
 
+mod pace;
+
+mod players;
+
+mod pressures;
+
+mod prosperity;
+
+mod regions;
+
+mod roadworks;
+
+mod routes;
+
+mod scenario;
+pub use scenario::{init_generated_scenario, init_scenario};
+
+mod schedule;
+pub use schedule::{Frequency, System, TickSchedule};
+
 mod sites;
+pub use sites::{Crossing, InfluenceBlend, InfluenceBlends, InfluenceKind, Terrain};
+
+mod snapshot;
+pub use snapshot::{Divergence, Snapshot};
+
+mod stances;
+pub use stances::Stance;
+
+mod stockpile;
+
+mod tags;
+
+mod templates;
+pub use templates::TemplateError;
 
 mod tick;
 pub use tick::*;
 
 mod tokens;
 
+mod unrest;
+
+mod upkeep;
+
+mod validate;
+pub use validate::{Diagnostic, Invariant};
+
 mod view;
 pub use view::*;
+
+mod view_codec;
+pub use view_codec::{ViewDecoder, ViewEncoder};
+
+mod visibility;
+
+mod worldgen;
+pub use worldgen::WorldGenParams;
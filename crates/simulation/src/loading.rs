@@ -0,0 +1,130 @@
+use util::arena::Arena;
+
+use crate::date::Date;
+use crate::scenario::FactionSetups;
+use crate::simulation::Simulation;
+use crate::templates::{EntityTemplates, TemplateError};
+use crate::worldgen::WorldGenParams;
+
+// Ticks of history generated at each step, a month at a time
+const HISTORY_STEP: u64 = Date::TICKS_IN_YEAR / 12;
+
+/// Builds a simulation a step at a time, so that a front end can show how far
+/// along it is between steps rather than freeze until the world is ready
+pub struct Loader {
+    world: Option<WorldGenParams>,
+    sim: Option<Simulation>,
+    stage: Stage,
+    // Template files read over the built-in templates, before any party spawns
+    templates: Vec<String>,
+    // Scenario files read over the built-in factions, before they are founded
+    factions: Vec<String>,
+    // Ticks of history to generate, and generated so far
+    history: u64,
+    generated: u64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Map,
+    Settlements,
+    History,
+    Ready,
+}
+
+impl Loader {
+    /// Loads the fixed scenario, or generates a map with the given parameters,
+    /// and runs years of history on it
+    pub fn new(world: Option<WorldGenParams>, history_years: u64) -> Self {
+        Self {
+            world,
+            sim: None,
+            stage: Stage::Map,
+            templates: vec![],
+            factions: vec![],
+            history: history_years * Date::TICKS_IN_YEAR,
+            generated: 0,
+        }
+    }
+
+    /// Reads a template file for the simulation to spawn parties from,
+    /// throughout its history and after
+    pub fn load_templates(&mut self, source: &str) -> Result<(), TemplateError> {
+        EntityTemplates::default().load(source)?;
+        self.templates.push(source.to_string());
+        Ok(())
+    }
+
+    /// Reads a scenario file of the factions the world starts with, and the
+    /// personalities their AI plays with
+    pub fn load_factions(&mut self, source: &str) -> Result<(), TemplateError> {
+        FactionSetups::default().load(source)?;
+        self.factions.push(source.to_string());
+        Ok(())
+    }
+
+    /// Does the next piece of work, and hands over the simulation once the
+    /// last one is done
+    pub fn step(&mut self, arena: &mut Arena) -> Option<Simulation> {
+        match self.stage {
+            Stage::Map => {
+                let mut sim = match &self.world {
+                    Some(params) => Simulation::generated(params),
+                    None => Simulation::new(),
+                };
+                for source in &self.templates {
+                    sim.load_templates(source).expect("read once already");
+                }
+                for source in &self.factions {
+                    sim.load_factions(source).expect("read once already");
+                }
+                self.sim = Some(sim);
+                self.stage = Stage::Settlements;
+            }
+            Stage::Settlements => {
+                let sim = self.sim.as_mut()?;
+                match &self.world {
+                    Some(params) => crate::init_generated_scenario(sim, arena, params),
+                    None => crate::init_scenario(sim, arena),
+                }
+                self.stage = Stage::History;
+            }
+            Stage::History if self.generated < self.history => {
+                let ticks = HISTORY_STEP.min(self.history - self.generated);
+                crate::tick::fast_forward(self.sim.as_mut()?, ticks, arena);
+                self.generated += ticks;
+            }
+            Stage::History | Stage::Ready => {
+                self.stage = Stage::Ready;
+                return self.sim.take();
+            }
+        }
+        None
+    }
+
+    /// Share of the work done so far
+    pub fn progress(&self) -> f32 {
+        match self.stage {
+            Stage::Map => 0.,
+            Stage::Settlements => 0.1,
+            Stage::History if self.history == 0 => 0.2,
+            Stage::History => 0.2 + 0.8 * self.generated as f32 / self.history as f32,
+            Stage::Ready => 1.,
+        }
+    }
+
+    /// What the next step is about
+    pub fn describe(&self) -> String {
+        match self.stage {
+            Stage::Map if self.world.is_some() => "Generating the map".to_string(),
+            Stage::Map => "Reading the map".to_string(),
+            Stage::Settlements => "Founding the settlements".to_string(),
+            Stage::History if self.generated < self.history => {
+                let year = self.generated / Date::TICKS_IN_YEAR + 1;
+                let years = self.history.div_ceil(Date::TICKS_IN_YEAR);
+                format!("Writing history, year {year} of {years}")
+            }
+            Stage::History | Stage::Ready => "Ready".to_string(),
+        }
+    }
+}
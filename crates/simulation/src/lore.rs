@@ -0,0 +1,163 @@
+use slotmap::SecondaryMap;
+
+use crate::scenario::NameGenerator;
+use crate::simulation::*;
+
+// Meanings of the prefixes the name generator puts before a name
+const PREFIXES: &[(&str, &str)] = &[
+    ("caer", "a fort"),
+    ("din", "a stronghold"),
+    ("llan", "a holy enclosure"),
+    ("tre", "a homestead"),
+];
+
+// Meanings of the syllables generated names are stitched from, longest first
+// so that they are matched greedily
+const SYLLABLES: &[(&str, &str)] = &[
+    ("bryn", "hill"),
+    ("gwen", "white"),
+    ("nant", "valley"),
+    ("ber", "summit"),
+    ("cad", "battle"),
+    ("dru", "oak"),
+    ("hel", "willow"),
+    ("lig", "host"),
+    ("mor", "sea"),
+    ("rhe", "swift"),
+    ("tal", "brow"),
+    ("wyn", "fair"),
+    ("an", "little"),
+    ("el", "bright"),
+    ("is", "low"),
+    ("or", "edge"),
+    ("ur", "first"),
+];
+
+// Whole words of the hand-authored names
+const WORDS: &[(&str, &str)] = &[
+    ("ligualid", "the strength of Lugus"),
+    ("drust", "Drust, a king of the Picts"),
+    ("heledd", "Heledd, a princess of the laments"),
+    ("rheged", "the realm itself"),
+    ("anava", "wealth"),
+    ("isura", "the lower water"),
+    ("ad", "at"),
+    ("candidam", "white"),
+    ("casam", "house"),
+];
+
+const SOURCES: &[&str] = &[
+    "as the oldest songs have it",
+    "or so the bards claim",
+    "though the monks write it otherwise",
+    "in the tongue of the first settlers",
+];
+
+const FOUNDERS: &[&str] = &[
+    "the Old",
+    "the Tall",
+    "of the Long Spear",
+    "the Generous",
+    "Ravenfeeder",
+];
+
+const DEEDS: &[&str] = &[
+    "after the legions left the north",
+    "having driven the raiders back beyond the wall",
+    "by marrying into the line of Coel",
+    "after a victory at the ford",
+    "by gathering the scattered war-bands of the hills",
+];
+
+/// Flavour texts about the names and beginnings of places and realms
+#[derive(Default, Clone)]
+pub(crate) struct Lore {
+    // Seeds the texts, along with the names they are about
+    pub seed: u64,
+    entries: SecondaryMap<EntityId, Legend>,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Legend {
+    pub etymology: Option<String>,
+    pub founding: Option<String>,
+}
+
+impl Lore {
+    pub fn get(&self, entity: EntityId) -> Option<&Legend> {
+        self.entries.get(entity)
+    }
+}
+
+/// Writes the lore of a newly created settlement or faction
+pub(crate) fn record(sim: &mut Simulation, entity: EntityId) {
+    let data = &sim.entities[entity];
+    let is_faction = data
+        .agent
+        .is_some_and(|agent| sim.agents[agent].flags.get(AgentFlag::IsFaction));
+    if data.location.is_none() && !is_faction {
+        return;
+    }
+
+    let mut rolls = NameGenerator::new(sim.lore.seed ^ hash(&data.name));
+    let legend = Legend {
+        etymology: data.location.map(|_| etymology(&data.name, &mut rolls)),
+        founding: is_faction.then(|| founding(&data.name, &mut rolls)),
+    };
+    sim.lore.entries.insert(entity, legend);
+}
+
+// FNV-1a, so that each name gets its own stream of rolls whatever the order
+// things are created in
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+fn etymology(name: &str, rolls: &mut NameGenerator) -> String {
+    let source = SOURCES[rolls.roll(SOURCES.len())];
+    let mut parts = vec![];
+    for word in name.split_whitespace() {
+        let word = word.to_lowercase();
+        if let Some(&(_, meaning)) = PREFIXES
+            .iter()
+            .chain(WORDS)
+            .find(|(known, _)| *known == word)
+        {
+            parts.push(format!("{word}, \"{meaning}\""));
+            continue;
+        }
+        match syllables(&word) {
+            Some(found) => parts.extend(
+                found
+                    .into_iter()
+                    .map(|(syllable, meaning)| format!("{syllable}, \"{meaning}\"")),
+            ),
+            None => return format!("The meaning of {name} is lost, {source}."),
+        }
+    }
+    format!("{name} comes from {}, {source}.", parts.join(" and "))
+}
+
+// Splits a word into known syllables, if it is made of nothing else
+fn syllables(word: &str) -> Option<Vec<(&'static str, &'static str)>> {
+    let mut rest = word;
+    let mut found = vec![];
+    while !rest.is_empty() {
+        let &(syllable, meaning) = SYLLABLES
+            .iter()
+            .find(|(syllable, _)| rest.starts_with(syllable))?;
+        found.push((syllable, meaning));
+        rest = &rest[syllable.len()..];
+    }
+    Some(found)
+}
+
+fn founding(name: &str, rolls: &mut NameGenerator) -> String {
+    let founder = rolls.next();
+    let epithet = FOUNDERS[rolls.roll(FOUNDERS.len())];
+    let deed = DEEDS[rolls.roll(DEEDS.len())];
+    let winters = 40 + rolls.roll(160);
+    format!("The realm of {name} was founded {winters} winters ago by {founder} {epithet}, {deed}.")
+}
@@ -0,0 +1,149 @@
+use crate::simulation::*;
+use crate::sites::InfluenceKind;
+use crate::tokens::{TokenCategory, TokenTypeId};
+
+// Below these, a location pushes its pops away
+const PUSH_FOOD_SATISFACTION: f64 = 0.8;
+const PUSH_PROSPERITY: f64 = 0.2;
+// A town must feed its own pops this well to draw migrants
+const PULL_FOOD_SATISFACTION: f64 = 0.95;
+// Monthly share of the largest pop that leaves a location in utter distress
+const MAX_MIGRATION_SHARE: f64 = 0.05;
+// Fewer migrants than this move without taking to the roads
+const MIN_CONVOY_SIZE: i64 = 50;
+
+/// Pops on their way from one location to another
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Migration {
+    pub origin: LocationId,
+    pub destination: LocationId,
+    pub pop: TokenTypeId,
+    pub size: i64,
+}
+
+/// Migrants that reached their destination
+pub(crate) struct Arrival {
+    pub entity: EntityId,
+    pub migration: Migration,
+}
+
+/// Once a month, pops leave hungry or impoverished locations for the town
+/// whose market reaches them the most. Returns the migrations large enough to
+/// be seen on the roads, whose pops have already left their origin.
+pub(crate) fn tick(sim: &mut Simulation) -> Vec<Migration> {
+    if !sim.date.is_new_month() {
+        return vec![];
+    }
+
+    let migrations: Vec<_> = sim
+        .locations
+        .iter()
+        .filter_map(|(id, location)| {
+            let distress = distress(location);
+            if distress <= 0. {
+                return None;
+            }
+            let destination = destination_for(sim, id, location)?;
+            let pop = sim
+                .tokens
+                .all_tokens_of_category(location.tokens, TokenCategory::Pop)
+                .max_by_key(|tok| tok.data.size)?;
+            let size = (pop.data.size as f64 * MAX_MIGRATION_SHARE * distress).round() as i64;
+            let migration = Migration {
+                origin: id,
+                destination,
+                pop: pop.data.typ,
+                size,
+            };
+            (size > 0).then_some((migration, pop.id))
+        })
+        .collect();
+
+    let mut convoys = vec![];
+    for (migration, token) in migrations {
+        let origin = &sim.locations[migration.origin];
+        let destination = &sim.locations[migration.destination];
+        let origin_tokens = origin.tokens;
+        let destination_tokens = destination.tokens;
+
+        if migration.size < MIN_CONVOY_SIZE {
+            let moved =
+                sim.tokens
+                    .transfer(origin_tokens, destination_tokens, token, migration.size);
+            if moved.is_ok() {
+                crate::demography::settle(
+                    sim,
+                    migration.origin,
+                    migration.destination,
+                    migration.size,
+                );
+            }
+            continue;
+        }
+        if sim.tokens.remove_size(token, migration.size).is_err() {
+            continue;
+        }
+
+        let entity = origin.entity;
+        let town = destination.entity;
+        let text = format!(
+            "{} {} left {} for {}",
+            migration.size,
+            sim.tokens.types[migration.pop].name.to_lowercase(),
+            sim.entities[entity].name,
+            sim.entities[town].name
+        );
+        sim.record_chronicle_about(&[entity, town], text);
+        convoys.push(migration);
+    }
+    convoys
+}
+
+// How badly the pops of a location want to leave, between 0 and 1
+fn distress(location: &LocationData) -> f64 {
+    let hunger = 1. - location.market.food_satisfaction() / PUSH_FOOD_SATISFACTION;
+    let poverty = 1. - location.prosperity / PUSH_PROSPERITY;
+    hunger.max(poverty).clamp(0., 1.)
+}
+
+// The town with the strongest market influence over the location, as long as
+// it is better off
+fn destination_for(
+    sim: &Simulation,
+    id: LocationId,
+    location: &LocationData,
+) -> Option<LocationId> {
+    sim.sites[location.site]
+        .influences
+        .iter()
+        .filter(|(typ, _)| typ.kind == InfluenceKind::Market)
+        .filter_map(|(typ, _)| sim.parties.get(typ.source)?.location)
+        .find(|&town| {
+            let town_data = &sim.locations[town];
+            town != id
+                && town_data.market.food_satisfaction() >= PULL_FOOD_SATISFACTION
+                && town_data.prosperity > location.prosperity
+        })
+}
+
+/// Settles the migrants that reached their destination
+pub(crate) fn resolve(sim: &mut Simulation, arrivals: Vec<Arrival>) {
+    for Arrival { entity, migration } in arrivals {
+        if !sim.locations.contains_key(migration.destination) {
+            continue;
+        }
+        crate::demography::settle(sim, migration.origin, migration.destination, migration.size);
+        let destination = &sim.locations[migration.destination];
+        let tokens = destination.tokens;
+        let town = destination.entity;
+        sim.tokens.add_token(tokens, migration.pop, migration.size);
+
+        let text = format!(
+            "{} {} settled in {}",
+            migration.size,
+            sim.tokens.types[migration.pop].name.to_lowercase(),
+            sim.entities[town].name
+        );
+        sim.record_chronicle_about(&[entity, town], text);
+    }
+}
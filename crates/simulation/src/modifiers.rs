@@ -0,0 +1,140 @@
+use crate::date::Season;
+use crate::famine::LocalConditions;
+use crate::simulation::*;
+
+// Opinion of another faction at or below which a faction counts as at war
+pub(crate) const HOSTILE_OPINION: f64 = -50.;
+
+#[derive(Clone, Copy)]
+enum Trigger {
+    Season(Season),
+    // The faction owning the market is at war
+    War,
+}
+
+// Multipliers of the demand and supply of a good while a trigger holds
+struct Modifier {
+    good: &'static str,
+    when: Trigger,
+    demand: f64,
+    supply: f64,
+}
+
+const MODIFIERS: &[Modifier] = &[
+    Modifier {
+        good: "lumber",
+        when: Trigger::Season(Season::Autumn),
+        demand: 1.2,
+        supply: 1.,
+    },
+    Modifier {
+        good: "lumber",
+        when: Trigger::Season(Season::Winter),
+        demand: 1.5,
+        supply: 0.8,
+    },
+    Modifier {
+        good: "meat",
+        when: Trigger::Season(Season::Winter),
+        demand: 1.,
+        supply: 0.8,
+    },
+    Modifier {
+        good: "tools",
+        when: Trigger::War,
+        demand: 1.5,
+        supply: 1.,
+    },
+    Modifier {
+        good: "meat",
+        when: Trigger::War,
+        demand: 1.2,
+        supply: 1.,
+    },
+];
+
+impl Trigger {
+    fn holds(self, conditions: &LocalConditions) -> bool {
+        match self {
+            Self::Season(season) => conditions.season == season,
+            Self::War => conditions.at_war,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Season(season) => season.name(),
+            Self::War => "war",
+        }
+    }
+}
+
+/// Multipliers of the demand and supply of a good at a market, under its
+/// local conditions
+pub(crate) fn factors(good: &GoodData, conditions: &LocalConditions) -> (f64, f64) {
+    MODIFIERS
+        .iter()
+        .filter(|modifier| modifier.good == good.tag && modifier.when.holds(conditions))
+        .fold((1., 1.), |(demand, supply), modifier| {
+            (demand * modifier.demand, supply * modifier.supply)
+        })
+}
+
+/// The modifiers in effect under some conditions, described, starting with
+/// those of the season on what the land yields and what pops eat
+pub(crate) fn describe(goods: &GoodTypes, conditions: &LocalConditions) -> Vec<String> {
+    let season = conditions.season;
+    let seasonal = goods.values().flat_map(|good| {
+        let parts = [
+            ("yield", good.output_in(season)),
+            ("eaten", good.demand_in(season)),
+        ];
+        parts
+            .into_iter()
+            .filter(|(_, factor)| *factor != 1.)
+            .map(move |(what, factor)| {
+                format!(
+                    "{} {what} {:+1.0}% ({})",
+                    good.tag,
+                    (factor - 1.) * 100.,
+                    season.name()
+                )
+            })
+    });
+    let triggered = MODIFIERS
+        .iter()
+        .filter(|modifier| modifier.when.holds(conditions))
+        .flat_map(|modifier| {
+            let parts = [("demand", modifier.demand), ("supply", modifier.supply)];
+            parts
+                .into_iter()
+                .filter(|(_, factor)| *factor != 1.)
+                .map(|(what, factor)| {
+                    format!(
+                        "{} {what} {:+1.0}% ({})",
+                        modifier.good,
+                        (factor - 1.) * 100.,
+                        modifier.when.name()
+                    )
+                })
+        });
+    seasonal.chain(triggered).collect()
+}
+
+/// Whether a faction is at odds with another faction badly enough to arm
+/// for it, or shuts its markets to one
+pub(crate) fn at_war(sim: &Simulation, faction: AgentId) -> bool {
+    let hostile = sim.relations.held_by(faction).any(|(other, opinion)| {
+        opinion <= HOSTILE_OPINION
+            && sim
+                .agents
+                .entries
+                .get(other)
+                .is_some_and(|agent| agent.flags.get(AgentFlag::IsFaction))
+    });
+    hostile
+        || sim
+            .relations
+            .embargoes_by(faction)
+            .any(|embargo| embargo.good.is_none() && embargo.target.is_some())
+}
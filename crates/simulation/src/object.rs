@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use crate::simulation::EntityId;
+use crate::simulation::{AgentId, EntityId};
 use crate::sites::SiteId;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -10,14 +10,24 @@ impl ObjectId {
     pub fn global() -> Self {
         Self(ObjectHandle::Global)
     }
+
+    /// Trade and building suggestions based on the current markets
+    pub fn advisor() -> Self {
+        Self(ObjectHandle::Advisor)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum ObjectHandle {
     Null,
     Global,
+    Advisor,
     Site(SiteId),
     Entity(EntityId),
+    // Economic statistics of a faction
+    Faction(AgentId),
+    // Report of a battle kept by the chronicle
+    Battle(usize),
 }
 
 impl Default for ObjectHandle {
@@ -26,15 +36,17 @@ impl Default for ObjectHandle {
     }
 }
 
-#[derive(Default)]
-pub struct Object(BTreeMap<String, Value>);
+#[derive(Default, Clone, PartialEq)]
+pub struct Object(pub(crate) BTreeMap<String, Value>);
 
+#[derive(Clone, PartialEq)]
 pub(crate) enum Value {
     Id(ObjectId),
     Flag(bool),
     String(String),
     Child(Object),
     List(Vec<Object>),
+    Series(Vec<f32>),
 }
 
 impl From<ObjectId> for Value {
@@ -85,6 +97,12 @@ impl From<Vec<Object>> for Value {
     }
 }
 
+impl From<Vec<f32>> for Value {
+    fn from(value: Vec<f32>) -> Self {
+        Self::Series(value)
+    }
+}
+
 impl Object {
     const EMPTY: &'static Object = &Object::new();
 
@@ -114,6 +132,14 @@ impl Object {
         }
     }
 
+    /// The text fields of the object, in the order of their names
+    pub fn texts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().filter_map(|(tag, value)| match value {
+            Value::String(str) => Some((tag.as_str(), str.as_str())),
+            _ => None,
+        })
+    }
+
     pub fn flag(&self, tag: &str) -> bool {
         match self.0.get(tag) {
             Some(Value::Flag(flag)) => *flag,
@@ -142,4 +168,11 @@ impl Object {
     pub fn list<'a>(&'a self, tag: &str) -> &'a [Object] {
         self.try_list(tag).unwrap_or_default()
     }
+
+    pub fn series<'a>(&'a self, tag: &str) -> &'a [f32] {
+        match self.0.get(tag) {
+            Some(Value::Series(values)) => values.as_slice(),
+            _ => &[],
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use crate::characters::Characters;
+use crate::date::{Duration, Season};
+use crate::groups::Groups;
+use crate::simulation::*;
+use crate::sites::Sites;
+use crate::tick::BASE_SPEED;
+
+// Share of its carry capacity a party can fill before the load slows it down
+const LIGHT_LOAD: f64 = 0.5;
+// Share of its speed a fully loaded party loses
+const ENCUMBRANCE_SLOWDOWN: f64 = 0.3;
+// How many times faster than usual a party on a forced march travels
+const FORCED_MARCH_SPEEDUP: f32 = 1.25;
+
+/// Something that makes a party travel faster or slower than its own speed
+#[derive(Clone, Copy)]
+pub(crate) enum Cause {
+    Terrain,
+    Road,
+    Weather(Season),
+    Encumbrance,
+    ForcedMarch,
+    Wayfaring,
+}
+
+impl Cause {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Terrain => "terrain",
+            Self::Road => "road",
+            Self::Weather(season) => season.name(),
+            Self::Encumbrance => "load",
+            Self::ForcedMarch => "forced march",
+            Self::Wayfaring => "wayfaring",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Modifier {
+    pub cause: Cause,
+    /// Multiplier of the speed of the party
+    pub factor: f32,
+}
+
+/// What bears on how fast parties travel
+pub(crate) struct Pace<'a> {
+    pub parties: &'a Parties,
+    pub groups: &'a Groups,
+    pub sites: &'a Sites,
+    pub good_types: &'a GoodTypes,
+    pub entities: &'a Entities,
+    pub characters: &'a Characters,
+    pub season: Season,
+}
+
+impl<'a> Pace<'a> {
+    pub fn of(sim: &'a Simulation) -> Self {
+        Self {
+            parties: &sim.parties,
+            groups: &sim.groups,
+            sites: &sim.sites,
+            good_types: &sim.good_types,
+            entities: &sim.entities,
+            characters: &sim.characters,
+            season: sim.date.season(),
+        }
+    }
+
+    /// How far a party travels in a tick along the leg it is on, if any,
+    /// along with its group at the pace of the slowest member
+    pub fn speed(&self, party: PartyId) -> f32 {
+        self.leg_modifiers(party)
+            .iter()
+            .fold(self.cruising_speed(party), |speed, modifier| {
+                speed * modifier.factor
+            })
+    }
+
+    /// How far a party travels in a tick with its group, before the ways it
+    /// travels along slow it down
+    pub fn cruising_speed(&self, party: PartyId) -> f32 {
+        let own = self
+            .groups
+            .members(party)
+            .iter()
+            .filter_map(|&member| self.parties.get(member).map(|data| (member, data)))
+            .chain(std::iter::once((party, &self.parties[party])))
+            .map(|(member, data)| {
+                self.own_modifiers(member)
+                    .iter()
+                    .fold(data.movement_speed, |speed, modifier| {
+                        speed * modifier.factor
+                    })
+            })
+            .fold(f32::INFINITY, f32::min);
+        own * weather(self.season)
+    }
+
+    /// How long a party takes to go along a path from where it stands, or
+    /// nothing if it cannot get there
+    pub fn travel_time(
+        &self,
+        party: PartyId,
+        path: impl IntoIterator<Item = GridCoord>,
+    ) -> Option<Duration> {
+        let speed = self.cruising_speed(party) * BASE_SPEED;
+        if speed <= 0. {
+            return None;
+        }
+        let mobility = self.groups.mobility(self.parties, party);
+        let mut at = self.parties[party].position;
+        let mut length = 0.;
+        for step in path {
+            if step == at {
+                continue;
+            }
+            let pair = GridCoord::as_colinear(at, step)?;
+            length += self.sites.way_length(pair.start, pair.end)
+                * (pair.t2 - pair.t1).abs()
+                * self.sites.slowdown(pair.start, pair.end, mobility);
+            at = step;
+        }
+        length
+            .is_finite()
+            .then(|| Duration::from_ticks((length / speed).ceil() as u64))
+    }
+
+    /// Everything changing the speed of a party, shared with its group or
+    /// its own
+    pub fn modifiers(&self, party: PartyId) -> Vec<Modifier> {
+        let leader = self.groups.leader_of(party).unwrap_or(party);
+        let mut modifiers = self.shared_modifiers(leader);
+        modifiers.extend(self.own_modifiers(party));
+        modifiers
+    }
+
+    // What the party and its group meet with on the way
+    fn shared_modifiers(&self, party: PartyId) -> Vec<Modifier> {
+        let mut modifiers = vec![Modifier {
+            cause: Cause::Weather(self.season),
+            factor: weather(self.season),
+        }];
+        modifiers.extend(self.leg_modifiers(party));
+        modifiers
+    }
+
+    // What the way the party is on is like
+    fn leg_modifiers(&self, party: PartyId) -> Vec<Modifier> {
+        let data = &self.parties[party];
+        let mut modifiers = vec![];
+        let leg = data
+            .movement
+            .path
+            .beginning()
+            .and_then(|step| GridCoord::as_colinear(data.position, step));
+        if let Some(leg) = leg {
+            let mobility = self.groups.mobility(self.parties, party);
+            let (terrain, road) = self.sites.slowdown_parts(leg.start, leg.end, mobility);
+            modifiers.push(Modifier {
+                cause: Cause::Terrain,
+                factor: 1. / terrain,
+            });
+            modifiers.push(Modifier {
+                cause: Cause::Road,
+                factor: 1. / road,
+            });
+        }
+        modifiers
+    }
+
+    // What holds for the party alone, whoever it travels with
+    fn own_modifiers(&self, party: PartyId) -> Vec<Modifier> {
+        let data = &self.parties[party];
+        let mut modifiers = vec![];
+        if data.carry_capacity > 0. {
+            let load = data.good_stock.weight(self.good_types) / data.carry_capacity;
+            let excess = ((load - LIGHT_LOAD) / (1. - LIGHT_LOAD)).clamp(0., 1.);
+            modifiers.push(Modifier {
+                cause: Cause::Encumbrance,
+                factor: (1. - ENCUMBRANCE_SLOWDOWN * excess) as f32,
+            });
+        }
+        if data.forced_march {
+            modifiers.push(Modifier {
+                cause: Cause::ForcedMarch,
+                factor: FORCED_MARCH_SPEEDUP,
+            });
+        }
+        // Characters who know the ways keep a better pace
+        if let Some(factor) = self.entities[data.entity]
+            .agent
+            .and_then(|agent| self.characters.wayfaring(agent))
+        {
+            modifiers.push(Modifier {
+                cause: Cause::Wayfaring,
+                factor,
+            });
+        }
+        modifiers
+    }
+}
+
+// Multiplier of the speed of parties travelling in a season, slowed by the
+// mud of spring and the snows of winter
+fn weather(season: Season) -> f32 {
+    match season {
+        Season::Spring => 0.9,
+        Season::Summer => 1.,
+        Season::Autumn => 0.95,
+        Season::Winter => 0.8,
+    }
+}
+
+/// The modifiers that change the speed of a party, described
+pub(crate) fn describe(modifiers: &[Modifier]) -> Vec<String> {
+    modifiers
+        .iter()
+        .filter(|modifier| (modifier.factor - 1.).abs() >= 0.005)
+        .map(|modifier| {
+            format!(
+                "{} {:+1.0}%",
+                modifier.cause.name(),
+                (modifier.factor - 1.) * 100.
+            )
+        })
+        .collect()
+}
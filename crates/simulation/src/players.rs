@@ -0,0 +1,113 @@
+use crate::chronicle::faction_of;
+use crate::object::*;
+use crate::simulation::*;
+
+/// Factions led by players rather than left to their own devices. Players take
+/// turns at the controls, and only the one whose seat is active gives orders.
+#[derive(Default, Clone)]
+pub(crate) struct Players {
+    seats: Vec<AgentId>,
+    active: usize,
+}
+
+impl Players {
+    pub fn active(&self) -> Option<AgentId> {
+        self.seats.get(self.active).copied()
+    }
+
+    pub fn controls(&self, faction: AgentId) -> bool {
+        self.seats.contains(&faction)
+    }
+
+    /// Gives up the seat of a faction, if a player had it. The controls stay
+    /// with the player who has them, or pass on if that was the seat.
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        if let Some(idx) = self.seats.iter().position(|&seat| seat == faction) {
+            self.seats.remove(idx);
+            if idx < self.active {
+                self.active -= 1;
+            }
+            if self.active >= self.seats.len() {
+                self.active = 0;
+            }
+        }
+    }
+}
+
+impl Simulation {
+    /// Hands a faction over to a player, who takes the controls if no one else
+    /// has them. Returns false if the object is not a faction.
+    pub fn add_player(&mut self, faction: ObjectId) -> bool {
+        let Some(faction) = faction_agent(self, faction) else {
+            return false;
+        };
+        if !self.players.controls(faction) {
+            self.players.seats.push(faction);
+        }
+        true
+    }
+
+    /// The faction of the player at the controls
+    pub fn player(&self) -> Option<ObjectId> {
+        self.players
+            .active()
+            .map(|faction| self.faction_object(faction))
+    }
+
+    /// The factions led by players, in the order they take turns
+    pub fn players(&self) -> Vec<ObjectId> {
+        self.players
+            .seats
+            .iter()
+            .map(|&faction| self.faction_object(faction))
+            .collect()
+    }
+
+    /// Passes the controls to the next player, and returns its faction
+    pub fn next_player(&mut self) -> Option<ObjectId> {
+        if !self.players.seats.is_empty() {
+            self.players.active = (self.players.active + 1) % self.players.seats.len();
+        }
+        self.player()
+    }
+
+    /// Gives up the seat of a player, as when it is left to observe
+    pub fn remove_player(&mut self, faction: ObjectId) {
+        if let Some(faction) = faction_agent(self, faction) {
+            self.players.forget_faction(faction);
+        }
+    }
+
+    fn faction_object(&self, faction: AgentId) -> ObjectId {
+        ObjectId(ObjectHandle::Entity(self.agents[faction].entity))
+    }
+}
+
+pub(crate) fn faction_agent(sim: &Simulation, id: ObjectId) -> Option<AgentId> {
+    let ObjectHandle::Entity(entity) = id.0 else {
+        return None;
+    };
+    let agent = sim.entities.get(entity)?.agent?;
+    sim.agents[agent]
+        .flags
+        .get(AgentFlag::IsFaction)
+        .then_some(agent)
+}
+
+/// Whether the active player may give an order about the given entity: only
+/// the parties and settlements of its own faction are at its command
+pub(crate) fn owns(sim: &Simulation, entity: EntityId) -> bool {
+    let Some(player) = sim.players.active() else {
+        return false;
+    };
+    sim.entities
+        .get(entity)
+        .and_then(|entity| entity.agent)
+        .and_then(|agent| faction_of(&sim.agents, agent))
+        == Some(player)
+}
+
+/// Whether the active player leads the faction with the given tag
+pub(crate) fn leads(sim: &Simulation, faction: &str) -> bool {
+    sim.players.active().is_some() && sim.tags.agents.lookup(faction) == sim.players.active()
+}
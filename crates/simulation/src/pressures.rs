@@ -0,0 +1,98 @@
+use crate::simulation::*;
+
+/// How a kind of pressure dies down, and what about the settlement speeds
+/// up its growth or its decay
+pub(crate) struct PressureRule {
+    /// Share of the pressure lost each day
+    pub decay: f64,
+    pub interactions: &'static [PressureInteraction],
+}
+
+/// Multipliers on the growth and the decay of a pressure while a condition
+/// of its settlement is over a threshold
+pub(crate) struct PressureInteraction {
+    pub condition: PressureCondition,
+    pub above: f64,
+    pub growth: f64,
+    pub decay: f64,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum PressureCondition {
+    /// Share of the food demand of the market met
+    FoodSatisfaction,
+    /// Population over the workers the land around can employ
+    Crowding,
+}
+
+impl PressureType {
+    pub(crate) fn rule(self) -> &'static PressureRule {
+        match self {
+            Self::Farmer => &PressureRule {
+                decay: 0.,
+                // Those the land cannot feed leave to peddle sooner
+                interactions: &[PressureInteraction {
+                    condition: PressureCondition::Crowding,
+                    above: 1.,
+                    growth: 1.5,
+                    decay: 1.,
+                }],
+            },
+            Self::Merchant => &PressureRule {
+                decay: 0.,
+                interactions: &[],
+            },
+            Self::Unrest => &PressureRule {
+                decay: 0.01,
+                // Fed people are quick to forget
+                interactions: &[PressureInteraction {
+                    condition: PressureCondition::FoodSatisfaction,
+                    above: 0.9,
+                    growth: 1.,
+                    decay: 3.,
+                }],
+            },
+        }
+    }
+}
+
+/// Where each condition of a settlement stands
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Conditions {
+    food_satisfaction: f64,
+    crowding: f64,
+}
+
+impl Conditions {
+    pub fn of(sim: &Simulation, location: LocationId) -> Self {
+        let location = &sim.locations[location];
+        let capacity: i64 = sim.sites[location.site]
+            .rgo
+            .deposits
+            .iter()
+            .map(|deposit| deposit.capacity)
+            .sum();
+        Self {
+            food_satisfaction: location.market.food_satisfaction(),
+            crowding: location.population as f64 / capacity.max(1) as f64,
+        }
+    }
+
+    fn get(&self, condition: PressureCondition) -> f64 {
+        match condition {
+            PressureCondition::FoodSatisfaction => self.food_satisfaction,
+            PressureCondition::Crowding => self.crowding,
+        }
+    }
+
+    /// Multipliers on the growth and the decay of a kind of pressure
+    pub fn factors(&self, typ: PressureType) -> (f64, f64) {
+        typ.rule()
+            .interactions
+            .iter()
+            .filter(|interaction| self.get(interaction.condition) > interaction.above)
+            .fold((1., 1.), |(growth, decay), interaction| {
+                (growth * interaction.growth, decay * interaction.decay)
+            })
+    }
+}
@@ -0,0 +1,63 @@
+use slotmap::SecondaryMap;
+
+use crate::simulation::*;
+
+// Prosperity of a location that wants for nothing and is as rich as can be
+const MAX_PROSPERITY: f64 = 0.6;
+// How much the satisfaction of demand weighs against wealth
+const SATISFACTION_WEIGHT: f64 = 0.5;
+// Daily income per head at which a location counts as half as wealthy as can be
+const REFERENCE_INCOME_PER_CAPITA: f64 = 1.;
+// Taken off the target of locations in the grip of a famine
+const FAMINE_PENALTY: f64 = 0.2;
+// Share of the way to its target prosperity covers each month. Losses from
+// unrest, rationing and famine in between are recovered at the same pace.
+const SMOOTHING: f64 = 0.2;
+
+/// The prosperity each location is heading towards
+#[derive(Default, Clone)]
+pub(crate) struct Outlook {
+    targets: SecondaryMap<LocationId, f64>,
+}
+
+impl Outlook {
+    pub fn target(&self, location: LocationId) -> Option<f64> {
+        self.targets.get(location).copied()
+    }
+}
+
+/// Once a month, moves the prosperity of each location towards what its
+/// economy supports
+pub(crate) fn tick(sim: &mut Simulation) {
+    if !sim.date.is_new_month() {
+        return;
+    }
+
+    sim.outlook.targets.clear();
+    for (id, location) in sim.locations.iter_mut() {
+        let mut target = MAX_PROSPERITY
+            * (SATISFACTION_WEIGHT * satisfaction(&location.market)
+                + (1. - SATISFACTION_WEIGHT) * wealth(location));
+        if sim.famines.is_starving(id) {
+            target -= FAMINE_PENALTY;
+        }
+        let target = target.max(0.);
+        location.prosperity += (target - location.prosperity) * SMOOTHING;
+        sim.outlook.targets.insert(id, target);
+    }
+}
+
+// Share of the demand met, weighted by its value
+pub(crate) fn satisfaction(market: &Market) -> f64 {
+    let (met, wanted) = market.goods.values().fold((0., 0.), |(met, wanted), good| {
+        let value = good.demand_base * good.price;
+        (met + value * good.satisfaction, wanted + value)
+    });
+    if wanted <= 0. { 1. } else { met / wanted }
+}
+
+// Income per head, between 0 and 1
+fn wealth(location: &LocationData) -> f64 {
+    let per_capita = (location.market.income / location.population.max(1) as f64).max(0.);
+    per_capita / (per_capita + REFERENCE_INCOME_PER_CAPITA)
+}
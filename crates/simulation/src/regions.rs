@@ -0,0 +1,128 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use slotmap::SecondaryMap;
+
+use crate::simulation::V2;
+use crate::sites::{SiteId, Sites};
+
+// Maps with fewer sites are pathed over directly, as A* over them is cheap
+const MIN_SITES: usize = 200;
+// Sites gathered into each region
+const REGION_SITES: usize = 16;
+
+/// Clusters of neighbouring sites, so that paths across a large map can be
+/// found between regions first and then refined within the regions crossed
+#[derive(Default, Clone)]
+pub(crate) struct Regions {
+    of_site: SecondaryMap<SiteId, usize>,
+    regions: Vec<Region>,
+}
+
+#[derive(Clone)]
+struct Region {
+    center: V2,
+    // Regions reached by a road out of this one
+    neighbours: BTreeSet<usize>,
+}
+
+impl Regions {
+    /// Grows regions out of the sites in turn along the roads, each until it
+    /// holds enough sites or runs out of unclaimed neighbours
+    pub fn build(sites: &Sites) -> Self {
+        let mut out = Self::default();
+        if sites.iter().len() < MIN_SITES {
+            return out;
+        }
+        let mut members: Vec<Vec<SiteId>> = vec![];
+        for (seed, _) in sites.iter() {
+            if out.of_site.contains_key(seed) {
+                continue;
+            }
+            let region = members.len();
+            let mut claimed = vec![];
+            let mut frontier = VecDeque::from([seed]);
+            out.of_site.insert(seed, region);
+            while let Some(site) = frontier.pop_front() {
+                claimed.push(site);
+                for &(next, _, _) in sites.neighbours(site) {
+                    if claimed.len() + frontier.len() >= REGION_SITES {
+                        break;
+                    }
+                    if !out.of_site.contains_key(next) {
+                        out.of_site.insert(next, region);
+                        frontier.push_back(next);
+                    }
+                }
+            }
+            members.push(claimed);
+        }
+
+        out.regions = members
+            .iter()
+            .map(|sites_of| {
+                let count = sites_of.len() as f32;
+                let (x, y) = sites_of.iter().fold((0., 0.), |(x, y), &site| {
+                    (x + sites[site].pos.x, y + sites[site].pos.y)
+                });
+                Region {
+                    center: V2 {
+                        x: x / count,
+                        y: y / count,
+                    },
+                    neighbours: BTreeSet::new(),
+                }
+            })
+            .collect();
+        for (site, _) in sites.iter() {
+            let region = out.of_site[site];
+            for &(next, _, _) in sites.neighbours(site) {
+                let other = out.of_site[next];
+                if other != region {
+                    out.regions[region].neighbours.insert(other);
+                }
+            }
+        }
+        out
+    }
+
+    /// The regions a path between two sites goes through, found over the
+    /// regions alone. None when the map is pathed over directly.
+    pub fn corridor(
+        &self,
+        start: SiteId,
+        end: SiteId,
+        known: &BTreeSet<(SiteId, SiteId)>,
+    ) -> Option<BTreeSet<usize>> {
+        let (&from, &to) = (self.of_site.get(start)?, self.of_site.get(end)?);
+        // Passages known to the party join regions too
+        let crossings: Vec<(usize, usize)> = known
+            .iter()
+            .filter_map(|&(a, b)| Some((*self.of_site.get(a)?, *self.of_site.get(b)?)))
+            .collect();
+        let cost = |a: usize, b: usize| {
+            (self.regions[a].center.distance(self.regions[b].center) * 1000.) as i64
+        };
+        let (steps, _) = pathfinding::directed::astar::astar(
+            &from,
+            |&region| {
+                let joined = crossings.iter().filter_map(move |&(a, b)| {
+                    (a == region).then_some(b).or((b == region).then_some(a))
+                });
+                self.regions[region]
+                    .neighbours
+                    .iter()
+                    .copied()
+                    .chain(joined)
+                    .map(move |next| (next, cost(region, next)))
+                    .collect::<Vec<_>>()
+            },
+            |&region| cost(region, to),
+            |&region| region == to,
+        )?;
+        Some(steps.into_iter().collect())
+    }
+
+    pub fn of(&self, site: SiteId) -> Option<usize> {
+        self.of_site.get(site).copied()
+    }
+}
@@ -0,0 +1,59 @@
+use crate::simulation::*;
+use crate::sites::SiteId;
+
+// Work a party leaves on a road by travelling it end to end
+pub(crate) const WORKS_PER_TRAVERSAL: f32 = 1.;
+// Work it takes to raise a road from each level to the next, per level
+const WORKS_PER_LEVEL: f32 = 300.;
+// Cash a faction pays for each point of work on a road
+const CASH_PER_WORKS: f64 = 10.;
+/// Upgrades a road takes at most
+pub(crate) const MAX_QUALITY: u8 = 3;
+
+/// Upgrades once a month the roads worn in or paid for enough since their
+/// last upgrade
+pub(crate) fn tick(sim: &mut Simulation) {
+    if !sim.date.is_new_month() {
+        return;
+    }
+    let ready: Vec<(SiteId, SiteId)> = sim
+        .sites
+        .roads()
+        .filter(|(_, road)| {
+            road.quality < MAX_QUALITY && road.works >= WORKS_PER_LEVEL * (road.quality + 1) as f32
+        })
+        .map(|(key, _)| key)
+        .collect();
+    for (a, b) in ready {
+        sim.sites.improve_road(a, b);
+        let towns: Vec<EntityId> = [a, b]
+            .into_iter()
+            .filter_map(|site| sim.sites[site].location)
+            .map(|location| sim.locations[location].entity)
+            .collect();
+        if towns.is_empty() {
+            continue;
+        }
+        let names: Vec<&str> = towns
+            .iter()
+            .map(|&entity| sim.entities[entity].name.as_str())
+            .collect();
+        let text = format!("the road out of {} was improved", names.join(" and "));
+        sim.record_chronicle_about(&towns, text);
+    }
+}
+
+/// A faction pays for work on the road between two sites, as much as its
+/// cash allows
+pub(crate) fn invest(sim: &mut Simulation, faction: AgentId, a: SiteId, b: SiteId, cash: f64) {
+    let cash = cash.min(sim.agents[faction].cash).max(0.);
+    let finished = sim
+        .sites
+        .road(a, b)
+        .is_none_or(|road| road.quality >= MAX_QUALITY);
+    if cash <= 0. || finished {
+        return;
+    }
+    sim.sites.add_works(a, b, (cash / CASH_PER_WORKS) as f32);
+    sim.agents[faction].cash -= cash;
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use slotmap::SlotMap;
+
+use crate::advisor::ROUTE_MIN_MARGIN;
+use crate::date::Date;
+use crate::object::*;
+use crate::simulation::*;
+use crate::tick::{CARAVAN_MOVEMENT_SPEED, PERSON_MOVEMENT_SPEED};
+
+// Days a price differential must last before merchants open a route
+const ROUTE_PERSISTENCE_DAYS: u32 = 30;
+// Days a route may lose money before it is abandoned. Routes only open on a
+// wide margin, but live on as long as any margin is left, since their own
+// deliveries narrow it.
+const ROUTE_ABANDON_DAYS: u32 = 60;
+const MAX_ROUTES_PER_LOCATION: usize = 2;
+// Merchants stay away from markets where prices are falling faster than this
+const MAX_FALLING_TREND: f64 = -0.1;
+const CARAVANS_PER_ROUTE: usize = 1;
+// Weight of the latest delivery in the throughput average
+const THROUGHPUT_SMOOTHING: f64 = 0.5;
+
+#[derive(Clone)]
+pub(crate) struct TradeRoute {
+    pub source: LocationId,
+    pub destination: LocationId,
+    pub good: GoodId,
+    /// Goods delivered per trip, averaged over recent trips
+    pub throughput: f64,
+    /// One way, at the pace of a caravan
+    pub travel_days: f32,
+    pub caravans: Vec<PartyId>,
+    pub established: Date,
+    unprofitable_days: u32,
+}
+
+impl TradeRoute {
+    pub fn links(&self, location: LocationId) -> bool {
+        self.source == location || self.destination == location
+    }
+}
+
+/// Standing trade routes, opened where price differentials persist
+#[derive(Default, Clone)]
+pub(crate) struct TradeRoutes {
+    pub routes: SlotMap<TradeRouteId, TradeRoute>,
+    // Days each differential has lasted, by source, destination and good
+    streaks: BTreeMap<(LocationId, LocationId, GoodId), u32>,
+}
+
+impl TradeRoutes {
+    pub fn of_location(
+        &self,
+        location: LocationId,
+    ) -> impl Iterator<Item = (TradeRouteId, &TradeRoute)> + '_ {
+        self.routes
+            .iter()
+            .filter(move |(_, route)| route.links(location))
+    }
+
+    pub fn record_delivery(&mut self, id: TradeRouteId, amount: f64) {
+        if let Some(route) = self.routes.get_mut(id) {
+            route.throughput += (amount - route.throughput) * THROUGHPUT_SMOOTHING;
+        }
+    }
+
+    fn connects(&self, a: LocationId, b: LocationId) -> bool {
+        self.routes
+            .values()
+            .any(|route| route.links(a) && route.links(b))
+    }
+}
+
+// Ratio between the selling and the buying price
+fn margin(locations: &Locations, source: LocationId, destination: LocationId, good: GoodId) -> f64 {
+    let buy = &locations[source].market.goods[good];
+    let sell = &locations[destination].market.goods[good];
+    sell.price / buy.price.max(0.01)
+}
+
+fn is_worth_opening(
+    locations: &Locations,
+    source: LocationId,
+    destination: LocationId,
+    good: GoodId,
+) -> bool {
+    locations[source].market.goods[good].stock > 0.
+        && margin(locations, source, destination, good) >= ROUTE_MIN_MARGIN
+        && locations[destination].market.goods[good].price_trend() > MAX_FALLING_TREND
+}
+
+/// Daily upkeep of the trade routes. Returns the routes short of caravans.
+pub(crate) fn tick(sim: &mut Simulation) -> Vec<TradeRouteId> {
+    abandon_routes(sim);
+    open_routes(sim);
+
+    // Caravans that disbanded or were despawned are replaced
+    let parties = &sim.parties;
+    let mut understaffed = vec![];
+    for (id, route) in sim.routes.routes.iter_mut() {
+        route.caravans.retain(|&party| parties.contains_key(party));
+        if route.caravans.len() < CARAVANS_PER_ROUTE {
+            understaffed.push(id);
+        }
+    }
+    understaffed
+}
+
+fn abandon_routes(sim: &mut Simulation) {
+    let locations = &sim.locations;
+    let mut abandoned = vec![];
+    for (id, route) in sim.routes.routes.iter_mut() {
+        let exists =
+            locations.contains_key(route.source) && locations.contains_key(route.destination);
+        if !exists {
+            abandoned.push((id, false));
+            continue;
+        }
+        if margin(locations, route.source, route.destination, route.good) > 1. {
+            route.unprofitable_days = 0;
+        } else {
+            route.unprofitable_days += 1;
+        }
+        if route.unprofitable_days >= ROUTE_ABANDON_DAYS {
+            abandoned.push((id, true));
+        }
+    }
+
+    for (id, record) in abandoned {
+        let route = sim.routes.routes.remove(id).unwrap();
+        for party in route.caravans {
+            let behavior = sim
+                .parties
+                .get(party)
+                .and_then(|party| sim.entities[party.entity].behavior);
+            if let Some(behavior) = behavior {
+                sim.beahviors[behavior].request_despawn = true;
+            }
+        }
+        if record {
+            let source = sim.locations[route.source].entity;
+            let text = format!(
+                "the {} route from {} to {} was abandoned",
+                sim.good_types[route.good].name.to_lowercase(),
+                sim.entities[source].name,
+                sim.entities[sim.locations[route.destination].entity].name
+            );
+            sim.record_chronicle(source, text);
+        }
+    }
+}
+
+fn open_routes(sim: &mut Simulation) {
+    let mut profitable = vec![];
+    for good in sim.good_types.keys() {
+        for source in sim.locations.keys() {
+            for destination in sim.locations.keys() {
+                if source != destination
+                    && is_worth_opening(&sim.locations, source, destination, good)
+                {
+                    profitable.push((source, destination, good));
+                }
+            }
+        }
+    }
+
+    let mut streaks = std::mem::take(&mut sim.routes.streaks);
+    streaks.retain(|key, _| profitable.contains(key));
+    for key in profitable {
+        let days = streaks.entry(key).or_default();
+        *days += 1;
+        if *days < ROUTE_PERSISTENCE_DAYS {
+            continue;
+        }
+        let (source, destination, good) = key;
+        if open_route(sim, source, destination, good) {
+            *days = 0;
+        }
+    }
+    sim.routes.streaks = streaks;
+}
+
+fn open_route(
+    sim: &mut Simulation,
+    source: LocationId,
+    destination: LocationId,
+    good: GoodId,
+) -> bool {
+    let routes = &sim.routes;
+    let saturated = |location| routes.of_location(location).count() >= MAX_ROUTES_PER_LOCATION;
+    if routes.connects(source, destination) || saturated(source) || saturated(destination) {
+        return false;
+    }
+
+    let source_entity = sim.locations[source].entity;
+    let destination_entity = sim.locations[destination].entity;
+    let Some(measurement) = sim.measure_route(
+        ObjectId(ObjectHandle::Entity(source_entity)),
+        ObjectId(ObjectHandle::Entity(destination_entity)),
+    ) else {
+        return false;
+    };
+
+    sim.routes.routes.insert(TradeRoute {
+        source,
+        destination,
+        good,
+        throughput: 0.,
+        travel_days: measurement.days_on_foot * PERSON_MOVEMENT_SPEED / CARAVAN_MOVEMENT_SPEED,
+        caravans: vec![],
+        established: sim.date,
+        unprofitable_days: 0,
+    });
+
+    let text = format!(
+        "merchants opened a {} route from {} to {}",
+        sim.good_types[good].name.to_lowercase(),
+        sim.entities[source_entity].name,
+        sim.entities[destination_entity].name
+    );
+    sim.record_chronicle(source_entity, text);
+    true
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+
+use util::arena::Arena;
+
+use crate::simulation::{Personality, Simulation};
+use crate::templates::TemplateError;
+use crate::tick::*;
+use crate::worldgen::WorldGenParams;
+
+/// The factions scenarios start with, and how their AI behaves. The built-in
+/// ones can be replaced, and new ones added, from scenario files.
+#[derive(Clone)]
+pub(crate) struct FactionSetups {
+    entries: BTreeMap<String, FactionSetup>,
+}
+
+#[derive(Clone)]
+pub(crate) struct FactionSetup {
+    pub name: String,
+    pub personality: Personality,
+}
+
+impl Default for FactionSetups {
+    fn default() -> Self {
+        let rheged = FactionSetup {
+            name: "Rheged".to_string(),
+            personality: Personality {
+                aggressiveness: 0.3,
+                trade_focus: 0.8,
+                expansion_appetite: 1.,
+                income_handicap: 1.,
+            },
+        };
+        Self {
+            entries: BTreeMap::from([("rheged".to_string(), rheged)]),
+        }
+    }
+}
+
+impl FactionSetups {
+    /// Reads factions from a file of `[tag]` sections of `key = value` lines,
+    /// each replacing the faction of the same tag. Keys left out keep their
+    /// default.
+    pub fn load(&mut self, source: &str) -> Result<(), TemplateError> {
+        let mut current: Option<(String, FactionSetup)> = None;
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| TemplateError {
+                line: idx + 1,
+                reason: reason.to_string(),
+            };
+            if let Some(tag) = line.strip_prefix('[') {
+                let tag = tag.strip_suffix(']').ok_or(error("unclosed faction tag"))?;
+                if let Some((tag, setup)) = current.take() {
+                    self.entries.insert(tag, setup);
+                }
+                let setup = FactionSetup {
+                    name: tag.trim().to_string(),
+                    personality: Personality::default(),
+                };
+                current = Some((tag.trim().to_string(), setup));
+                continue;
+            }
+            let Some((_, setup)) = &mut current else {
+                return Err(error("field outside of a faction"));
+            };
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or(error("expected `key = value`"))?;
+            let number = || value.parse::<f64>().map_err(|_| error("expected a number"));
+            let personality = &mut setup.personality;
+            match key {
+                "name" => setup.name = value.to_string(),
+                "aggressiveness" => personality.aggressiveness = number()?,
+                "trade_focus" => personality.trade_focus = number()?,
+                "expansion_appetite" => personality.expansion_appetite = number()?,
+                "income_handicap" => personality.income_handicap = number()?,
+                _ => return Err(error(&format!("unknown key `{key}`"))),
+            }
+        }
+        if let Some((tag, setup)) = current {
+            self.entries.insert(tag, setup);
+        }
+        Ok(())
+    }
+}
+
+pub fn init_scenario(sim: &mut Simulation, arena: &Arena) {
+    struct Desc<'a> {
+        name: &'a str,
+        site: &'a str,
+        kind: &'a str,
+    }
+
+    let descs = [
+        Desc {
+            name: "Caer Ligualid",
+            site: "caer_ligualid",
+            kind: "town",
+        },
+        Desc {
+            name: "Anava",
+            site: "anava",
+            kind: "village",
+        },
+        Desc {
+            name: "Din Drust",
+            site: "din_drust",
+            kind: "hillfort",
+        },
+        Desc {
+            name: "Llan Heledd",
+            site: "llan_heledd",
+            kind: "village",
+        },
+        Desc {
+            name: "Isura",
+            site: "isura",
+            kind: "village",
+        },
+        Desc {
+            name: "Ad Candidam Casam",
+            site: "ad_candidam_casam",
+            kind: "village",
+        },
+        Desc {
+            name: "Din Rheged",
+            site: "din_rheged",
+            kind: "hillfort",
+        },
+        Desc {
+            name: "Maia",
+            site: "maia",
+            kind: "village",
+        },
+    ];
+
+    create_factions(sim, arena);
+
+    let mut request = TickRequest::default();
+    for desc in descs {
+        let (prosperity, tokens) = settlement_setup(desc.kind);
+        request.commands.create_location(CreateLocationParams {
+            name: desc.name,
+            site: desc.site,
+            settlement_kind: desc.kind,
+            faction: "rheged",
+            prosperity,
+            tokens,
+        });
+    }
+
+    request.commands.create_person(CreatePersonParams {
+        name: "Federico",
+        site: "caer_ligualid",
+        faction: "rheged",
+    });
+    request.commands.create_person(CreatePersonParams {
+        name: "Test",
+        site: "din_drust",
+        faction: "rheged",
+    });
+    sim.tick(request, arena);
+}
+
+/// Populates a procedurally generated map: the most fertile sites, kept
+/// apart from each other, become the settlements of a single faction
+pub fn init_generated_scenario(sim: &mut Simulation, arena: &Arena, params: &WorldGenParams) {
+    const MAX_SETTLEMENTS: usize = 8;
+
+    create_factions(sim, arena);
+
+    let wheat = sim.tags.goods.lookup("wheat");
+    let mut candidates: Vec<_> = sim
+        .sites
+        .iter()
+        .map(|(id, site)| {
+            let fertility = wheat.map(|good| site.rgo.rate_of(good)).unwrap_or(0.);
+            (id, fertility)
+        })
+        .filter(|&(_, fertility)| fertility > 0.)
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut chosen = vec![];
+    for (id, _) in candidates {
+        if chosen.len() == MAX_SETTLEMENTS {
+            break;
+        }
+        let crowded = sim
+            .sites
+            .neighbours(id)
+            .iter()
+            .any(|(neighbour, _, _)| chosen.contains(neighbour));
+        if !crowded {
+            chosen.push(id);
+        }
+    }
+
+    let mut names = NameGenerator::new(params.seed);
+    let mut request = TickRequest::default();
+    for (idx, &id) in chosen.iter().enumerate() {
+        let kind = match idx {
+            0 => "town",
+            1 | 2 => "hillfort",
+            _ => "village",
+        };
+        let (prosperity, tokens) = settlement_setup(kind);
+        let site = arena.alloc_str(&sim.sites.get(id).unwrap().tag);
+        let name = arena.alloc_str(&names.next());
+        request.commands.create_location(CreateLocationParams {
+            name,
+            site,
+            settlement_kind: kind,
+            faction: "rheged",
+            prosperity,
+            tokens,
+        });
+
+        if idx < 2 {
+            request.commands.create_person(CreatePersonParams {
+                name: arena.alloc_str(&names.next()),
+                site,
+                faction: "rheged",
+            });
+        }
+    }
+    sim.tick(request, arena);
+}
+
+fn create_factions(sim: &mut Simulation, arena: &Arena) {
+    let mut request = TickRequest::default();
+    for (tag, setup) in &sim.factions.entries {
+        request.commands.create_faction(CreateFactionParams {
+            tag: arena.alloc_str(tag),
+            name: arena.alloc_str(&setup.name),
+            personality: setup.personality,
+        });
+    }
+    sim.tick(request, arena);
+}
+
+// Brittonic-sounding names stitched together from syllables
+pub(crate) struct NameGenerator {
+    state: u64,
+}
+
+impl NameGenerator {
+    const PREFIXES: &[&str] = &["Caer ", "Din ", "Llan ", "Tre ", "", "", ""];
+    const SYLLABLES: &[&str] = &[
+        "an", "ber", "bryn", "cad", "dru", "el", "gwen", "hel", "is", "lig", "mor", "nant", "or",
+        "rhe", "tal", "ur", "wyn",
+    ];
+
+    pub fn new(seed: u64) -> Self {
+        // The xorshift state must never be zero
+        Self {
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+        }
+    }
+
+    pub fn roll(&mut self, n: usize) -> usize {
+        // xorshift, so that names depend only on the seed
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+
+    pub fn next(&mut self) -> String {
+        let mut name = String::from(Self::PREFIXES[self.roll(Self::PREFIXES.len())]);
+        name.push_str(&self.given_name());
+        name
+    }
+
+    /// A name without the prefixes of places, fit for a person
+    pub fn given_name(&mut self) -> String {
+        let syllables = 2 + self.roll(2);
+        let mut name = String::new();
+        for _ in 0..syllables {
+            name.push_str(Self::SYLLABLES[self.roll(Self::SYLLABLES.len())]);
+        }
+        name[..1].make_ascii_uppercase();
+        name
+    }
+}
+
+pub(crate) fn settlement_setup(kind: &str) -> (f64, &'static [CreateToken<'static>]) {
+    let prosperity = match kind {
+        "town" => 0.4,
+        "hillfort" => 0.3,
+        "village" => 0.3,
+        _ => panic!(),
+    };
+
+    let tokens: &'static [CreateToken<'static>] = match kind {
+        "village" => &[CreateToken {
+            tag: "paesants",
+            size: 5_000,
+        }],
+        "hillfort" => &[
+            CreateToken {
+                tag: "paesants",
+                size: 5_000,
+            },
+            CreateToken {
+                tag: "levy",
+                size: 30,
+            },
+            CreateToken {
+                tag: "artisans",
+                size: 100,
+            },
+            CreateToken {
+                tag: "nobles",
+                size: 50,
+            },
+        ],
+        "town" => &[
+            CreateToken {
+                tag: "paesants",
+                size: 7_500,
+            },
+            CreateToken {
+                tag: "levy",
+                size: 50,
+            },
+            CreateToken {
+                tag: "men_at_arms",
+                size: 10,
+            },
+            CreateToken {
+                tag: "artisans",
+                size: 1_000,
+            },
+            CreateToken {
+                tag: "nobles",
+                size: 200,
+            },
+            CreateToken {
+                tag: "toolmaker",
+                size: 1,
+            },
+        ],
+        _ => &[],
+    };
+
+    (prosperity, tokens)
+}
@@ -0,0 +1,165 @@
+use slotmap::Key;
+use strum::{EnumCount, EnumIter, IntoEnumIterator};
+use util::enum_map::{EnumMap, EnumMapKey};
+
+use crate::date::Date;
+
+/// How often a system runs
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Frequency {
+    #[default]
+    EveryTick,
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+impl Frequency {
+    pub(crate) fn is_due(self, date: Date) -> bool {
+        match self {
+            Self::EveryTick => true,
+            Self::Hourly => date.is_new_hour(),
+            Self::Daily => date.is_new_day(),
+            Self::Monthly => date.is_new_month(),
+        }
+    }
+
+    /// Days between two runs, which each run makes up for
+    pub(crate) fn days(self) -> f64 {
+        match self {
+            Self::EveryTick => 1. / Date::TICKS_IN_DAY as f64,
+            Self::Hourly => 1. / Date::HOURS_IN_DAY as f64,
+            Self::Daily => 1.,
+            Self::Monthly => Date::DAYS_IN_MONTH as f64,
+        }
+    }
+}
+
+/// Systems whose frequency can be scheduled
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter, EnumCount)]
+pub enum System {
+    Influences,
+    Pressures,
+    Market,
+    Behaviors,
+    // Factions taking note of the sites in sight
+    Visibility,
+}
+
+impl From<System> for usize {
+    fn from(value: System) -> Self {
+        value as usize
+    }
+}
+
+impl EnumMapKey for System {}
+
+/// The share of the entries of a system processed on one tick. Entries are
+/// assigned by key, so that each is visited once per round however the
+/// collection changes in the meantime.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Slice {
+    index: usize,
+    count: usize,
+}
+
+impl Slice {
+    pub fn contains(self, key: impl Key) -> bool {
+        // The low bits of a key are its slot, which does not change with the
+        // version
+        let slot = key.data().as_ffi() as u32 as usize;
+        slot % self.count == self.index
+    }
+
+    /// Whether this is the first slice of its round
+    pub fn is_first(self) -> bool {
+        self.index == 0
+    }
+}
+
+#[derive(Clone)]
+pub struct TickSchedule {
+    frequencies: EnumMap<System, Frequency, { System::COUNT }>,
+    // Number of consecutive ticks a round of each system is spread over
+    slices: EnumMap<System, usize, { System::COUNT }>,
+    // Next slice of the round in progress, if any
+    cursors: EnumMap<System, Option<usize>, { System::COUNT }>,
+    ticks_per_frame: usize,
+}
+
+impl Default for TickSchedule {
+    fn default() -> Self {
+        Self {
+            frequencies: EnumMap::with_iter([
+                (System::Influences, Frequency::EveryTick),
+                (System::Pressures, Frequency::Daily),
+                (System::Market, Frequency::Daily),
+                (System::Behaviors, Frequency::EveryTick),
+                (System::Visibility, Frequency::Hourly),
+            ]),
+            slices: EnumMap::with_iter(System::iter().map(|system| (system, 1))),
+            cursors: EnumMap::with_iter(System::iter().map(|system| (system, None))),
+            ticks_per_frame: 1,
+        }
+    }
+}
+
+impl TickSchedule {
+    pub fn frequency(&self, system: System) -> Frequency {
+        *self.frequencies.get(system)
+    }
+
+    pub fn set_frequency(&mut self, system: System, frequency: Frequency) {
+        self.frequencies.set(system, frequency);
+    }
+
+    pub(crate) fn is_due(&self, system: System, date: Date) -> bool {
+        self.frequency(system).is_due(date)
+    }
+
+    /// Days each run of the system makes up for, by which it scales what it
+    /// adds up
+    pub(crate) fn days_per_run(&self, system: System) -> f64 {
+        self.frequency(system).days()
+    }
+
+    pub fn slices(&self, system: System) -> usize {
+        *self.slices.get(system)
+    }
+
+    /// Spreads each round of the system over this many ticks, starting on the
+    /// tick it is due, to bound the work done on any one tick. A round still
+    /// in progress when the system is due again is finished first.
+    pub fn set_slices(&mut self, system: System, slices: usize) {
+        self.slices.set(system, slices.max(1));
+        self.cursors.set(system, None);
+    }
+
+    /// The slice of the system to run on this tick, if any
+    pub(crate) fn next_slice(&mut self, system: System, date: Date) -> Option<Slice> {
+        let count = self.slices(system);
+        let index = match *self.cursors.get(system) {
+            Some(index) => index,
+            None if self.is_due(system, date) => 0,
+            None => return None,
+        };
+        let next = index + 1;
+        self.cursors.set(system, (next < count).then_some(next));
+        Some(Slice { index, count })
+    }
+
+    /// Game speed, as the number of ticks to run per real frame
+    pub fn ticks_per_frame(&self) -> usize {
+        self.ticks_per_frame
+    }
+
+    /// Sets the game speed, refusing to stop the game altogether. Returns
+    /// whether the speed was changed.
+    pub fn set_ticks_per_frame(&mut self, ticks: usize) -> bool {
+        if ticks == 0 {
+            return false;
+        }
+        self.ticks_per_frame = ticks;
+        true
+    }
+}
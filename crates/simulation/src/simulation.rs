@@ -1,17 +1,23 @@
+use std::collections::VecDeque;
+
 use slotmap::*;
-use std::collections::*;
 use strum::{EnumCount, EnumIter};
 use util::arena::*;
 use util::enum_map::{EnumMap, EnumMapKey};
 use util::hierarchy::Hierarchy;
+use util::ring_buffer::RingBuffer;
+use util::spatial_grid::SpatialGrid;
 use util::tally::Tally;
 
 use crate::date::Date;
 use crate::sites::*;
+use crate::stances::Stance;
+use crate::tags::*;
 use crate::tick::TickRequest;
 use crate::tokens::*;
+use crate::view::{SimWarning, TagKind};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Simulation {
     pub(crate) date: Date,
     pub(crate) sites: Sites,
@@ -23,6 +29,63 @@ pub struct Simulation {
     pub(crate) locations: Locations,
     pub(crate) pressurables: Pressurables,
     pub(crate) beahviors: Behaviors,
+    pub(crate) chronicle: crate::chronicle::Chronicle,
+    pub(crate) tags: crate::tags::TagRegistry,
+    pub(crate) relations: crate::diplomacy::Relations,
+    pub(crate) intel: crate::espionage::Intel,
+    pub(crate) exploration: crate::exploration::Exploration,
+    pub(crate) famines: crate::famine::Famines,
+    pub(crate) routes: crate::routes::TradeRoutes,
+    pub(crate) constructions: crate::construction::Constructions,
+    pub(crate) demography: crate::demography::Demography,
+    pub(crate) outlook: crate::prosperity::Outlook,
+    pub(crate) ledger: crate::ledger::Ledger,
+    pub(crate) lore: crate::lore::Lore,
+    pub(crate) contracts: crate::contracts::Contracts,
+    pub(crate) currency: crate::currency::Currency,
+    pub(crate) hospitality: crate::hospitality::Hospitality,
+    pub(crate) autonomy: crate::autonomy::Autonomy,
+    pub(crate) realms: crate::elimination::Realms,
+    pub(crate) players: crate::players::Players,
+    pub(crate) guilds: crate::guilds::Guilds,
+    pub(crate) groups: crate::groups::Groups,
+    pub(crate) escorts: crate::escorts::Escorts,
+    pub(crate) combat: crate::combat::Combat,
+    pub(crate) stockpiles: crate::stockpile::Stockpiles,
+    pub(crate) upkeep: crate::upkeep::Upkeep,
+    pub(crate) templates: crate::templates::EntityTemplates,
+    pub(crate) factions: crate::scenario::FactionSetups,
+    pub(crate) ambitions: crate::ambitions::Ambitions,
+    pub(crate) characters: crate::characters::Characters,
+    pub(crate) visibility: crate::visibility::Memories,
+    // Where the parties stand on the map, to find those in an area quickly
+    pub(crate) party_grid: SpatialGrid<PartyId>,
+    pub(crate) rng: SimRng,
+    pub schedule: crate::schedule::TickSchedule,
+    /// How overlapping influences of the same kind add up
+    pub influence_blends: crate::sites::InfluenceBlends,
+    /// Whether parties slow down on crowded roads, and plan around them
+    pub road_congestion: bool,
+    /// How many times slower than on a road parties that can walk off it do so
+    pub off_road_slowdown: f32,
+    // Skips the work only views need, while generating history
+    pub(crate) fast_forward: bool,
+    // Bumped whenever something visible through extracted objects changes
+    pub(crate) view_revision: u64,
+    pub(crate) object_cache: crate::view::ObjectCache,
+    // Events accumulated since the last view was extracted
+    pub(crate) events: Vec<crate::view::SimEvent>,
+    pub(crate) warnings: Vec<crate::view::SimWarning>,
+}
+
+// Seeded, so that runs from the same scenario are reproducible
+#[derive(Clone)]
+pub(crate) struct SimRng(pub rand::rngs::SmallRng);
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self(rand::SeedableRng::seed_from_u64(0))
+    }
 }
 
 new_key_type! { pub (crate) struct EntityId; }
@@ -40,6 +103,7 @@ new_key_type! { pub(crate) struct LocationId; }
 new_key_type! { pub(crate) struct PartyId; }
 
 new_key_type! { pub(crate) struct GoodId; }
+new_key_type! { pub(crate) struct TradeRouteId; }
 
 pub(crate) type GoodTypes = SlotMap<GoodId, GoodData>;
 pub(crate) type Entities = SlotMap<EntityId, EntityData>;
@@ -52,16 +116,90 @@ impl Simulation {
     pub fn new() -> Simulation {
         let mut sim = Simulation::default();
         init(&mut sim);
+        init_sites(&mut sim);
+        crate::exploration::hide_passages(&mut sim);
+        sim.sites.build_regions();
+        sim
+    }
+
+    /// A simulation on a procedurally generated map instead of the hand-authored one
+    pub fn generated(params: &crate::worldgen::WorldGenParams) -> Simulation {
+        let mut sim = Simulation::default();
+        sim.lore.seed = params.seed;
+        init(&mut sim);
+        crate::worldgen::generate(&mut sim, params);
+        crate::exploration::hide_passages(&mut sim);
+        sim.sites.build_regions();
         sim
     }
 
     pub fn tick(&mut self, request: TickRequest, arena: &Arena) -> crate::view::SimView {
         crate::tick::tick(self, request, arena)
     }
+
+    /// Moves the date the simulation starts from. Meant for scenarios, before
+    /// the first tick.
+    pub fn set_start_date(&mut self, day: u64, month: u64, year: u64) {
+        self.date = Date::with_calendar(day.max(1), month.clamp(1, 12), year);
+    }
+
+    /// Reads entity templates from a template file, replacing the built-in
+    /// ones of the same tags
+    pub fn load_templates(&mut self, source: &str) -> Result<(), crate::templates::TemplateError> {
+        self.templates.load(source)
+    }
+
+    /// Reads the factions the scenario starts with from a scenario file,
+    /// replacing the built-in ones of the same tags. Meant for scenarios,
+    /// before they are set up.
+    pub fn load_factions(&mut self, source: &str) -> Result<(), crate::templates::TemplateError> {
+        self.factions.load(source)
+    }
+
+    /// Runs years of simulation ahead of play, so that play starts in a world
+    /// with a past. No views are extracted along the way.
+    pub fn generate_history(&mut self, years: u64, arena: &mut Arena) {
+        crate::tick::fast_forward(self, years * Date::TICKS_IN_YEAR, arena);
+    }
+
+    /// The parties within a distance of a point, found through the party grid
+    pub(crate) fn parties_near(
+        &self,
+        centre: V2,
+        radius: f32,
+    ) -> impl Iterator<Item = PartyId> + '_ {
+        let (min, max) = Extents::around(centre, radius).corners();
+        self.party_grid
+            .query(min, max)
+            .into_iter()
+            .filter(move |&id| {
+                self.parties
+                    .get(id)
+                    .is_some_and(|party| party.pos.distance(centre) <= radius)
+            })
+    }
+
+    /// Captures the full simulation state, to be later brought back with `restore`
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(Box::new(self.clone()))
+    }
+
+    /// Rolls back to a checkpoint. The checkpoint is left intact, so the same
+    /// state can be restored multiple times.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.clone_from(&checkpoint.0);
+    }
 }
 
-pub(crate) trait Tagged {
-    fn tag(&self) -> &str;
+/// Opaque snapshot of a simulation
+#[derive(Clone)]
+pub struct Checkpoint(Box<Simulation>);
+
+impl Checkpoint {
+    /// Tick the snapshot was taken at
+    pub fn epoch(&self) -> u64 {
+        self.0.date.epoch()
+    }
 }
 
 pub(crate) trait TaggedCollection {
@@ -70,20 +208,11 @@ pub(crate) trait TaggedCollection {
     fn lookup(&self, tag: &str) -> Option<Self::Output>;
 }
 
-impl<K: slotmap::Key, V: Tagged> TaggedCollection for SlotMap<K, V> {
-    type Output = K;
-
-    fn lookup(&self, tag: &str) -> Option<Self::Output> {
-        self.iter()
-            .find(|(_, data)| data.tag() == tag)
-            .map(|(id, _)| id)
-    }
-}
-
 fn parse_tally<C: TaggedCollection>(
     coll: &C,
     items: &[(&str, f64)],
-    kind_name: &str,
+    kind: TagKind,
+    warnings: &mut Vec<SimWarning>,
 ) -> Tally<C::Output>
 where
     C::Output: Copy + Ord,
@@ -92,86 +221,93 @@ where
     for (tag, value) in items {
         match coll.lookup(tag) {
             Some(id) => out.add_one(id, *value),
-            None => println!("Undefined {kind_name} with tag '{tag}'"),
+            None => warnings.push(SimWarning::UndefinedTag {
+                kind,
+                tag: tag.to_string(),
+            }),
         }
     }
     out
 }
 
-fn parse_tally_sm<K: Key, T: Tagged>(
+fn parse_tally_sm<K: Key + Ord + std::hash::Hash, T>(
     coll: &SlotMap<K, T>,
+    tags: &Tags<K>,
     items: &[(&str, f64)],
-    kind_name: &str,
+    kind: TagKind,
+    warnings: &mut Vec<SimWarning>,
 ) -> SecondaryMap<K, f64> {
     let mut out: SecondaryMap<K, f64> = coll.keys().map(|id| (id, 0.)).collect();
     for (tag, value) in items {
-        match coll.lookup(tag) {
+        match tags.lookup(tag) {
             Some(id) => out[id] += *value,
-            None => println!("Undefined {kind_name} with tag '{tag}'"),
+            None => warnings.push(SimWarning::UndefinedTag {
+                kind,
+                tag: tag.to_string(),
+            }),
         }
     }
     out
 }
 
-pub(crate) struct GoodData {
-    pub tag: &'static str,
-    pub name: &'static str,
-    pub price: f64,
-    pub food_rate: f64,
-}
-
-impl Tagged for GoodData {
-    fn tag(&self) -> &str {
-        self.tag
-    }
-}
-
-pub(crate) struct Tags<T: Copy + Ord + std::hash::Hash> {
-    string_to_id: HashMap<String, T>,
-    id_to_string: HashMap<T, String>,
+/// Broad kinds of goods, for reasoning about a market a category at a time
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, EnumIter)]
+pub(crate) enum GoodCategory {
+    Food,
+    RawMaterial,
+    Manufactured,
+    Luxury,
 }
 
-impl<T: Copy + Ord + std::hash::Hash> Default for Tags<T> {
-    fn default() -> Self {
-        Self {
-            string_to_id: HashMap::default(),
-            id_to_string: HashMap::default(),
+impl GoodCategory {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Food => "Food",
+            Self::RawMaterial => "Raw materials",
+            Self::Manufactured => "Manufactured goods",
+            Self::Luxury => "Luxuries",
         }
     }
 }
 
-impl<T: Copy + Ord + std::hash::Hash> Tags<T> {
-    pub fn insert(&mut self, tag: impl Into<String>, id: T) {
-        let str = tag.into();
-        self.string_to_id.insert(str.clone(), id);
-        self.id_to_string.insert(id, str);
-    }
-
-    pub fn unbind(&mut self, tag: &str) {
-        if let Some(id) = self.string_to_id.remove(tag) {
-            self.id_to_string.remove(&id);
-        }
-    }
-
-    pub fn remove(&mut self, id: &T) {
-        if let Some(tag) = self.id_to_string.remove(id) {
-            self.string_to_id.remove(&tag);
-        }
-    }
+#[derive(Clone)]
+pub(crate) struct GoodData {
+    pub tag: &'static str,
+    pub name: &'static str,
+    pub category: GoodCategory,
+    pub price: f64,
+    pub food_rate: f64,
+    /// Share of the stock that spoils each day
+    pub decay_rate: f64,
+    /// Room one unit takes in a party's cargo
+    pub weight: f64,
+    /// How strongly a gap between supply and demand moves the price
+    pub elasticity: f64,
+    /// Bounds of the price the balance of supply and demand alone can set, as
+    /// multipliers of the base price
+    pub min_price: f64,
+    pub max_price: f64,
+    /// Share of the way to its target the price covers at each market update
+    pub convergence: f64,
+    /// Multipliers of what the land yields of the good in each season, from spring
+    pub seasonal_output: [f64; 4],
+    /// Multipliers of what pops eat of the good in each season, from spring
+    pub seasonal_demand: [f64; 4],
+}
 
-    pub fn lookup(&self, tag: &str) -> Option<T> {
-        self.string_to_id.get(tag).copied()
+impl GoodData {
+    pub fn output_in(&self, season: crate::date::Season) -> f64 {
+        self.seasonal_output[season as usize]
     }
 
-    pub fn reverse_lookup(&self, id: &T) -> Option<&str> {
-        self.id_to_string.get(id).map(|x| x.as_str())
+    pub fn demand_in(&self, season: crate::date::Season) -> f64 {
+        self.seasonal_demand[season as usize]
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct Agents {
     pub entries: SlotMap<AgentId, AgentData>,
-    pub tags: Tags<AgentId>,
     pub political_hierarchy: Hierarchy<AgentId, AgentId>,
 }
 
@@ -182,7 +318,6 @@ impl Agents {
 
     pub fn despawn(&mut self, arena: &Arena, id: AgentId) {
         self.entries.remove(id);
-        self.tags.remove(&id);
         self.political_hierarchy.remove_child(id);
         self.political_hierarchy.remove_parents(arena, &[id]);
     }
@@ -202,16 +337,62 @@ impl std::ops::IndexMut<AgentId> for Agents {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct AgentData {
     pub entity: EntityId,
     pub flags: AgentFlags,
     pub cash: f64,
+    pub personality: Personality,
+    /// Share of the income of their subjects remitted to this agent
+    pub tax_rate: f64,
+    // Taxes collected at the last market update
+    pub tax_revenue: f64,
+    /// Share of the value of the trades foreign parties make in the markets
+    /// of this faction it collects as tolls
+    pub toll_rate: f64,
+    // Tolls collected so far
+    pub toll_revenue: f64,
+}
+
+pub(crate) const DEFAULT_TAX_RATE: f64 = 0.05;
+pub(crate) const DEFAULT_TOLL_RATE: f64 = 0.05;
+
+/// How the AI of a faction behaves. Agents follow the personality of their faction.
+#[derive(Clone, Copy, Debug)]
+pub struct Personality {
+    /// Willingness to pick fights. Parties of the faction rate their own
+    /// strength by it against the enemies they see, take up bolder stances
+    /// above a point, and its characters more readily set out to claim titles.
+    pub aggressiveness: f64,
+    /// Share of their cash traders spend on goods
+    pub trade_focus: f64,
+    /// Multiplier on the pressures that make settlements send out new parties
+    pub expansion_appetite: f64,
+    /// Multiplier on the proceeds of sales, to make a faction stronger or weaker
+    pub income_handicap: f64,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Self {
+            aggressiveness: 0.5,
+            trade_focus: 1.,
+            expansion_appetite: 1.,
+            income_handicap: 1.,
+        }
+    }
+}
+
+pub(crate) fn personality_of(agents: &Agents, agent: AgentId) -> Personality {
+    crate::chronicle::faction_of(agents, agent)
+        .map(|faction| agents[faction].personality)
+        .unwrap_or_default()
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumCount)]
 pub(crate) enum AgentFlag {
     IsFaction,
+    IsCharacter,
 }
 
 #[derive(Default, Clone, Copy, Debug)]
@@ -336,6 +517,22 @@ impl Default for Extents {
 }
 
 impl Extents {
+    /// The square reaching as far as the radius from the centre on each side
+    pub(crate) fn around(centre: V2, radius: f32) -> Self {
+        Self {
+            top_left: V2::new(centre.x - radius, centre.y - radius),
+            bottom_right: V2::new(centre.x + radius, centre.y + radius),
+        }
+    }
+
+    // Its corners, as taken by spatial grids
+    pub(crate) fn corners(&self) -> ((f32, f32), (f32, f32)) {
+        (
+            (self.top_left.x, self.top_left.y),
+            (self.bottom_right.x, self.bottom_right.y),
+        )
+    }
+
     pub(crate) fn contains(&self, point: V2) -> bool {
         point.x >= self.top_left.x
             && point.y >= self.top_left.y
@@ -344,9 +541,11 @@ impl Extents {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct EntityData {
     pub name: String,
+    // Freeform player note
+    pub note: String,
     pub kind_name: &'static str,
     pub agent: Option<AgentId>,
     pub party: Option<PartyId>,
@@ -354,6 +553,7 @@ pub(crate) struct EntityData {
     pub pressure_agent: Option<PressurableId>,
     pub behavior: Option<BehaviorId>,
 }
+#[derive(Clone)]
 pub(crate) struct LocationData {
     pub entity: EntityId,
     pub party: PartyId,
@@ -365,12 +565,13 @@ pub(crate) struct LocationData {
     pub tokens: TokenContainerId,
 }
 
+#[derive(Clone)]
 pub(crate) struct InfluenceSource {
     pub kind: InfluenceKind,
     pub population_modifier: f64,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct MarketGood {
     pub stock: f64,
     pub stock_delta: f64,
@@ -383,13 +584,40 @@ pub(crate) struct MarketGood {
     pub demand_effective: f64,
     pub consumed: f64,
     pub satisfaction: f64,
+    // Stock spoiled on the last day
+    pub wasted: f64,
+    // One price sample per market update, oldest first
+    pub price_history: RingBuffer<f32>,
+}
+
+// Market updates covered by the price history of a good
+pub(crate) const PRICE_HISTORY_LEN: usize = 360;
+
+impl MarketGood {
+    /// Relative change of the price over its recorded history
+    pub fn price_trend(&self) -> f64 {
+        match (self.price_history.oldest(), self.price_history.newest()) {
+            (Some(&oldest), Some(&newest)) if oldest > 0. => (newest / oldest - 1.) as f64,
+            _ => 0.,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub(crate) struct Market {
     pub goods: SecondaryMap<GoodId, MarketGood>,
+    pub food_demand: f64,
     pub food_consumed: f64,
     pub food_stockpile: f64,
     pub income: f64,
+    // Paid by the buildings to their workers, out of their income
+    pub wages: f64,
+    // Pops hired by the buildings, and those of the same kinds left idle
+    pub employed: f64,
+    pub unemployed: f64,
+    // Share of their full output each kind of building reached, held back by
+    // missing workers or inputs
+    pub throughput: SecondaryMap<TokenTypeId, f64>,
 }
 
 impl Market {
@@ -403,28 +631,116 @@ impl Market {
                         MarketGood {
                             price: typ.price,
                             target_price: typ.price,
+                            price_history: RingBuffer::new(PRICE_HISTORY_LEN),
                             ..Default::default()
                         },
                     )
                 })
                 .collect(),
+            food_demand: 0.,
             food_consumed: 0.,
             food_stockpile: 0.,
             income: 0.,
+            wages: 0.,
+            employed: 0.,
+            unemployed: 0.,
+            throughput: SecondaryMap::new(),
+        }
+    }
+
+    /// Share of the pops looking for work at the buildings that found none
+    pub fn unemployment(&self) -> f64 {
+        let labor = self.employed + self.unemployed;
+        if labor <= 0. {
+            0.
+        } else {
+            self.unemployed / labor
+        }
+    }
+
+    pub fn food_satisfaction(&self) -> f64 {
+        if self.food_demand <= 0. {
+            1.
+        } else {
+            (self.food_consumed / self.food_demand).min(1.)
+        }
+    }
+
+    /// Supply and demand of all the goods of a category taken together, or
+    /// none if the market trades no good of it
+    pub fn category_totals(
+        &self,
+        good_types: &GoodTypes,
+        category: GoodCategory,
+    ) -> Option<CategoryTotals> {
+        let mut goods = good_types
+            .iter()
+            .filter(|(_, typ)| typ.category == category)
+            .filter_map(|(id, _)| self.goods.get(id))
+            .peekable();
+        goods.peek()?;
+        let mut totals = CategoryTotals::default();
+        for good in goods {
+            totals.supply += good.supply_effective;
+            totals.demand += good.demand_base;
+            totals.consumed += good.consumed;
+            totals.stock += good.stock;
+        }
+        Some(totals)
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct CategoryTotals {
+    pub supply: f64,
+    pub demand: f64,
+    pub consumed: f64,
+    pub stock: f64,
+}
+
+impl CategoryTotals {
+    pub fn satisfaction(&self) -> f64 {
+        if self.demand <= 0. {
+            1.
+        } else {
+            (self.consumed / self.demand).min(1.)
         }
     }
 }
 
 // Pressure Agent
+#[derive(Clone)]
 pub(crate) struct Pressureble {
     pub entity: EntityId,
     pub current: PressureMap,
     pub innate_growth: Vec<(PressureType, f64)>,
+    pub triggers: Vec<PressureTrigger>,
+}
+
+/// Event set off once a pressure reaches a threshold, which then falls back
+/// by the given amount
+#[derive(Clone, Copy)]
+pub(crate) struct PressureTrigger {
+    pub target: PressureType,
+    pub threshold: f64,
+    pub subtract: f64,
+    pub event: PressureEventType,
+}
+
+/// Party a settlement sends out when a trigger goes off
+#[derive(Clone, Copy)]
+pub(crate) enum PressureEventType {
+    Farmers,
+    Merchants,
+    Rebels,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumIter, EnumCount)]
 pub(crate) enum PressureType {
     Farmer,
+    Merchant,
+    // Fed by shortages and taxes rather than growing on its own
+    Unrest,
 }
 
 impl From<PressureType> for usize {
@@ -438,24 +754,60 @@ impl EnumMapKey for PressureType {}
 const PRESSURE_TYPE_COUNT: usize = PressureType::COUNT;
 pub(crate) type PressureMap = EnumMap<PressureType, f64, PRESSURE_TYPE_COUNT>;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct Behavior {
     pub entity: EntityId,
     pub goal: Goal,
-    pub task: Option<Task>,
-    pub memory: BehaviorMemory,
+    // The goal the party was raised for, which it goes back to once nothing
+    // calls for another
+    pub vocation: Goal,
+    // The tasks lined up towards the goal, the one under way first. The goal
+    // plans another round once they run out.
+    pub plan: VecDeque<Task>,
+    // How the party meets the hostile parties it sees
+    pub stance: Stance,
     pub request_despawn: bool,
-}
-
-#[derive(Default)]
-pub(crate) struct BehaviorMemory {
-    pub state: usize,
+    // Delivery order taken up, seen to before the goal
+    pub contract: Option<crate::contracts::ContractId>,
 }
 
 #[derive(Clone, PartialEq)]
 pub(crate) enum Goal {
     Idle,
-    LocalTrade { base: PartyId },
+    LocalTrade {
+        base: PartyId,
+    },
+    // Trades like local traders, but slips past the customs of markets that
+    // embargo its faction rather than paying their tariffs
+    Smuggle {
+        base: PartyId,
+    },
+    // Sets out from its base for whichever market pays the most over it, net
+    // of the cost of the journey
+    ArbitrageTrade {
+        base: PartyId,
+    },
+    RouteTrade {
+        route: TradeRouteId,
+    },
+    Ambition {
+        ambition: crate::ambitions::Ambition,
+        since: crate::date::Date,
+    },
+    Migrate(crate::migration::Migration),
+    // Marches on the market town of its home to extract concessions, then
+    // goes back home to disband
+    Rebel {
+        base: PartyId,
+    },
+    // Heads back to its base to hand over what it carries and disband
+    ReturnHome {
+        base: PartyId,
+    },
+    // Shelters in a settlement of its faction from the enemies about
+    Flee {
+        refuge: PartyId,
+    },
 }
 
 impl Default for Goal {
@@ -464,20 +816,57 @@ impl Default for Goal {
     }
 }
 
-#[derive(Default)]
+/// A step of a plan: getting somewhere, then doing something there
+#[derive(Clone, Copy, PartialEq)]
 pub(crate) struct Task {
-    pub target: PartyId,
-    // Remember to add this task to the set of memories
-    pub on_complete_state: usize,
-    // Continue this task after arriving at the
-    // destination?
-    pub continue_after_arrival: bool,
-    // Despawn the party after this task is complete?
-    pub despawn_on_complete: bool,
-    // Give away goods to target
-    pub give_away_to_target: bool,
-    // Trade with the target
-    pub trade_with_target: bool,
+    pub destination: Destination,
+    pub action: Action,
+}
+
+impl Task {
+    pub fn visit(party: PartyId, action: Action) -> Self {
+        Self {
+            destination: Destination::Party(party),
+            action,
+        }
+    }
+
+    /// Going back to a base to hand over all that was gathered, and disband
+    pub fn homecoming(base: PartyId) -> [Self; 2] {
+        [
+            Self::visit(base, Action::Unload),
+            Self::visit(base, Action::Disband),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Destination {
+    // Wherever the party stands
+    Here,
+    Party(PartyId),
+    Site(SiteId),
+}
+
+/// What a party does once a task gets it where it was going
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    // Trade at the market of the destination, on behalf of a route if any
+    Trade { route: Option<TradeRouteId> },
+    // Hand over all the goods carried to the destination
+    Unload,
+    // Break up the party
+    Disband,
+    // Act on the ambition of the behavior
+    PursueAmbition,
+    // Settle the migrants of the behavior in the destination location
+    Settle,
+    // Hand over the goods of the delivery order of the behavior
+    Deliver,
+    // Rise up against the owner of the destination location
+    Revolt,
+    // Stay out of harm's way at the destination
+    Shelter,
 }
 
 // Grid
@@ -628,6 +1017,7 @@ impl Path {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct PartyData {
     pub entity: EntityId,
     pub location: Option<LocationId>,
@@ -639,8 +1029,31 @@ pub(crate) struct PartyData {
     pub movement_speed: f32,
     pub movement: PartyMovement,
     pub good_stock: GoodStock,
+    /// Total weight of the goods the party can carry
+    pub carry_capacity: f64,
+    /// Whether the party can travel along rivers
+    pub boats: bool,
+    /// Whether the party can walk straight across country between nearby sites
+    pub off_road: bool,
+    /// Whether the party is a ship, sailing the seas and nothing else
+    pub ship: bool,
+    /// The soldiers the party fights with, if it has any
+    pub troops: Option<TokenContainerId>,
+    /// Whether the party pushes on faster than it can keep up for long
+    pub forced_march: bool,
 }
 
+impl PartyData {
+    pub fn mobility(&self) -> crate::sites::Mobility {
+        crate::sites::Mobility {
+            boats: self.boats,
+            off_road: self.off_road,
+            ship: self.ship,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct GoodStock {
     pub amount: SecondaryMap<GoodId, f64>,
 }
@@ -652,6 +1065,14 @@ impl GoodStock {
         }
     }
 
+    /// Room the goods take up, by their weight
+    pub fn weight(&self, good_types: &GoodTypes) -> f64 {
+        self.amount
+            .iter()
+            .map(|(good_id, amount)| amount * good_types[good_id].weight)
+            .sum()
+    }
+
     pub fn clear(&mut self) {
         self.amount.values_mut().for_each(|x| *x = 0.0);
     }
@@ -676,64 +1097,141 @@ impl std::ops::IndexMut<GoodId> for GoodStock {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub(crate) enum MovementTarget {
     Site(SiteId),
     Party(PartyId),
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct PartyMovement {
     pub target: Option<MovementTarget>,
     pub path: Path,
     pub destination: Option<GridCoord>,
+    // The last destination could not be reached, and the order was dropped
+    pub blocked: bool,
+    // Where to head for next, in turn, as each target is reached
+    pub waypoints: VecDeque<MovementTarget>,
+    // Reached targets go back to the end of the waypoints, round and round
+    pub patrol: bool,
+    // Running from or chasing a party it saw, in place of its orders
+    pub reaction: Option<crate::stances::Reaction>,
+}
+
+impl PartyMovement {
+    /// Drops the target along with the waypoints after it
+    pub fn clear_orders(&mut self) {
+        self.target = None;
+        self.waypoints.clear();
+        self.patrol = false;
+        self.reaction = None;
+    }
 }
 
 fn init(sim: &mut Simulation) {
     sim.date = Date::with_calendar(1, 1, 363);
+    sim.off_road_slowdown = crate::sites::DEFAULT_OFF_ROAD_SLOWDOWN;
     // Init goods
     {
         struct Desc<'a> {
             tag: &'a str,
             name: &'a str,
+            category: GoodCategory,
             price: f64,
             food_rate: f64,
+            decay_rate: f64,
+            weight: f64,
+            elasticity: f64,
+            min_price: f64,
+            max_price: f64,
+            convergence: f64,
+            seasonal_output: [f64; 4],
+            seasonal_demand: [f64; 4],
         }
 
         const DESCS: &[Desc] = &[
             Desc {
                 tag: "wheat",
                 name: "Wheat",
+                category: GoodCategory::Food,
                 price: 10.,
                 food_rate: 1.0,
+                decay_rate: 0.002,
+                weight: 1.0,
+                elasticity: 0.8,
+                min_price: 0.4,
+                max_price: 2.0,
+                convergence: 0.1,
+                seasonal_output: [0.85, 1.05, 1.3, 0.8],
+                seasonal_demand: [1., 0.95, 1., 1.05],
             },
             Desc {
                 tag: "meat",
                 name: "Meat",
+                category: GoodCategory::Food,
                 price: 10.,
                 food_rate: 1.,
+                decay_rate: 0.02,
+                weight: 1.0,
+                elasticity: 1.0,
+                min_price: 0.25,
+                max_price: 1.75,
+                convergence: 0.1,
+                seasonal_output: [1.1, 1., 1., 0.9],
+                seasonal_demand: [1., 1., 1., 1.1],
             },
             Desc {
                 tag: "lumber",
                 name: "Lumber",
+                category: GoodCategory::RawMaterial,
                 price: 10.,
                 food_rate: 0.0,
+                decay_rate: 0.0005,
+                weight: 2.0,
+                elasticity: 1.0,
+                min_price: 0.25,
+                max_price: 1.75,
+                convergence: 0.1,
+                seasonal_output: [1.; 4],
+                seasonal_demand: [1.; 4],
             },
             Desc {
                 tag: "tools",
                 name: "Tools",
+                category: GoodCategory::Manufactured,
                 price: 20.,
                 food_rate: 0.0,
+                decay_rate: 0.0,
+                weight: 0.5,
+                elasticity: 1.2,
+                min_price: 0.25,
+                max_price: 2.5,
+                convergence: 0.05,
+                seasonal_output: [1.; 4],
+                seasonal_demand: [1.; 4],
             },
         ];
 
         for desc in DESCS {
-            sim.good_types.insert(GoodData {
+            if !is_tag_free(&sim.tags.goods, TagKind::Good, desc.tag, &mut sim.warnings) {
+                continue;
+            }
+            let id = sim.good_types.insert(GoodData {
                 tag: desc.tag,
                 name: desc.name,
+                category: desc.category,
                 price: desc.price,
                 food_rate: desc.food_rate,
+                decay_rate: desc.decay_rate,
+                weight: desc.weight,
+                elasticity: desc.elasticity,
+                min_price: desc.min_price,
+                max_price: desc.max_price,
+                convergence: desc.convergence,
+                seasonal_output: desc.seasonal_output,
+                seasonal_demand: desc.seasonal_demand,
             });
+            sim.tags.goods.insert(desc.tag, id);
         }
     }
 
@@ -773,14 +1271,28 @@ fn init(sim: &mut Simulation) {
         ];
 
         for desc in DESCS {
-            sim.tokens.define_type(TokenType {
-                tag: desc.tag,
+            let kind = TagKind::TokenType;
+            if !is_tag_free(&sim.tags.token_types, kind, desc.tag, &mut sim.warnings) {
+                continue;
+            }
+            let id = sim.tokens.define_type(TokenType {
                 name: desc.name,
                 category: TokenCategory::Pop,
                 supply: Default::default(),
-                demand: parse_tally_sm(&sim.good_types, desc.demand, "goods"),
+                demand: parse_tally_sm(
+                    &sim.good_types,
+                    &sim.tags.goods,
+                    desc.demand,
+                    TagKind::Good,
+                    &mut sim.warnings,
+                ),
                 rgo_points: desc.rgo_points,
+                jobs: Default::default(),
+                hospitality: 0.,
+                construction: None,
+                strength: 0.,
             });
+            sim.tags.token_types.insert(desc.tag, id);
         }
     }
 
@@ -791,6 +1303,13 @@ fn init(sim: &mut Simulation) {
             name: &'a str,
             inputs: &'a [(&'a str, f64)],
             outputs: &'a [(&'a str, f64)],
+            // Workers of each pop needed to run at full output
+            jobs: &'a [(&'a str, f64)],
+            // Construction cost, paid over the building days
+            cost: &'a [(&'a str, f64)],
+            cash: f64,
+            days: u32,
+            hospitality: f64,
         }
 
         const DESCS: &[Desc] = &[
@@ -799,33 +1318,323 @@ fn init(sim: &mut Simulation) {
                 name: "Wheat Farm",
                 inputs: &[],
                 outputs: &[("wheat", 100.)],
+                jobs: &[("paesants", 500.)],
+                cost: &[("lumber", 200.), ("tools", 20.)],
+                cash: 500.,
+                days: 30,
+                hospitality: 0.,
             },
             Desc {
                 tag: "lumber_field",
                 name: "Lumber Field",
                 inputs: &[],
                 outputs: &[("lumber", 100.)],
+                jobs: &[("paesants", 300.)],
+                cost: &[("tools", 20.)],
+                cash: 300.,
+                days: 20,
+                hospitality: 0.,
             },
             Desc {
                 tag: "toolmaker",
                 name: "Toolmaker",
                 inputs: &[("lumber", 10.)],
                 outputs: &[("tools", 100.)],
+                jobs: &[("artisans", 800.)],
+                cost: &[("lumber", 300.), ("tools", 50.)],
+                cash: 1000.,
+                days: 60,
+                hospitality: 0.,
+            },
+            Desc {
+                tag: "inn",
+                name: "Inn",
+                inputs: &[("wheat", 1.), ("meat", 0.5)],
+                outputs: &[],
+                jobs: &[("paesants", 50.)],
+                cost: &[("lumber", 100.)],
+                cash: 200.,
+                days: 15,
+                hospitality: 10.,
+            },
+            Desc {
+                tag: "caravansary",
+                name: "Caravansary",
+                inputs: &[("wheat", 2.), ("lumber", 0.5)],
+                outputs: &[],
+                jobs: &[("paesants", 100.)],
+                cost: &[("lumber", 300.), ("tools", 30.)],
+                cash: 800.,
+                days: 45,
+                hospitality: 25.,
             },
         ];
 
         for desc in DESCS {
-            sim.tokens.define_type(TokenType {
-                tag: desc.tag,
+            let kind = TagKind::TokenType;
+            if !is_tag_free(&sim.tags.token_types, kind, desc.tag, &mut sim.warnings) {
+                continue;
+            }
+            let id = sim.tokens.define_type(TokenType {
                 name: desc.name,
                 category: TokenCategory::Building,
-                demand: parse_tally_sm(&sim.good_types, desc.inputs, "goods"),
-                supply: parse_tally_sm(&sim.good_types, desc.outputs, "goods"),
+                demand: parse_tally_sm(
+                    &sim.good_types,
+                    &sim.tags.goods,
+                    desc.inputs,
+                    TagKind::Good,
+                    &mut sim.warnings,
+                ),
+                supply: parse_tally_sm(
+                    &sim.good_types,
+                    &sim.tags.goods,
+                    desc.outputs,
+                    TagKind::Good,
+                    &mut sim.warnings,
+                ),
+                rgo_points: 0.,
+                jobs: parse_tally_sm(
+                    &sim.tokens.types,
+                    &sim.tags.token_types,
+                    desc.jobs,
+                    TagKind::TokenType,
+                    &mut sim.warnings,
+                ),
+                hospitality: desc.hospitality,
+                construction: Some(ConstructionCost {
+                    goods: parse_tally_sm(
+                        &sim.good_types,
+                        &sim.tags.goods,
+                        desc.cost,
+                        TagKind::Good,
+                        &mut sim.warnings,
+                    ),
+                    cash: desc.cash,
+                    days: desc.days,
+                }),
+                strength: 0.,
+            });
+            sim.tags.token_types.insert(desc.tag, id);
+        }
+    }
+
+    // Init troops
+    {
+        struct Desc<'a> {
+            tag: &'a str,
+            name: &'a str,
+            strength: f64,
+        }
+
+        const DESCS: &[Desc] = &[
+            Desc {
+                tag: "levy",
+                name: "Levy",
+                strength: 1.,
+            },
+            Desc {
+                tag: "men_at_arms",
+                name: "Men-at-arms",
+                strength: 3.,
+            },
+        ];
+
+        for desc in DESCS {
+            let kind = TagKind::TokenType;
+            if !is_tag_free(&sim.tags.token_types, kind, desc.tag, &mut sim.warnings) {
+                continue;
+            }
+            let id = sim.tokens.define_type(TokenType {
+                name: desc.name,
+                category: TokenCategory::Military,
+                demand: Default::default(),
+                supply: Default::default(),
                 rgo_points: 0.,
+                jobs: Default::default(),
+                hospitality: 0.,
+                construction: None,
+                strength: desc.strength,
             });
+            sim.tags.token_types.insert(desc.tag, id);
+        }
+    }
+    // Init entity templates
+    {
+        use crate::templates::{EntityTemplate, TemplateGoal};
+        use crate::tick::*;
+
+        struct Desc {
+            tag: &'static str,
+            name: &'static str,
+            kind_name: &'static str,
+            faction: &'static str,
+            character: bool,
+            cash: f64,
+            image: &'static str,
+            movement_speed: f32,
+            carry_capacity: f64,
+            boats: bool,
+            off_road: bool,
+            ship: bool,
+            troops: &'static [(&'static str, i64)],
+            goal: TemplateGoal,
+            stance: Option<Stance>,
+        }
+
+        const DESCS: &[Desc] = &[
+            Desc {
+                tag: "farmers",
+                name: "Farmers",
+                kind_name: "",
+                faction: "$faction",
+                character: false,
+                cash: 1000.,
+                image: "farmers",
+                movement_speed: 2.,
+                carry_capacity: FARMER_CARRY_CAPACITY,
+                boats: false,
+                off_road: false,
+                ship: false,
+                troops: &[],
+                goal: TemplateGoal::LocalTrade,
+                stance: None,
+            },
+            Desc {
+                tag: "smugglers",
+                name: "Smugglers",
+                kind_name: "",
+                faction: "$faction",
+                character: false,
+                cash: 1000.,
+                image: "farmers",
+                movement_speed: 2.,
+                carry_capacity: FARMER_CARRY_CAPACITY,
+                boats: false,
+                off_road: false,
+                ship: false,
+                troops: &[],
+                goal: TemplateGoal::Smuggle,
+                stance: None,
+            },
+            Desc {
+                tag: "merchants",
+                name: "Merchants",
+                kind_name: "",
+                faction: "$faction",
+                character: false,
+                cash: 2000.,
+                image: "farmers",
+                movement_speed: CARAVAN_MOVEMENT_SPEED,
+                carry_capacity: MERCHANT_CARRY_CAPACITY,
+                boats: true,
+                off_road: false,
+                ship: false,
+                troops: &[],
+                goal: TemplateGoal::ArbitrageTrade,
+                stance: None,
+            },
+            // Merchants of the ports, trading along the coasts
+            Desc {
+                tag: "ship_merchants",
+                name: "Merchant Ship",
+                kind_name: "",
+                faction: "$faction",
+                character: false,
+                cash: 3000.,
+                image: "farmers",
+                movement_speed: SHIP_MOVEMENT_SPEED,
+                carry_capacity: SHIP_CARRY_CAPACITY,
+                boats: false,
+                off_road: false,
+                ship: true,
+                troops: &[],
+                goal: TemplateGoal::ArbitrageTrade,
+                stance: None,
+            },
+            // Rebels answer to no one
+            Desc {
+                tag: "rebels",
+                name: "Rebels",
+                kind_name: "",
+                faction: "",
+                character: false,
+                cash: 500.,
+                image: "farmers",
+                movement_speed: 2.,
+                carry_capacity: REBEL_CARRY_CAPACITY,
+                boats: false,
+                off_road: false,
+                ship: false,
+                // Raised by unrest at the uprising threshold, and more with more
+                troops: &[("levy", 40)],
+                goal: TemplateGoal::Rebel,
+                stance: None,
+            },
+            Desc {
+                tag: "person",
+                name: "Wanderer",
+                kind_name: "Person",
+                faction: "$faction",
+                character: true,
+                cash: CHARACTER_STARTING_CASH,
+                image: "person",
+                movement_speed: PERSON_MOVEMENT_SPEED,
+                carry_capacity: PERSON_CARRY_CAPACITY,
+                boats: false,
+                off_road: true,
+                ship: false,
+                troops: &[],
+                goal: TemplateGoal::Idle,
+                stance: None,
+            },
+            // Raised by factions to fight, and left to their orders
+            Desc {
+                tag: "warband",
+                name: "Warband",
+                kind_name: "",
+                faction: "$faction",
+                character: false,
+                cash: 500.,
+                image: "farmers",
+                movement_speed: 2.,
+                carry_capacity: REBEL_CARRY_CAPACITY,
+                boats: false,
+                off_road: false,
+                ship: false,
+                troops: &[("levy", 40), ("men_at_arms", 5)],
+                goal: TemplateGoal::Idle,
+                stance: Some(Stance::Aggressive),
+            },
+        ];
+
+        for desc in DESCS {
+            let template = EntityTemplate {
+                name: desc.name.to_string(),
+                kind_name: desc.kind_name,
+                faction: desc.faction.to_string(),
+                character: desc.character,
+                cash: desc.cash,
+                image: desc.image,
+                size: 1.,
+                movement_speed: desc.movement_speed,
+                carry_capacity: desc.carry_capacity,
+                boats: desc.boats,
+                off_road: desc.off_road,
+                ship: desc.ship,
+                troops: desc
+                    .troops
+                    .iter()
+                    .map(|&(tag, size)| (tag.to_string(), size))
+                    .collect(),
+                goal: desc.goal,
+                stance: desc.stance,
+            };
+            sim.templates.insert(desc.tag, template);
         }
     }
-    // Init sites
+}
+
+fn init_sites(sim: &mut Simulation) {
     {
         struct Desc {
             tag: &'static str,
@@ -833,7 +1642,7 @@ fn init(sim: &mut Simulation) {
             rgo: &'static [(&'static str, f64)],
         }
 
-        const NORMAL_COUNTRYSIDE_RGO: &[(&str, f64)] = &[("wheat", 1.2), ("lumber", 0.5)];
+        const NORMAL_COUNTRYSIDE_RGO: &[(&str, f64)] = &[("wheat", 1.9), ("lumber", 1.0)];
 
         const DESCS: &[Desc] = &[
             Desc {
@@ -886,45 +1695,88 @@ fn init(sim: &mut Simulation) {
                 pos: (-19., -6.2),
                 rgo: NORMAL_COUNTRYSIDE_RGO,
             },
+            Desc {
+                tag: "maia",
+                pos: (-6., 3.),
+                rgo: &[("wheat", 1.2)],
+            },
+            Desc {
+                tag: "solway_firth",
+                pos: (-13., 4.),
+                rgo: &[],
+            },
+            Desc {
+                tag: "irish_sea",
+                pos: (-27., -2.),
+                rgo: &[],
+            },
         ];
 
+        let lumber = sim.tags.goods.lookup("lumber");
         for desc in DESCS {
-            let rgo = SiteRGO {
-                rates: parse_tally(&sim.good_types, desc.rgo, "goods"),
-                capacity: 5_000,
-            };
-            sim.sites.define(desc.tag, desc.pos.into(), rgo);
-        }
-
-        const CONNECTIONS: &[(&str, &str)] = &[
-            ("caer_ligualid", "anava"),
-            ("din_drust", "anava"),
-            ("caer_ligualid", "caer_ligualid_south"),
-            ("caer_ligualid_south", "llan_heledd"),
-            ("caer_ligualid", "caer_ligualid-din_drust"),
-            ("din_drust", "caer_ligualid-din_drust"),
-            ("din_drust", "isura"),
-            ("isura", "isura_west"),
-            ("isura_west", "din_rheged"),
-            ("isura_west", "ad_candidam_casam"),
+            let rates = parse_tally(&sim.tags.goods, desc.rgo, TagKind::Good, &mut sim.warnings);
+            let rgo = SiteRGO::with_rates(rates.iter(), 5_000, lumber);
+            if !is_tag_free(&sim.tags.sites, TagKind::Site, desc.tag, &mut sim.warnings) {
+                continue;
+            }
+            let id = sim.sites.define(desc.tag, desc.pos.into(), rgo);
+            sim.tags.sites.insert(desc.tag, id);
+        }
+
+        const CONNECTIONS: &[(&str, &str, Terrain)] = &[
+            ("caer_ligualid", "anava", Terrain::Road),
+            ("din_drust", "anava", Terrain::Road),
+            ("caer_ligualid", "caer_ligualid_south", Terrain::Road),
+            ("caer_ligualid_south", "llan_heledd", Terrain::Ford),
+            ("caer_ligualid", "caer_ligualid-din_drust", Terrain::Road),
+            ("din_drust", "caer_ligualid-din_drust", Terrain::Road),
+            ("din_drust", "isura", Terrain::Forest),
+            ("isura", "isura_west", Terrain::Trail),
+            ("isura_west", "din_rheged", Terrain::Trail),
+            ("isura_west", "ad_candidam_casam", Terrain::Trail),
+            ("anava", "llan_heledd", Terrain::River),
+            ("caer_ligualid", "maia", Terrain::Road),
+            ("maia", "solway_firth", Terrain::Sea),
+            ("solway_firth", "ad_candidam_casam", Terrain::Sea),
+            ("solway_firth", "irish_sea", Terrain::Sea),
+            ("irish_sea", "din_rheged", Terrain::Sea),
         ];
 
-        for (tag1, tag2) in CONNECTIONS {
-            let id1 = match sim.sites.lookup(&tag1) {
-                Some((id, _)) => id,
+        for &(tag1, tag2, terrain) in CONNECTIONS {
+            let id1 = match sim.tags.sites.lookup(tag1) {
+                Some(id) => id,
                 None => {
-                    println!("Unknown site '{tag1}'");
+                    sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Site,
+                        tag: tag1.to_string(),
+                    });
                     continue;
                 }
             };
-            let id2 = match sim.sites.lookup(&tag2) {
-                Some((id, _)) => id,
+            let id2 = match sim.tags.sites.lookup(tag2) {
+                Some(id) => id,
                 None => {
-                    println!("Unknown site '{tag2}'");
+                    sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Site,
+                        tag: tag2.to_string(),
+                    });
                     continue;
                 }
             };
-            sim.sites.connect(id1, id2);
+            sim.sites.connect(id1, id2, terrain);
+        }
+
+        // Where those on foot can get across the rivers
+        const CROSSINGS: &[(&str, Crossing)] = &[("llan_heledd", Crossing::Ferry)];
+
+        for &(tag, crossing) in CROSSINGS {
+            match sim.tags.sites.lookup(tag) {
+                Some(id) => sim.sites.set_crossing(id, crossing),
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Site,
+                    tag: tag.to_string(),
+                }),
+            }
         }
     }
 }
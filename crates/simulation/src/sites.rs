@@ -1,40 +1,174 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use slotmap::{SecondaryMap, SlotMap, new_key_type};
-use util::{
-    arena::{AVec, Arena, ArenaSafe},
-    tally::Tally,
-};
+use util::arena::ArenaSafe;
 
+use strum::{EnumCount, EnumIter};
+use util::enum_map::{EnumMap, EnumMapKey};
+use util::spatial_grid::SpatialGrid;
+
+use crate::schedule::Slice;
 use crate::simulation::*;
 
 new_key_type! { pub(crate) struct SiteId; }
 
 impl ArenaSafe for SiteId {}
 
-#[derive(Default)]
-pub(crate) struct SiteRGO {
-    pub rates: Tally<GoodId>,
+/// A resource worked at a site, such as a field, a forest or a mine
+#[derive(Clone)]
+pub(crate) struct Deposit {
+    pub good: GoodId,
+    // Output of each worker while the deposit is untouched
+    pub rate: f64,
+    // Most workers the deposit employs
     pub capacity: i64,
+    // Share of the deposit left, from 0 when exhausted to 1 when untouched
+    pub level: f64,
+    // Level lost in a day of work at full capacity
+    pub depletion: f64,
+    // Level regained each day
+    pub regeneration: f64,
+    // Workers at the deposit on the last day of work
+    pub workers: f64,
+}
+
+impl Deposit {
+    /// A deposit that never runs out
+    pub fn renewable(good: GoodId, rate: f64, capacity: i64) -> Self {
+        Self {
+            good,
+            rate,
+            capacity,
+            level: 1.,
+            depletion: 0.,
+            regeneration: 0.,
+            workers: 0.,
+        }
+    }
+
+    pub fn with_depletion(mut self, depletion: f64, regeneration: f64) -> Self {
+        self.depletion = depletion;
+        self.regeneration = regeneration;
+        self
+    }
+
+    /// Output of a day of work by the given number of workers
+    pub fn output(&self, workers: f64) -> f64 {
+        self.rate * workers.min(self.capacity as f64) * self.level
+    }
+
+    fn extract(&mut self, workers: f64, days: f64) {
+        self.workers = workers;
+        let share = workers.min(self.capacity as f64) / self.capacity.max(1) as f64;
+        let depletion = 1. - (1. - self.depletion * share).max(0.).powf(days);
+        self.level = (self.level - depletion * self.level).max(0.);
+    }
+
+    fn regenerate(&mut self) {
+        self.level = (self.level + self.regeneration).min(1.);
+    }
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct SiteRGO {
+    pub deposits: Vec<Deposit>,
+}
+
+// Forests are logged out within a year of full work, and take two to grow back
+const FOREST_DEPLETION: f64 = 1. / 360.;
+const FOREST_REGROWTH: f64 = 1. / 720.;
+
+impl SiteRGO {
+    /// A deposit for each good produced, all employing up to `capacity`
+    /// workers. Lumber comes from forests that can be logged out.
+    pub fn with_rates(
+        rates: impl IntoIterator<Item = (GoodId, f64)>,
+        capacity: i64,
+        lumber: Option<GoodId>,
+    ) -> Self {
+        let deposits = rates
+            .into_iter()
+            .map(|(good, rate)| {
+                let deposit = Deposit::renewable(good, rate, capacity);
+                if Some(good) == lumber {
+                    deposit.with_depletion(FOREST_DEPLETION, FOREST_REGROWTH)
+                } else {
+                    deposit
+                }
+            })
+            .collect();
+        Self { deposits }
+    }
+
+    /// Splits the workers among the deposits in proportion to the value of a
+    /// day of work at each, so they turn to the goods that fetch the most.
+    /// The workers a full deposit turns away go to the others.
+    pub fn allocate(&self, workers: f64, value: impl Fn(&Deposit) -> f64) -> Vec<f64> {
+        let mut values: Vec<f64> = self
+            .deposits
+            .iter()
+            .map(|deposit| value(deposit).max(0.))
+            .collect();
+        if values.iter().all(|&value| value <= 0.) {
+            values.fill(1.);
+        }
+        let mut allocation = vec![0.; self.deposits.len()];
+        let mut left = workers;
+        // Every pass either places all the workers left or fills a deposit
+        for _ in 0..self.deposits.len() {
+            let open = |idx: usize| allocation[idx] < self.deposits[idx].capacity as f64;
+            let total: f64 = (0..values.len())
+                .filter(|&idx| open(idx))
+                .map(|idx| values[idx])
+                .sum();
+            if left <= 0. || total <= 0. {
+                break;
+            }
+            let shares: Vec<f64> = (0..values.len())
+                .map(|idx| {
+                    if !open(idx) {
+                        return 0.;
+                    }
+                    let room = self.deposits[idx].capacity as f64 - allocation[idx];
+                    (left * values[idx] / total).min(room)
+                })
+                .collect();
+            for (placed, share) in allocation.iter_mut().zip(shares) {
+                *placed += share;
+                left -= share;
+            }
+        }
+        allocation
+    }
+
+    /// Output of each worker across the deposits of a good, while untouched
+    pub fn rate_of(&self, good: GoodId) -> f64 {
+        self.deposits
+            .iter()
+            .filter(|deposit| deposit.good == good)
+            .map(|deposit| deposit.rate)
+            .sum()
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct SiteData {
     pub tag: String,
     pub pos: V2,
-    pub neighbours: Vec<(SiteId, f32)>,
+    pub neighbours: Vec<(SiteId, f32, EdgeKind)>,
     pub location: Option<LocationId>,
+    /// Lets parties on foot across the rivers running from the site
+    pub crossing: Option<Crossing>,
+    /// Faction holding the site, by influence or by conquest
+    pub controller: Option<AgentId>,
+    /// Faction that took the site by force, and holds it whatever the
+    /// influences over it
+    pub conqueror: Option<AgentId>,
     pub rgo: SiteRGO,
     pub influences: Influences,
 }
 
-impl Tagged for SiteData {
-    fn tag(&self) -> &str {
-        &self.tag
-    }
-}
-
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct Influences(Vec<(InfluenceType, i32)>);
 
 impl Influences {
@@ -49,14 +183,188 @@ impl Influences {
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a (InfluenceType, i32)> + use<'a> {
         self.0.iter()
     }
+
+    /// Combined strength of all the sources of a kind
+    pub fn strength(&self, kind: InfluenceKind, blend: InfluenceBlend) -> f64 {
+        blend.combine(
+            self.0
+                .iter()
+                .filter(|(typ, _)| typ.kind == kind)
+                .map(|&(_, amount)| amount as f64),
+        )
+    }
+}
+
+// Parties a road holds per unit of length before it gets crowded
+const ROAD_CAPACITY_PER_UNIT: f32 = 4.;
+const MIN_ROAD_CAPACITY: u32 = 2;
+// Crowded roads slow parties down at most this many times
+const MAX_SLOWDOWN: f32 = 4.;
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct Road {
+    pub capacity: u32,
+    /// Parties currently travelling the road
+    pub traffic: u32,
+    pub terrain: Terrain,
+    /// Upgrades the road has seen, each making it faster to travel
+    pub quality: u8,
+    /// Work done towards the next upgrade, by the parties that wore it in
+    /// and the factions that paid for it
+    pub works: f32,
+}
+
+impl Road {
+    /// How many times slower than on an empty paved road parties travel
+    pub fn slowdown(&self) -> f32 {
+        self.congestion() * self.terrain.slowdown()
+    }
+
+    // Slowdown from the traffic on the road, eased by its upgrades
+    fn congestion(&self) -> f32 {
+        let load = self.traffic as f32 / self.capacity.max(1) as f32;
+        load.clamp(1., MAX_SLOWDOWN) / (1. + QUALITY_SPEEDUP * self.quality as f32)
+    }
+}
+
+// Share of its speed a road gains with each upgrade
+const QUALITY_SPEEDUP: f32 = 0.1;
+
+/// What the way between two connected sites is like
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Terrain {
+    #[default]
+    Road,
+    Trail,
+    Forest,
+    Ford,
+    MountainPass,
+    /// Only travelled by boat, or crossed where there is a bridge or a ferry
+    River,
+    /// Only sailed by ships
+    Sea,
+}
+
+impl Terrain {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Road => "road",
+            Self::Trail => "trail",
+            Self::Forest => "forest",
+            Self::Ford => "ford",
+            Self::MountainPass => "mountain pass",
+            Self::River => "river",
+            Self::Sea => "sea",
+        }
+    }
+
+    // How many times slower than on a road parties travel it, boats along
+    // rivers
+    fn slowdown(self) -> f32 {
+        match self {
+            Self::Road => 1.,
+            Self::Trail => 1.25,
+            Self::Forest => 1.6,
+            Self::Ford => 1.2,
+            Self::MountainPass => 1.5,
+            Self::River => 0.6,
+            Self::Sea => 1.,
+        }
+    }
+}
+
+/// Whether a connection between sites is travelled over land or by water
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum EdgeKind {
+    Land,
+    River,
+    Sea,
+}
+
+/// A way across the rivers at a site
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Crossing {
+    Bridge,
+    Ferry,
+}
+
+impl Crossing {
+    // How many times slower than on a road parties cross it
+    fn slowdown(self) -> f32 {
+        match self {
+            Self::Bridge => 1.,
+            Self::Ferry => 1.5,
+        }
+    }
+}
+
+/// A crossing off the roads, only used by the factions that found it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PassageKind {
+    Ford,
+    MountainPass,
+}
+
+impl PassageKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ford => "ford",
+            Self::MountainPass => "mountain pass",
+        }
+    }
+
+    // How many times slower than on a road parties cross it
+    fn slowdown(self) -> f32 {
+        match self {
+            Self::Ford => Terrain::Ford,
+            Self::MountainPass => Terrain::MountainPass,
+        }
+        .slowdown()
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(crate) struct Sites {
     entries: SlotMap<SiteId, SiteData>,
     distances: BTreeMap<(SiteId, SiteId), f32>,
+    roads: BTreeMap<(SiteId, SiteId), Road>,
+    passages: BTreeMap<(SiteId, SiteId), PassageKind>,
+    regions: crate::regions::Regions,
+    // Paths found since the roads last changed, by their endpoints and the
+    // passages known to whoever asked
+    paths: BTreeMap<PathQuery, Option<(Vec<SiteId>, f32)>>,
+    // Where the influence of each source site reaches, kept until its
+    // emissions change
+    influence_fields: BTreeMap<SiteId, InfluenceField>,
+    // Where the sites lie, to find those in an area quickly
+    grid: SpatialGrid<SiteId>,
+    // How many times slower than on a road parties walk where there is none
+    off_road_slowdown: f32,
 }
 
+/// Which ways besides the roads and known passages a party can travel
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) struct Mobility {
+    /// Along rivers, by boat
+    pub boats: bool,
+    /// Straight across country between nearby sites of the same region
+    pub off_road: bool,
+    /// Across the sea, and nowhere else
+    pub ship: bool,
+}
+
+// Along with how the party gets around
+type PathQuery = (SiteId, SiteId, Vec<(SiteId, SiteId)>, Mobility);
+
+// Paths kept before the cache starts over
+const MAX_CACHED_PATHS: usize = 4096;
+
+/// How many times slower than on a road parties walk off it, unless set
+/// otherwise
+pub const DEFAULT_OFF_ROAD_SLOWDOWN: f32 = 2.;
+// Furthest parties walk off road between two sites
+const OFF_ROAD_RANGE: f32 = 8.;
+
 impl std::ops::Index<SiteId> for Sites {
     type Output = SiteData;
 
@@ -67,24 +375,44 @@ impl std::ops::Index<SiteId> for Sites {
 
 impl Sites {
     pub fn define(&mut self, tag: impl Into<String>, pos: V2, rgo: SiteRGO) -> SiteId {
-        self.entries.insert(SiteData {
+        let id = self.entries.insert(SiteData {
             tag: tag.into(),
             pos,
             neighbours: vec![],
             location: None,
+            crossing: None,
+            controller: None,
+            conqueror: None,
             rgo,
             influences: Influences::default(),
-        })
+        });
+        self.grid.insert_point(id, (pos.x, pos.y));
+        id
+    }
+
+    /// The sites within an area
+    pub fn within(&self, extents: Extents) -> impl Iterator<Item = (SiteId, &SiteData)> + '_ {
+        let (min, max) = extents.corners();
+        self.grid
+            .query(min, max)
+            .into_iter()
+            .map(|id| (id, &self.entries[id]))
+            .filter(move |(_, site)| extents.contains(site.pos))
     }
 
     pub fn make_secondary_map<T>(&self) -> SecondaryMap<SiteId, T> {
         SecondaryMap::with_capacity(self.entries.capacity())
     }
 
-    pub fn connect(&mut self, id1: SiteId, id2: SiteId) {
+    pub fn connect(&mut self, id1: SiteId, id2: SiteId, terrain: Terrain) {
         let distance = self.entries[id1].pos.distance(self.entries[id2].pos);
-        Self::insert_no_repeat(&mut self.entries[id1].neighbours, id2, distance);
-        Self::insert_no_repeat(&mut self.entries[id2].neighbours, id1, distance);
+        let kind = match terrain {
+            Terrain::River => EdgeKind::River,
+            Terrain::Sea => EdgeKind::Sea,
+            _ => EdgeKind::Land,
+        };
+        Self::insert_no_repeat(&mut self.entries[id1].neighbours, id2, distance, kind);
+        Self::insert_no_repeat(&mut self.entries[id2].neighbours, id1, distance, kind);
 
         // Record distance
         let min_id = id1.min(id2);
@@ -93,19 +421,90 @@ impl Sites {
         let p2 = self[max_id].pos;
         let distance = p1.distance(p2);
         self.distances.insert((min_id, max_id), distance);
+
+        self.paths.clear();
+        let capacity = ((distance * ROAD_CAPACITY_PER_UNIT).ceil() as u32).max(MIN_ROAD_CAPACITY);
+        self.influence_fields.clear();
+        self.roads.insert(
+            (min_id, max_id),
+            Road {
+                capacity,
+                traffic: 0,
+                terrain,
+                quality: 0,
+                works: 0.,
+            },
+        );
+    }
+
+    /// Adds a crossing between two sites that pathfinding only takes when told
+    /// it is known
+    pub fn add_passage(&mut self, id1: SiteId, id2: SiteId, kind: PassageKind) {
+        let key = (id1.min(id2), id1.max(id2));
+        let distance = self.entries[id1].pos.distance(self.entries[id2].pos);
+        self.distances.entry(key).or_insert(distance);
+        self.passages.insert(key, kind);
+        self.paths.clear();
+    }
+
+    pub fn passages(&self) -> impl Iterator<Item = ((SiteId, SiteId), PassageKind)> + '_ {
+        self.passages.iter().map(|(&key, &kind)| (key, kind))
+    }
+
+    pub fn passage(&self, id1: SiteId, id2: SiteId) -> Option<PassageKind> {
+        self.passages.get(&(id1.min(id2), id1.max(id2))).copied()
+    }
+
+    /// Builds a bridge or sets up a ferry across the rivers at a site
+    pub fn set_crossing(&mut self, id: SiteId, crossing: Crossing) {
+        if let Some(site) = self.entries.get_mut(id) {
+            site.crossing = Some(crossing);
+            self.paths.clear();
+        }
+    }
+
+    /// Returns whether the site changed hands
+    pub fn set_controller(&mut self, id: SiteId, controller: Option<AgentId>) -> bool {
+        let site = &mut self.entries[id];
+        let changed = site.controller != controller;
+        site.controller = controller;
+        changed
     }
 
-    fn insert_no_repeat(vs: &mut Vec<(SiteId, f32)>, id: SiteId, distance: f32) {
+    pub fn set_conqueror(&mut self, id: SiteId, faction: AgentId) {
+        if let Some(site) = self.entries.get_mut(id) {
+            site.conqueror = Some(faction);
+        }
+    }
+
+    fn insert_no_repeat(
+        vs: &mut Vec<(SiteId, f32, EdgeKind)>,
+        id: SiteId,
+        distance: f32,
+        kind: EdgeKind,
+    ) {
         if vs.iter().all(|x| x.0 != id) {
-            vs.push((id, distance));
+            vs.push((id, distance, kind));
         }
     }
 
-    pub fn lookup<'a>(&'a self, tag: &str) -> Option<(SiteId, &'a SiteData)> {
-        self.entries
-            .iter()
-            .find(|(_, data)| data.tag.as_str() == tag)
-            .map(|(id, data)| (id, data))
+    /// Wears down the deposits of a site by a day of work, given the workers
+    /// at each deposit
+    /// Works the deposits of a site for some days, depleting them
+    pub fn extract(&mut self, id: SiteId, workers: &[f64], days: f64) {
+        if let Some(site) = self.entries.get_mut(id) {
+            for (deposit, &workers) in site.rgo.deposits.iter_mut().zip(workers) {
+                deposit.extract(workers, days);
+            }
+        }
+    }
+
+    pub fn regenerate_deposits(&mut self) {
+        for site in self.entries.values_mut() {
+            for deposit in &mut site.rgo.deposits {
+                deposit.regenerate();
+            }
+        }
     }
 
     pub fn get(&self, id: SiteId) -> Option<&SiteData> {
@@ -132,19 +531,10 @@ impl Sites {
         self.entries.iter()
     }
 
-    pub fn neighbours(&self, id: SiteId) -> &[(SiteId, f32)] {
+    pub fn neighbours(&self, id: SiteId) -> &[(SiteId, f32, EdgeKind)] {
         &self.entries[id].neighbours
     }
 
-    pub fn greater_neighbours(&self, id: SiteId) -> impl Iterator<Item = SiteId> + use<'_> {
-        self.entries
-            .get(id)
-            .into_iter()
-            .flat_map(|data| data.neighbours.iter().copied())
-            .filter(move |&x| x.0 > id)
-            .map(|x| x.0)
-    }
-
     pub fn distance(&self, id1: SiteId, id2: SiteId) -> f32 {
         if id1 == id2 {
             return 0.;
@@ -157,7 +547,223 @@ impl Sites {
             .unwrap_or(f32::INFINITY)
     }
 
+    pub fn road(&self, id1: SiteId, id2: SiteId) -> Option<&Road> {
+        self.roads.get(&(id1.min(id2), id1.max(id2)))
+    }
+
+    pub fn roads(&self) -> impl Iterator<Item = ((SiteId, SiteId), &Road)> + '_ {
+        self.roads.iter().map(|(&key, road)| (key, road))
+    }
+
+    /// Adds work towards the next upgrade of the road between two sites
+    pub fn add_works(&mut self, id1: SiteId, id2: SiteId, works: f32) {
+        // The sea wears in no road
+        if let Some(road) = self.roads.get_mut(&(id1.min(id2), id1.max(id2)))
+            && road.terrain != Terrain::Sea
+        {
+            road.works += works;
+        }
+    }
+
+    /// Upgrades a road, spending the work done towards it
+    pub fn improve_road(&mut self, id1: SiteId, id2: SiteId) {
+        if let Some(road) = self.roads.get_mut(&(id1.min(id2), id1.max(id2))) {
+            road.quality += 1;
+            road.works = 0.;
+            self.paths.clear();
+        }
+    }
+
+    /// Whether a site lies out at sea, reached only by ships
+    pub fn is_sea(&self, site: SiteId) -> bool {
+        let neighbours = self.neighbours(site);
+        !neighbours.is_empty() && neighbours.iter().all(|&(_, _, kind)| kind == EdgeKind::Sea)
+    }
+
+    /// Whether ships call at a site, where goods and passengers go between
+    /// land and sea
+    pub fn is_port(&self, site: SiteId) -> bool {
+        let neighbours = self.neighbours(site);
+        neighbours.iter().any(|&(_, _, kind)| kind == EdgeKind::Sea)
+            && neighbours.iter().any(|&(_, _, kind)| kind != EdgeKind::Sea)
+    }
+
+    /// Whether a position is ashore, where parties on foot can stand
+    pub fn ashore(&self, position: GridCoord) -> bool {
+        match position {
+            GridCoord::At(site) => !self.is_sea(site),
+            GridCoord::Between(a, b, _) => self.terrain(a, b) != Terrain::Sea,
+        }
+    }
+
+    pub fn terrain(&self, id1: SiteId, id2: SiteId) -> Terrain {
+        self.road(id1, id2)
+            .map_or(Terrain::Road, |road| road.terrain)
+    }
+
+    pub fn set_off_road_slowdown(&mut self, slowdown: f32) {
+        if self.off_road_slowdown != slowdown {
+            self.off_road_slowdown = slowdown;
+            self.paths.clear();
+        }
+    }
+
+    /// How far a party travels between two sites: along the road or passage
+    /// joining them, or straight across country if there is none
+    pub fn way_length(&self, id1: SiteId, id2: SiteId) -> f32 {
+        let distance = self.distance(id1, id2);
+        if distance.is_finite() {
+            return distance;
+        }
+        self.entries[id1].pos.distance(self.entries[id2].pos)
+    }
+
+    /// How many times slower than on an empty road a party travels between
+    /// two sites, infinitely so over a river it has no boats or crossing for
+    pub fn slowdown(&self, id1: SiteId, id2: SiteId, mobility: Mobility) -> f32 {
+        let (terrain, road) = self.slowdown_parts(id1, id2, mobility);
+        terrain * road
+    }
+
+    /// The slowdown between two sites, split between what the way crosses
+    /// and the state of the road, if there is one
+    pub fn slowdown_parts(&self, id1: SiteId, id2: SiteId, mobility: Mobility) -> (f32, f32) {
+        // Ships keep to the sea, and only ships take to it
+        let at_sea = self
+            .road(id1, id2)
+            .is_some_and(|road| road.terrain == Terrain::Sea);
+        if at_sea != mobility.ship {
+            return (f32::INFINITY, 1.);
+        }
+        match self.road(id1, id2) {
+            Some(road) if road.terrain == Terrain::River && !mobility.boats => {
+                let crossing = [id1, id2]
+                    .into_iter()
+                    .filter_map(|id| self.entries[id].crossing)
+                    .map(Crossing::slowdown)
+                    .reduce(f32::min)
+                    .unwrap_or(f32::INFINITY);
+                (crossing, road.congestion())
+            }
+            Some(road) => (road.terrain.slowdown(), road.congestion()),
+            None => (
+                self.passage(id1, id2)
+                    .map_or(self.off_road_slowdown, PassageKind::slowdown),
+                1.,
+            ),
+        }
+    }
+
+    /// Counts the parties on each road, given the positions of all the moving
+    /// parties
+    pub fn update_traffic(&mut self, positions: impl IntoIterator<Item = GridCoord>) {
+        let before: Vec<f32> = self.roads.values().map(Road::slowdown).collect();
+        for road in self.roads.values_mut() {
+            road.traffic = 0;
+        }
+        for position in positions {
+            if let GridCoord::Between(a, b, _) = position
+                && let Some(road) = self.roads.get_mut(&(a, b))
+            {
+                road.traffic += 1;
+            }
+        }
+        // Paths found on roads that are no longer as slow are stale
+        if !self.roads.values().map(Road::slowdown).eq(before) {
+            self.paths.clear();
+        }
+    }
+
+    /// Like `astar_with`, but answers queries already asked since the roads
+    /// last changed from the cache
+    pub fn cached_path(
+        &mut self,
+        start_node: SiteId,
+        end_node: SiteId,
+        known: &BTreeSet<(SiteId, SiteId)>,
+        mobility: Mobility,
+    ) -> Option<(Vec<SiteId>, f32)> {
+        let query = (
+            start_node,
+            end_node,
+            known.iter().copied().collect(),
+            mobility,
+        );
+        if let Some(path) = self.paths.get(&query) {
+            return path.clone();
+        }
+        let path = self.astar_with(start_node, end_node, known, mobility);
+        if self.paths.len() >= MAX_CACHED_PATHS {
+            self.paths.clear();
+        }
+        self.paths.insert(query, path.clone());
+        path
+    }
+
+    /// Shortest path by travel time over land, where crowded roads count as
+    /// longer
     pub fn astar(&self, start_node: SiteId, end_node: SiteId) -> Option<(Vec<SiteId>, f32)> {
+        self.astar_with(start_node, end_node, &BTreeSet::new(), Mobility::default())
+    }
+
+    /// Clusters the sites into regions for pathfinding, once the map is laid out
+    pub fn build_regions(&mut self) {
+        self.regions = crate::regions::Regions::build(self);
+        self.paths.clear();
+    }
+
+    /// Shortest path by travel time, also crossing the known passages, and
+    /// along rivers or off road for parties that can. On large maps the path is found
+    /// through the regions first, and only searched for across the whole map
+    /// if the regions crossed hold none.
+    pub fn astar_with(
+        &self,
+        start_node: SiteId,
+        end_node: SiteId,
+        known: &BTreeSet<(SiteId, SiteId)>,
+        mobility: Mobility,
+    ) -> Option<(Vec<SiteId>, f32)> {
+        if let Some(corridor) = self.regions.corridor(start_node, end_node, known) {
+            let within = |site: SiteId| {
+                self.regions
+                    .of(site)
+                    .is_some_and(|region| corridor.contains(&region))
+            };
+            if let Some(path) = self.astar_within(start_node, end_node, known, mobility, within) {
+                return Some(path);
+            }
+        }
+        self.astar_within(start_node, end_node, known, mobility, |_| true)
+    }
+
+    // Sites within walking range of a site across country, in its region and
+    // not already joined to it, with how far each lies
+    fn off_road_neighbours(&self, site: SiteId) -> Vec<(SiteId, f32)> {
+        let pos = self.entries[site].pos;
+        let region = self.regions.of(site);
+        self.grid
+            .query(
+                (pos.x - OFF_ROAD_RANGE, pos.y - OFF_ROAD_RANGE),
+                (pos.x + OFF_ROAD_RANGE, pos.y + OFF_ROAD_RANGE),
+            )
+            .into_iter()
+            .filter(|&other| other != site && self.distance(site, other).is_infinite())
+            .filter(|&other| !self.is_sea(other))
+            .filter(|&other| self.regions.of(other) == region)
+            .map(|other| (other, pos.distance(self.entries[other].pos)))
+            .filter(|&(_, d)| d <= OFF_ROAD_RANGE)
+            .collect()
+    }
+
+    // Shortest path that only goes through the sites allowed
+    fn astar_within(
+        &self,
+        start_node: SiteId,
+        end_node: SiteId,
+        known: &BTreeSet<(SiteId, SiteId)>,
+        mobility: Mobility,
+        allowed: impl Fn(SiteId) -> bool,
+    ) -> Option<(Vec<SiteId>, f32)> {
         const RATE: f32 = 1000.;
 
         fn metric(x: f32) -> i64 {
@@ -171,7 +777,33 @@ impl Sites {
         let end_v2 = self.get(end_node).unwrap().pos;
         pathfinding::directed::astar::astar(
             &start_node,
-            |&site| self.neighbours(site).iter().map(|&(s, d)| (s, metric(d))),
+            |&site| {
+                let crossings = known.iter().filter_map(move |&(a, b)| {
+                    let other = if a == site {
+                        b
+                    } else if b == site {
+                        a
+                    } else {
+                        return None;
+                    };
+                    Some((other, self.distance(site, other)))
+                });
+                let shortcuts = mobility
+                    .off_road
+                    .then(|| self.off_road_neighbours(site))
+                    .into_iter()
+                    .flatten();
+                self.neighbours(site)
+                    .iter()
+                    .map(|&(s, d, _)| (s, d))
+                    .chain(crossings)
+                    .chain(shortcuts)
+                    .filter(|&(s, _)| allowed(s))
+                    .map(move |(s, d)| (s, d * self.slowdown(site, s, mobility)))
+                    .filter(|&(_, cost)| cost.is_finite())
+                    .map(|(s, cost)| (s, metric(cost)))
+                    .collect::<Vec<_>>()
+            },
             |&site| {
                 let site_v2 = self.get(site).unwrap().pos;
                 metric(end_v2.distance(site_v2))
@@ -182,66 +814,202 @@ impl Sites {
     }
 }
 
+// Roads influence crosses away from its source, at most
+const MAX_INFLUENCE_HOPS: u32 = 12;
+
+/// Influence a source site last emitted, and where it reaches
+#[derive(Clone)]
+pub(crate) struct InfluenceField {
+    emitted: Vec<(InfluenceType, i32)>,
+    reach: Vec<(SiteId, InfluenceType, i32)>,
+}
+
+/// Spreads the influence of the sources in the slice whose emissions
+/// changed since their last turn out over the roads, decaying with each road
+/// crossed, and sets the influences of every site to the strongest of each
+/// type that reaches it. Returns whether any site changed.
 pub(crate) fn propagate_influences(
-    arena: &Arena,
     sites: &mut Sites,
     sources: &SecondaryMap<SiteId, &[(InfluenceType, i32)]>,
-) {
-    fn decay(kind: InfluenceKind, x: i32, distance: f32) -> i32 {
+    slice: Slice,
+) -> bool {
+    let mut changed = false;
+    // Sources outside the slice keep their fields until their turn
+    let gone: Vec<SiteId> = sites
+        .influence_fields
+        .keys()
+        .copied()
+        .filter(|&id| slice.contains(id) && !sources.contains_key(id))
+        .collect();
+    for id in gone {
+        sites.influence_fields.remove(&id);
+        changed = true;
+    }
+    for (origin, &emitted) in sources.iter().filter(|&(id, _)| slice.contains(id)) {
+        let unchanged = sites
+            .influence_fields
+            .get(&origin)
+            .is_some_and(|field| field.emitted == emitted);
+        if unchanged {
+            continue;
+        }
+        let reach = spread_influence(sites, origin, emitted);
+        sites.influence_fields.insert(
+            origin,
+            InfluenceField {
+                emitted: emitted.to_vec(),
+                reach,
+            },
+        );
+        changed = true;
+    }
+    if !changed {
+        return false;
+    }
+
+    let mut combined: SecondaryMap<SiteId, Vec<(InfluenceType, i32)>> = sites.make_secondary_map();
+    for field in sites.influence_fields.values() {
+        for &(site, typ, amount) in &field.reach {
+            let at = combined.entry(site).unwrap().or_default();
+            match at.binary_search_by_key(&typ, |x| x.0) {
+                Ok(idx) => at[idx].1 = at[idx].1.max(amount),
+                Err(idx) => at.insert(idx, (typ, amount)),
+            }
+        }
+    }
+
+    let mut updated = false;
+    for (id, site) in sites.entries.iter_mut() {
+        let mut influences = combined.remove(id).unwrap_or_default();
+        influences.sort_by_key(|(_, x)| -x);
+        if site.influences.0 != influences {
+            site.influences.0 = influences;
+            updated = true;
+        }
+    }
+    updated
+}
+
+// Where the influence a site emits reaches, breadth first over the roads
+fn spread_influence(
+    sites: &Sites,
+    origin: SiteId,
+    emitted: &[(InfluenceType, i32)],
+) -> Vec<(SiteId, InfluenceType, i32)> {
+    fn decay(kind: InfluenceKind, x: i32) -> i32 {
         let speed = match kind {
             InfluenceKind::Market => 0.3,
+            InfluenceKind::Security => 0.5,
         };
         let x = x as f32;
         let loss = x * speed;
         (x - loss).round().max(0.) as i32
     }
 
-    let updates = arena.alloc_iter(sites.iter().map(|(site_id, _)| {
-        // Accumulate contributions from sources
-        let mut contributions: AVec<(InfluenceType, i32)> = arena.new_vec();
-        let from_source = sources.get(site_id).copied().unwrap_or_default();
-        contributions.extend(from_source);
-
-        // Accumulate contributions from neighbours
-        for &(neighbour, distance) in sites.neighbours(site_id) {
-            let neighbour_data = &sites[neighbour];
-            for &(inf_type, amount) in &neighbour_data.influences.0 {
-                let propagated = decay(inf_type.kind, amount, distance);
-                if propagated > 0 {
-                    contributions.push((inf_type, propagated));
+    let mut reach = vec![];
+    for &(typ, power) in emitted {
+        let mut visited = BTreeSet::from([origin]);
+        let mut frontier = VecDeque::from([(origin, power, 0)]);
+        while let Some((site, amount, hops)) = frontier.pop_front() {
+            reach.push((site, typ, amount));
+            let next_amount = decay(typ.kind, amount);
+            if hops == MAX_INFLUENCE_HOPS || next_amount <= 0 {
+                continue;
+            }
+            for &(next, _, _) in sites.neighbours(site) {
+                if visited.insert(next) {
+                    frontier.push_back((next, next_amount, hops + 1));
                 }
             }
         }
+    }
+    reach
+}
 
-        // Combine contributions
-        let mut combined: AVec<(InfluenceType, i32)> =
-            arena.new_vec_with_capacity(contributions.len());
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, EnumIter, EnumCount)]
+pub enum InfluenceKind {
+    Market,
+    Security,
+}
+impl ArenaSafe for InfluenceKind {}
 
-        for (typ, amt) in contributions {
-            match combined.binary_search_by_key(&typ, |x| x.0) {
-                Ok(idx) => combined[idx].1 = combined[idx].1.max(amt),
-                Err(idx) => combined.insert(idx, (typ, amt)),
-            }
-        }
+impl From<InfluenceKind> for usize {
+    fn from(value: InfluenceKind) -> Self {
+        value as usize
+    }
+}
 
-        combined.sort_by_key(|(_, x)| -x);
+impl EnumMapKey for InfluenceKind {}
+
+/// How the influences of several sources of the same kind add up at a site
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum InfluenceBlend {
+    /// Only the strongest source counts
+    #[default]
+    Max,
+    /// Sources add up, tapering off towards the cap
+    SaturatingSum { cap: f64 },
+    /// Smooth maximum, where two equal sources are worth one plus `temperature * ln 2`
+    Softmax { temperature: f64 },
+}
 
-        (site_id, combined.into_bump_slice())
-    }));
+impl InfluenceBlend {
+    pub(crate) fn combine(self, amounts: impl IntoIterator<Item = f64>) -> f64 {
+        match self {
+            Self::Max => amounts.into_iter().fold(0., f64::max),
+            Self::SaturatingSum { cap } => {
+                let sum: f64 = amounts.into_iter().sum();
+                if cap <= 0. {
+                    sum
+                } else {
+                    cap * (1. - (-sum / cap).exp())
+                }
+            }
+            Self::Softmax { temperature } => {
+                let amounts: Vec<f64> = amounts.into_iter().collect();
+                let max = amounts.iter().copied().fold(0., f64::max);
+                if amounts.is_empty() || temperature <= 0. {
+                    return max;
+                }
+                // Shifted by the maximum, so that the exponentials cannot overflow
+                let sum: f64 = amounts
+                    .iter()
+                    .map(|amount| ((amount - max) / temperature).exp())
+                    .sum();
+                max + temperature * sum.ln()
+            }
+        }
+    }
+}
 
-    // Apply updates
-    for &mut (id, influences) in updates {
-        let site = &mut sites.entries[id];
-        site.influences.0.clear();
-        site.influences.0.extend_from_slice(influences);
+/// Combination mode of each kind of influence
+#[derive(Clone)]
+pub struct InfluenceBlends(EnumMap<InfluenceKind, InfluenceBlend, { InfluenceKind::COUNT }>);
+
+impl Default for InfluenceBlends {
+    fn default() -> Self {
+        Self(EnumMap::with_iter([
+            (
+                InfluenceKind::Market,
+                InfluenceBlend::SaturatingSum { cap: 20_000. },
+            ),
+            (
+                InfluenceKind::Security,
+                InfluenceBlend::Softmax { temperature: 500. },
+            ),
+        ]))
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) enum InfluenceKind {
-    Market,
+impl InfluenceBlends {
+    pub fn get(&self, kind: InfluenceKind) -> InfluenceBlend {
+        *self.0.get(kind)
+    }
+
+    pub fn set(&mut self, kind: InfluenceKind, blend: InfluenceBlend) {
+        self.0.set(kind, blend);
+    }
 }
-impl ArenaSafe for InfluenceKind {}
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct InfluenceType {
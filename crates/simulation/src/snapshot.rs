@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use rand::RngCore;
+use slotmap::Key;
+
+use crate::simulation::*;
+
+/// Flattened state of a simulation, one field per line, for comparing runs that
+/// should have stayed in lockstep. Floats are written exactly, so that any
+/// divergence shows up at the first field it touches.
+#[derive(Default, Clone, PartialEq)]
+pub struct Snapshot {
+    fields: Vec<(String, String)>,
+}
+
+/// The first field two snapshots disagree on
+pub struct Divergence {
+    pub field: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let left = self.left.as_deref().unwrap_or("<missing>");
+        let right = self.right.as_deref().unwrap_or("<missing>");
+        write!(f, "{}: {left} != {right}", self.field)
+    }
+}
+
+impl Snapshot {
+    fn push(&mut self, field: String, value: impl std::fmt::Debug) {
+        self.fields.push((field, format!("{value:?}")));
+    }
+
+    /// One `field<TAB>value` line per field
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (field, value) in &self.fields {
+            let _ = writeln!(out, "{field}\t{value}");
+        }
+        out
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+        Self { fields }
+    }
+
+    pub fn first_divergence(&self, other: &Snapshot) -> Option<Divergence> {
+        let len = self.fields.len().max(other.fields.len());
+        (0..len).find_map(|idx| {
+            let left = self.fields.get(idx);
+            let right = other.fields.get(idx);
+            if left == right {
+                return None;
+            }
+            // Name the field as the run that has it calls it
+            let field = left.or(right).map(|(field, _)| field.clone())?;
+            let describe = |entry: Option<&(String, String)>| {
+                entry.map(|(name, value)| {
+                    if *name == field {
+                        value.clone()
+                    } else {
+                        format!("{name} = {value}")
+                    }
+                })
+            };
+            Some(Divergence {
+                field: field.clone(),
+                left: describe(left),
+                right: describe(right),
+            })
+        })
+    }
+}
+
+fn key(id: impl Key) -> u64 {
+    id.data().as_ffi()
+}
+
+impl Simulation {
+    pub fn snapshot(&self) -> Snapshot {
+        let mut out = Snapshot::default();
+        out.push("date".to_string(), self.date.epoch());
+        // The next roll, which differs as soon as the streams of rolls do
+        out.push("rng".to_string(), self.rng.0.clone().next_u64());
+
+        for (id, entity) in &self.entities {
+            let path = format!("entity.{}", key(id));
+            out.push(format!("{path}.name"), &entity.name);
+            out.push(format!("{path}.agent"), entity.agent.map(key));
+            out.push(format!("{path}.party"), entity.party.map(key));
+            out.push(format!("{path}.location"), entity.location.map(key));
+            out.push(format!("{path}.behavior"), entity.behavior.map(key));
+        }
+
+        for (id, agent) in &self.agents.entries {
+            let path = format!("agent.{}", key(id));
+            out.push(format!("{path}.cash"), agent.cash);
+            out.push(format!("{path}.tax_rate"), agent.tax_rate);
+            out.push(format!("{path}.toll_rate"), agent.toll_rate);
+            out.push(
+                format!("{path}.parent"),
+                self.agents.political_hierarchy.parent(id).map(key),
+            );
+            if let Some(character) = self.characters.get(id) {
+                out.push(format!("{path}.born"), character.born.epoch());
+            }
+        }
+
+        for (id, party) in &self.parties {
+            let path = format!("party.{}", key(id));
+            out.push(format!("{path}.position"), party.position.as_triple());
+            out.push(format!("{path}.target"), party.movement.target.is_some());
+            out.push(format!("{path}.path"), party.movement.path.iter().count());
+            for (good, amount) in &party.good_stock.amount {
+                let tag = self.good_types[good].tag;
+                out.push(format!("{path}.stock.{tag}"), amount);
+            }
+        }
+
+        for (id, location) in &self.locations {
+            let path = format!("location.{}", key(id));
+            out.push(format!("{path}.population"), location.population);
+            out.push(format!("{path}.prosperity"), location.prosperity);
+            out.push(format!("{path}.income"), location.market.income);
+            for (good, data) in location.market.goods.iter() {
+                let tag = self.good_types[good].tag;
+                out.push(format!("{path}.{tag}.stock"), data.stock);
+                out.push(format!("{path}.{tag}.price"), data.price);
+            }
+            for token in self.tokens.all_tokens_in(location.tokens) {
+                out.push(format!("{path}.token.{}", token.typ.name), token.data.size);
+            }
+        }
+
+        for (id, behavior) in &self.beahviors {
+            let path = format!("behavior.{}", key(id));
+            let target = behavior.plan.front().map(|task| match task.destination {
+                Destination::Here => None,
+                Destination::Party(party) => Some(key(party)),
+                Destination::Site(site) => Some(key(site)),
+            });
+            out.push(format!("{path}.task"), target);
+            out.push(format!("{path}.plan"), behavior.plan.len());
+        }
+
+        for (id, site) in self.sites.iter() {
+            let path = format!("site.{}", key(id));
+            for deposit in &site.rgo.deposits {
+                let tag = self.good_types[deposit.good].tag;
+                out.push(format!("{path}.{tag}"), deposit.level);
+            }
+            for (typ, amount) in site.influences.iter() {
+                let source = key(typ.source);
+                out.push(format!("{path}.{:?}.{source}", typ.kind), amount);
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,202 @@
+use crate::combat::{faction_of_party, hostile, refuge, strength};
+use crate::interception::in_contact;
+use crate::simulation::*;
+use crate::tick::pos_of_grid_coordinate;
+
+// How far parties see hostile ones coming
+const PERCEPTION_RANGE: f32 = 3.;
+// Aggressiveness of a faction from which its traders stand up to weaker
+// enemies rather than run from all, and its rebels go looking for fights
+const BOLD: f64 = 0.7;
+
+/// How a party meets the hostile parties it comes across
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Stance {
+    /// Goes about its errand as if it saw nothing
+    #[default]
+    Passive,
+    /// Runs from enemies stronger than itself, and stands up to the others
+    Defensive,
+    /// Chases down the enemies weaker than itself
+    Aggressive,
+    /// Runs from any armed enemy
+    Evasive,
+}
+
+impl Stance {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Passive => "passive",
+            Self::Defensive => "defensive",
+            Self::Aggressive => "aggressive",
+            Self::Evasive => "evasive",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "passive" => Self::Passive,
+            "defensive" => Self::Defensive,
+            "aggressive" => Self::Aggressive,
+            "evasive" => Self::Evasive,
+            _ => return None,
+        })
+    }
+
+    /// The stance after this one, back to the first after the last
+    pub fn next(self) -> Self {
+        match self {
+            Self::Passive => Self::Defensive,
+            Self::Defensive => Self::Aggressive,
+            Self::Aggressive => Self::Evasive,
+            Self::Evasive => Self::Passive,
+        }
+    }
+
+    /// The stance a party takes up unless told otherwise, by what it is
+    /// out to do and how aggressive its faction is
+    pub(crate) fn of_goal(goal: &Goal, aggressiveness: f64) -> Self {
+        let bold = aggressiveness >= BOLD;
+        match goal {
+            Goal::Idle | Goal::Ambition { .. } => Self::Passive,
+            Goal::LocalTrade { .. }
+            | Goal::Smuggle { .. }
+            | Goal::ArbitrageTrade { .. }
+            | Goal::RouteTrade { .. }
+            | Goal::ReturnHome { .. }
+                if bold =>
+            {
+                Self::Defensive
+            }
+            Goal::LocalTrade { .. }
+            | Goal::Smuggle { .. }
+            | Goal::ArbitrageTrade { .. }
+            | Goal::RouteTrade { .. }
+            | Goal::Migrate(_)
+            | Goal::ReturnHome { .. }
+            | Goal::Flee { .. } => Self::Evasive,
+            Goal::Rebel { .. } if bold => Self::Aggressive,
+            Goal::Rebel { .. } => Self::Defensive,
+        }
+    }
+}
+
+/// A party turned aside from its errand by a hostile party it saw
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Reaction {
+    /// The settlement it runs to, or the party it chases
+    pub target: PartyId,
+    pub fleeing: bool,
+    /// The order it was following, taken up again once it is over
+    pub resume: Option<MovementTarget>,
+}
+
+/// Has each party with a stance react to the hostile parties in sight,
+/// before it decides what to do about its errand. Reacting parties head for
+/// the target of their reaction instead of that of their task.
+pub(crate) fn perceive(sim: &mut Simulation) {
+    let changes: Vec<(PartyId, Option<(PartyId, bool)>)> = sim
+        .beahviors
+        .values()
+        .filter_map(|behavior| {
+            let party = sim.entities[behavior.entity].party?;
+            let data = sim.parties.get(party)?;
+            // Those in a group or under escort go where they are taken
+            if data.movement_speed == 0.
+                || sim.groups.leader_of(party).is_some()
+                || sim.escorts.escort_of(party).is_some()
+            {
+                return None;
+            }
+            let reaction = react(sim, party, behavior.stance);
+            let current = data
+                .movement
+                .reaction
+                .map(|reaction| (reaction.target, reaction.fleeing));
+            (reaction != current).then_some((party, reaction))
+        })
+        .collect();
+    for (party, reaction) in changes {
+        let movement = &mut sim.parties[party].movement;
+        let resume = match movement.reaction {
+            Some(previous) => previous.resume,
+            None => movement.target,
+        };
+        movement.reaction = reaction.map(|(target, fleeing)| Reaction {
+            target,
+            fleeing,
+            resume,
+        });
+        movement.target = match reaction {
+            Some((target, _)) => Some(MovementTarget::Party(target)),
+            None => resume,
+        };
+    }
+}
+
+// Where a party heads given the hostile parties in sight, and whether it
+// runs there for safety
+fn react(sim: &Simulation, party: PartyId, stance: Stance) -> Option<(PartyId, bool)> {
+    let data = &sim.parties[party];
+    // Parties running for safety keep running until they get there
+    if let Some(reaction) = data.movement.reaction
+        && reaction.fleeing
+        && let Some(refuge) = sim.parties.get(reaction.target)
+        && !in_contact(&sim.sites, data, refuge)
+    {
+        return Some((reaction.target, true));
+    }
+    if stance == Stance::Passive {
+        return None;
+    }
+
+    let faction = faction_of_party(sim, data);
+    // Parties rate their own strength by their courage, and by how keen on a
+    // fight their faction is, at face value for the default personality
+    let keenness = faction.map_or(1., |faction| {
+        sim.agents[faction].personality.aggressiveness / Personality::default().aggressiveness
+    });
+    let own = strength(sim, data)
+        * keenness
+        * sim.entities[data.entity]
+            .agent
+            .map_or(1., |agent| sim.characters.courage(agent));
+    let from = pos_of_grid_coordinate(&sim.sites, data.position);
+    let mut seen: Vec<(f32, PartyId, f64)> = sim
+        .parties_near(from, PERCEPTION_RANGE)
+        .map(|id| (id, &sim.parties[id]))
+        .filter(|&(id, other)| id != party && other.location.is_none() && other.movement_speed > 0.)
+        .map(|(id, other)| {
+            let distance = pos_of_grid_coordinate(&sim.sites, other.position).distance(from);
+            (distance, id, other)
+        })
+        .filter(|&(_, _, other)| hostile(sim, faction, faction_of_party(sim, other)))
+        .map(|(distance, id, other)| (distance, id, strength(sim, other)))
+        .collect();
+    seen.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let threatened = match stance {
+        Stance::Evasive => seen.iter().any(|&(_, _, enemy)| enemy > 0.),
+        Stance::Defensive => seen.iter().any(|&(_, _, enemy)| enemy > own),
+        Stance::Passive | Stance::Aggressive => false,
+    };
+    if threatened {
+        return refuge(sim, party)
+            .filter(|&refuge| !in_contact(&sim.sites, data, &sim.parties[refuge]))
+            .map(|refuge| (refuge, true));
+    }
+    if stance != Stance::Aggressive {
+        return None;
+    }
+    // Keep after the quarry already chased while it is still in sight
+    let chased = data
+        .movement
+        .reaction
+        .filter(|reaction| !reaction.fleeing)
+        .map(|reaction| reaction.target);
+    seen.iter()
+        .filter(|&&(_, _, enemy)| enemy < own)
+        .find(|&&(_, id, _)| Some(id) == chased)
+        .or_else(|| seen.iter().find(|&&(_, _, enemy)| enemy < own))
+        .map(|&(_, id, _)| (id, false))
+}
@@ -0,0 +1,34 @@
+use std::collections::BTreeMap;
+
+use crate::simulation::*;
+
+/// Stocks of goods locations keep in their markets. Traders only get to buy
+/// what is over the target, and while a market is short of it the treasury of
+/// its realm pays a premium on what traders bring.
+#[derive(Default, Clone)]
+pub(crate) struct Stockpiles {
+    targets: BTreeMap<(LocationId, GoodId), f64>,
+}
+
+// Share of the price paid on top to traders who sell a good a location is
+// short of
+pub(crate) const STOCKPILE_PREMIUM: f64 = 0.2;
+
+impl Stockpiles {
+    pub fn target(&self, location: LocationId, good: GoodId) -> f64 {
+        self.targets.get(&(location, good)).copied().unwrap_or(0.)
+    }
+
+    /// Sets the stock a location keeps of a good. A target of zero lifts the policy.
+    pub fn set(&mut self, location: LocationId, good: GoodId, target: f64) {
+        if target > 0. {
+            self.targets.insert((location, good), target);
+        } else {
+            self.targets.remove(&(location, good));
+        }
+    }
+
+    pub fn forget_location(&mut self, location: LocationId) {
+        self.targets.retain(|&(of, _), _| of != location);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::object::*;
+use crate::simulation::*;
+use crate::sites::SiteId;
+use crate::tokens::TokenTypeId;
+use crate::view::{SimWarning, TagKind};
+
+#[derive(Clone)]
+pub(crate) struct Tags<T: Copy + Ord + std::hash::Hash> {
+    string_to_id: HashMap<String, T>,
+    id_to_string: HashMap<T, String>,
+}
+
+impl<T: Copy + Ord + std::hash::Hash> Default for Tags<T> {
+    fn default() -> Self {
+        Self {
+            string_to_id: HashMap::default(),
+            id_to_string: HashMap::default(),
+        }
+    }
+}
+
+impl<T: Copy + Ord + std::hash::Hash> TaggedCollection for Tags<T> {
+    type Output = T;
+
+    fn lookup(&self, tag: &str) -> Option<T> {
+        Tags::lookup(self, tag)
+    }
+}
+
+impl<T: Copy + Ord + std::hash::Hash> Tags<T> {
+    pub fn insert(&mut self, tag: impl Into<String>, id: T) {
+        let str = tag.into();
+        self.string_to_id.insert(str.clone(), id);
+        self.id_to_string.insert(id, str);
+    }
+
+    pub fn unbind(&mut self, tag: &str) {
+        if let Some(id) = self.string_to_id.remove(tag) {
+            self.id_to_string.remove(&id);
+        }
+    }
+
+    pub fn remove(&mut self, id: &T) {
+        if let Some(tag) = self.id_to_string.remove(id) {
+            self.string_to_id.remove(&tag);
+        }
+    }
+
+    pub fn lookup(&self, tag: &str) -> Option<T> {
+        self.string_to_id.get(tag).copied()
+    }
+
+    pub fn reverse_lookup(&self, id: &T) -> Option<&str> {
+        self.id_to_string.get(id).map(|x| x.as_str())
+    }
+}
+
+/// String tags of everything that can be addressed by tag, one namespace per kind
+#[derive(Default, Clone)]
+pub(crate) struct TagRegistry {
+    pub sites: Tags<SiteId>,
+    pub goods: Tags<GoodId>,
+    pub token_types: Tags<TokenTypeId>,
+    pub agents: Tags<AgentId>,
+}
+
+/// Checks a tag is not yet taken within its kind, warning about the collision otherwise
+pub(crate) fn is_tag_free<T: Copy + Ord + std::hash::Hash>(
+    tags: &Tags<T>,
+    kind: TagKind,
+    tag: &str,
+    warnings: &mut Vec<SimWarning>,
+) -> bool {
+    if tags.lookup(tag).is_some() {
+        warnings.push(SimWarning::DuplicateTag {
+            kind,
+            tag: tag.to_string(),
+        });
+        return false;
+    }
+    true
+}
+
+impl Simulation {
+    /// Finds the entity of the agent with the given tag
+    pub fn entity_with_tag(&self, tag: &str) -> Option<ObjectId> {
+        let agent = self.tags.agents.lookup(tag)?;
+        let entity = self.agents.entries.get(agent)?.entity;
+        Some(ObjectId(ObjectHandle::Entity(entity)))
+    }
+
+    pub fn site_with_tag(&self, tag: &str) -> Option<ObjectId> {
+        let site = self.tags.sites.lookup(tag)?;
+        Some(ObjectId(ObjectHandle::Site(site)))
+    }
+
+    /// Finds a site or, failing that, an entity with the given tag
+    pub fn object_with_tag(&self, tag: &str) -> Option<ObjectId> {
+        self.site_with_tag(tag)
+            .or_else(|| self.entity_with_tag(tag))
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use crate::stances::Stance;
+
+/// Blueprints of the parties spawned by tag, by settlements under pressure,
+/// by scripts and by commands. The built-in ones can be replaced, and new
+/// ones added, from template files.
+#[derive(Default, Clone)]
+pub(crate) struct EntityTemplates {
+    entries: BTreeMap<String, EntityTemplate>,
+    // Names of images and kinds read from files, kept for as long as the
+    // parties that show them
+    interned: Vec<&'static str>,
+}
+
+#[derive(Clone)]
+pub(crate) struct EntityTemplate {
+    // May name the parameters, as in `Rebels of $home`
+    pub name: String,
+    pub kind_name: &'static str,
+    // Tag of the faction the party answers to, `$faction` for that of whoever
+    // spawns it, or empty for none
+    pub faction: String,
+    pub character: bool,
+    pub cash: f64,
+    pub image: &'static str,
+    pub size: f32,
+    pub movement_speed: f32,
+    pub carry_capacity: f64,
+    pub boats: bool,
+    pub off_road: bool,
+    pub ship: bool,
+    // Troops the party sets out with, by token type tag
+    pub troops: Vec<(String, i64)>,
+    pub goal: TemplateGoal,
+    // How the party meets hostile parties, or by its goal when left out
+    pub stance: Option<Stance>,
+}
+
+impl Default for EntityTemplate {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind_name: "",
+            faction: "$faction".to_string(),
+            character: false,
+            cash: 0.,
+            image: "farmers",
+            size: 1.,
+            movement_speed: 2.,
+            carry_capacity: 0.,
+            boats: false,
+            off_road: false,
+            ship: false,
+            troops: vec![],
+            goal: TemplateGoal::Idle,
+            stance: None,
+        }
+    }
+}
+
+/// What a spawned party sets out to do, from the settlement it spawns at
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum TemplateGoal {
+    Idle,
+    LocalTrade,
+    Smuggle,
+    ArbitrageTrade,
+    Rebel,
+}
+
+impl TemplateGoal {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "idle" => Self::Idle,
+            "local_trade" => Self::LocalTrade,
+            "smuggle" => Self::Smuggle,
+            "arbitrage_trade" => Self::ArbitrageTrade,
+            "rebel" => Self::Rebel,
+            _ => return None,
+        })
+    }
+}
+
+/// Values of the parameters of a template, by name
+pub(crate) struct TemplateArgs<'a> {
+    pub site: &'a str,
+    pub faction: Option<&'a str>,
+    // Name of the settlement the party sets out from
+    pub home: &'a str,
+}
+
+/// A template or scenario file that could not be read, and where
+#[derive(Clone, PartialEq, Debug)]
+pub struct TemplateError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl EntityTemplates {
+    pub fn get(&self, tag: &str) -> Option<&EntityTemplate> {
+        self.entries.get(tag)
+    }
+
+    pub fn insert(&mut self, tag: &str, template: EntityTemplate) {
+        self.entries.insert(tag.to_string(), template);
+    }
+
+    /// Reads templates from a file of `[tag]` sections of `key = value`
+    /// lines, each replacing the template of the same tag. Keys left out
+    /// keep their default.
+    pub fn load(&mut self, source: &str) -> Result<(), TemplateError> {
+        let mut current: Option<(String, EntityTemplate)> = None;
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| TemplateError {
+                line: idx + 1,
+                reason: reason.to_string(),
+            };
+            if let Some(tag) = line.strip_prefix('[') {
+                let tag = tag
+                    .strip_suffix(']')
+                    .ok_or(error("unclosed template tag"))?;
+                if let Some((tag, template)) = current.take() {
+                    self.entries.insert(tag, template);
+                }
+                current = Some((tag.trim().to_string(), EntityTemplate::default()));
+                continue;
+            }
+            let Some((_, template)) = &mut current else {
+                return Err(error("field outside of a template"));
+            };
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or(error("expected `key = value`"))?;
+            let number = || value.parse::<f64>().map_err(|_| error("expected a number"));
+            match key {
+                "name" => template.name = value.to_string(),
+                "kind" => template.kind_name = intern(&mut self.interned, value),
+                "faction" => template.faction = value.to_string(),
+                "character" => {
+                    template.character =
+                        value.parse().map_err(|_| error("expected true or false"))?
+                }
+                "boats" => {
+                    template.boats = value.parse().map_err(|_| error("expected true or false"))?
+                }
+                "off_road" => {
+                    template.off_road =
+                        value.parse().map_err(|_| error("expected true or false"))?
+                }
+                "ship" => {
+                    template.ship = value.parse().map_err(|_| error("expected true or false"))?
+                }
+                "troops" => {
+                    template.troops =
+                        parse_troops(value).ok_or(error("expected `tag size, ...`"))?
+                }
+                "cash" => template.cash = number()?,
+                "image" => template.image = intern(&mut self.interned, value),
+                "size" => template.size = number()? as f32,
+                "speed" => template.movement_speed = number()? as f32,
+                "carry" => template.carry_capacity = number()?,
+                "goal" => {
+                    template.goal = TemplateGoal::parse(value).ok_or(error("unknown goal"))?
+                }
+                "stance" => {
+                    template.stance = Some(Stance::parse(value).ok_or(error("unknown stance"))?)
+                }
+                _ => return Err(error(&format!("unknown key `{key}`"))),
+            }
+        }
+        if let Some((tag, template)) = current {
+            self.entries.insert(tag, template);
+        }
+        Ok(())
+    }
+}
+
+// Reads troops given as `levy 40, men_at_arms 5`
+fn parse_troops(value: &str) -> Option<Vec<(String, i64)>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (tag, size) = part.split_once(' ')?;
+            Some((tag.to_string(), size.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Fills the parameters a text names with their values
+pub(crate) fn substitute(text: &str, args: &TemplateArgs) -> String {
+    text.replace("$site", args.site)
+        .replace("$faction", args.faction.unwrap_or(""))
+        .replace("$home", args.home)
+}
+
+fn intern(interned: &mut Vec<&'static str>, name: &str) -> &'static str {
+    if let Some(&known) = interned.iter().find(|&&known| known == name) {
+        return known;
+    }
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    interned.push(name);
+    name
+}
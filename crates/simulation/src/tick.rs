@@ -1,9 +1,20 @@
+use std::collections::BTreeSet;
+
 use slotmap::SecondaryMap;
-use util::arena::Arena;
+use strum::IntoEnumIterator;
+use util::arena::{Arena, ArenaSafe};
 
+use crate::espionage::CovertAction;
+use crate::famine::{
+    Edict, HUNGER_PRICE_SHOCK, LocalConditions, PRICE_CEILING_MARGIN, RATIONING_DEMAND,
+};
 use crate::object::*;
+use crate::pressures::Conditions;
+use crate::schedule::{Slice, System};
 use crate::simulation::*;
 use crate::sites::*;
+use crate::tags::*;
+use crate::templates::{TemplateArgs, TemplateGoal, substitute};
 use crate::tokens::*;
 use crate::view;
 use crate::view::*;
@@ -13,15 +24,13 @@ pub struct TickRequest<'a> {
     pub commands: TickCommands<'a>,
     pub num_ticks: usize,
     pub map_viewport: Extents,
+    pub map_filter: MapFilter,
+    /// Faction whose sight the map is limited to, or None to see everything
+    pub observer: Option<ObjectId>,
     pub objects_to_extract: Vec<ObjectId>,
 }
 
 pub(super) fn tick(sim: &mut Simulation, mut request: TickRequest, arena: &Arena) -> SimView {
-    // Apply movement orders
-    if let Some((subject, target)) = request.commands.move_to {
-        apply_move_order_to(sim, subject, target);
-    }
-
     // Inner ticks
     if request.num_ticks == 0 {
         let cmds = std::mem::take(&mut request.commands);
@@ -30,53 +39,212 @@ pub(super) fn tick(sim: &mut Simulation, mut request: TickRequest, arena: &Arena
     for _ in 0..request.num_ticks {
         let cmds = std::mem::take(&mut request.commands);
         tick_inner(sim, cmds, true, arena);
+        #[cfg(feature = "validate")]
+        check_invariants(sim);
     }
 
     // Extract view
     let mut view = SimView::default();
-    view.map_items = view::map_view_items(sim, request.map_viewport);
+    let sight = request
+        .observer
+        .and_then(|observer| crate::players::faction_agent(sim, observer))
+        .map(|faction| crate::visibility::Sight::of(sim, faction));
+    view.map_items = view::map_view_items(
+        sim,
+        request.map_viewport,
+        request.map_filter,
+        sight.as_ref(),
+    );
     view.map_lines = view::map_view_lines(sim, request.map_viewport);
-    view.objects = request
-        .objects_to_extract
-        .iter()
-        .map(|&id| view::extract_object(sim, id))
-        .collect();
+    view.map_territory = view::map_view_territory(sim, request.map_viewport);
+    view.objects = view::extract_objects(sim, &request.objects_to_extract);
+    view.events = std::mem::take(&mut sim.events);
+    view.warnings = std::mem::take(&mut sim.warnings);
     view
 }
 
-fn tick_inner(sim: &mut Simulation, mut commands: TickCommands, advance_time: bool, arena: &Arena) {
+/// Ticks the simulation without extracting views along the way. What happened
+/// in the meantime is left to the chronicle.
+pub(crate) fn fast_forward(sim: &mut Simulation, num_ticks: u64, arena: &mut Arena) {
+    sim.fast_forward = true;
+    for _ in 0..num_ticks {
+        if sim.date.is_new_day() {
+            arena.reset();
+        }
+        tick_inner(sim, TickCommands::default(), true, arena);
+    }
+    sim.fast_forward = false;
+    sim.events.clear();
+    sim.view_revision += 1;
+}
+
+// Reports every broken invariant among the warnings of the tick
+#[cfg(feature = "validate")]
+fn check_invariants(sim: &mut Simulation) {
+    let diagnostics = sim.validate();
+    let tick = sim.date.epoch();
+    sim.warnings.extend(
+        diagnostics
+            .iter()
+            .map(|diagnostic| SimWarning::BrokenInvariant {
+                tick,
+                diagnostic: diagnostic.to_string(),
+            }),
+    );
+    debug_assert!(diagnostics.is_empty(), "simulation invariants violated");
+}
+
+fn tick_inner(sim: &mut Simulation, commands: TickCommands, advance_time: bool, arena: &Arena) {
+    let mut despawns = vec![];
+    process_commands(sim, commands, &mut despawns, arena);
+    // Characters who died rather than disbanded
+    let mut deaths = vec![];
+
     let mut create_entitity_requests = vec![];
     if advance_time {
         sim.date.advance();
 
-        let is_new_day = sim.date.is_new_day();
+        if sim.date.is_new_day() {
+            sim.view_revision += 1;
+            sim.events.push(SimEvent::NewDay);
+        }
 
-        tick_influences(arena, &mut sim.sites, &sim.locations);
+        let date = sim.date;
+        let influences = sim.schedule.next_slice(System::Influences, date);
+        let market = sim
+            .schedule
+            .next_slice(System::Market, date)
+            .map(|slice| MarketRound {
+                slice,
+                price_index: sim.currency.index(),
+                days: sim.schedule.days_per_run(System::Market),
+            });
 
-        // Pressures
+        if let Some(slice) = influences
+            && tick_influences(arena, &mut sim.sites, &sim.locations, slice)
         {
-            let events = tick_pressures(&mut sim.pressurables, is_new_day);
+            crate::control::update(sim);
+            sim.view_revision += 1;
+        }
+
+        // Pressures
+        if sim.schedule.is_due(System::Pressures, date) {
+            let days = sim.schedule.days_per_run(System::Pressures);
+            let appetites = sim
+                .pressurables
+                .iter()
+                .map(|(id, pressurable)| {
+                    let appetite = sim.entities[pressurable.entity]
+                        .agent
+                        .map(|agent| personality_of(&sim.agents, agent).expansion_appetite)
+                        .unwrap_or(1.);
+                    (id, appetite)
+                })
+                .collect();
+            let conditions = sim
+                .pressurables
+                .iter()
+                .filter_map(|(id, pressurable)| {
+                    let location = sim.entities[pressurable.entity].location?;
+                    Some((id, Conditions::of(sim, location)))
+                })
+                .collect();
+            crate::unrest::stir(sim, days);
+            let events = tick_pressures(&mut sim.pressurables, &appetites, &conditions, days);
             let creations = handle_pressure_events(arena, sim, events);
+            // Guilds put up the capital of the merchants of their towns
+            for create in &creations {
+                let (Some(agent), Some(behavior)) = (&create.agent, &create.behavior) else {
+                    continue;
+                };
+                let town = behavior.base.and_then(|base| sim.parties[base].location);
+                if let (true, Some(town)) = (behavior.arbitrage, town) {
+                    crate::guilds::put_up(sim, town, agent.cash);
+                }
+            }
             create_entitity_requests.extend(creations);
+            sim.view_revision += 1;
         }
 
         // Simulate economy at locations
-        tick_location_economy(
+        let conditions = crate::famine::local_conditions(sim);
+        let workings = tick_location_economy(
             arena,
             &mut sim.locations,
             &sim.tokens,
             &sim.good_types,
             &sim.sites,
-            is_new_day,
+            &conditions,
+            market,
         );
+        for (site, workers, days) in workings {
+            sim.sites.extract(site, &workers, days);
+        }
+        if let Some(round) = market {
+            collect_taxes(sim, round);
+            // Rounds may run more often than daily, or a slice at a time
+            sim.view_revision += 1;
+        }
+        if sim.date.is_new_day() {
+            decay_goods(sim);
+            sim.tokens.expire_modifiers(sim.date);
+            crate::hospitality::tick(sim);
+            crate::currency::tick(sim);
+            sim.sites.regenerate_deposits();
+            crate::famine::tick(sim);
+            crate::demography::tick(sim);
+            crate::prosperity::tick(sim);
+            crate::ledger::tick(sim);
+            crate::guilds::tick(sim);
+            crate::guilds::stake(sim);
+            crate::roadworks::tick(sim);
+            crate::ambitions::tick(sim);
+            let (died, births) = crate::characters::tick(sim);
+            despawns.extend(&died);
+            deaths = died;
+            create_entitity_requests.extend(spawn_heirs(arena, sim, births));
+            crate::arbitration::tick(sim);
+            crate::autonomy::tick(sim);
+            crate::construction::tick(sim);
+            crate::contracts::tick(sim);
+            crate::exploration::tick(sim);
+            let understaffed = crate::routes::tick(sim);
+            create_entitity_requests.extend(spawn_caravans(arena, sim, understaffed));
+            let convoys = crate::migration::tick(sim);
+            create_entitity_requests.extend(spawn_migrants(arena, sim, convoys));
+            crate::elimination::tick(sim);
+        }
 
         // nnnnnnors
-        let effects = tick_behaviors::tick_behaviors(sim);
+        if sim.schedule.is_due(System::Behaviors, date) {
+            let effects = tick_behaviors::tick_behaviors(sim);
+            if !effects.transfers.is_empty()
+                || !effects.trade_events.is_empty()
+                || !effects.ambitions.is_empty()
+                || !effects.arrivals.is_empty()
+                || !effects.deliveries.is_empty()
+                || !effects.revolts.is_empty()
+            {
+                sim.view_revision += 1;
+            }
 
-        transfer::resolve(sim, effects.transfers);
-        trade::resolve(sim, effects.trade_events);
+            let traders: Vec<_> = effects
+                .trade_events
+                .iter()
+                .map(|event| (sim.parties[event.party].entity, event.location))
+                .collect();
+            transfer::resolve(sim, effects.transfers);
+            trade::resolve(sim, effects.trade_events);
+            crate::contracts::take_up(sim, traders);
+            crate::contracts::deliver(sim, effects.deliveries);
+            let foundations = crate::ambitions::resolve(sim, effects.ambitions);
+            create_entitity_requests.extend(found_settlements(arena, sim, foundations));
+            crate::migration::resolve(sim, effects.arrivals);
+            despawns.extend(crate::unrest::resolve(sim, effects.revolts));
+        }
 
         // Tick party AI (deciding where to go)
+        advance_waypoints(sim);
         let result = tick_party_ai(sim);
         for update in result {
             let movement = &mut sim.parties[update.id].movement;
@@ -85,13 +253,36 @@ fn tick_inner(sim: &mut Simulation, mut commands: TickCommands, advance_time: bo
         }
 
         // Pathfinding
-        for (id, update) in pathfind(&sim.parties, &sim.sites) {
+        let traffic = sim
+            .parties
+            .values()
+            .filter(|_| sim.road_congestion)
+            .map(|party| party.position);
+        sim.sites.update_traffic(traffic);
+        sim.sites.set_off_road_slowdown(sim.off_road_slowdown);
+        let known_passages = crate::exploration::known_by_party(sim);
+        for (id, update) in pathfind(&sim.parties, &sim.groups, &mut sim.sites, &known_passages) {
             let party = &mut sim.parties[id];
             match update {
                 ChangePath::Keep => {}
                 ChangePath::Clear => party.movement.path.clear(),
                 ChangePath::Set(steps) => {
                     party.movement.path = Path::new(steps);
+                    party.movement.blocked = false;
+                }
+                ChangePath::Blocked => {
+                    // Give up on the order rather than wander towards it
+                    let movement = &mut party.movement;
+                    movement.path.clear();
+                    movement.clear_orders();
+                    movement.destination = None;
+                    if !movement.blocked {
+                        movement.blocked = true;
+                        sim.view_revision += 1;
+                        sim.events.push(SimEvent::PathBlocked {
+                            party: ObjectId(ObjectHandle::Entity(party.entity)),
+                        });
+                    }
                 }
             }
         }
@@ -105,25 +296,40 @@ fn tick_inner(sim: &mut Simulation, mut commands: TickCommands, advance_time: bo
         }
 
         // Update coordinates and positions
-        let movements = move_to_next_coord(&sim.parties, &sim.sites);
+        let movements = move_to_next_coord(&crate::pace::Pace::of(sim));
         for movement in movements {
             let party = &mut sim.parties[movement.party_id];
+            // Parties wear in the roads they finish travelling
+            if let (GridCoord::Between(a, b, _), GridCoord::At(_)) =
+                (party.position, movement.next_position)
+            {
+                sim.sites
+                    .add_works(a, b, crate::roadworks::WORKS_PER_TRAVERSAL);
+            }
             party.position = movement.next_position;
+            // Kept up even when fast forwarding, as perception and fighting
+            // find parties through the grid
             party.pos = pos_of_grid_coordinate(&sim.sites, party.position);
+            sim.party_grid
+                .insert_point(movement.party_id, (party.pos.x, party.pos.y));
+        }
+        crate::groups::follow(sim);
+        crate::escorts::carry(sim);
+        crate::hospitality::observe(sim);
+        despawns.extend(crate::combat::tick(sim));
+        // Memories are of no use to the history made before anyone looks
+        if !sim.fast_forward && sim.schedule.is_due(System::Visibility, date) {
+            crate::visibility::tick(sim);
         }
     }
 
     // Create entities
-    {
-        let cmds = commands
-            .create_entity_cmds
-            .drain(..)
-            .chain(create_entitity_requests);
-        process_entity_create_commands(sim, cmds);
+    if !create_entitity_requests.is_empty() {
+        process_entity_create_commands(sim, create_entitity_requests.into_iter());
+        sim.view_revision += 1;
     }
 
     // Despawns
-    let mut despawns = vec![];
     despawns.extend(
         sim.beahviors
             .values()
@@ -131,22 +337,55 @@ fn tick_inner(sim: &mut Simulation, mut commands: TickCommands, advance_time: bo
             .map(|x| x.entity),
     );
 
+    if !despawns.is_empty() {
+        sim.view_revision += 1;
+    }
     for entity in despawns {
+        if let Some(data) = sim.entities.get(entity) {
+            let text = if data.location.is_some() {
+                format!("the settlement of {} was abandoned", data.name)
+            } else if let Some(agent) = data.agent.filter(|_| deaths.contains(&entity)) {
+                crate::characters::pass_away(sim, agent)
+            } else {
+                format!("{} disbanded", data.name)
+            };
+            sim.record_chronicle(entity, text);
+        }
+        crate::guilds::bequeath(sim, entity);
         let entity = match sim.entities.remove(entity) {
             Some(x) => x,
             None => continue,
         };
         if let Some(id) = entity.party {
-            sim.parties.remove(id);
+            crate::groups::detach(sim, id);
+            crate::escorts::release(sim, id);
+            sim.combat.forget_party(id);
+            if let Some(party) = sim.parties.remove(id)
+                && let Some(troops) = party.troops
+            {
+                sim.tokens.despawn(troops);
+            }
+            sim.party_grid.remove(id);
         }
         if let Some(id) = entity.behavior {
             sim.beahviors.remove(id);
         }
         if let Some(id) = entity.agent {
             sim.agents.despawn(arena, id);
+            sim.characters.forget(arena, id);
+            sim.tags.agents.remove(&id);
+            sim.relations.forget(id);
+            sim.intel.forget_faction(id);
+            sim.famines.forget_faction(id);
+            sim.exploration.forget_faction(id);
+            sim.guilds.forget_faction(id);
+            sim.visibility.forget_faction(id);
+            sim.players.forget_faction(id);
         }
         if let Some(id) = entity.location {
             let location = sim.locations.remove(id).unwrap();
+            sim.intel.forget_location(id);
+            sim.stockpiles.forget_location(id);
             sim.tokens.despawn(location.tokens);
             sim.sites.unbind_location(location.site);
         }
@@ -156,27 +395,516 @@ fn tick_inner(sim: &mut Simulation, mut commands: TickCommands, advance_time: bo
     }
 }
 
-fn apply_move_order_to(sim: &mut Simulation, subject: ObjectId, target: ObjectId) {
-    let subject = match subject.0 {
-        ObjectHandle::Entity(id) => match sim.entities[id].party {
-            Some(x) => x,
-            None => return,
+fn process_commands(
+    sim: &mut Simulation,
+    commands: TickCommands,
+    despawns: &mut Vec<EntityId>,
+    arena: &Arena,
+) {
+    if commands.queue.is_empty() {
+        return;
+    }
+    sim.view_revision += 1;
+
+    for (command, by_player) in commands.queue {
+        if by_player && !permitted(sim, &command) {
+            sim.warnings.push(SimWarning::NotPermitted);
+            continue;
+        }
+        match command {
+            Command::MoveTo { subject, target } => apply_move_order_to(sim, subject, target),
+            Command::QueueMove { subject, target } => queue_move_order_to(sim, subject, target),
+            Command::Merge { leader, party } => {
+                if let (Some(leader), Some(party)) = (party_of(sim, leader), party_of(sim, party)) {
+                    crate::groups::merge(sim, leader, party);
+                }
+            }
+            Command::Detach(subject) => {
+                if let Some(party) = party_of(sim, subject) {
+                    crate::groups::detach(sim, party);
+                }
+            }
+            Command::Escort { escort, party } => {
+                if let (Some(escort), Some(party)) = (party_of(sim, escort), party_of(sim, party)) {
+                    crate::escorts::attach(sim, escort, party);
+                }
+            }
+            Command::Release(subject) => {
+                // Passengers are only put ashore
+                if let Some(party) = party_of(sim, subject)
+                    && sim.sites.ashore(sim.parties[party].position)
+                {
+                    crate::escorts::release(sim, party);
+                }
+            }
+            Command::Patrol(subject) => {
+                if let Some(party) = party_of(sim, subject) {
+                    sim.parties[party].movement.patrol = true;
+                }
+            }
+            Command::ForcedMarch { subject, on } => {
+                if let Some(party) = party_of(sim, subject) {
+                    sim.parties[party].forced_march = on;
+                }
+            }
+            Command::MoveToTag { subject, tag } => match sim.object_with_tag(tag) {
+                Some(target) => apply_move_order_to(sim, subject, target),
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Site,
+                    tag: tag.to_string(),
+                }),
+            },
+            Command::SetStance { subject, stance } => {
+                if let ObjectHandle::Entity(id) = subject.0
+                    && let Some(behavior) = sim.entities.get(id).and_then(|e| e.behavior)
+                {
+                    sim.beahviors[behavior].stance = stance;
+                }
+            }
+            Command::Halt(subject) => {
+                if let ObjectHandle::Entity(id) = subject.0
+                    && let Some(party) = sim.entities.get(id).and_then(|e| e.party)
+                {
+                    sim.combat.lift_siege(party);
+                    sim.parties[party].movement.clear_orders();
+                }
+            }
+            Command::Create(create) => {
+                process_entity_create_commands(sim, std::iter::once(*create))
+            }
+            Command::Spawn(params) => {
+                let args = TemplateArgs {
+                    site: params.site,
+                    faction: Some(params.faction).filter(|tag| !tag.is_empty()),
+                    home: params.site,
+                };
+                match from_template(arena, sim, params.template, &args, None) {
+                    Some(mut create) => {
+                        if let Some(name) = params.name {
+                            create.name = name;
+                        }
+                        process_entity_create_commands(sim, std::iter::once(create))
+                    }
+                    None => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Template,
+                        tag: params.template.to_string(),
+                    }),
+                }
+            }
+            Command::Despawn(id) => {
+                if let ObjectHandle::Entity(id) = id.0 {
+                    despawns.push(id);
+                }
+            }
+            Command::Station(subject) => {
+                if let Some(party) = party_of(sim, subject) {
+                    crate::combat::station(sim, party);
+                }
+            }
+            Command::Besiege { subject, target } => {
+                if let (Some(party), Some(location)) =
+                    (party_of(sim, subject), location_of(sim, target))
+                {
+                    apply_move_order_to(sim, subject, target);
+                    sim.combat.besiege(party, location);
+                }
+            }
+            Command::Rename { subject, name } => {
+                if let ObjectHandle::Entity(id) = subject.0
+                    && let Some(entity) = sim.entities.get_mut(id)
+                    && !name.is_empty()
+                {
+                    entity.name = name.to_string();
+                }
+            }
+            Command::Covert(params) => {
+                let actor = sim.tags.agents.lookup(params.actor);
+                let target = location_of(sim, params.target);
+                match (actor, target) {
+                    (Some(actor), _) if !sim.agents[actor].flags.get(AgentFlag::IsFaction) => {
+                        sim.warnings.push(SimWarning::NotAFaction {
+                            tag: params.actor.to_string(),
+                        })
+                    }
+                    (Some(actor), Some(target)) => {
+                        crate::espionage::resolve(sim, actor, target, params.action)
+                    }
+                    (None, _) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Agent,
+                        tag: params.actor.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            Command::Ship(params) => {
+                let from = location_of(sim, params.from);
+                let to = location_of(sim, params.to);
+                let good = sim.tags.goods.lookup(params.good);
+                match (from, to, good) {
+                    (Some(from), Some(to), Some(good)) => {
+                        crate::famine::ship(sim, from, to, good, params.amount)
+                    }
+                    (_, _, None) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Good,
+                        tag: params.good.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            Command::Transfer(params) => {
+                let party = match params.party.0 {
+                    ObjectHandle::Entity(id) => sim.entities.get(id).and_then(|e| e.party),
+                    _ => None,
+                };
+                match (party, sim.tags.goods.lookup(params.good)) {
+                    (Some(party), Some(good)) => {
+                        transfer::exchange(sim, party, good, params.amount)
+                    }
+                    (_, None) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Good,
+                        tag: params.good.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            Command::Edict(params) => match sim.tags.agents.lookup(params.faction) {
+                Some(faction) => {
+                    crate::famine::set_edict(sim, faction, params.edict, params.active)
+                }
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Agent,
+                    tag: params.faction.to_string(),
+                }),
+            },
+            Command::Embargo(params) => apply_embargo(sim, params),
+            Command::Construct { location, building } => {
+                let typ = sim.tags.token_types.lookup(building);
+                match (location_of(sim, location), typ) {
+                    (Some(location), Some(typ)) if sim.tokens.types[typ].construction.is_some() => {
+                        crate::construction::start(sim, location, typ)
+                    }
+                    (_, Some(_)) => sim.warnings.push(SimWarning::NotABuilding {
+                        tag: building.to_string(),
+                    }),
+                    (_, None) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::TokenType,
+                        tag: building.to_string(),
+                    }),
+                }
+            }
+            Command::Autonomy { location, managed } => {
+                if let Some(location) = location_of(sim, location) {
+                    sim.autonomy.set_managed(location, managed);
+                }
+            }
+            Command::Stockpile {
+                location,
+                good,
+                target,
+            } => match (location_of(sim, location), sim.tags.goods.lookup(good)) {
+                (Some(location), Some(good)) => sim.stockpiles.set(location, good, target),
+                (_, None) => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Good,
+                    tag: good.to_string(),
+                }),
+                _ => {}
+            },
+            Command::TaxRate(params) => match sim.tags.agents.lookup(params.faction) {
+                Some(faction) => sim.agents[faction].tax_rate = params.rate.clamp(0., 1.),
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Agent,
+                    tag: params.faction.to_string(),
+                }),
+            },
+            Command::TollRate(params) => match sim.tags.agents.lookup(params.faction) {
+                Some(faction) => sim.agents[faction].toll_rate = params.rate.clamp(0., 1.),
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Agent,
+                    tag: params.faction.to_string(),
+                }),
+            },
+            Command::RoadWorks(params) => {
+                let Some(faction) = sim.tags.agents.lookup(params.faction) else {
+                    sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Agent,
+                        tag: params.faction.to_string(),
+                    });
+                    continue;
+                };
+                let ends = [params.from, params.to].map(|tag| {
+                    let site = sim.tags.sites.lookup(tag);
+                    if site.is_none() {
+                        sim.warnings.push(SimWarning::UndefinedTag {
+                            kind: TagKind::Site,
+                            tag: tag.to_string(),
+                        });
+                    }
+                    site
+                });
+                if let [Some(from), Some(to)] = ends {
+                    crate::roadworks::invest(sim, faction, from, to, params.cash);
+                }
+            }
+            Command::Conquer(params) => {
+                let faction = sim.tags.agents.lookup(params.faction);
+                let site = sim.tags.sites.lookup(params.site);
+                match (faction, site) {
+                    (Some(faction), Some(site)) => crate::control::conquer(sim, faction, site),
+                    (None, _) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Agent,
+                        tag: params.faction.to_string(),
+                    }),
+                    (_, None) => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Site,
+                        tag: params.site.to_string(),
+                    }),
+                }
+            }
+            Command::Blight(params) => match sim.tags.sites.lookup(params.site) {
+                Some(site) => crate::famine::strike(sim, site, params.severity, params.seasons),
+                None => sim.warnings.push(SimWarning::UndefinedTag {
+                    kind: TagKind::Site,
+                    tag: params.site.to_string(),
+                }),
+            },
+            Command::ModifyToken(params) => modify_token(sim, params),
+            Command::SetNote { subject, note } => {
+                if let ObjectHandle::Entity(id) = subject.0
+                    && let Some(entity) = sim.entities.get_mut(id)
+                {
+                    entity.note = note.to_string();
+                }
+            }
+        }
+    }
+}
+
+fn modify_token(sim: &mut Simulation, params: TokenModifierParams) {
+    let mut undefined = |kind, tag: &str| {
+        sim.warnings.push(SimWarning::UndefinedTag {
+            kind,
+            tag: tag.to_string(),
+        })
+    };
+    let Some(location) = sim
+        .tags
+        .sites
+        .lookup(params.site)
+        .and_then(|site| sim.sites.get(site)?.location)
+    else {
+        return undefined(TagKind::Site, params.site);
+    };
+    let Some(typ) = sim.tags.token_types.lookup(params.token) else {
+        return undefined(TagKind::TokenType, params.token);
+    };
+    let modifier = TokenModifier {
+        name: params.name.to_string(),
+        rgo_points: params.rgo_points.max(0.),
+        demand: params.demand.max(0.),
+        supply: params.supply.max(0.),
+        until: sim.date.add_days(params.days),
+    };
+    let container = sim.locations[location].tokens;
+    if sim.tokens.add_modifier(container, typ, modifier) {
+        sim.view_revision += 1;
+    }
+}
+
+fn apply_embargo(sim: &mut Simulation, params: EmbargoParams) {
+    let mut undefined = |kind, tag: &str| {
+        sim.warnings.push(SimWarning::UndefinedTag {
+            kind,
+            tag: tag.to_string(),
+        })
+    };
+    let Some(imposer) = sim.tags.agents.lookup(params.imposer) else {
+        return undefined(TagKind::Agent, params.imposer);
+    };
+    let target = match params.target {
+        Some(tag) => match sim.tags.agents.lookup(tag) {
+            Some(id) => Some(id),
+            None => return undefined(TagKind::Agent, tag),
         },
-        _ => return,
+        None => None,
     };
-    let target = match target.0 {
-        ObjectHandle::Site(site) => Some(MovementTarget::Site(site)),
-        ObjectHandle::Entity(entity) => sim
-            .entities
-            .get(entity)
-            .and_then(|e| e.party)
-            .map(MovementTarget::Party),
+    let good = match params.good {
+        Some(tag) => match sim.tags.goods.lookup(tag) {
+            Some(id) => Some(id),
+            None => return undefined(TagKind::Good, tag),
+        },
+        None => None,
+    };
+    // Only factions trade as one, and answer for it
+    let not_faction = [(imposer, params.imposer)]
+        .into_iter()
+        .chain(target.zip(params.target))
+        .find(|&(agent, _)| !sim.agents[agent].flags.get(AgentFlag::IsFaction));
+    if let Some((_, tag)) = not_faction {
+        return sim.warnings.push(SimWarning::NotAFaction {
+            tag: tag.to_string(),
+        });
+    }
+    if target == Some(imposer) {
+        return;
+    }
+
+    let embargo = crate::diplomacy::Embargo {
+        imposer,
+        target,
+        good,
+    };
+    if !sim.relations.set_embargo(embargo, params.active) {
+        return;
+    }
+
+    let goods = good
+        .map(|id| sim.good_types[id].name.to_lowercase())
+        .unwrap_or("all trade".to_string());
+    let against = target
+        .map(|id| sim.entities[sim.agents[id].entity].name.clone())
+        .unwrap_or("all foreigners".to_string());
+    let entity = sim.agents[imposer].entity;
+    let text = if params.active {
+        format!(
+            "{} embargoed {goods} with {against}",
+            sim.entities[entity].name
+        )
+    } else {
+        format!(
+            "{} lifted the embargo on {goods} with {against}",
+            sim.entities[entity].name
+        )
+    };
+    sim.record_chronicle(entity, text);
+}
+
+// Whether the player at the controls may give a command. It orders only the
+// parties and settlements of its faction, and acts only on behalf of it.
+fn permitted(sim: &Simulation, command: &Command) -> bool {
+    let owns = |id: &ObjectId| match id.0 {
+        ObjectHandle::Entity(id) => crate::players::owns(sim, id),
+        _ => false,
+    };
+    let leads = |tag: &str| crate::players::leads(sim, tag);
+    match command {
+        Command::MoveTo { subject, .. }
+        | Command::MoveToTag { subject, .. }
+        | Command::QueueMove { subject, .. }
+        | Command::Patrol(subject)
+        | Command::ForcedMarch { subject, .. }
+        | Command::SetStance { subject, .. }
+        | Command::Detach(subject)
+        | Command::Release(subject)
+        | Command::Halt(subject)
+        | Command::Station(subject)
+        | Command::Besiege { subject, .. }
+        // Names and notes are kept on the entity, seen by every player
+        | Command::Rename { subject, .. }
+        | Command::SetNote { subject, .. } => owns(subject),
+        // A player cannot disband the faction it leads
+        Command::Despawn(subject) => {
+            owns(subject) && crate::players::faction_agent(sim, *subject).is_none()
+        }
+        Command::Merge { leader, party } => owns(leader) && owns(party),
+        Command::Escort { escort, party } => owns(escort) && owns(party),
+        Command::Transfer(params) => owns(&params.party),
+        Command::Ship(params) => owns(&params.from),
+        Command::Construct { location, .. }
+        | Command::Autonomy { location, .. }
+        | Command::Stockpile { location, .. } => owns(location),
+        Command::Covert(params) => leads(params.actor),
+        Command::Edict(params) => leads(params.faction),
+        Command::Embargo(params) => leads(params.imposer),
+        Command::TaxRate(params) => leads(params.faction),
+        Command::TollRate(params) => leads(params.faction),
+        Command::RoadWorks(params) => leads(params.faction),
+        Command::Conquer(params) => leads(params.faction),
+        Command::Create(_) | Command::Spawn(_) | Command::Blight(_) | Command::ModifyToken(_) => {
+            false
+        }
+    }
+}
+
+fn location_of(sim: &Simulation, id: ObjectId) -> Option<LocationId> {
+    match id.0 {
+        ObjectHandle::Entity(id) => sim.entities.get(id).and_then(|e| e.location),
+        _ => None,
+    }
+}
+
+fn party_of(sim: &Simulation, id: ObjectId) -> Option<PartyId> {
+    match id.0 {
+        ObjectHandle::Entity(id) => sim.entities.get(id).and_then(|e| e.party),
         _ => None,
+    }
+}
+
+fn movement_target_of(sim: &Simulation, target: ObjectId) -> Option<MovementTarget> {
+    match target.0 {
+        ObjectHandle::Site(site) => Some(MovementTarget::Site(site)),
+        _ => party_of(sim, target).map(MovementTarget::Party),
+    }
+}
+
+fn apply_move_order_to(sim: &mut Simulation, subject: ObjectId, target: ObjectId) {
+    let Some(subject) = party_of(sim, subject) else {
+        return;
+    };
+    let target = movement_target_of(sim, target);
+    sim.combat.lift_siege(subject);
+    let movement = &mut sim.parties[subject].movement;
+    movement.clear_orders();
+    movement.target = target;
+    // A fresh order gets a fresh chance to report an unreachable destination
+    movement.blocked = false;
+}
+
+// Adds a waypoint after those the party already heads for, or makes it the
+// target of a party going nowhere
+fn queue_move_order_to(sim: &mut Simulation, subject: ObjectId, target: ObjectId) {
+    let (Some(subject), Some(target)) = (party_of(sim, subject), movement_target_of(sim, target))
+    else {
+        return;
     };
-    sim.parties[subject].movement.target = target;
+    let movement = &mut sim.parties[subject].movement;
+    if movement.target.is_none() {
+        movement.target = Some(target);
+        movement.blocked = false;
+    } else {
+        movement.waypoints.push_back(target);
+    }
+}
+
+// Moves the parties that reached their target on to their next waypoint,
+// sending the target to the back of the queue on patrol
+fn advance_waypoints(sim: &mut Simulation) {
+    let reached: Vec<PartyId> = sim
+        .parties
+        .iter()
+        .filter(|(_, party)| !party.movement.waypoints.is_empty())
+        .filter(|(_, party)| party.movement.reaction.is_none())
+        .filter(|(_, party)| match party.movement.target {
+            Some(MovementTarget::Site(site)) => party.position == GridCoord::at(site),
+            Some(MovementTarget::Party(other)) => sim
+                .parties
+                .get(other)
+                .is_none_or(|other| crate::interception::in_contact(&sim.sites, other, party)),
+            None => true,
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for id in reached {
+        let movement = &mut sim.parties[id].movement;
+        if movement.patrol
+            && let Some(target) = movement.target
+        {
+            movement.waypoints.push_back(target);
+        }
+        movement.target = movement.waypoints.pop_front();
+    }
 }
 
-fn tick_influences(arena: &Arena, sites: &mut Sites, locations: &Locations) {
+fn tick_influences(arena: &Arena, sites: &mut Sites, locations: &Locations, slice: Slice) -> bool {
     let mut sources = sites.make_secondary_map();
 
     for location in locations.values() {
@@ -202,54 +930,55 @@ fn tick_influences(arena: &Arena, sites: &mut Sites, locations: &Locations) {
         assert!(prev.is_none())
     }
 
-    crate::sites::propagate_influences(arena, sites, &sources);
-}
-
-#[derive(Clone, Copy)]
-enum PressureEventType {
-    SpawnFarmer,
+    crate::sites::propagate_influences(sites, &sources, slice)
 }
 
 struct PressureEvent {
     typ: PressureEventType,
     target: EntityId,
+    // Pressure that set the trigger off
+    pressure: f64,
 }
 
-fn tick_pressures(agents: &mut Pressurables, is_new_day: bool) -> Vec<PressureEvent> {
+fn tick_pressures(
+    agents: &mut Pressurables,
+    appetites: &SecondaryMap<PressurableId, f64>,
+    conditions: &SecondaryMap<PressurableId, Conditions>,
+    days: f64,
+) -> Vec<PressureEvent> {
     let mut events = vec![];
-    if is_new_day {
-        for agent in agents.values_mut() {
-            for &(typ, value) in &agent.innate_growth {
-                agent.current.update(typ, |x| (x + value).max(0.));
-            }
-        }
-
-        struct Trigger {
-            target: PressureType,
-            threshold: f64,
-            subtract: f64,
-            event: PressureEventType,
-        }
-
-        const TRIGGERS: &[Trigger] = &[Trigger {
-            target: PressureType::Farmer,
-            threshold: 20.,
-            subtract: 20.,
-            event: PressureEventType::SpawnFarmer,
-        }];
-
-        for agent in agents.values_mut() {
-            for trigger in TRIGGERS {
-                let current = *agent.current.get(trigger.target);
-                if current >= trigger.threshold {
-                    agent
-                        .current
-                        .set(trigger.target, (current - trigger.subtract).max(0.));
-                    events.push(PressureEvent {
-                        typ: trigger.event,
-                        target: agent.entity,
-                    });
-                }
+    for (id, agent) in agents.iter_mut() {
+        let scale = appetites.get(id).copied().unwrap_or(1.);
+        let conditions = conditions.get(id).copied().unwrap_or_default();
+        for typ in PressureType::iter() {
+            let (growth, decay) = conditions.factors(typ);
+            // Rules are daily, and compound over the days a run makes up for
+            let decay = 1. - (1. - (typ.rule().decay * decay).min(1.)).powf(days);
+            agent.current.update(typ, |x| x - x * decay);
+            let innate = agent
+                .innate_growth
+                .iter()
+                .filter(|&&(of, _)| of == typ)
+                .map(|&(_, value)| value)
+                .sum::<f64>();
+            agent
+                .current
+                .update(typ, |x| (x + innate * scale * growth * days).max(0.));
+        }
+    }
+
+    for agent in agents.values_mut() {
+        for trigger in &agent.triggers {
+            let current = *agent.current.get(trigger.target);
+            if current >= trigger.threshold {
+                agent
+                    .current
+                    .set(trigger.target, (current - trigger.subtract).max(0.));
+                events.push(PressureEvent {
+                    typ: trigger.event,
+                    target: agent.entity,
+                    pressure: current,
+                });
             }
         }
     }
@@ -264,66 +993,437 @@ fn handle_pressure_events<'a>(
     let mut out = vec![];
     // Handle pressure events
     for event in events {
-        match event.typ {
-            PressureEventType::SpawnFarmer => {
-                let target_entity = &sim.entities[event.target];
+        let target_entity = &sim.entities[event.target];
 
-                let political_parent = target_entity
-                    .agent
-                    .and_then(|id| sim.agents.political_hierarchy.parent(id))
-                    .and_then(|id| sim.agents.tags.reverse_lookup(&id))
-                    .map(|str| arena.alloc_str(str));
-
-                let target_location = &sim.locations[target_entity.location.unwrap()];
-                let site = arena.alloc_str(&sim.sites[target_location.site].tag);
-
-                out.push(CreateEntity {
-                    name: "Farmers",
-                    agent: Some(CreateAgent {
-                        tag: "",
-                        flags: &[],
-                        political_parent,
-                        cash: 1000.,
-                    }),
-                    party: Some(CreateParty {
-                        site,
-                        image: "farmers",
-                        size: 1.,
-                        movement_speed: 2.,
-                        layer: 1,
-                    }),
-                    behavior: Some(CreateBehavior {
-                        base: Some(target_entity.party.unwrap()),
-                    }),
-                    ..Default::default()
-                });
+        let faction = target_entity
+            .agent
+            .and_then(|id| sim.agents.political_hierarchy.parent(id))
+            .and_then(|id| sim.tags.agents.reverse_lookup(&id));
+
+        let target_location = &sim.locations[target_entity.location.unwrap()];
+        let template = match event.typ {
+            PressureEventType::Farmers
+                if embargoed_abroad(sim, target_location.site, target_entity.agent) =>
+            {
+                "smugglers"
+            }
+            PressureEventType::Farmers => "farmers",
+            // Ports send their merchants out by sea
+            PressureEventType::Merchants if sim.sites.is_port(target_location.site) => {
+                "ship_merchants"
             }
+            PressureEventType::Merchants => "merchants",
+            PressureEventType::Rebels => "rebels",
+        };
+        let args = TemplateArgs {
+            site: &sim.sites[target_location.site].tag,
+            faction,
+            home: &target_entity.name,
+        };
+        let Some(mut create) = from_template(arena, sim, template, &args, target_entity.party)
+        else {
+            continue;
+        };
+        // Rebels rise in numbers with the unrest behind them
+        if let PressureEventType::Rebels = event.typ
+            && let Some(party) = &mut create.party
+        {
+            let scale = event.pressure / crate::unrest::UPRISING_THRESHOLD;
+            party.troops = arena.alloc_iter(party.troops.iter().map(|token| CreateToken {
+                tag: token.tag,
+                size: (token.size as f64 * scale).round() as i64,
+            }));
         }
+        out.push(create);
     }
     out
 }
 
+// Children are born where their parent stays, into the faction of their parent
+fn spawn_heirs<'a>(
+    arena: &'a Arena,
+    sim: &Simulation,
+    births: Vec<crate::characters::Birth>,
+) -> Vec<CreateEntity<'a>> {
+    births
+        .into_iter()
+        .filter_map(|birth| {
+            let faction = sim
+                .agents
+                .political_hierarchy
+                .parent(birth.parent)
+                .and_then(|id| sim.tags.agents.reverse_lookup(&id));
+            let site = &sim.sites[birth.site];
+            let home = sim.locations.get(site.location?)?.entity;
+            let args = TemplateArgs {
+                site: &site.tag,
+                faction,
+                home: &sim.entities[home].name,
+            };
+            let mut create = from_template(arena, sim, "person", &args, None)?;
+            create.name = arena.alloc_str(&birth.name);
+            if let Some(agent) = &mut create.agent {
+                agent.family_parent = Some(birth.parent);
+            }
+            Some(create)
+        })
+        .collect()
+}
+
+// Spawns a party from a template, setting out from the given base if the
+// template gives it something to do there
+fn from_template<'a>(
+    arena: &'a Arena,
+    sim: &Simulation,
+    tag: &str,
+    args: &TemplateArgs,
+    base: Option<PartyId>,
+) -> Option<CreateEntity<'a>> {
+    let template = sim.templates.get(tag)?;
+    let faction = substitute(&template.faction, args);
+    let goal = template.goal;
+    Some(CreateEntity {
+        name: arena.alloc_str(&substitute(&template.name, args)),
+        kind_name: template.kind_name,
+        agent: Some(CreateAgent {
+            tag: "",
+            flags: if template.character {
+                &[AgentFlag::IsCharacter]
+            } else {
+                &[]
+            },
+            political_parent: (!faction.is_empty()).then(|| arena.alloc_str(&faction)),
+            family_parent: None,
+            cash: template.cash,
+            personality: Personality::default(),
+        }),
+        party: Some(CreateParty {
+            site: arena.alloc_str(args.site),
+            image: template.image,
+            size: template.size,
+            movement_speed: template.movement_speed,
+            layer: 1,
+            carry_capacity: template.carry_capacity,
+            boats: template.boats,
+            off_road: template.off_road,
+            ship: template.ship,
+            troops: arena.alloc_iter(template.troops.iter().map(|(tag, size)| CreateToken {
+                tag: arena.alloc_str(tag),
+                size: *size,
+            })),
+        }),
+        behavior: Some(CreateBehavior {
+            base: base.filter(|_| goal != TemplateGoal::Idle),
+            smuggle: goal == TemplateGoal::Smuggle,
+            arbitrage: goal == TemplateGoal::ArbitrageTrade,
+            route: None,
+            migration: None,
+            rebel: goal == TemplateGoal::Rebel,
+            stance: template.stance,
+        }),
+        ..Default::default()
+    })
+}
+
+// Whether the market local traders from a site head for embargoes anything
+// of their faction, which turns them to smuggling
+fn embargoed_abroad(sim: &Simulation, site: SiteId, agent: Option<AgentId>) -> bool {
+    let guest = agent.and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent));
+    let host = sim.sites[site]
+        .influences
+        .top_source(InfluenceKind::Market)
+        .and_then(|party| sim.parties.get(party))
+        .and_then(|party| sim.entities[party.entity].agent)
+        .and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent));
+    let Some(host) = host.filter(|&host| guest != Some(host)) else {
+        return false;
+    };
+    sim.relations.embargoes_any(host, guest)
+}
+
+// Founded settlements start out as villages named after their founder
+fn found_settlements<'a>(
+    arena: &'a Arena,
+    sim: &Simulation,
+    foundations: Vec<crate::ambitions::Foundation>,
+) -> Vec<CreateEntity<'a>> {
+    foundations
+        .into_iter()
+        .filter_map(|foundation| {
+            let founder = &sim.entities[foundation.founder];
+            let faction = founder
+                .agent
+                .and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent))
+                .and_then(|id| sim.tags.agents.reverse_lookup(&id))?;
+            // Prolific founders run through the prefixes before repeating themselves
+            let name = ["Tre", "Llan", "Caer", "Din"]
+                .iter()
+                .map(|prefix| format!("{prefix} {}", founder.name))
+                .find(|name| {
+                    sim.locations
+                        .values()
+                        .all(|location| sim.entities[location.entity].name != *name)
+                })
+                .unwrap_or_else(|| format!("Tre {}", founder.name));
+            let (prosperity, tokens) = crate::scenario::settlement_setup("village");
+            Some(settlement_entity(CreateLocationParams {
+                name: arena.alloc_str(&name),
+                site: arena.alloc_str(&sim.sites[foundation.site].tag),
+                faction: arena.alloc_str(faction),
+                settlement_kind: "village",
+                prosperity,
+                tokens,
+            }))
+        })
+        .collect()
+}
+
+fn spawn_caravans<'a>(
+    arena: &'a Arena,
+    sim: &Simulation,
+    routes: Vec<TradeRouteId>,
+) -> Vec<CreateEntity<'a>> {
+    routes
+        .into_iter()
+        .map(|id| {
+            let source = &sim.locations[sim.routes.routes[id].source];
+            let source_entity = &sim.entities[source.entity];
+
+            let political_parent = source_entity
+                .agent
+                .and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent))
+                .and_then(|id| sim.tags.agents.reverse_lookup(&id))
+                .map(|str| arena.alloc_str(str));
+
+            CreateEntity {
+                name: "Caravan",
+                agent: Some(CreateAgent {
+                    tag: "",
+                    flags: &[],
+                    political_parent,
+                    family_parent: None,
+                    cash: 1000.,
+                    personality: Personality::default(),
+                }),
+                party: Some(CreateParty {
+                    site: arena.alloc_str(&sim.sites[source.site].tag),
+                    image: "farmers",
+                    size: 1.,
+                    movement_speed: CARAVAN_MOVEMENT_SPEED,
+                    layer: 1,
+                    carry_capacity: CARAVAN_CARRY_CAPACITY,
+                    boats: false,
+                    off_road: false,
+                    ship: false,
+                    troops: &[],
+                }),
+                behavior: Some(CreateBehavior {
+                    base: Some(source.party),
+                    smuggle: false,
+                    arbitrage: false,
+                    route: Some(id),
+                    migration: None,
+                    rebel: false,
+                    stance: None,
+                }),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn spawn_migrants<'a>(
+    arena: &'a Arena,
+    sim: &Simulation,
+    convoys: Vec<crate::migration::Migration>,
+) -> Vec<CreateEntity<'a>> {
+    convoys
+        .into_iter()
+        .map(|migration| {
+            let origin = &sim.locations[migration.origin];
+            CreateEntity {
+                name: "Migrants",
+                party: Some(CreateParty {
+                    site: arena.alloc_str(&sim.sites[origin.site].tag),
+                    image: "farmers",
+                    size: 1.,
+                    movement_speed: MIGRANT_MOVEMENT_SPEED,
+                    layer: 1,
+                    carry_capacity: 0.,
+                    boats: false,
+                    off_road: false,
+                    ship: false,
+                    troops: &[],
+                }),
+                behavior: Some(CreateBehavior {
+                    base: Some(origin.party),
+                    smuggle: false,
+                    arbitrage: false,
+                    route: None,
+                    migration: Some(migration),
+                    rebel: false,
+                    stance: None,
+                }),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// Goods spoil at their own rate, in markets and on the road alike
+fn decay_goods(sim: &mut Simulation) {
+    for location in sim.locations.values_mut() {
+        let market = &mut location.market;
+        for (good_id, good_type) in sim.good_types.iter() {
+            let good = &mut market.goods[good_id];
+            good.wasted = good.stock * good_type.decay_rate;
+            good.stock -= good.wasted;
+            good.stock_delta -= good.wasted;
+            market.food_stockpile -= good.wasted * good_type.food_rate;
+        }
+    }
+
+    for party in sim.parties.values_mut() {
+        for (good_id, amount) in party.good_stock.amount.iter_mut() {
+            *amount -= *amount * sim.good_types[good_id].decay_rate;
+        }
+    }
+}
+
+// Locations remit a share of their income to their faction, which passes a
+// share of it on to its own overlord, and so on up the political hierarchy
+// Taxes the locations whose market was updated on this tick, on their income
+// over the days the round makes up for
+fn collect_taxes(sim: &mut Simulation, round: MarketRound) {
+    let slice = round.slice;
+    if slice.is_first() {
+        for agent in sim.agents.entries.values_mut() {
+            agent.tax_revenue = 0.;
+        }
+    }
+
+    for (id, location) in sim.locations.iter() {
+        if !slice.contains(id) {
+            continue;
+        }
+        let Some(settlement) = sim.entities[location.entity].agent else {
+            continue;
+        };
+        let mut payer = settlement;
+        let mut amount = location.market.income.max(0.) * round.days;
+        while let Some(overlord) = sim.agents.political_hierarchy.parent(payer) {
+            amount *= sim.agents[overlord].tax_rate;
+            if amount <= 0. {
+                break;
+            }
+            // Settlements remit out of their income rather than their purse
+            if payer != settlement {
+                sim.agents[payer].cash -= amount;
+            }
+            let overlord_data = &mut sim.agents[overlord];
+            overlord_data.cash += amount;
+            overlord_data.tax_revenue += amount;
+            payer = overlord;
+        }
+    }
+}
+
+// Share of the output value of buildings paid out as wages
+const WAGE_SHARE: f64 = 0.3;
+// Prosperity lost each day with every pop looking for work idle
+const UNEMPLOYMENT_PROSPERITY_LOSS: f64 = 0.001;
+
+// Share of its jobs a building has filled, which its output follows
+fn staffing(
+    typ: &TokenType,
+    jobs: &SecondaryMap<TokenTypeId, f64>,
+    hired: &SecondaryMap<TokenTypeId, f64>,
+) -> f64 {
+    let mut slots = 0.;
+    let mut filled = 0.;
+    for (pop, &workers) in &typ.jobs {
+        if workers <= 0. {
+            continue;
+        }
+        let wanted = jobs.get(pop).copied().unwrap_or(0.);
+        let fill = if wanted > 0. {
+            hired.get(pop).copied().unwrap_or(0.) / wanted
+        } else {
+            0.
+        };
+        slots += workers;
+        filled += workers * fill;
+    }
+    if slots <= 0. { 1. } else { filled / slots }
+}
+
+#[derive(Clone, Copy)]
+struct MarketRound {
+    // Locations whose market is updated on this tick
+    slice: Slice,
+    price_index: f64,
+    // Days the round makes up for. Market figures are kept as daily rates,
+    // and what flows in and out of stock is that many days of them.
+    days: f64,
+}
+
 fn tick_location_economy(
     arena: &Arena,
     locations: &mut Locations,
     tokens: &Tokens,
     good_types: &GoodTypes,
     sites: &Sites,
-    tick_market: bool,
-) {
+    conditions: &SecondaryMap<LocationId, LocalConditions>,
+    market: Option<MarketRound>,
+) -> Vec<(SiteId, Vec<f64>, f64)> {
+    // Workers at the deposits of each site, and the days they worked them
+    let mut workings = vec![];
+
     // New location economic tick
-    for location in locations.values_mut() {
+    for (location_id, location) in locations.iter_mut() {
         let tokens = arena.alloc_iter(tokens.all_tokens_in(location.tokens));
 
         location.population = Tokens::count_size(tokens, TokenCategory::Pop);
 
-        if !tick_market {
+        let Some(round) = market.filter(|round| round.slice.contains(location_id)) else {
             continue;
-        }
+        };
+        // Base price of goods, following the money supply
+        let base_price = |good_type: &GoodData| good_type.price * round.price_index;
 
         const GOODS_POPULATION_SCALE: f64 = 0.01;
 
         let mut new_market = Market::new(good_types);
+        let conditions = conditions.get(location_id).copied().unwrap_or_default();
+
+        // Fill the jobs at the buildings with the pops at hand. When short of
+        // workers, every building gets the same share of those available.
+        let mut jobs: SecondaryMap<TokenTypeId, f64> = SecondaryMap::new();
+        for tok in tokens.iter() {
+            for (pop, &workers) in &tok.typ.jobs {
+                if workers <= 0. {
+                    continue;
+                }
+                *jobs.entry(pop).unwrap().or_default() += workers * tok.data.size as f64;
+            }
+        }
+        let mut hired: SecondaryMap<TokenTypeId, f64> = SecondaryMap::new();
+        for tok in tokens.iter() {
+            if tok.typ.category != TokenCategory::Pop {
+                continue;
+            }
+            let Some(&wanted) = jobs.get(tok.data.typ) else {
+                continue;
+            };
+            let available = tok.data.size as f64;
+            let workers = available.min(wanted);
+            hired.insert(tok.data.typ, workers);
+            new_market.employed += workers;
+            // Pops working the land are not idle
+            if tok.typ.rgo_points <= 0. {
+                new_market.unemployed += available - workers;
+            }
+        }
 
         // Calculate token contributions
         let mut rgo_work_points = 0.0;
@@ -333,14 +1433,38 @@ fn tick_location_economy(
 
             for tok in tokens {
                 let (scale, is_commerical) = match tok.typ.category {
-                    TokenCategory::Building => (1., true),
+                    TokenCategory::Building => (staffing(tok.typ, &jobs, &hired), true),
                     TokenCategory::Pop => (GOODS_POPULATION_SCALE, false),
+                    // Garrison troops are not townsfolk, and work no market
+                    TokenCategory::Military => continue,
                 };
 
                 let size = tok.data.size as f64 * scale;
+                let (rgo_factor, demand_factor, supply_factor) = tok.data.factors();
+                // Buildings only turn out as much as the inputs they got last time allow
+                let output = if is_commerical {
+                    let inputs = tok
+                        .typ
+                        .demand
+                        .iter()
+                        .filter(|&(_, &amt)| amt > 0.)
+                        .map(|(good_id, _)| location.market.goods[good_id].satisfaction)
+                        .fold(1., f64::min);
+                    new_market.throughput.insert(tok.data.typ, scale * inputs);
+                    size * inputs
+                } else {
+                    size
+                };
 
                 for (good_id, &amt) in &tok.typ.demand {
-                    let amount = amt * size;
+                    let mut amount = amt * size * demand_factor;
+                    let good_type = &good_types[good_id];
+                    if !is_commerical && good_type.food_rate > 0. {
+                        amount *= good_type.demand_in(conditions.season);
+                        if conditions.rationing {
+                            amount *= RATIONING_DEMAND;
+                        }
+                    }
                     let price = amount * location.market.goods[good_id].price;
                     let value = amount * price;
                     if is_commerical {
@@ -351,7 +1475,7 @@ fn tick_location_economy(
                 }
 
                 for (good_id, &amt) in &tok.typ.supply {
-                    let amount = amt * size;
+                    let amount = amt * output * supply_factor;
                     let price = amount * location.market.goods[good_id].price;
                     let value = amount * price;
 
@@ -361,34 +1485,61 @@ fn tick_location_economy(
 
                     new_market.goods[good_id].supply_base += amount;
                 }
-                rgo_work_points += tok.typ.rgo_points * size;
+                // Pops hired by the buildings are off the land
+                let at_work = hired.get(tok.data.typ).copied().unwrap_or(0.) * scale;
+                rgo_work_points += tok.typ.rgo_points * rgo_factor * (size - at_work);
             }
 
-            new_market.income += value_of_token_production;
+            new_market.wages = value_of_token_production * WAGE_SHARE;
+            new_market.income += value_of_token_production - new_market.wages;
             new_market.income -= value_of_token_consumption;
         }
 
+        location.prosperity = (location.prosperity
+            - UNEMPLOYMENT_PROSPERITY_LOSS * new_market.unemployment() * round.days)
+            .max(0.);
+
         // Calculate RGO production
         {
             let rgo = &sites[location.site].rgo;
-            let num_workers = rgo_work_points.floor().min(rgo.capacity as f64);
+            let num_workers = rgo_work_points.floor();
+
+            // How the season and the harvest bear on the output of a good
+            let yield_of = |good_id: GoodId| {
+                let good_type = &good_types[good_id];
+                let mut factor = good_type.output_in(conditions.season);
+                if good_type.food_rate > 0. {
+                    factor *= conditions.harvest;
+                }
+                factor
+            };
+            let allocation = rgo.allocate(num_workers, |deposit| {
+                let good_id = deposit.good;
+                deposit.output(1.) * yield_of(good_id) * location.market.goods[good_id].price
+            });
 
             let mut value_of_rgo_production = 0.0;
 
-            for (good_id, rate) in rgo.rates.iter() {
-                let produced = rate * num_workers;
+            for (deposit, &workers) in rgo.deposits.iter().zip(&allocation) {
+                let good_id = deposit.good;
+                let produced = deposit.output(workers) * yield_of(good_id);
                 let price = location.market.goods[good_id].price;
                 value_of_rgo_production += price * produced;
                 new_market.goods[good_id].supply_base += produced;
             }
+            workings.push((location.site, allocation, round.days));
 
             new_market.income += value_of_rgo_production;
         }
 
         {
             // Calculate effective supply and demand (used for pricing)
-            for good_id in good_types.keys() {
+            for (good_id, good_type) in good_types {
                 let good_data = &mut new_market.goods[good_id];
+                let (demand, supply) = crate::modifiers::factors(good_type, &conditions);
+                good_data.demand_base *= demand;
+                good_data.supply_base *= supply;
+
                 good_data.supply_effective += good_data.supply_base;
                 good_data.supply_effective += good_data.supply_from_stock;
 
@@ -401,6 +1552,9 @@ fn tick_location_economy(
             }
         }
 
+        // Going hungry drives up the price of all food
+        let hunger = 1. - location.market.food_satisfaction();
+
         // Update good prices and stock
         for (good_id, good_type) in good_types {
             let new_good = &mut new_market.goods[good_id];
@@ -413,24 +1567,38 @@ fn tick_location_economy(
                         .supply_effective
                         .max(new_good.demand_effective)
                         .max(0.1);
-                    (numerator / denominator).clamp(-0.75, 0.75)
+                    (good_type.elasticity * numerator / denominator)
+                        .clamp(good_type.min_price - 1., good_type.max_price - 1.)
                 };
                 let prosperity_modifier = location.prosperity.max(0.);
-                let target_price =
-                    good_type.price * (1. + sd_modifier) * (1. + prosperity_modifier);
+                let mut target_price =
+                    base_price(good_type) * (1. + sd_modifier) * (1. + prosperity_modifier);
+                if good_type.food_rate > 0. {
+                    target_price *= 1. + HUNGER_PRICE_SHOCK * hunger;
+                }
                 let current_price = location.market.goods[good_id].price;
-                const PRICE_CONVERGENCE_SPEED: f64 = 0.1;
-                let new_price = lerp_f64(current_price, target_price, PRICE_CONVERGENCE_SPEED);
+                let convergence = 1. - (1. - good_type.convergence).powf(round.days);
+                let mut new_price = lerp_f64(current_price, target_price, convergence);
+                if conditions.price_ceiling && good_type.food_rate > 0. {
+                    let ceiling = base_price(good_type) * (1. + PRICE_CEILING_MARGIN);
+                    target_price = target_price.min(ceiling);
+                    new_price = new_price.min(ceiling);
+                }
 
                 new_good.target_price = target_price;
                 new_good.price = new_price;
+
+                let old_good = &mut location.market.goods[good_id];
+                new_good.price_history = std::mem::take(&mut old_good.price_history);
+                new_good.price_history.push(new_price as f32);
             }
 
             // Handle stock
             {
                 let prev_stock = location.market.goods[good_id].stock;
-                let available = prev_stock + new_good.supply_base;
-                new_good.consumed = available.min(new_good.demand_base);
+                let available = prev_stock + new_good.supply_base * round.days;
+                let consumed = available.min(new_good.demand_base * round.days);
+                new_good.consumed = consumed / round.days;
                 new_good.satisfaction = if new_good.demand_base <= 0.0 {
                     1.0
                 } else {
@@ -438,11 +1606,12 @@ fn tick_location_economy(
                 };
 
                 let max_stock = location.population as f64 * GOODS_POPULATION_SCALE * 10.0;
-                new_good.stock = (available - new_good.consumed).clamp(0.0, max_stock);
+                new_good.stock = (available - consumed).clamp(0.0, max_stock);
                 new_good.stock_delta = new_good.stock - prev_stock;
             }
 
             // Food
+            new_market.food_demand += new_good.demand_base * good_type.food_rate;
             new_market.food_consumed += new_good.consumed * good_type.food_rate;
             new_market.food_stockpile += new_good.stock * good_type.food_rate;
         }
@@ -450,12 +1619,15 @@ fn tick_location_economy(
         // Update market proper
         location.market = new_market;
     }
+    workings
 }
 
 enum ChangePath {
     Clear,
     Keep,
     Set(Vec<GridCoord>),
+    // No route leads to the destination any longer, or it is gone
+    Blocked,
 }
 
 #[derive(Default)]
@@ -472,14 +1644,22 @@ fn tick_party_ai(sim: &Simulation) -> Vec<Navigate> {
             let target;
             let destination;
 
-            if party_data.movement_speed == 0.0 {
+            // Those in a group go where its leader takes them
+            if party_data.movement_speed == 0.0 || sim.groups.leader_of(party_id).is_some() {
                 target = None;
                 destination = None;
+            } else if sim.escorts.escort_of(party_id).is_some() {
+                // Escorted parties keep their orders for when they are let go
+                target = party_data.movement.target;
+                destination = None;
             } else {
                 target = party_data.movement.target;
                 destination = target.and_then(|tgt| match tgt {
                     MovementTarget::Site(site) => Some(GridCoord::at(site)),
-                    MovementTarget::Party(party) => sim.parties.get(party).map(|x| x.position),
+                    MovementTarget::Party(party) => sim
+                        .parties
+                        .contains_key(party)
+                        .then(|| crate::interception::intercept(sim, party_id, party)),
                 });
             };
 
@@ -492,7 +1672,13 @@ fn tick_party_ai(sim: &Simulation) -> Vec<Navigate> {
         .collect()
 }
 
-fn pathfind(parties: &Parties, sites: &Sites) -> Vec<(PartyId, ChangePath)> {
+fn pathfind(
+    parties: &Parties,
+    groups: &crate::groups::Groups,
+    sites: &mut Sites,
+    known_passages: &SecondaryMap<PartyId, BTreeSet<(SiteId, SiteId)>>,
+) -> Vec<(PartyId, ChangePath)> {
+    let no_passages = BTreeSet::new();
     parties
         .iter()
         .map(|(party_id, party_data)| {
@@ -500,9 +1686,21 @@ fn pathfind(parties: &Parties, sites: &Sites) -> Vec<(PartyId, ChangePath)> {
                 .movement
                 .destination
                 .unwrap_or(party_data.position);
-            let update = if party_data.position == destination {
+            let mobility = groups.mobility(parties, party_id);
+            // The party it was sent after is gone, unless it was only chasing
+            // or running, which it gives up on by itself
+            let target_lost = party_data.movement.reaction.is_none()
+                && matches!(
+                    party_data.movement.target,
+                    Some(MovementTarget::Party(target)) if !parties.contains_key(target)
+                );
+            let update = if target_lost {
+                ChangePath::Blocked
+            } else if party_data.position == destination {
                 ChangePath::Clear
-            } else if Some(destination) == party_data.movement.path.endpoint() {
+            } else if Some(destination) == party_data.movement.path.endpoint()
+                && passable(sites, &party_data.movement.path, mobility)
+            {
                 ChangePath::Keep
             } else {
                 let current_pos = party_data.position;
@@ -512,23 +1710,12 @@ fn pathfind(parties: &Parties, sites: &Sites) -> Vec<(PartyId, ChangePath)> {
                     let start_node = current_pos.closest_endpoint();
                     let end_node = destination.closest_endpoint();
 
-                    let steps = sites.astar(start_node, end_node).unwrap_or_default().0;
-
-                    // Construct path
-                    let mut path = Vec::with_capacity(steps.len() + 1);
-
-                    let touches = |idx: usize| {
-                        steps
-                            .get(idx)
-                            .map(|&s| current_pos.touches(s))
-                            .unwrap_or(false)
+                    let known = known_passages.get(party_id).unwrap_or(&no_passages);
+                    let Some((steps, _)) = sites.cached_path(start_node, end_node, known, mobility)
+                    else {
+                        return (party_id, ChangePath::Blocked);
                     };
-
-                    let skip = if touches(0) && touches(1) { 1 } else { 0 };
-                    path.extend(steps.into_iter().skip(skip).map(|site| GridCoord::at(site)));
-
-                    path.push(destination);
-                    path
+                    path_through(current_pos, steps, destination)
                 };
                 ChangePath::Set(path)
             };
@@ -537,13 +1724,53 @@ fn pathfind(parties: &Parties, sites: &Sites) -> Vec<(PartyId, ChangePath)> {
         .collect()
 }
 
+// Whether a party can still go all the way along its path, which stops being
+// so once a crossing goes or the party can no longer travel as it planned.
+// The leg it is on is finished whatever happens.
+fn passable(sites: &Sites, path: &Path, mobility: Mobility) -> bool {
+    let mut steps = path.iter();
+    let Some(mut at) = steps.next() else {
+        return true;
+    };
+    steps.all(|step| {
+        let leg = GridCoord::as_colinear(at, step);
+        let stay = step == at;
+        at = step;
+        stay || leg.is_some_and(|leg| sites.slowdown(leg.start, leg.end, mobility).is_finite())
+    })
+}
+
+/// The path from a position to a destination through the sites found
+/// between their closest endpoints
+pub(crate) fn path_through(
+    current_pos: GridCoord,
+    steps: Vec<SiteId>,
+    destination: GridCoord,
+) -> Vec<GridCoord> {
+    let mut path = Vec::with_capacity(steps.len() + 1);
+
+    let touches = |idx: usize| {
+        steps
+            .get(idx)
+            .map(|&s| current_pos.touches(s))
+            .unwrap_or(false)
+    };
+
+    let skip = if touches(0) && touches(1) { 1 } else { 0 };
+    path.extend(steps.into_iter().skip(skip).map(|site| GridCoord::at(site)));
+
+    path.push(destination);
+    path
+}
+
 struct Movement {
     party_id: PartyId,
     next_position: GridCoord,
 }
 
-fn move_to_next_coord(parties: &Parties, sites: &Sites) -> Vec<Movement> {
-    parties
+fn move_to_next_coord(pace: &crate::pace::Pace) -> Vec<Movement> {
+    let sites = pace.sites;
+    pace.parties
         .iter()
         .map(|(party_id, party_data)| {
             let next_position = party_data
@@ -561,14 +1788,10 @@ fn move_to_next_coord(parties: &Parties, sites: &Sites) -> Vec<Movement> {
 
                     // Get the actual distance between the two
                     let t_direction = (end_t - current_t).signum();
-                    let distance = sites.distance(start, end);
-                    if distance == f32::INFINITY {
-                        println!("WARNING: Movement to infinitely far location!");
-                    }
+                    let distance = sites.way_length(start, end);
                     // We are moving with a certain speed
-                    const BASE_SPEED: f32 = 0.01;
-                    let speed = party_data.movement_speed * BASE_SPEED;
-                    let t_speed = if speed / sites.distance(start, end) == 0.0 {
+                    let speed = pace.speed(party_id) * BASE_SPEED;
+                    let t_speed = if speed / distance == 0.0 {
                         0.0
                     } else {
                         speed / distance
@@ -588,7 +1811,38 @@ fn move_to_next_coord(parties: &Parties, sites: &Sites) -> Vec<Movement> {
         .collect()
 }
 
-fn pos_of_grid_coordinate(sites: &Sites, coord: GridCoord) -> V2 {
+// Distance covered in one tick by a party with a movement speed of 1
+pub(crate) const BASE_SPEED: f32 = 0.01;
+
+pub(crate) const PERSON_MOVEMENT_SPEED: f32 = 2.5;
+pub(crate) const CARAVAN_MOVEMENT_SPEED: f32 = 2.;
+pub(crate) const MIGRANT_MOVEMENT_SPEED: f32 = 1.;
+pub(crate) const SHIP_MOVEMENT_SPEED: f32 = 3.;
+
+// Weight of the goods each kind of party can carry
+pub(crate) const PERSON_CARRY_CAPACITY: f64 = 100.;
+pub(crate) const FARMER_CARRY_CAPACITY: f64 = 200.;
+const CARAVAN_CARRY_CAPACITY: f64 = 500.;
+pub(crate) const MERCHANT_CARRY_CAPACITY: f64 = 300.;
+pub(crate) const SHIP_CARRY_CAPACITY: f64 = 1000.;
+pub(crate) const REBEL_CARRY_CAPACITY: f64 = 200.;
+
+// Puts the tokens into a container, warning about those of unknown types
+fn add_tokens(sim: &mut Simulation, container: TokenContainerId, tokens: &[CreateToken]) {
+    for create in tokens {
+        match sim.tags.token_types.lookup(create.tag) {
+            Some(typ) => {
+                sim.tokens.add_token(container, typ, create.size);
+            }
+            None => sim.warnings.push(SimWarning::UndefinedTag {
+                kind: TagKind::TokenType,
+                tag: create.tag.to_string(),
+            }),
+        }
+    }
+}
+
+pub(crate) fn pos_of_grid_coordinate(sites: &Sites, coord: GridCoord) -> V2 {
     match coord {
         GridCoord::At(site) => sites.get(site).map(|x| x.pos).unwrap_or_default(),
         GridCoord::Between(site1, site2, t) => {
@@ -625,7 +1879,10 @@ struct CreateAgent<'a> {
     tag: &'a str,
     flags: &'a [AgentFlag],
     political_parent: Option<&'a str>,
+    // The character a newborn character is the child of
+    family_parent: Option<AgentId>,
     cash: f64,
+    personality: Personality,
 }
 
 pub struct CreateToken<'a> {
@@ -633,14 +1890,18 @@ pub struct CreateToken<'a> {
     pub size: i64,
 }
 
+impl<'a> ArenaSafe for CreateToken<'a> {}
+
 pub struct CreatePressureAgent<'a> {
     pressures: &'a [(PressureType, f64)],
+    triggers: &'a [PressureTrigger],
 }
 
 struct CreateLocation<'a> {
     site: &'a str,
     prosperity: f64,
     is_town: bool,
+    is_fortified: bool,
     tokens: &'a [CreateToken<'a>],
 }
 
@@ -650,119 +1911,519 @@ struct CreateParty<'a> {
     size: f32,
     movement_speed: f32,
     layer: u8,
+    carry_capacity: f64,
+    boats: bool,
+    off_road: bool,
+    ship: bool,
+    troops: &'a [CreateToken<'a>],
+}
+
+struct CreateBehavior {
+    base: Option<PartyId>,
+    smuggle: bool,
+    arbitrage: bool,
+    route: Option<TradeRouteId>,
+    migration: Option<crate::migration::Migration>,
+    rebel: bool,
+    // Taken up in place of the stance of the goal
+    stance: Option<crate::stances::Stance>,
+}
+
+enum Command<'a> {
+    MoveTo {
+        subject: ObjectId,
+        target: ObjectId,
+    },
+    MoveToTag {
+        subject: ObjectId,
+        tag: &'a str,
+    },
+    QueueMove {
+        subject: ObjectId,
+        target: ObjectId,
+    },
+    Patrol(ObjectId),
+    ForcedMarch {
+        subject: ObjectId,
+        on: bool,
+    },
+    SetStance {
+        subject: ObjectId,
+        stance: crate::stances::Stance,
+    },
+    Merge {
+        leader: ObjectId,
+        party: ObjectId,
+    },
+    Detach(ObjectId),
+    Escort {
+        escort: ObjectId,
+        party: ObjectId,
+    },
+    Release(ObjectId),
+    Station(ObjectId),
+    Besiege {
+        subject: ObjectId,
+        target: ObjectId,
+    },
+    Halt(ObjectId),
+    Create(Box<CreateEntity<'a>>),
+    Spawn(SpawnParams<'a>),
+    Despawn(ObjectId),
+    Rename {
+        subject: ObjectId,
+        name: &'a str,
+    },
+    SetNote {
+        subject: ObjectId,
+        note: &'a str,
+    },
+    Covert(CovertActionParams<'a>),
+    Ship(ShipmentParams<'a>),
+    Transfer(TransferParams<'a>),
+    Edict(EdictParams<'a>),
+    Embargo(EmbargoParams<'a>),
+    TaxRate(TaxRateParams<'a>),
+    TollRate(TollRateParams<'a>),
+    RoadWorks(RoadWorksParams<'a>),
+    Conquer(ConquerParams<'a>),
+    Construct {
+        location: ObjectId,
+        building: &'a str,
+    },
+    Autonomy {
+        location: ObjectId,
+        managed: bool,
+    },
+    Stockpile {
+        location: ObjectId,
+        good: &'a str,
+        target: f64,
+    },
+    Blight(BlightParams<'a>),
+    ModifyToken(TokenModifierParams<'a>),
+}
+
+/// Orders for the simulation, applied in the order they were issued
+#[derive(Default)]
+pub struct TickCommands<'a> {
+    // Along with whether each command came from the player at the controls
+    queue: Vec<(Command<'a>, bool)>,
+    by_player: bool,
+}
+
+pub struct CreateLocationParams<'a> {
+    pub name: &'a str,
+    pub site: &'a str,
+    pub faction: &'a str,
+    pub settlement_kind: &'static str,
+    pub prosperity: f64,
+    pub tokens: &'a [CreateToken<'a>],
+}
+
+pub struct CreatePersonParams<'a> {
+    pub name: &'a str,
+    pub site: &'a str,
+    pub faction: &'a str,
+}
+
+pub struct SpawnParams<'a> {
+    /// Tag of the template of the party
+    pub template: &'a str,
+    /// Name of the party, if not the one the template gives it
+    pub name: Option<&'a str>,
+    pub site: &'a str,
+    /// Tag of the faction of whoever spawns the party, empty for none
+    pub faction: &'a str,
+}
+
+pub struct CovertActionParams<'a> {
+    /// Tag of the faction behind the action
+    pub actor: &'a str,
+    /// Location targeted by the action
+    pub target: ObjectId,
+    pub action: CovertAction,
+}
+
+pub struct ShipmentParams<'a> {
+    /// Location whose market stock is shipped
+    pub from: ObjectId,
+    pub to: ObjectId,
+    pub good: &'a str,
+    pub amount: f64,
+}
+
+pub struct TransferParams<'a> {
+    /// Party trading goods with the location it stands at
+    pub party: ObjectId,
+    pub good: &'a str,
+    /// Positive amounts are unloaded into the market, negative ones loaded from
+    /// it. Clamped to what there is and what the party can carry.
+    pub amount: f64,
 }
 
-struct CreateBehavior {
-    base: Option<PartyId>,
-}
+pub struct EdictParams<'a> {
+    pub faction: &'a str,
+    pub edict: Edict,
+    /// Whether the edict is decreed or lifted
+    pub active: bool,
+}
+
+pub struct EmbargoParams<'a> {
+    /// Tag of the faction imposing the embargo
+    pub imposer: &'a str,
+    /// Tag of the embargoed faction, every other faction if missing
+    pub target: Option<&'a str>,
+    /// Tag of the embargoed good. Without one, traders are turned away entirely.
+    pub good: Option<&'a str>,
+    /// Whether the embargo is imposed or lifted
+    pub active: bool,
+}
+
+pub struct TaxRateParams<'a> {
+    pub faction: &'a str,
+    /// Share of the income of its subjects the faction collects, between 0 and 1
+    pub rate: f64,
+}
+
+pub struct TollRateParams<'a> {
+    pub faction: &'a str,
+    /// Share of the value of the trades of foreign parties the faction
+    /// collects, between 0 and 1
+    pub rate: f64,
+}
+
+pub struct RoadWorksParams<'a> {
+    pub faction: &'a str,
+    /// Tags of the sites at the ends of the road
+    pub from: &'a str,
+    pub to: &'a str,
+    /// Cash the faction spends on the road, at most all it has
+    pub cash: f64,
+}
+
+pub struct ConquerParams<'a> {
+    pub faction: &'a str,
+    pub site: &'a str,
+}
+
+pub struct BlightParams<'a> {
+    pub site: &'a str,
+    /// Share of the food harvest lost at the site, half as much around it
+    pub severity: f64,
+    pub seasons: u64,
+}
+
+pub struct TokenModifierParams<'a> {
+    /// Site of the location whose token is affected
+    pub site: &'a str,
+    /// Tag of the type of pop or building affected
+    pub token: &'a str,
+    /// Name of the effect, like "plague". A modifier of the same name on the
+    /// token is replaced.
+    pub name: &'a str,
+    /// Multipliers of the land the token works, what it demands and what it supplies
+    pub rgo_points: f64,
+    pub demand: f64,
+    pub supply: f64,
+    pub days: u64,
+}
+
+pub struct CreateFactionParams<'a> {
+    pub tag: &'a str,
+    pub name: &'a str,
+    pub personality: Personality,
+}
+
+impl<'a> TickCommands<'a> {
+    /// Marks the commands issued from now on as coming from the player at the
+    /// controls. Those that do not concern its own faction are dropped.
+    pub fn as_player(&mut self, by_player: bool) {
+        self.by_player = by_player;
+    }
+
+    fn push(&mut self, command: Command<'a>) {
+        self.queue.push((command, self.by_player));
+    }
+
+    pub fn issue_move_to_object(&mut self, subject: ObjectId, target: ObjectId) {
+        self.push(Command::MoveTo { subject, target });
+    }
+
+    /// Moves towards the site or entity with the given tag
+    pub fn issue_move_to_tag(&mut self, subject: ObjectId, tag: &'a str) {
+        self.push(Command::MoveToTag { subject, tag });
+    }
+
+    /// Adds a waypoint the party heads for once it reaches those it was
+    /// already ordered to
+    pub fn queue_move_to_object(&mut self, subject: ObjectId, target: ObjectId) {
+        self.push(Command::QueueMove { subject, target });
+    }
+
+    /// Sets a party going round its waypoints, back to the first after the
+    /// last, until ordered otherwise
+    pub fn patrol(&mut self, subject: ObjectId) {
+        self.push(Command::Patrol(subject));
+    }
+
+    /// Sets a party marching faster than its usual pace, or back to it
+    pub fn forced_march(&mut self, subject: ObjectId, on: bool) {
+        self.push(Command::ForcedMarch { subject, on });
+    }
+
+    /// A party joins the group of another standing where it is, to travel
+    /// with it as one
+    pub fn merge_party(&mut self, leader: ObjectId, party: ObjectId) {
+        self.push(Command::Merge { leader, party });
+    }
+
+    /// A party leaves its group and goes its own way
+    pub fn detach_party(&mut self, party: ObjectId) {
+        self.push(Command::Detach(party));
+    }
+
+    /// A party takes another standing where it is along under its escort,
+    /// wherever it goes
+    pub fn escort_party(&mut self, escort: ObjectId, party: ObjectId) {
+        self.push(Command::Escort { escort, party });
+    }
+
+    /// Lets an escorted party go its own way, or has an escort let go of
+    /// all those it takes along
+    pub fn release_party(&mut self, party: ObjectId) {
+        self.push(Command::Release(party));
+    }
+
+    /// Leaves the troops of a party in the garrison of the settlement of its
+    /// own faction it stands in
+    pub fn station_troops(&mut self, party: ObjectId) {
+        self.push(Command::Station(party));
+    }
+
+    /// Sends a party to a settlement, to take it by storm once there if it
+    /// is still hostile
+    pub fn besiege(&mut self, party: ObjectId, settlement: ObjectId) {
+        self.push(Command::Besiege {
+            subject: party,
+            target: settlement,
+        });
+    }
+
+    /// Changes how a party meets the hostile parties it sees
+    pub fn set_stance(&mut self, subject: ObjectId, stance: crate::stances::Stance) {
+        self.push(Command::SetStance { subject, stance });
+    }
+
+    /// Stops a party where it stands
+    pub fn halt(&mut self, subject: ObjectId) {
+        self.push(Command::Halt(subject));
+    }
+
+    pub fn despawn(&mut self, subject: ObjectId) {
+        self.push(Command::Despawn(subject));
+    }
+
+    pub fn covert_action(&mut self, params: CovertActionParams<'a>) {
+        self.push(Command::Covert(params));
+    }
+
+    pub fn ship_goods(&mut self, params: ShipmentParams<'a>) {
+        self.push(Command::Ship(params));
+    }
+
+    /// Moves goods between a party and the market of the location it stands at
+    pub fn transfer_goods(&mut self, params: TransferParams<'a>) {
+        self.push(Command::Transfer(params));
+    }
+
+    pub fn set_edict(&mut self, params: EdictParams<'a>) {
+        self.push(Command::Edict(params));
+    }
+
+    pub fn embargo(&mut self, params: EmbargoParams<'a>) {
+        self.push(Command::Embargo(params));
+    }
+
+    /// Starts raising a building at a location, or another level of one it has
+    pub fn construct_building(&mut self, location: ObjectId, building: &'a str) {
+        self.push(Command::Construct { location, building });
+    }
+
+    /// Leaves a location to build and send for food on its own, or takes it
+    /// back under direct control
+    pub fn set_autonomy(&mut self, location: ObjectId, managed: bool) {
+        self.push(Command::Autonomy { location, managed });
+    }
+
+    /// Has a location keep this much of a good in its market. Traders only
+    /// buy what is over the target, and are paid a premium for bringing more
+    /// while the market is short. A target of zero lifts the policy.
+    pub fn set_stockpile_policy(&mut self, location: ObjectId, good: &'a str, target: f64) {
+        self.push(Command::Stockpile {
+            location,
+            good,
+            target,
+        });
+    }
+
+    pub fn set_tax_rate(&mut self, params: TaxRateParams<'a>) {
+        self.push(Command::TaxRate(params));
+    }
+
+    /// Sets the tolls a faction levies on the trades foreign parties make in
+    /// its markets
+    pub fn set_toll_rate(&mut self, params: TollRateParams<'a>) {
+        self.push(Command::TollRate(params));
+    }
 
-#[derive(Default)]
-pub struct TickCommands<'a> {
-    create_entity_cmds: Vec<CreateEntity<'a>>,
-    move_to: Option<(ObjectId, ObjectId)>,
-}
+    /// Has a faction pay for the upgrade of the road between two sites
+    pub fn invest_in_road(&mut self, params: RoadWorksParams<'a>) {
+        self.push(Command::RoadWorks(params));
+    }
 
-pub struct CreateLocationParams<'a> {
-    pub name: &'a str,
-    pub site: &'a str,
-    pub faction: &'a str,
-    pub settlement_kind: &'static str,
-    pub prosperity: f64,
-    pub tokens: &'a [CreateToken<'a>],
-}
+    /// Has a faction take a site by force, holding it whatever the influences
+    /// over it
+    pub fn conquer(&mut self, params: ConquerParams<'a>) {
+        self.push(Command::Conquer(params));
+    }
 
-pub struct CreatePersonParams<'a> {
-    pub name: &'a str,
-    pub site: &'a str,
-    pub faction: &'a str,
-}
+    /// Ruins the harvest around a site, for testing and scenario scripting
+    pub fn blight(&mut self, params: BlightParams<'a>) {
+        self.push(Command::Blight(params));
+    }
 
-pub struct CreateFactionParams<'a> {
-    pub tag: &'a str,
-    pub name: &'a str,
-}
+    /// Puts a passing effect on a pop or building of a location, like a
+    /// plague or a festival
+    pub fn modify_token(&mut self, params: TokenModifierParams<'a>) {
+        self.push(Command::ModifyToken(params));
+    }
 
-impl<'a> TickCommands<'a> {
-    pub fn issue_move_to_object(&mut self, subject: ObjectId, target: ObjectId) {
-        self.move_to = Some((subject, target));
+    pub fn rename(&mut self, subject: ObjectId, name: &'a str) {
+        self.push(Command::Rename { subject, name });
     }
 
-    pub fn create_location(&mut self, params: CreateLocationParams<'a>) {
-        let size = match params.settlement_kind {
-            "town" => 2.5,
-            "hillfort" => 2.,
-            "village" => 1.5,
-            _ => 1.,
-        };
-        let is_town = match params.settlement_kind {
-            "town" => true,
-            _ => false,
-        };
-        let pressures: &[(PressureType, f64)] = match params.settlement_kind {
-            "village" => &[(PressureType::Farmer, 1.0)],
-            _ => &[],
-        };
+    /// Attaches a note to an entity, an empty note clears it
+    pub fn set_note(&mut self, subject: ObjectId, note: &'a str) {
+        self.push(Command::SetNote { subject, note });
+    }
 
-        self.create_entity_cmds.push(CreateEntity {
-            name: params.name,
-            kind_name: "Location",
-            agent: Some(CreateAgent {
-                tag: "",
-                flags: &[],
-                political_parent: Some(params.faction),
-                cash: 0.,
-            }),
-            location: Some(CreateLocation {
-                site: params.site,
-                prosperity: params.prosperity,
-                is_town,
-                tokens: params.tokens,
-            }),
-            party: Some(CreateParty {
-                site: params.site,
-                image: params.settlement_kind,
-                size,
-                movement_speed: 0.,
-                layer: 0,
-            }),
-            pressure_agent: Some(CreatePressureAgent { pressures }),
-            ..Default::default()
-        });
+    pub fn create_location(&mut self, params: CreateLocationParams<'a>) {
+        self.push(Command::Create(Box::new(settlement_entity(params))));
     }
 
     pub fn create_person(&mut self, params: CreatePersonParams<'a>) {
-        self.create_entity_cmds.push(CreateEntity {
-            name: params.name,
-            kind_name: "Person",
-            agent: Some(CreateAgent {
-                tag: "",
-                flags: &[],
-                political_parent: Some(params.faction),
-                cash: 0.,
-            }),
-            party: Some(CreateParty {
-                site: params.site,
-                image: "person",
-                size: 1.,
-                movement_speed: 2.5,
-                layer: 1,
-            }),
-            ..Default::default()
+        self.spawn(SpawnParams {
+            template: "person",
+            name: Some(params.name),
+            site: params.site,
+            faction: params.faction,
         });
     }
 
+    /// Spawns a party from the template with the given tag
+    pub fn spawn(&mut self, params: SpawnParams<'a>) {
+        self.push(Command::Spawn(params));
+    }
+
     pub fn create_faction(&mut self, params: CreateFactionParams<'a>) {
-        self.create_entity_cmds.push(CreateEntity {
+        self.push(Command::Create(Box::new(CreateEntity {
             name: params.name,
             kind_name: "Faction",
             agent: Some(CreateAgent {
                 tag: params.tag,
                 flags: &[AgentFlag::IsFaction],
                 political_parent: None,
+                family_parent: None,
                 cash: 0.,
+                personality: params.personality,
             }),
             ..Default::default()
-        });
+        })));
+    }
+}
+
+// Purse characters start with, to trade and scheme with
+pub(crate) const CHARACTER_STARTING_CASH: f64 = 200.;
+
+// Merchant pressure ports build up towards sending out a ship
+const PORT_MERCHANT_GROWTH: f64 = 0.25;
+
+// What settlements do once their pressures build up
+const SETTLEMENT_TRIGGERS: &[PressureTrigger] = &[
+    PressureTrigger {
+        target: PressureType::Farmer,
+        threshold: 20.,
+        subtract: 20.,
+        event: PressureEventType::Farmers,
+    },
+    PressureTrigger {
+        target: PressureType::Merchant,
+        threshold: 40.,
+        subtract: 40.,
+        event: PressureEventType::Merchants,
+    },
+    PressureTrigger {
+        target: PressureType::Unrest,
+        threshold: crate::unrest::UPRISING_THRESHOLD,
+        subtract: crate::unrest::UPRISING_THRESHOLD,
+        event: PressureEventType::Rebels,
+    },
+];
+
+fn settlement_entity(params: CreateLocationParams<'_>) -> CreateEntity<'_> {
+    let size = match params.settlement_kind {
+        "town" => 2.5,
+        "hillfort" => 2.,
+        "village" => 1.5,
+        _ => 1.,
+    };
+    let is_town = match params.settlement_kind {
+        "town" => true,
+        _ => false,
+    };
+    let is_fortified = matches!(params.settlement_kind, "town" | "hillfort");
+    let pressures: &[(PressureType, f64)] = match params.settlement_kind {
+        "village" => &[(PressureType::Farmer, 1.0)],
+        "town" => &[(PressureType::Merchant, 0.5)],
+        _ => &[],
+    };
+
+    CreateEntity {
+        name: params.name,
+        kind_name: "Location",
+        agent: Some(CreateAgent {
+            tag: "",
+            flags: &[],
+            political_parent: Some(params.faction),
+            family_parent: None,
+            cash: 0.,
+            personality: Personality::default(),
+        }),
+        location: Some(CreateLocation {
+            site: params.site,
+            prosperity: params.prosperity,
+            is_town,
+            is_fortified,
+            tokens: params.tokens,
+        }),
+        party: Some(CreateParty {
+            site: params.site,
+            image: params.settlement_kind,
+            size,
+            movement_speed: 0.,
+            layer: 0,
+            carry_capacity: 0.,
+            boats: false,
+            off_road: false,
+            ship: false,
+            troops: &[],
+        }),
+        pressure_agent: Some(CreatePressureAgent {
+            pressures,
+            triggers: SETTLEMENT_TRIGGERS,
+        }),
+        ..Default::default()
     }
 }
 
@@ -782,26 +2443,47 @@ fn process_entity_create_commands<'a>(
                 entity,
                 flags: AgentFlags::new(args.flags),
                 cash: args.cash,
+                personality: args.personality,
+                tax_rate: DEFAULT_TAX_RATE,
+                tax_revenue: 0.,
+                toll_rate: DEFAULT_TOLL_RATE,
+                toll_revenue: 0.,
             });
 
-            if !args.tag.is_empty() {
-                sim.agents.tags.insert(args.tag, id);
+            if !args.tag.is_empty()
+                && is_tag_free(
+                    &sim.tags.agents,
+                    TagKind::Agent,
+                    args.tag,
+                    &mut sim.warnings,
+                )
+            {
+                sim.tags.agents.insert(args.tag, id);
             }
 
             if let Some(parent) = args.political_parent {
-                match sim.agents.tags.lookup(parent) {
+                match sim.tags.agents.lookup(parent) {
                     Some(parent) => sim.agents.political_hierarchy.insert(parent, id),
-                    None => println!("Unknown agent with tag '{parent}'"),
+                    None => sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Agent,
+                        tag: parent.to_string(),
+                    }),
                 }
             }
+            if args.flags.contains(&AgentFlag::IsCharacter) {
+                crate::characters::register(sim, id, args.family_parent);
+            }
             id
         });
 
         let party = command.party.and_then(|args| {
-            let (position, pos) = match sim.sites.lookup(args.site) {
-                Some((id, data)) => (GridCoord::at(id), data.pos),
+            let (position, pos) = match sim.tags.sites.lookup(args.site) {
+                Some(id) => (GridCoord::at(id), sim.sites[id].pos),
                 None => {
-                    println!("Undefined site '{}'", args.site);
+                    sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Site,
+                        tag: args.site.to_string(),
+                    });
                     return None;
                 }
             };
@@ -817,15 +2499,30 @@ fn process_entity_create_commands<'a>(
                 movement_speed: args.movement_speed,
                 movement: PartyMovement::default(),
                 good_stock: GoodStock::new(&sim.good_types),
+                carry_capacity: args.carry_capacity,
+                boats: args.boats,
+                off_road: args.off_road,
+                ship: args.ship,
+                troops: None,
+                forced_march: false,
             });
+            if !args.troops.is_empty() {
+                let troops = sim.tokens.add_container();
+                add_tokens(sim, troops, args.troops);
+                sim.parties[id].troops = Some(troops);
+            }
+            sim.party_grid.insert_point(id, (pos.x, pos.y));
             Some(id)
         });
 
         let location = command.location.and_then(|args| {
-            let site = match sim.sites.lookup(args.site) {
-                Some((id, _)) => id,
+            let site = match sim.tags.sites.lookup(args.site) {
+                Some(id) => id,
                 None => {
-                    println!("Undefined site '{}'", args.site);
+                    sim.warnings.push(SimWarning::UndefinedTag {
+                        kind: TagKind::Site,
+                        tag: args.site.to_string(),
+                    });
                     return None;
                 }
             };
@@ -833,23 +2530,15 @@ fn process_entity_create_commands<'a>(
             let party = match party {
                 Some(id) => id,
                 None => {
-                    println!("Location creation requires party");
+                    sim.warnings.push(SimWarning::LocationWithoutParty {
+                        name: command.name.to_string(),
+                    });
                     return None;
                 }
             };
 
             let tokens = sim.tokens.add_container();
-            for create in args.tokens {
-                match sim.tokens.types.lookup(create.tag) {
-                    Some(typ) => {
-                        sim.tokens.add_token(tokens, typ, create.size);
-                    }
-                    None => {
-                        println!("Unknown token type '{}'", create.tag);
-                        continue;
-                    }
-                }
-            }
+            add_tokens(sim, tokens, args.tokens);
 
             let mut influence_sources = vec![];
 
@@ -860,6 +2549,13 @@ fn process_entity_create_commands<'a>(
                 });
             }
 
+            if args.is_fortified {
+                influence_sources.push(InfluenceSource {
+                    kind: InfluenceKind::Security,
+                    population_modifier: 0.2,
+                });
+            }
+
             let location = sim.locations.insert(LocationData {
                 entity,
                 party,
@@ -877,74 +2573,155 @@ fn process_entity_create_commands<'a>(
             Some(location)
         });
 
+        let port = location.is_some_and(|location| sim.sites.is_port(sim.locations[location].site));
         let pressure_agent = command.pressure_agent.map(|args| {
+            let mut innate_growth: Vec<_> = args.pressures.to_vec();
+            // Ports send out merchant ships, even when no market is held there
+            if port
+                && !innate_growth
+                    .iter()
+                    .any(|&(kind, _)| kind == PressureType::Merchant)
+            {
+                innate_growth.push((PressureType::Merchant, PORT_MERCHANT_GROWTH));
+            }
             sim.pressurables.insert(Pressureble {
                 entity,
                 current: PressureMap::default(),
-                innate_growth: args.pressures.iter().copied().collect(),
+                innate_growth,
+                triggers: args.triggers.to_vec(),
             })
         });
 
+        let base = command.behavior.as_ref().and_then(|args| args.base);
         let behavior = command.behavior.map(|args| {
-            let goal = match args.base {
-                Some(base) => Goal::LocalTrade { base },
-                None => Goal::Idle,
+            let goal = match (args.migration, args.route, args.base) {
+                (Some(migration), _, _) => Goal::Migrate(migration),
+                (None, Some(route), _) => Goal::RouteTrade { route },
+                (None, None, Some(base)) if args.rebel => Goal::Rebel { base },
+                (None, None, Some(base)) if args.smuggle => Goal::Smuggle { base },
+                (None, None, Some(base)) if args.arbitrage => Goal::ArbitrageTrade { base },
+                (None, None, Some(base)) => Goal::LocalTrade { base },
+                (None, None, None) => Goal::Idle,
             };
+            if let (Some(route), Some(party)) = (args.route, party)
+                && let Some(route) = sim.routes.routes.get_mut(route)
+            {
+                route.caravans.push(party);
+            }
             sim.beahviors.insert(Behavior {
                 entity,
+                stance: args.stance.unwrap_or_else(|| {
+                    let aggressiveness = agent
+                        .map_or(Personality::default().aggressiveness, |agent| {
+                            personality_of(&sim.agents, agent).aggressiveness
+                        });
+                    crate::stances::Stance::of_goal(&goal, aggressiveness)
+                }),
+                vocation: goal.clone(),
                 goal,
                 ..Default::default()
             })
         });
 
+        sim.events.push(SimEvent::EntityCreated {
+            id: ObjectId(ObjectHandle::Entity(entity)),
+        });
+
+        let entity_id = entity;
         let entity = &mut sim.entities[entity];
         entity.agent = agent;
         entity.party = party;
         entity.location = location;
         entity.pressure_agent = pressure_agent;
         entity.behavior = behavior;
+
+        let name = &sim.entities[entity_id].name;
+        let parent = agent.and_then(|id| sim.characters.parent(id));
+        let text = if agent.is_some_and(|id| sim.agents[id].flags.get(AgentFlag::IsFaction)) {
+            Some(format!("the realm of {name} was proclaimed"))
+        } else if let Some(parent) = parent {
+            let parent_name = &sim.entities[sim.agents[parent].entity].name;
+            Some(format!("{name} was born to {parent_name}"))
+        } else if location.is_some() {
+            Some(format!("the settlement of {name} was founded"))
+        } else if let Some(base) = base {
+            let base_name = sim
+                .parties
+                .get(base)
+                .map(|party| sim.entities[party.entity].name.as_str())
+                .unwrap_or("the wilds");
+            Some(format!("{name} set out from {base_name}"))
+        } else if party.is_some() {
+            Some(format!("{name} took to the roads"))
+        } else {
+            None
+        };
+        if let Some(text) = text {
+            sim.record_chronicle(entity_id, text);
+        }
+        crate::lore::record(sim, entity_id);
     }
 }
 
 mod tick_behaviors {
-    use slotmap::Key;
-
     #[derive(Default)]
     pub(super) struct Effects {
         pub transfers: Vec<super::transfer::Event>,
         pub trade_events: Vec<super::trade::Event>,
+        pub ambitions: Vec<crate::ambitions::Event>,
+        pub arrivals: Vec<crate::migration::Arrival>,
+        pub deliveries: Vec<crate::contracts::Delivery>,
+        pub revolts: Vec<crate::unrest::Revolt>,
     }
 
     use super::*;
+    use crate::ambitions::Ambition;
+
     pub(super) fn tick_behaviors(sim: &mut Simulation) -> Effects {
         let mut effects = Effects::default();
 
+        crate::stances::perceive(sim);
         let mut behaviors = std::mem::take(&mut sim.beahviors);
         for (_, behavior) in &mut behaviors {
             let my_entity = &sim.entities[behavior.entity];
             let my_party = &sim.parties[my_entity.party.unwrap()];
 
-            behavior.task = behavior
-                .task
-                .take()
-                .filter(|task| {
-                    let validation = validate_task(sim, task, my_party);
-                    if validation.is_over {
-                        on_task_complete(sim, task, &validation, behavior, &mut effects);
-                    }
-                    !validation.is_over
-                })
-                .or_else(|| decide_task(sim, &behavior.goal, &behavior.memory));
+            // Get through the tasks already done where the party stands
+            while let Some(&task) = behavior.plan.front() {
+                let validation = validate_task(sim, &task, my_party);
+                if !validation.is_over {
+                    break;
+                }
+                behavior.plan.pop_front();
+                on_task_complete(sim, &task, &validation, behavior, &mut effects);
+            }
+            if behavior.plan.is_empty() {
+                behavior.plan = plan(sim, &behavior.goal, my_party)
+                    .unwrap_or_default()
+                    .into();
+            }
         }
 
         for (_, behavior) in &behaviors {
+            // Idle characters are left to the orders of the player
+            if behavior.goal == Goal::Idle {
+                continue;
+            }
             let party = sim.entities[behavior.entity].party.unwrap();
             let party_data = &mut sim.parties[party];
-            party_data.movement.target = behavior
-                .task
-                .as_ref()
-                .filter(|x| !x.target.is_null())
-                .map(|x| MovementTarget::Party(x.target));
+            // Those reacting to a party they saw head for it instead
+            if party_data.movement.reaction.is_some() {
+                continue;
+            }
+            party_data.movement.target =
+                behavior
+                    .plan
+                    .front()
+                    .and_then(|task| match task.destination {
+                        Destination::Here => None,
+                        Destination::Party(party) => Some(MovementTarget::Party(party)),
+                        Destination::Site(site) => Some(MovementTarget::Site(site)),
+                    });
         }
 
         sim.beahviors = behaviors;
@@ -960,18 +2737,20 @@ mod tick_behaviors {
 
     fn validate_task(sim: &Simulation, task: &Task, my_party: &PartyData) -> TaskValidation {
         let mut result = TaskValidation::default();
-
-        if task.target.is_null() {
-            result.is_over = true;
-        }
-
-        if let Some(target) = sim.parties.get(task.target) {
-            if !task.continue_after_arrival && my_party.position == target.position {
-                result.is_over = true;
-                result.at_target = Some(task.target)
-            }
+        match task.destination {
+            Destination::Here => result.is_over = true,
+            Destination::Site(site) => result.is_over = my_party.position == GridCoord::at(site),
+            Destination::Party(target) => match sim.parties.get(target) {
+                // Whatever was to be done there is off
+                None => result.is_over = true,
+                Some(data) => {
+                    if crate::interception::in_contact(&sim.sites, my_party, data) {
+                        result.is_over = true;
+                        result.at_target = Some(target);
+                    }
+                }
+            },
         }
-
         result
     }
 
@@ -982,81 +2761,199 @@ mod tick_behaviors {
         behavior: &mut Behavior,
         effects: &mut Effects,
     ) {
-        behavior.memory.state = task.on_complete_state;
-
-        if task.despawn_on_complete {
-            behavior.request_despawn = true;
+        let entity = &sim.entities[behavior.entity];
+        let location = validation
+            .at_target
+            .and_then(|target| sim.parties[target].location);
+        match task.action {
+            Action::Trade { route } => {
+                if let Some(location) = location {
+                    effects.trade_events.push(trade::Event {
+                        party: entity.party.unwrap(),
+                        agent: entity.agent.unwrap(),
+                        location,
+                        route,
+                        smuggler: matches!(behavior.goal, Goal::Smuggle { .. }),
+                    });
+                }
+            }
+            Action::Unload => {
+                if let Some(target) = validation.at_target {
+                    effects.transfers.push(super::transfer::Event {
+                        source: entity.party.unwrap(),
+                        target,
+                    });
+                }
+            }
+            Action::Disband => behavior.request_despawn = true,
+            Action::PursueAmbition => {
+                if let Goal::Ambition { ambition, .. } = behavior.goal {
+                    effects.ambitions.push(crate::ambitions::Event {
+                        entity: behavior.entity,
+                        ambition,
+                    });
+                }
+            }
+            Action::Settle => {
+                if validation.at_target.is_some()
+                    && let Goal::Migrate(migration) = behavior.goal
+                {
+                    effects.arrivals.push(crate::migration::Arrival {
+                        entity: behavior.entity,
+                        migration,
+                    });
+                }
+            }
+            Action::Deliver => {
+                if validation.at_target.is_some()
+                    && let Some(contract) = behavior.contract
+                {
+                    effects.deliveries.push(crate::contracts::Delivery {
+                        entity: behavior.entity,
+                        contract,
+                    });
+                }
+            }
+            Action::Revolt => {
+                if let Some(location) = location {
+                    effects.revolts.push(crate::unrest::Revolt {
+                        entity: behavior.entity,
+                        location,
+                    });
+                }
+            }
+            Action::Shelter => {}
         }
+    }
 
-        if task.trade_with_target
-            && let Some(target) = validation.at_target
-            && let Some(location) = sim.parties[target].location
-        {
-            let entity = &sim.entities[behavior.entity];
-            effects.trade_events.push(trade::Event {
-                party: entity.party.unwrap(),
-                agent: entity.agent.unwrap(),
-                location,
-            });
-        }
+    // Daily cost merchants reckon with for each day on the road
+    const MERCHANT_DAILY_COST: f64 = 25.;
 
-        if task.give_away_to_target
-            && let Some(target) = validation.at_target
-        {
-            let source = sim.entities[behavior.entity].party.unwrap();
-            effects
-                .transfers
-                .push(super::transfer::Event { source, target });
-        }
+    // The market where a cargo from the base sells for the most over what it
+    // costs there, less the cost of the journey there and back, by the ways
+    // the merchant travels. None when no market is worth the trip.
+    fn most_profitable_market(
+        sim: &Simulation,
+        base: PartyId,
+        merchant: &PartyData,
+    ) -> Option<PartyId> {
+        let base_party = sim.parties.get(base)?;
+        let home = &sim.locations[base_party.location?].market;
+        let start = base_party.position.as_site()?;
+        // Guilds hold their members back from the riskier runs while short
+        let min_profit = crate::guilds::min_profit(sim, base_party.location?);
+        sim.locations
+            .values()
+            .filter(|location| location.party != base)
+            .filter_map(|location| {
+                let (_, cost) = sim.sites.astar_with(
+                    start,
+                    location.site,
+                    &BTreeSet::new(),
+                    merchant.mobility(),
+                )?;
+                let ticks = cost / (merchant.movement_speed * BASE_SPEED);
+                let days = (ticks / crate::date::Date::TICKS_IN_DAY as f32) as f64;
+                // The widest spread on a full load of one good
+                let gain = sim
+                    .good_types
+                    .iter()
+                    .map(|(good_id, good)| {
+                        let spread =
+                            location.market.goods[good_id].price - home.goods[good_id].price;
+                        let load = if good.weight > 0. {
+                            merchant.carry_capacity / good.weight
+                        } else {
+                            f64::MAX
+                        };
+                        spread.max(0.) * load.min(home.goods[good_id].stock)
+                    })
+                    .fold(0., f64::max);
+                let profit = gain - 2. * days * MERCHANT_DAILY_COST;
+                (profit > min_profit).then_some((location.party, profit))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(party, _)| party)
     }
 
-    fn decide_task(sim: &Simulation, goal: &Goal, memory: &BehaviorMemory) -> Option<Task> {
+    // The tasks that take a party towards its goal, from where it stands now.
+    // None when there is nothing to be done for the time being.
+    fn plan(sim: &Simulation, goal: &Goal, my_party: &PartyData) -> Option<Vec<Task>> {
+        let trade = Action::Trade { route: None };
         match goal {
             Goal::Idle => None,
-            &Goal::LocalTrade { base } => {
-                const STATE_BEGIN: usize = 0;
-                const STATE_OUTGOING: usize = 1;
-                const STATE_RETURING: usize = 2;
-                let base_party = sim.parties.get(base)?;
-                // Are we on the outgoing or the return leg?
-                // Go home if we are not home
-                Some(
-                    if memory.state == STATE_BEGIN || memory.state == STATE_RETURING {
-                        // If we already had traded once (by completing the outgoing task), mark for death
-                        let is_returning = memory.state == STATE_RETURING;
-                        let on_complete_state = if !is_returning {
-                            STATE_OUTGOING
-                        } else {
-                            STATE_RETURING
-                        };
-                        Task {
-                            target: base,
-                            give_away_to_target: is_returning,
-                            trade_with_target: !is_returning,
-                            despawn_on_complete: is_returning,
-                            on_complete_state,
-                            ..Default::default()
-                        }
-                    } else {
-                        // Set out from home
-                        let site = base_party.position.as_site()?;
-                        let target = sim.sites[site]
-                            .influences
-                            .top_source(InfluenceKind::Market)?;
-                        Task {
-                            target,
-                            on_complete_state: STATE_RETURING,
-                            trade_with_target: true,
-                            ..Default::default()
-                        }
-                    },
-                )
+            &Goal::LocalTrade { base }
+            | &Goal::Smuggle { base }
+            | &Goal::ArbitrageTrade { base } => {
+                let site = sim.parties.get(base)?.position.as_site()?;
+                let market = match goal {
+                    Goal::ArbitrageTrade { .. } => most_profitable_market(sim, base, my_party)?,
+                    _ => sim.sites[site]
+                        .influences
+                        .top_source(InfluenceKind::Market)?,
+                };
+                // Buy at home, sell at the market, and bring the takings home
+                let mut plan = vec![Task::visit(base, trade), Task::visit(market, trade)];
+                plan.extend(Task::homecoming(base));
+                Some(plan)
+            }
+            &Goal::RouteTrade { route } => {
+                let route_data = sim.routes.routes.get(route)?;
+                // Shuttle between the two ends of the route, trading at both
+                let trade = Action::Trade { route: Some(route) };
+                Some(vec![
+                    Task::visit(sim.locations.get(route_data.source)?.party, trade),
+                    Task::visit(sim.locations.get(route_data.destination)?.party, trade),
+                ])
             }
+            &Goal::Rebel { base } => {
+                // March on the town whose market the home looks to, which is
+                // the home itself for a town, then bring the spoils home and
+                // go back to the fields
+                let site = sim.parties.get(base)?.position.as_site()?;
+                let target = sim.sites[site]
+                    .influences
+                    .top_source(InfluenceKind::Market)
+                    .unwrap_or(base);
+                let mut plan = vec![Task::visit(target, Action::Revolt)];
+                plan.extend(Task::homecoming(base));
+                Some(plan)
+            }
+            &Goal::ReturnHome { base } => Some(Task::homecoming(base).to_vec()),
+            &Goal::Flee { refuge } => Some(vec![Task::visit(refuge, Action::Shelter)]),
+            &Goal::Migrate(migration) => Some(match sim.locations.get(migration.destination) {
+                Some(destination) => vec![
+                    Task::visit(destination.party, Action::Settle),
+                    Task::visit(destination.party, Action::Disband),
+                ],
+                // Migrants whose destination is gone scatter on the spot
+                None => vec![Task {
+                    destination: Destination::Here,
+                    action: Action::Disband,
+                }],
+            }),
+            &Goal::Ambition { ambition, .. } => match ambition {
+                Ambition::Title { location } => Some(vec![Task::visit(
+                    sim.locations.get(location)?.party,
+                    Action::PursueAmbition,
+                )]),
+                Ambition::Settlement { site } => Some(vec![Task {
+                    destination: Destination::Site(site),
+                    action: Action::PursueAmbition,
+                }]),
+                // Peddle from one settlement to the next, in turn
+                Ambition::Wealth => Some(
+                    sim.locations
+                        .values()
+                        .map(|location| Task::visit(location.party, trade))
+                        .collect(),
+                ),
+            },
         }
     }
 }
 
-mod transfer {
+pub(crate) mod transfer {
     use super::*;
     use crate::PartyId;
 
@@ -1066,7 +2963,8 @@ mod transfer {
         pub target: PartyId,
     }
 
-    pub fn resolve(sim: &mut Simulation, events: impl IntoIterator<Item = Event>) {
+    // Parties handing over goods give away all they carry
+    pub(super) fn resolve(sim: &mut Simulation, events: impl IntoIterator<Item = Event>) {
         for event in events {
             let source_data = &mut sim.parties[event.source];
             let bundle = source_data.good_stock.amount.clone();
@@ -1086,37 +2984,210 @@ mod transfer {
             }
         }
     }
+
+    /// Unloads some of a good into the market of the location the party stands
+    /// at, or loads it from there when the amount is negative. Parties only
+    /// load from the locations of their own realm.
+    pub(crate) fn exchange(sim: &mut Simulation, party: PartyId, good: GoodId, amount: f64) {
+        let Some(location) = location_under(sim, party) else {
+            return;
+        };
+        let party_data = &sim.parties[party];
+        let stock = sim.locations[location].market.goods[good].stock;
+        let moved = if amount >= 0. {
+            amount.min(party_data.good_stock[good])
+        } else {
+            if !can_load(sim, party, location) {
+                return;
+            }
+            let weight = sim.good_types[good].weight;
+            let room = party_data.carry_capacity - party_data.good_stock.weight(&sim.good_types);
+            let mut load = (-amount).min(stock);
+            if weight > 0. {
+                load = load.min(room / weight);
+            }
+            -load.max(0.)
+        };
+        if moved == 0. {
+            return;
+        }
+        sim.parties[party].good_stock[good] -= moved;
+        let in_market = &mut sim.locations[location].market.goods[good];
+        in_market.stock += moved;
+        in_market.stock_delta += moved;
+    }
+
+    /// The location whose site a party stands on, other than its own
+    pub(crate) fn location_under(sim: &Simulation, party: PartyId) -> Option<LocationId> {
+        let party_data = sim.parties.get(party)?;
+        let site = party_data.position.as_site()?;
+        let location = sim.sites.get(site)?.location?;
+        (party_data.location != Some(location)).then_some(location)
+    }
+
+    pub(crate) fn can_load(sim: &Simulation, party: PartyId, location: LocationId) -> bool {
+        let entity = &sim.entities[sim.parties[party].entity];
+        let realm = entity
+            .agent
+            .and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent));
+        realm.is_some() && realm == crate::autonomy::treasury_of(sim, location)
+    }
 }
 
 mod trade {
+    use std::collections::BTreeMap;
+
+    use rand::Rng;
+
     use super::*;
+    use crate::chronicle::faction_of;
+    use crate::diplomacy::{EMBARGO_TARIFF, SMUGGLING_CHANCE};
+    use crate::stockpile::STOCKPILE_PREMIUM;
 
     #[derive(Clone, Copy)]
     pub(super) struct Event {
         pub party: PartyId,
         pub agent: AgentId,
         pub location: LocationId,
+        pub route: Option<TradeRouteId>,
+        pub smuggler: bool,
     }
 
-    pub fn resolve(sim: &mut Simulation, events: impl IntoIterator<Item = Event>) {
-        let scratch = &mut Scratch::new(&sim.good_types);
+    // Clearing prices rise at most this many times the posted price before the
+    // goods are rationed
+    const MAX_CLEARING_MARKUP: f64 = 2.;
+    // Steps of the search for the clearing price
+    const CLEARING_ITERATIONS: usize = 20;
+
+    /// Clears the markets the traders visited this tick. Everything traders
+    /// offer at a location goes to market together with its stock, and each
+    /// good then changes hands at a single price, so the order traders arrive
+    /// in does not matter.
+    pub(super) fn resolve(sim: &mut Simulation, events: impl IntoIterator<Item = Event>) {
         let mut traders = collect_traders(sim, events);
 
-        // Process
+        // Intents
         for trader in &mut traders {
-            let market = &mut sim.locations[trader.event.location].market;
-            resolve_trade(&sim.good_types, trader, market, scratch);
+            // Route caravans report what they bring to the destination
+            if let Some(route) = trader.event.route
+                && let Some(route_data) = sim.routes.routes.get(route)
+                && route_data.destination == trader.event.location
+            {
+                let delivered = trader.goods[route_data.good].quantity;
+                sim.routes.record_delivery(route, delivered);
+            }
+            if trader.customs.is_some() {
+                trader.smuggling = sim.rng.0.gen_bool(trader.evasion_chance.clamp(0., 1.));
+                if trader.event.smuggler && !trader.smuggling {
+                    confiscate(sim, trader);
+                }
+            }
+            let market = &sim.locations[trader.event.location].market;
+            place_sales(&sim.good_types, trader, market);
+        }
+
+        // Clearing, one market at a time
+        let mut by_location: BTreeMap<LocationId, Vec<usize>> = BTreeMap::new();
+        for (idx, trader) in traders.iter().enumerate() {
+            by_location
+                .entry(trader.event.location)
+                .or_default()
+                .push(idx);
+        }
+        for (location, members) in by_location {
+            let treasury = crate::autonomy::treasury_of(sim, location);
+            let market = &mut sim.locations[location].market;
+            // Stock kept back from traders, and how far short of it the
+            // market is before they sell
+            let reserved: SecondaryMap<GoodId, f64> = market
+                .goods
+                .keys()
+                .map(|good_id| (good_id, sim.stockpiles.target(location, good_id)))
+                .collect();
+            let shortfall: SecondaryMap<GoodId, f64> = market
+                .goods
+                .iter()
+                .map(|(good_id, in_market)| {
+                    (good_id, (reserved[good_id] - in_market.stock).max(0.))
+                })
+                .collect();
+            // What is on offer once every trader has put up its goods
+            let mut pool: SecondaryMap<GoodId, f64> = market
+                .goods
+                .iter()
+                .map(|(good_id, in_market)| (good_id, in_market.stock))
+                .collect();
+            for &idx in &members {
+                for (good_id, in_trader) in &traders[idx].goods {
+                    pool[good_id] += in_trader.sold;
+                }
+            }
+            for (good_id, pool) in &mut pool {
+                *pool = (*pool - reserved[good_id]).max(0.);
+            }
+            for &idx in &members {
+                place_bids(&sim.good_types, &mut traders[idx], market, &pool);
+            }
+            let clearing: SecondaryMap<_, _> = sim
+                .good_types
+                .keys()
+                .map(|good_id| {
+                    let posted = market.goods[good_id].price;
+                    let cleared =
+                        clearing_price(good_id, &traders, &members, posted, pool[good_id]);
+                    (good_id, cleared)
+                })
+                .collect();
+            settle(
+                &sim.good_types,
+                &mut traders,
+                &members,
+                market,
+                &reserved,
+                &clearing,
+            );
+            if let Some(treasury) = treasury {
+                pay_premiums(sim, treasury, &mut traders, &members, &shortfall, &clearing);
+            }
         }
 
         // Write back
         for trader in traders {
             let agent_data = &mut sim.agents[trader.event.agent];
-            let party_data = &mut sim.parties[trader.event.party];
-
             agent_data.cash = trader.cash;
+            if let Some(customs) = trader.customs {
+                sim.agents[customs].cash += trader.tariffs;
+            }
+            if let Some((collector, _)) = trader.tolls {
+                let collector_data = &mut sim.agents[collector];
+                collector_data.cash += trader.tolls_paid;
+                collector_data.toll_revenue += trader.tolls_paid;
+            }
+            if let Some(faction) = trader.faction {
+                sim.ledger
+                    .record_trade(faction, trader.sales, trader.purchases);
+            }
+            let tolls = trader
+                .tolls
+                .map(|(collector, _)| (collector, trader.tolls_paid));
+            crate::guilds::settle_trade(sim, trader.event.agent, trader.sales, tolls);
+            sim.characters.practise_trade(trader.event.agent);
+            let party_data = &mut sim.parties[trader.event.party];
             for good_id in sim.good_types.keys() {
                 party_data.good_stock[good_id] = trader.goods[good_id].quantity;
             }
+
+            let party_entity = party_data.entity;
+            let location_entity = sim.locations[trader.event.location].entity;
+            sim.events.push(SimEvent::TradeResolved {
+                party: ObjectId(ObjectHandle::Entity(party_entity)),
+                location: ObjectId(ObjectHandle::Entity(location_entity)),
+            });
+            let text = format!(
+                "{} traded at {}",
+                sim.entities[party_entity].name, sim.entities[location_entity].name
+            );
+            sim.record_history(party_entity, text);
         }
     }
 
@@ -1125,22 +3196,81 @@ mod trade {
             .into_iter()
             .map(|event| {
                 let cash = sim.agents[event.agent].cash;
+                let personality = personality_of(&sim.agents, event.agent);
                 let party_data = &sim.parties[event.party];
-                let goods = sim
+                // At the source of a route, caravans only load the good it carries
+                let route_good = event
+                    .route
+                    .and_then(|route| sim.routes.routes.get(route))
+                    .filter(|route| route.source == event.location)
+                    .map(|route| route.good);
+
+                // Embargoes of the faction owning the market
+                let location = &sim.locations[event.location];
+                let host = sim.entities[location.entity]
+                    .agent
+                    .and_then(|agent| faction_of(&sim.agents, agent));
+                let guest = faction_of(&sim.agents, event.agent);
+                let refused = host.is_some_and(|host| sim.relations.refuses(host, guest));
+                // Smugglers deal in what the market turns away, at their own risk
+                let embargoed = |good_id| {
+                    (refused && event.smuggler)
+                        || host
+                            .is_some_and(|host| sim.relations.embargoes_good(host, guest, good_id))
+                };
+                let refused = refused && !event.smuggler;
+
+                let goods: SecondaryMap<_, _> = sim
                     .good_types
                     .keys()
                     .map(|good_id| {
                         let quantity = party_data.good_stock[good_id];
                         let data = TraderGood {
                             quantity,
-                            can_sell: true,
-                            can_buy: true,
+                            can_sell: !refused,
+                            can_buy: !refused && route_good.is_none_or(|good| good == good_id),
+                            embargoed: embargoed(good_id),
+                            ..Default::default()
                         };
                         (good_id, data)
                     })
                     .collect();
 
-                Trader { cash, goods, event }
+                // Foreign parties pay tolls on all they sell and buy
+                let tolls = host
+                    .filter(|&host| guest != Some(host))
+                    .filter(|&host| !crate::guilds::exempts(sim, event.agent, host))
+                    .map(|host| (host, sim.agents[host].toll_rate))
+                    .filter(|&(_, rate)| rate > 0.);
+
+                let customs = host.filter(|_| goods.values().any(|good| good.embargoed));
+                let evasion_chance = customs
+                    .map(|host| {
+                        let security = crate::espionage::security_of(sim, location.site, host);
+                        SMUGGLING_CHANCE / (1. + security)
+                    })
+                    .unwrap_or(0.);
+
+                Trader {
+                    cash,
+                    goods,
+                    carry_capacity: party_data.carry_capacity,
+                    event,
+                    trade_focus: (personality.trade_focus * sim.characters.greed(event.agent))
+                        .clamp(0., 1.),
+                    income_handicap: personality.income_handicap
+                        * sim.characters.bargaining(event.agent),
+                    customs,
+                    evasion_chance,
+                    smuggling: false,
+                    tariffs: 0.,
+                    faction: guest,
+                    tolls,
+                    tolls_paid: 0.,
+                    sales: 0.,
+                    purchases: 0.,
+                    proceeds: 0.,
+                }
             })
             .collect()
     }
@@ -1150,89 +3280,283 @@ mod trade {
         quantity: f64,
         can_sell: bool,
         can_buy: bool,
+        // Put up for sale this tick
+        sold: f64,
+        // Cash set aside for buying the good, and the most the cargo holds of it
+        bid: f64,
+        most: f64,
+        // Taxed when sold and not for sale, unless smuggled
+        embargoed: bool,
     }
 
     struct Trader {
         cash: f64,
         goods: SecondaryMap<GoodId, TraderGood>,
+        carry_capacity: f64,
         event: Event,
+        trade_focus: f64,
+        income_handicap: f64,
+        // Faction collecting tariffs on embargoed goods
+        customs: Option<AgentId>,
+        evasion_chance: f64,
+        smuggling: bool,
+        tariffs: f64,
+        faction: Option<AgentId>,
+        // Faction collecting tolls from the trader, and at what rate
+        tolls: Option<(AgentId, f64)>,
+        tolls_paid: f64,
+        // Expected from the sales, at the posted prices
+        proceeds: f64,
+        // Value of the goods sold and bought, at the clearing prices
+        sales: f64,
+        purchases: f64,
     }
 
-    struct Scratch {
-        weights: SecondaryMap<GoodId, f64>,
+    impl Trader {
+        // Share of the value of its sales of a good the trader pays in tariffs
+        fn tariff_on(&self, good_id: GoodId) -> f64 {
+            if self.goods[good_id].embargoed && !self.smuggling {
+                EMBARGO_TARIFF
+            } else {
+                0.
+            }
+        }
+
+        fn toll_rate(&self) -> f64 {
+            self.tolls.map_or(0., |(_, rate)| rate)
+        }
+
+        // Quantity of a good the trader asks for at a price, tolls included
+        fn asked(&self, good_id: GoodId, price: f64) -> f64 {
+            asked(&self.goods[good_id], price * (1. + self.toll_rate()))
+        }
     }
 
-    impl Scratch {
-        fn new(good_types: &GoodTypes) -> Self {
-            Self {
-                weights: good_types.keys().map(|x| (x, 0.0)).collect(),
+    // The treasury of a market short of its stockpile pays the sellers of the
+    // goods it lacks a premium on what makes up the shortfall, as far as its
+    // cash goes
+    fn pay_premiums(
+        sim: &mut Simulation,
+        treasury: AgentId,
+        traders: &mut [Trader],
+        members: &[usize],
+        shortfall: &SecondaryMap<GoodId, f64>,
+        clearing: &SecondaryMap<GoodId, (f64, f64)>,
+    ) {
+        for (good_id, &short) in shortfall {
+            let sold: f64 = members
+                .iter()
+                .map(|&idx| traders[idx].goods[good_id].sold)
+                .sum();
+            if short <= 0. || sold <= 0. {
+                continue;
+            }
+            let (price, _) = clearing[good_id];
+            let covered = (short / sold).min(1.);
+            for &idx in members {
+                let trader = &mut traders[idx];
+                let premium = trader.goods[good_id].sold * covered * price * STOCKPILE_PREMIUM;
+                let premium = premium.min(sim.agents[treasury].cash.max(0.));
+                sim.agents[treasury].cash -= premium;
+                trader.cash += premium;
             }
         }
     }
 
-    fn resolve_trade(
-        goods: &GoodTypes,
-        trader: &mut Trader,
-        market: &mut Market,
-        scratch: &mut Scratch,
-    ) {
-        // Decide what to buy and what to sell
-        scratch.weights.values_mut().for_each(|x| *x = 0.0);
+    // Customs seize the embargoed goods of smugglers they catch, and hand them
+    // to the market
+    fn confiscate(sim: &mut Simulation, trader: &mut Trader) {
+        let market = &mut sim.locations[trader.event.location].market;
+        let mut seized = vec![];
+        for (good_id, in_trader) in trader.goods.iter_mut() {
+            if !in_trader.embargoed || in_trader.quantity <= 0. {
+                continue;
+            }
+            let in_market = &mut market.goods[good_id];
+            in_market.stock += in_trader.quantity;
+            in_market.stock_delta += in_trader.quantity;
+            seized.push(format!(
+                "{:1.0} {}",
+                in_trader.quantity,
+                sim.good_types[good_id].name.to_lowercase()
+            ));
+            in_trader.quantity = 0.;
+        }
+        if seized.is_empty() {
+            return;
+        }
 
-        // Perform sales
+        let party_entity = sim.parties[trader.event.party].entity;
+        let location_entity = sim.locations[trader.event.location].entity;
+        let text = format!(
+            "the guards of {} seized {} from {}",
+            sim.entities[location_entity].name,
+            seized.join(" and "),
+            sim.entities[party_entity].name
+        );
+        sim.record_chronicle_about(&[party_entity, location_entity], text);
+    }
+
+    // Puts up for sale everything the trader can sell. The proceeds are only
+    // known once the market clears, so the budget counts them at the posted
+    // price, which the clearing price never falls below.
+    fn place_sales(goods: &GoodTypes, trader: &mut Trader, market: &Market) {
         for good_id in goods.keys() {
             let in_trader = &mut trader.goods[good_id];
             if !in_trader.can_sell {
                 continue;
             }
-
-            let in_market = &mut market.goods[good_id];
-
-            let quantity = in_trader.quantity;
-            let value = in_market.price * quantity;
-            trader.cash += value;
-
-            in_market.stock += quantity;
-            in_market.stock_delta += quantity;
-            in_trader.quantity -= quantity;
+            in_trader.sold = in_trader.quantity;
+            in_trader.quantity = 0.;
+            let value = in_trader.sold * market.goods[good_id].price;
+            let levied = trader.tariff_on(good_id) + trader.toll_rate();
+            trader.proceeds += value * (1. - levied).max(0.) * trader.income_handicap;
         }
+    }
 
-        // Perform buys
-        // First calculate how much money the trader wants to spend on each goods
+    // Splits the budget of the trader between the goods on offer, bidding for
+    // as much of each as the cash set aside buys and the cargo holds
+    fn place_bids(
+        goods: &GoodTypes,
+        trader: &mut Trader,
+        market: &Market,
+        pool: &SecondaryMap<GoodId, f64>,
+    ) {
+        let mut weights: SecondaryMap<GoodId, f64> = SecondaryMap::new();
         let mut total_weight = 0.0;
         for good_id in goods.keys() {
             let in_trader = &trader.goods[good_id];
-            let in_market = &market.goods[good_id];
-
-            let want_weight = if in_trader.can_buy { 1.0 } else { 0.0 };
-            let exists_weight = if in_market.stock <= 0.0 { 0.0 } else { 1.0 };
-            let price_weight = 1.0 / in_market.price;
+            let allowed = !in_trader.embargoed || trader.smuggling;
+            let want_weight = if in_trader.can_buy && allowed {
+                1.0
+            } else {
+                0.0
+            };
+            let exists_weight = if pool[good_id] <= 0.0 { 0.0 } else { 1.0 };
+            let price_weight = 1.0 / market.goods[good_id].price;
             let weight = price_weight * want_weight * exists_weight;
-            scratch.weights[good_id] = weight;
+            weights.insert(good_id, weight);
             total_weight += weight;
         }
+        if total_weight == 0.0 {
+            return;
+        }
+
+        // Room left in the cargo once the sales are done
+        let load: f64 = goods
+            .iter()
+            .map(|(good_id, good)| trader.goods[good_id].quantity * good.weight)
+            .sum();
+        let room = (trader.carry_capacity - load).max(0.);
+
+        let cash = trader.cash + trader.proceeds;
+        let budget = cash * trader.trade_focus;
+        for (good_id, good) in goods {
+            let prop = weights[good_id] / total_weight;
+            let in_trader = &mut trader.goods[good_id];
+            in_trader.bid = (budget * prop).min(cash);
+            in_trader.most = if good.weight > 0. {
+                room * prop / good.weight
+            } else {
+                f64::MAX
+            };
+        }
+    }
+
+    // Quantity a bid asks for at a price
+    fn asked(in_trader: &TraderGood, price: f64) -> f64 {
+        if in_trader.bid <= 0. {
+            return 0.;
+        }
+        let can_afford = if price == 0. {
+            f64::MAX
+        } else {
+            in_trader.bid / price
+        };
+        can_afford.min(in_trader.most)
+    }
+
+    // The price one good changes hands at in one market, and the share of its
+    // bids filled. The posted price holds when there is enough for everyone,
+    // otherwise it rises until the bids fit what is on offer, and past the
+    // markup cap the offer is rationed among the bids.
+    fn clearing_price(
+        good_id: GoodId,
+        traders: &[Trader],
+        members: &[usize],
+        posted: f64,
+        pool: f64,
+    ) -> (f64, f64) {
+        let demand = |price: f64| -> f64 {
+            members
+                .iter()
+                .map(|&idx| traders[idx].asked(good_id, price))
+                .sum()
+        };
+
+        let mut price = posted;
+        if demand(posted) > pool && posted > 0. {
+            let (mut low, mut high) = (posted, posted * MAX_CLEARING_MARKUP);
+            if demand(high) <= pool {
+                for _ in 0..CLEARING_ITERATIONS {
+                    let mid = (low + high) / 2.;
+                    if demand(mid) > pool {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+            }
+            price = high;
+        }
+        let wanted = demand(price);
+        let share = if wanted > pool { pool / wanted } else { 1. };
+        (price, share)
+    }
 
-        // Actually effectuate the transaction
-        if total_weight != 0.0 {
+    // Pays the sellers first, so that what they earn is there for their bids.
+    // Tolls come out of the proceeds of the sales and on top of the purchases.
+    fn settle(
+        goods: &GoodTypes,
+        traders: &mut [Trader],
+        members: &[usize],
+        market: &mut Market,
+        reserved: &SecondaryMap<GoodId, f64>,
+        clearing: &SecondaryMap<GoodId, (f64, f64)>,
+    ) {
+        for &idx in members {
+            let trader = &mut traders[idx];
             for good_id in goods.keys() {
-                let weight = scratch.weights[good_id];
-                let prop = weight / total_weight;
-                let cash_allocated = (trader.cash * prop).min(trader.cash);
+                let (price, _) = clearing[good_id];
+                let tariff = trader.tariff_on(good_id);
+                let toll = trader.toll_rate().min(1. - tariff);
+                let in_trader = &trader.goods[good_id];
+                let value = price * in_trader.sold;
+                trader.cash += value * (1. - tariff - toll) * trader.income_handicap;
+                trader.tariffs += value * tariff;
+                trader.tolls_paid += value * toll;
+                trader.sales += value;
+                let in_market = &mut market.goods[good_id];
+                in_market.stock += in_trader.sold;
+                in_market.stock_delta += in_trader.sold;
+            }
+        }
 
+        for &idx in members {
+            let trader = &mut traders[idx];
+            for good_id in goods.keys() {
+                let (price, share) = clearing[good_id];
                 let in_market = &mut market.goods[good_id];
-                let price = in_market.price;
-                let can_afford = if price == 0.0 {
-                    f64::MAX
-                } else {
-                    cash_allocated / price
-                };
-                let bought = can_afford.min(in_market.stock);
+                let available = (in_market.stock - reserved[good_id]).max(0.);
+                let bought = (trader.asked(good_id, price) * share).min(available);
                 in_market.stock -= bought;
                 in_market.stock_delta -= bought;
-
-                let in_trader = &mut trader.goods[good_id];
-                in_trader.quantity += bought;
-                trader.cash = (trader.cash - bought * in_market.price).max(0.);
+                trader.goods[good_id].quantity += bought;
+                let cost = bought * price;
+                let toll = (cost * trader.toll_rate()).min((trader.cash - cost).max(0.));
+                trader.cash = (trader.cash - cost - toll).max(0.);
+                trader.tolls_paid += toll;
+                trader.purchases += cost;
             }
         }
     }
@@ -1,3 +1,4 @@
+use crate::date::Date;
 use crate::simulation::*;
 
 use num_enum::TryFromPrimitive;
@@ -17,27 +18,66 @@ new_key_type! { pub(crate) struct TokenId; }
 pub(crate) enum TokenCategory {
     Building,
     Pop,
+    Military,
 }
 
+#[derive(Clone)]
 pub(crate) struct TokenType {
-    pub tag: &'static str,
     pub name: &'static str,
     pub category: TokenCategory,
     pub demand: SecondaryMap<GoodId, f64>,
     pub supply: SecondaryMap<GoodId, f64>,
     pub rgo_points: f64,
+    // Workers of each pop employed by one building
+    pub jobs: SecondaryMap<TokenTypeId, f64>,
+    // Daily fee one building charges each traveller staying at its location
+    pub hospitality: f64,
+    // What it takes to build one more, for buildings
+    pub construction: Option<ConstructionCost>,
+    // Fighting strength of each unit, for troops
+    pub strength: f64,
 }
 
-impl Tagged for TokenType {
-    fn tag(&self) -> &str {
-        self.tag
-    }
+#[derive(Clone)]
+pub(crate) struct ConstructionCost {
+    pub goods: SecondaryMap<GoodId, f64>,
+    pub cash: f64,
+    pub days: u32,
 }
 
+#[derive(Clone)]
 pub(crate) struct TokenData {
     pub container: TokenContainerId,
     pub typ: TokenTypeId,
     pub size: i64,
+    pub modifiers: Vec<TokenModifier>,
+}
+
+impl TokenData {
+    /// Multipliers of the land the token works, what it demands and what it
+    /// supplies, under all its modifiers
+    pub fn factors(&self) -> (f64, f64, f64) {
+        self.modifiers
+            .iter()
+            .fold((1., 1., 1.), |(rgo, demand, supply), modifier| {
+                (
+                    rgo * modifier.rgo_points,
+                    demand * modifier.demand,
+                    supply * modifier.supply,
+                )
+            })
+    }
+}
+
+/// A passing effect on a token, like a plague among its pops or a festival,
+/// that lasts until a date
+#[derive(Clone)]
+pub(crate) struct TokenModifier {
+    pub name: String,
+    pub rgo_points: f64,
+    pub demand: f64,
+    pub supply: f64,
+    pub until: Date,
 }
 
 pub(crate) struct ReadToken<'a> {
@@ -48,22 +88,64 @@ pub(crate) struct ReadToken<'a> {
 
 impl<'a> ArenaSafe for ReadToken<'a> {}
 
-#[derive(Default)]
+/// Why a change to the tokens was turned down. A change turned down leaves
+/// every token as it was.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TokenError {
+    NoSuchToken,
+    NoSuchContainer,
+    // The token is held by another container than the one named
+    WrongContainer,
+    // More was asked of the token than it holds, or less than nothing, or
+    // nothing at all to be parted off
+    Underflow { asked: i64, held: i64 },
+}
+
+/// The tokens held together, as by a location, indexed by their category and
+/// their type. Types never change, so the indexes only follow tokens coming
+/// and going.
+#[derive(Default, Clone)]
+pub(crate) struct TokenContainer {
+    all: BTreeSet<TokenId>,
+    by_category: BTreeMap<TokenCategory, BTreeSet<TokenId>>,
+    by_type: BTreeMap<TokenTypeId, BTreeSet<TokenId>>,
+}
+
+impl TokenContainer {
+    pub fn iter(&self) -> impl Iterator<Item = TokenId> + '_ {
+        self.all.iter().copied()
+    }
+
+    fn insert(&mut self, id: TokenId, typ: TokenTypeId, category: TokenCategory) {
+        self.all.insert(id);
+        self.by_category.entry(category).or_default().insert(id);
+        self.by_type.entry(typ).or_default().insert(id);
+    }
+
+    fn remove(&mut self, id: TokenId, typ: TokenTypeId, category: TokenCategory) {
+        self.all.remove(&id);
+        if let Some(ids) = self.by_category.get_mut(&category) {
+            ids.remove(&id);
+        }
+        if let Some(ids) = self.by_type.get_mut(&typ) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.by_type.remove(&typ);
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub(crate) struct Tokens {
     pub types: SlotMap<TokenTypeId, TokenType>,
-    pub containers: SlotMap<TokenContainerId, BTreeSet<TokenId>>,
+    pub containers: SlotMap<TokenContainerId, TokenContainer>,
     pub tokens: SlotMap<TokenId, TokenData>,
 }
 
 impl Tokens {
     pub fn define_type(&mut self, typ: TokenType) -> TokenTypeId {
-        match self.types.lookup(typ.tag) {
-            Some(existing) => {
-                println!("Redefition of token type with tag '{}'", typ.tag);
-                existing
-            }
-            None => self.types.insert(typ),
-        }
+        self.types.insert(typ)
     }
 
     pub fn add_container(&mut self) -> TokenContainerId {
@@ -86,8 +168,10 @@ impl Tokens {
                     container,
                     typ,
                     size,
+                    modifiers: vec![],
                 });
-                self.containers[container].insert(id);
+                let category = self.types[typ].category;
+                self.containers[container].insert(id, typ, category);
                 id
             }
         }
@@ -98,8 +182,12 @@ impl Tokens {
         container: TokenContainerId,
         category: TokenCategory,
     ) -> impl Iterator<Item = ReadToken<'a>> + use<'a> {
-        self.all_tokens_in(container)
-            .filter(move |tok| tok.typ.category == category)
+        self.containers
+            .get(container)
+            .and_then(|container| container.by_category.get(&category))
+            .into_iter()
+            .flatten()
+            .map(|&id| self.read(id))
     }
 
     pub fn all_tokens_in<'a>(
@@ -109,12 +197,14 @@ impl Tokens {
         self.containers
             .get(container)
             .into_iter()
-            .flat_map(|container| container.iter().copied())
-            .map(|id| {
-                let data = &self.tokens[id];
-                let typ = &self.types[data.typ];
-                ReadToken { id, data, typ }
-            })
+            .flat_map(|container| container.iter())
+            .map(|id| self.read(id))
+    }
+
+    fn read(&self, id: TokenId) -> ReadToken<'_> {
+        let data = &self.tokens[id];
+        let typ = &self.types[data.typ];
+        ReadToken { id, data, typ }
     }
 
     pub fn find_token_with_characteristics(
@@ -122,9 +212,12 @@ impl Tokens {
         container: TokenContainerId,
         typ: TokenTypeId,
     ) -> Option<TokenId> {
-        self.all_tokens_in(container)
-            .find(|tok| tok.data.typ == typ)
-            .map(|tok| tok.id)
+        self.containers
+            .get(container)?
+            .by_type
+            .get(&typ)?
+            .first()
+            .copied()
     }
 
     pub fn count_size(tokens: &[ReadToken], category: TokenCategory) -> i64 {
@@ -135,11 +228,212 @@ impl Tokens {
             .sum()
     }
 
+    /// Takes some of the size of a token, and returns what is left of it. A
+    /// token left with nothing is gone from its container.
+    pub fn remove_size(&mut self, token: TokenId, size: i64) -> Result<i64, TokenError> {
+        let data = self.tokens.get_mut(token).ok_or(TokenError::NoSuchToken)?;
+        if size < 0 || size > data.size {
+            return Err(TokenError::Underflow {
+                asked: size,
+                held: data.size,
+            });
+        }
+        data.size -= size;
+        let left = data.size;
+        if left == 0 {
+            let (container, typ) = (data.container, data.typ);
+            let category = self.types[typ].category;
+            if let Some(container) = self.containers.get_mut(container) {
+                container.remove(token, typ, category);
+            }
+            self.tokens.remove(token);
+        }
+        Ok(left)
+    }
+
+    /// Parts some of the size of a token off into a token of its own, in the
+    /// same container, and returns the new token
+    pub fn split(&mut self, token: TokenId, size: i64) -> Result<TokenId, TokenError> {
+        let data = self
+            .tokens
+            .get(token)
+            .ok_or(TokenError::NoSuchToken)?
+            .clone();
+        if size == 0 {
+            return Err(TokenError::Underflow {
+                asked: size,
+                held: data.size,
+            });
+        }
+        self.remove_size(token, size)?;
+        let container = data.container;
+        let (typ, category) = (data.typ, self.types[data.typ].category);
+        let id = self.tokens.insert(TokenData { size, ..data });
+        self.containers[container].insert(id, typ, category);
+        Ok(id)
+    }
+
+    /// Moves some of the size of a token from one container to another, where
+    /// it joins any token of the same type, and returns the token it ends up in
+    pub fn transfer(
+        &mut self,
+        from: TokenContainerId,
+        to: TokenContainerId,
+        token: TokenId,
+        size: i64,
+    ) -> Result<TokenId, TokenError> {
+        if !self.containers.contains_key(from) || !self.containers.contains_key(to) {
+            return Err(TokenError::NoSuchContainer);
+        }
+        let data = self.tokens.get(token).ok_or(TokenError::NoSuchToken)?;
+        if data.container != from {
+            return Err(TokenError::WrongContainer);
+        }
+        let typ = data.typ;
+        let part = self.split(token, size)?;
+        let category = self.types[typ].category;
+        self.containers[from].remove(part, typ, category);
+        self.tokens.remove(part);
+        Ok(self.add_token(to, typ, size))
+    }
+
+    /// Puts a modifier on the token of a type in a container, in place of any
+    /// of the same name. Returns false if the container holds no such token.
+    pub fn add_modifier(
+        &mut self,
+        container: TokenContainerId,
+        typ: TokenTypeId,
+        modifier: TokenModifier,
+    ) -> bool {
+        let Some(id) = self.find_token_with_characteristics(container, typ) else {
+            return false;
+        };
+        let modifiers = &mut self.tokens[id].modifiers;
+        modifiers.retain(|other| other.name != modifier.name);
+        modifiers.push(modifier);
+        true
+    }
+
+    /// Lifts the modifiers that ran out by the given date
+    pub fn expire_modifiers(&mut self, date: Date) {
+        for token in self.tokens.values_mut() {
+            token.modifiers.retain(|modifier| date < modifier.until);
+        }
+    }
+
     pub fn despawn(&mut self, id: TokenContainerId) {
         if let Some(container) = self.containers.remove(id) {
-            for id in container {
+            for id in container.all {
                 self.tokens.remove(id);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens_with_a_type() -> (Tokens, TokenTypeId) {
+        let mut tokens = Tokens::default();
+        let typ = tokens.define_type(TokenType {
+            name: "Levy",
+            category: TokenCategory::Military,
+            demand: SecondaryMap::default(),
+            supply: SecondaryMap::default(),
+            rgo_points: 0.,
+            jobs: SecondaryMap::default(),
+            hospitality: 0.,
+            construction: None,
+            strength: 1.,
+        });
+        (tokens, typ)
+    }
+
+    #[test]
+    fn removing_more_than_held_underflows() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let container = tokens.add_container();
+        let token = tokens.add_token(container, typ, 5);
+
+        let result = tokens.remove_size(token, 6);
+        assert_eq!(result, Err(TokenError::Underflow { asked: 6, held: 5 }));
+        assert_eq!(tokens.tokens[token].size, 5);
+    }
+
+    #[test]
+    fn removing_a_negative_size_underflows() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let container = tokens.add_container();
+        let token = tokens.add_token(container, typ, 5);
+
+        let result = tokens.remove_size(token, -1);
+        assert_eq!(result, Err(TokenError::Underflow { asked: -1, held: 5 }));
+        assert_eq!(
+            tokens.split(token, -1),
+            Err(TokenError::Underflow { asked: -1, held: 5 })
+        );
+        assert_eq!(tokens.tokens[token].size, 5);
+    }
+
+    #[test]
+    fn removing_all_of_a_token_drops_it() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let container = tokens.add_container();
+        let token = tokens.add_token(container, typ, 5);
+
+        assert_eq!(tokens.remove_size(token, 5), Ok(0));
+        assert!(!tokens.tokens.contains_key(token));
+        assert_eq!(tokens.all_tokens_in(container).count(), 0);
+        assert_eq!(
+            tokens
+                .all_tokens_of_category(container, TokenCategory::Military)
+                .count(),
+            0
+        );
+        assert_eq!(tokens.find_token_with_characteristics(container, typ), None);
+    }
+
+    #[test]
+    fn transfer_from_the_wrong_container_is_refused() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let from = tokens.add_container();
+        let to = tokens.add_container();
+        let token = tokens.add_token(from, typ, 5);
+
+        assert_eq!(
+            tokens.transfer(to, from, token, 2),
+            Err(TokenError::WrongContainer)
+        );
+        assert_eq!(tokens.tokens[token].size, 5);
+        assert_eq!(tokens.all_tokens_in(to).count(), 0);
+    }
+
+    #[test]
+    fn full_transfer_leaves_nothing_behind() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let from = tokens.add_container();
+        let to = tokens.add_container();
+        let token = tokens.add_token(from, typ, 5);
+        let held = tokens.add_token(to, typ, 3);
+
+        let moved = tokens.transfer(from, to, token, 5).unwrap();
+        assert_eq!(moved, held);
+        assert_eq!(tokens.tokens[held].size, 8);
+        assert!(!tokens.tokens.contains_key(token));
+        assert_eq!(tokens.all_tokens_in(from).count(), 0);
+        assert_eq!(tokens.find_token_with_characteristics(from, typ), None);
+    }
+
+    #[test]
+    fn splitting_off_everything_moves_the_whole_token() {
+        let (mut tokens, typ) = tokens_with_a_type();
+        let container = tokens.add_container();
+        let token = tokens.add_token(container, typ, 5);
+
+        let part = tokens.split(token, 5).unwrap();
+        assert!(!tokens.tokens.contains_key(token));
+        assert_eq!(tokens.tokens[part].size, 5);
+        assert_eq!(tokens.all_tokens_in(container).count(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::combat::Assault;
+use crate::simulation::*;
+
+// Daily unrest at a settlement that gets none of what it wants
+const UNREST_PER_SHORTFALL: f64 = 2.;
+// Taxes a settlement bears without complaint, and the daily unrest for each
+// point of tax over them
+const TOLERATED_TAX: f64 = 0.1;
+const UNREST_PER_TAX: f64 = 20.;
+/// Unrest at which a settlement rises up
+pub(crate) const UPRISING_THRESHOLD: f64 = 100.;
+// Cut in its tax rate an overlord concedes to rebels
+const TAX_CONCESSION: f64 = 0.02;
+// Share of the stock of each good in the market rebels carry off
+const PLUNDER_SHARE: f64 = 0.2;
+
+/// Rebels come to a location to have their grievances heard
+pub(crate) struct Revolt {
+    pub entity: EntityId,
+    pub location: LocationId,
+}
+
+/// Stirs up unrest in settlements whose markets fall short of what they want
+/// and whose overlords tax them too hard, over some days. It dies down by the
+/// rule of its pressure.
+pub(crate) fn stir(sim: &mut Simulation, days: f64) {
+    for location in sim.locations.values() {
+        let entity = &sim.entities[location.entity];
+        let Some(pressurable) = entity.pressure_agent else {
+            continue;
+        };
+        let shortfall = 1. - crate::prosperity::satisfaction(&location.market);
+        let taxes = entity
+            .agent
+            .and_then(|agent| sim.agents.political_hierarchy.parent(agent))
+            .map_or(0., |overlord| sim.agents[overlord].tax_rate);
+        let growth = days
+            * (UNREST_PER_SHORTFALL * shortfall + UNREST_PER_TAX * (taxes - TOLERATED_TAX).max(0.));
+        sim.pressurables[pressurable]
+            .current
+            .update(PressureType::Unrest, |x| (x + growth).max(0.));
+    }
+}
+
+/// Rebels that reach a location and get past its garrison wring a cut in
+/// taxes out of the overlord it pays them to, and plunder its market.
+/// Returns the entities of the rebels the garrisons destroyed.
+pub(crate) fn resolve(sim: &mut Simulation, revolts: Vec<Revolt>) -> Vec<EntityId> {
+    let mut destroyed = vec![];
+    for revolt in revolts {
+        let Some(party) = sim
+            .entities
+            .get(revolt.entity)
+            .and_then(|entity| entity.party)
+        else {
+            continue;
+        };
+        if !sim.locations.contains_key(revolt.location) {
+            continue;
+        }
+        match crate::combat::assault(sim, party, revolt.location) {
+            Assault::Taken => {}
+            Assault::Repelled => {
+                crate::combat::retreat(sim, party);
+                continue;
+            }
+            Assault::Destroyed => {
+                destroyed.push(revolt.entity);
+                continue;
+            }
+        }
+        let location = &mut sim.locations[revolt.location];
+
+        let party_data = &mut sim.parties[party];
+        let mut room = party_data.carry_capacity - party_data.good_stock.weight(&sim.good_types);
+        for (good_id, good) in location.market.goods.iter_mut() {
+            let weight = sim.good_types[good_id].weight;
+            let mut taken = good.stock * PLUNDER_SHARE;
+            if weight > 0. {
+                taken = taken.min(room.max(0.) / weight);
+            }
+            good.stock -= taken;
+            party_data.good_stock[good_id] += taken;
+            room -= taken * weight;
+        }
+
+        let location_entity = location.entity;
+        let overlord = sim.entities[location_entity]
+            .agent
+            .and_then(|agent| sim.agents.political_hierarchy.parent(agent));
+        let town = &sim.entities[location_entity].name;
+        let (text, about) = match overlord {
+            Some(overlord) => {
+                let data = &mut sim.agents[overlord];
+                data.tax_rate = (data.tax_rate - TAX_CONCESSION).max(0.);
+                let text = format!(
+                    "rebels plundered {town}, and {} eased its taxes to {:1.0}%",
+                    sim.entities[data.entity].name,
+                    data.tax_rate * 100.
+                );
+                (text, vec![revolt.entity, location_entity, data.entity])
+            }
+            None => (
+                format!("rebels plundered {town}"),
+                vec![revolt.entity, location_entity],
+            ),
+        };
+        sim.record_chronicle_about(&about, text);
+    }
+    destroyed
+}
+
+#[cfg(test)]
+mod tests {
+    use util::arena::Arena;
+
+    use super::*;
+    use crate::tick::{SpawnParams, TickCommands, TickRequest};
+    use crate::tokens::TokenCategory;
+
+    #[test]
+    fn revolt_overwhelms_a_small_garrison() {
+        let arena = Arena::default();
+        let mut sim = Simulation::new();
+        crate::scenario::init_scenario(&mut sim, &arena);
+
+        // Din Drust is a hillfort, held by a levy of thirty. Leave ten.
+        let (location, garrison) = sim
+            .locations
+            .iter()
+            .find(|(_, location)| sim.entities[location.entity].name == "Din Drust")
+            .map(|(id, location)| (id, location.tokens))
+            .unwrap();
+        let soldiers: Vec<_> = sim
+            .tokens
+            .all_tokens_of_category(garrison, TokenCategory::Military)
+            .map(|token| (token.id, token.data.size))
+            .collect();
+        let mut left = 10;
+        for (token, size) in soldiers {
+            let kept = size.min(left);
+            left -= kept;
+            sim.tokens.remove_size(token, size - kept).unwrap();
+        }
+
+        let mut commands = TickCommands::default();
+        commands.spawn(SpawnParams {
+            template: "rebels",
+            name: Some("Rebels"),
+            site: "din_drust",
+            faction: "",
+        });
+        sim.tick(
+            TickRequest {
+                commands,
+                ..Default::default()
+            },
+            &arena,
+        );
+        let party = sim
+            .parties
+            .iter()
+            .find(|(_, party)| sim.entities[party.entity].name == "Rebels")
+            .map(|(id, _)| id)
+            .unwrap();
+
+        assert!(matches!(
+            crate::combat::assault(&mut sim, party, location),
+            Assault::Taken
+        ));
+    }
+}
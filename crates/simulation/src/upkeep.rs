@@ -0,0 +1,112 @@
+use slotmap::SecondaryMap;
+
+use crate::simulation::*;
+
+// Daily cost of keeping a party on the road, paid by its own agent
+const PARTY_UPKEEP: f64 = 2.;
+// Daily share of their purse parties spend on the road, on top of the upkeep
+const PARTY_SPENDING_RATE: f64 = 0.01;
+// Food a party eats each day, for each unit of its size
+const RATIONS: f64 = 0.05;
+// Markup on the base price of the food parties buy along the way, when they
+// carry none of their own
+const PROVISIONS_MARKUP: f64 = 1.5;
+// Days in a row a party can go unpaid or hungry before it heads home, and
+// twice as many before it breaks up on the spot
+const GRACE_DAYS: u32 = 10;
+
+/// Days in a row each party on the road went without its upkeep
+#[derive(Default, Clone)]
+pub(crate) struct Upkeep {
+    arrears: SecondaryMap<PartyId, u32>,
+}
+
+impl Upkeep {
+    /// Whether a party went unpaid long enough to be sent home
+    pub fn stranded(&self, party: PartyId) -> bool {
+        self.arrears
+            .get(party)
+            .is_some_and(|&days| days >= GRACE_DAYS)
+    }
+}
+
+/// Charges parties on the road their daily upkeep and feeds them, from their
+/// own cargo when they carry food and out of their purse otherwise. Parties
+/// that cannot pay head home to disband, or break up where they are if they
+/// have no home. Returns the cash spent.
+pub(crate) fn tick(sim: &mut Simulation) -> f64 {
+    let index = sim.currency.index();
+    let mut foods: Vec<_> = sim
+        .good_types
+        .iter()
+        .filter(|(_, good)| good.food_rate > 0.)
+        .map(|(id, good)| (id, good.food_rate, good.price))
+        .collect();
+    foods.sort_by(|a, b| (a.2 / a.1).total_cmp(&(b.2 / b.1)));
+    // Cheapest food on the road, by what it costs to eat a unit of rations
+    let provisions = foods.first().map_or(0., |&(_, rate, price)| {
+        price / rate * index * PROVISIONS_MARKUP
+    });
+
+    let mut drained = 0.;
+    let mut bankrupt = vec![];
+    for entity in sim.entities.values() {
+        let (Some(agent), Some(party), None) = (entity.agent, entity.party, entity.location) else {
+            continue;
+        };
+        // Children are kept by their family
+        if sim.characters.is_minor(sim.date, agent) {
+            continue;
+        }
+        let party_data = &mut sim.parties[party];
+        let mut hunger = RATIONS * party_data.size as f64;
+        for &(good_id, rate, _) in &foods {
+            let eaten = (hunger / rate).min(party_data.good_stock[good_id]);
+            party_data.good_stock[good_id] -= eaten;
+            hunger -= eaten * rate;
+        }
+
+        let data = &mut sim.agents[agent];
+        let due = PARTY_UPKEEP + hunger * provisions + data.cash.max(0.) * PARTY_SPENDING_RATE;
+        let paid = due.min(data.cash).max(0.);
+        data.cash -= paid;
+        drained += paid;
+
+        if paid < due {
+            let days = sim.upkeep.arrears.entry(party).unwrap().or_default();
+            *days += 1;
+            if *days >= GRACE_DAYS {
+                bankrupt.push((entity.behavior, *days));
+            }
+        } else {
+            sim.upkeep.arrears.remove(party);
+        }
+    }
+    sim.upkeep
+        .arrears
+        .retain(|party, _| sim.parties.contains_key(party));
+
+    for (behavior, days) in bankrupt {
+        let Some(behavior) = behavior.and_then(|id| sim.beahviors.get_mut(id)) else {
+            continue;
+        };
+        let base = match behavior.vocation {
+            Goal::LocalTrade { base }
+            | Goal::Smuggle { base }
+            | Goal::ArbitrageTrade { base }
+            | Goal::Rebel { base } => Some(base).filter(|&base| sim.parties.contains_key(base)),
+            _ => None,
+        };
+        match base {
+            Some(base) if days < 2 * GRACE_DAYS => {
+                let homecoming = Goal::ReturnHome { base };
+                if behavior.goal != homecoming {
+                    behavior.goal = homecoming;
+                    behavior.plan.clear();
+                }
+            }
+            _ => behavior.request_despawn = true,
+        }
+    }
+    drained
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use slotmap::{Key, SlotMap};
+
+use crate::simulation::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Invariant {
+    DanglingReference,
+    BrokenBackReference,
+    TokenOwnership,
+    NegativeStock,
+    HierarchyCycle,
+}
+
+/// A broken simulation invariant
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub invariant: Invariant,
+    pub subject: String,
+    pub detail: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} on {}: {}",
+            self.invariant, self.subject, self.detail
+        )
+    }
+}
+
+struct Validator<'a> {
+    sim: &'a Simulation,
+    out: Vec<Diagnostic>,
+}
+
+impl Simulation {
+    /// Checks the internal consistency of the simulation state. An empty
+    /// result means every invariant holds.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut validator = Validator {
+            sim: self,
+            out: vec![],
+        };
+        validator.entities();
+        validator.parties();
+        validator.locations();
+        validator.tokens();
+        validator.hierarchy();
+        validator.out
+    }
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, invariant: Invariant, subject: String, detail: String) {
+        self.out.push(Diagnostic {
+            invariant,
+            subject,
+            detail,
+        });
+    }
+
+    fn entity_name(&self, id: EntityId) -> String {
+        match self.sim.entities.get(id) {
+            Some(entity) => format!("'{}'", entity.name),
+            None => format!("entity {:?}", id.data()),
+        }
+    }
+
+    // Checks that `id` exists in `map`, and that it points back to `owner`
+    fn component<K: Key, V>(
+        &mut self,
+        owner: EntityId,
+        field: &str,
+        id: Option<K>,
+        map: &SlotMap<K, V>,
+        entity_of: impl Fn(&V) -> EntityId,
+    ) {
+        let Some(id) = id else {
+            return;
+        };
+        match map.get(id) {
+            None => self.report(
+                Invariant::DanglingReference,
+                self.entity_name(owner),
+                format!("{field} {:?} does not exist", id.data()),
+            ),
+            Some(data) if entity_of(data) != owner => self.report(
+                Invariant::BrokenBackReference,
+                self.entity_name(owner),
+                format!("{field} {:?} belongs to another entity", id.data()),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    fn entities(&mut self) {
+        let sim = self.sim;
+        for (id, entity) in &sim.entities {
+            self.component(id, "agent", entity.agent, &sim.agents.entries, |x| x.entity);
+            self.component(id, "party", entity.party, &sim.parties, |x| x.entity);
+            self.component(id, "location", entity.location, &sim.locations, |x| {
+                x.entity
+            });
+            self.component(
+                id,
+                "pressure agent",
+                entity.pressure_agent,
+                &sim.pressurables,
+                |x| x.entity,
+            );
+            self.component(id, "behavior", entity.behavior, &sim.beahviors, |x| {
+                x.entity
+            });
+        }
+    }
+
+    fn parties(&mut self) {
+        let sim = self.sim;
+        for party in sim.parties.values() {
+            if !sim.entities.contains_key(party.entity) {
+                self.report(
+                    Invariant::DanglingReference,
+                    self.entity_name(party.entity),
+                    "party outlived its entity".to_string(),
+                );
+            }
+            if let Some(location) = party.location
+                && !sim.locations.contains_key(location)
+            {
+                self.report(
+                    Invariant::DanglingReference,
+                    self.entity_name(party.entity),
+                    format!("location {:?} does not exist", location.data()),
+                );
+            }
+        }
+    }
+
+    fn locations(&mut self) {
+        let sim = self.sim;
+        for (id, location) in &sim.locations {
+            let name = self.entity_name(location.entity);
+            if !sim.parties.contains_key(location.party) {
+                self.report(
+                    Invariant::DanglingReference,
+                    name.clone(),
+                    format!("party {:?} does not exist", location.party.data()),
+                );
+            }
+            match sim.sites.get(location.site) {
+                None => self.report(
+                    Invariant::DanglingReference,
+                    name.clone(),
+                    format!("site {:?} does not exist", location.site.data()),
+                ),
+                Some(site) if site.location != Some(id) => self.report(
+                    Invariant::BrokenBackReference,
+                    name.clone(),
+                    format!("site '{}' is bound to another location", site.tag),
+                ),
+                Some(_) => {}
+            }
+            for (good_id, good) in location.market.goods.iter() {
+                if good.stock < 0. || good.stock.is_nan() {
+                    self.report(
+                        Invariant::NegativeStock,
+                        name.clone(),
+                        format!("{} stock is {}", sim.good_types[good_id].name, good.stock),
+                    );
+                }
+            }
+        }
+    }
+
+    fn tokens(&mut self) {
+        let sim = self.sim;
+        let mut owners = BTreeMap::new();
+        for location in sim.locations.values() {
+            if !sim.tokens.containers.contains_key(location.tokens) {
+                self.report(
+                    Invariant::TokenOwnership,
+                    self.entity_name(location.entity),
+                    "token container does not exist".to_string(),
+                );
+            }
+            if let Some(other) = owners.insert(location.tokens, location.entity) {
+                self.report(
+                    Invariant::TokenOwnership,
+                    self.entity_name(location.entity),
+                    format!("token container shared with {}", self.entity_name(other)),
+                );
+            }
+        }
+        // Troops travel with the party they serve in
+        for party in sim.parties.values() {
+            let Some(troops) = party.troops else {
+                continue;
+            };
+            if !sim.tokens.containers.contains_key(troops) {
+                self.report(
+                    Invariant::TokenOwnership,
+                    self.entity_name(party.entity),
+                    "troop container does not exist".to_string(),
+                );
+            }
+            if let Some(other) = owners.insert(troops, party.entity) {
+                self.report(
+                    Invariant::TokenOwnership,
+                    self.entity_name(party.entity),
+                    format!("troop container shared with {}", self.entity_name(other)),
+                );
+            }
+        }
+
+        for (container_id, container) in &sim.tokens.containers {
+            if !owners.contains_key(&container_id) {
+                self.report(
+                    Invariant::TokenOwnership,
+                    format!("token container {:?}", container_id.data()),
+                    "not owned by any location or party".to_string(),
+                );
+            }
+            for token_id in container.iter() {
+                let matches = sim
+                    .tokens
+                    .tokens
+                    .get(token_id)
+                    .is_some_and(|token| token.container == container_id);
+                if !matches {
+                    self.report(
+                        Invariant::TokenOwnership,
+                        format!("token {:?}", token_id.data()),
+                        format!("not stored in container {:?}", container_id.data()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn hierarchy(&mut self) {
+        let sim = self.sim;
+        let hierarchy = &sim.agents.political_hierarchy;
+        for (child, parent) in hierarchy.iter() {
+            for id in [child, parent] {
+                if !sim.agents.entries.contains_key(id) {
+                    self.report(
+                        Invariant::DanglingReference,
+                        format!("agent {:?}", id.data()),
+                        "referenced in the political hierarchy".to_string(),
+                    );
+                }
+            }
+        }
+
+        // Walking up from any agent must end at a root in less steps than there are agents
+        let limit = sim.agents.entries.len();
+        for id in sim.agents.entries.keys() {
+            let mut current = id;
+            let mut steps = 0;
+            while let Some(parent) = hierarchy.parent(current) {
+                current = parent;
+                steps += 1;
+                if steps > limit {
+                    let entity = sim.agents.entries[id].entity;
+                    self.report(
+                        Invariant::HierarchyCycle,
+                        self.entity_name(entity),
+                        "political hierarchy contains a cycle".to_string(),
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,89 +1,531 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use strum::IntoEnumIterator;
+
+use crate::date::{Date, Duration};
+use crate::espionage::CovertAction;
 use crate::object::*;
 use crate::simulation::*;
+use crate::sites::{SiteId, Terrain};
+use crate::tick::{
+    BASE_SPEED, PERSON_MOVEMENT_SPEED, path_through, pos_of_grid_coordinate, transfer,
+};
 use crate::tokens::*;
+use crate::visibility::Sight;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct SimView {
-    pub map_lines: Vec<(V2, V2)>,
+    /// Roads between sites, with their terrain and the upgrades they have seen
+    pub map_lines: Vec<(V2, V2, Terrain, u8)>,
     pub map_items: Vec<MapItem>,
+    pub map_territory: Vec<MapTerritory>,
     pub objects: Vec<Option<Object>>,
+    pub events: Vec<SimEvent>,
+    pub warnings: Vec<SimWarning>,
+}
+
+/// Notable things that happened during the ticks that produced a view
+#[derive(Clone, PartialEq)]
+pub enum SimEvent {
+    NewDay,
+    EntityCreated {
+        id: ObjectId,
+    },
+    TradeResolved {
+        party: ObjectId,
+        location: ObjectId,
+    },
+    CovertActionResolved {
+        actor: ObjectId,
+        target: ObjectId,
+        action: CovertAction,
+        succeeded: bool,
+        discovered: bool,
+    },
+    FamineChanged {
+        location: ObjectId,
+        starving: bool,
+    },
+    /// A party was ordered somewhere no route leads to, or the party it was
+    /// sent after is gone
+    PathBlocked {
+        party: ObjectId,
+    },
+    /// A faction lost the last of its locations and parties
+    FactionEliminated {
+        faction: ObjectId,
+    },
+    /// Two hostile parties met and fought, the loser fleeing or destroyed
+    Battle {
+        winner: ObjectId,
+        loser: ObjectId,
+        destroyed: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TagKind {
+    Site,
+    Agent,
+    Good,
+    TokenType,
+    Template,
+}
+
+impl TagKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Site => "site",
+            Self::Agent => "agent",
+            Self::Good => "good",
+            Self::TokenType => "token type",
+            Self::Template => "template",
+        }
+    }
+}
+
+/// Problems found while applying commands, whose offending data was dropped
+#[derive(Clone, PartialEq, Debug)]
+pub enum SimWarning {
+    UndefinedTag { kind: TagKind, tag: String },
+    DuplicateTag { kind: TagKind, tag: String },
+    LocationWithoutParty { name: String },
+    NotABuilding { tag: String },
+    // An agent named where only a faction will do, as the actor of a covert
+    // action
+    NotAFaction { tag: String },
+    // A command of the player about what is not its own
+    NotPermitted,
+    // A broken invariant found by validating the simulation after a tick
+    BrokenInvariant { tick: u64, diagnostic: String },
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+impl std::fmt::Display for SimWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndefinedTag { kind, tag } => write!(f, "Undefined {} '{tag}'", kind.name()),
+            Self::DuplicateTag { kind, tag } => {
+                write!(f, "Duplicate {} tag '{tag}'", kind.name())
+            }
+            Self::LocationWithoutParty { name } => {
+                write!(f, "Location '{name}' requires a party")
+            }
+            Self::NotABuilding { tag } => write!(f, "'{tag}' cannot be built"),
+            Self::NotAFaction { tag } => write!(f, "'{tag}' is not a faction"),
+            Self::NotPermitted => write!(f, "Only your own parties and settlements take orders"),
+            Self::BrokenInvariant { tick, diagnostic } => write!(f, "Tick {tick}: {diagnostic}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MapItemKind {
     Site,
     Party,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct MapItem {
     pub id: ObjectId,
     pub kind: MapItemKind,
     pub name: String,
-    pub image: &'static str,
+    pub note: String,
+    pub image: Cow<'static, str>,
     pub pos: V2,
     pub size: f32,
     pub layer: u8,
+    /// Shown as the observer last saw it, being out of its sight
+    pub remembered: bool,
+}
+
+/// A site held by a faction, drawn in its colour
+#[derive(Clone, Copy, PartialEq)]
+pub struct MapTerritory {
+    pub pos: V2,
+    pub faction: ObjectId,
+    /// Rank of the faction among all, to tell factions apart by colour
+    pub colour: u8,
+}
+
+/// Which parties on the move are drawn on the map. Settlements always are.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MapFilter {
+    pub traders: bool,
+    /// Parties with no trade to ply, like farmers and travellers
+    pub civilians: bool,
+    /// Parties not of the faction of the player at the controls
+    pub foreign: bool,
 }
 
-pub(crate) fn map_view_lines(sim: &Simulation, viewport: Extents) -> Vec<(V2, V2)> {
+impl Default for MapFilter {
+    fn default() -> Self {
+        Self {
+            traders: true,
+            civilians: true,
+            foreign: true,
+        }
+    }
+}
+
+impl MapFilter {
+    fn shows(&self, sim: &Simulation, party: &PartyData) -> bool {
+        let entity = &sim.entities[party.entity];
+        if entity.location.is_some() {
+            return true;
+        }
+        let trading = entity
+            .behavior
+            .and_then(|behavior| sim.beahviors.get(behavior))
+            .is_some_and(|behavior| {
+                matches!(
+                    behavior.vocation,
+                    Goal::LocalTrade { .. }
+                        | Goal::Smuggle { .. }
+                        | Goal::ArbitrageTrade { .. }
+                        | Goal::RouteTrade { .. }
+                )
+            });
+        let shown = if trading {
+            self.traders
+        } else {
+            self.civilians
+        };
+        let foreign = sim.players.active().is_some_and(|player| {
+            entity
+                .agent
+                .and_then(|agent| crate::chronicle::faction_of(&sim.agents, agent))
+                != Some(player)
+        });
+        shown && (self.foreign || !foreign)
+    }
+}
+
+// The modifiers on a token, with how they bear on it and how long they last
+fn describe_modifiers(date: Date, token: &TokenData) -> String {
+    let parts: Vec<_> = token
+        .modifiers
+        .iter()
+        .map(|modifier| {
+            let effects: Vec<_> = [
+                ("work", modifier.rgo_points),
+                ("demand", modifier.demand),
+                ("output", modifier.supply),
+            ]
+            .into_iter()
+            .filter(|(_, factor)| *factor != 1.)
+            .map(|(what, factor)| format!("{what} {:+1.0}%", (factor - 1.) * 100.))
+            .collect();
+            let days = modifier.until.epoch().saturating_sub(date.epoch()) / Date::TICKS_IN_DAY;
+            format!("{}: {} ({days} days)", modifier.name, effects.join(", "))
+        })
+        .collect();
+    parts.join("; ")
+}
+
+pub(crate) fn map_view_lines(sim: &Simulation, viewport: Extents) -> Vec<(V2, V2, Terrain, u8)> {
     let mut out = Vec::with_capacity(100);
-    for (id, site) in sim.sites.iter() {
-        let parent_out = !viewport.contains(site.pos);
-        for neigh_id in sim.sites.greater_neighbours(id) {
-            let destination = sim.sites.get(neigh_id).unwrap().pos;
-            let child_out = !viewport.contains(destination);
-            if !parent_out || !child_out {
-                out.push((site.pos, destination));
+    // Roads with at least one end in view, each once
+    for (id, site) in sim.sites.within(viewport) {
+        for &(neigh_id, _, _) in sim.sites.neighbours(id) {
+            let destination = sim.sites[neigh_id].pos;
+            if neigh_id < id && viewport.contains(destination) {
+                continue;
             }
+            let quality = sim.sites.road(id, neigh_id).map_or(0, |road| road.quality);
+            let (a, b) = if id < neigh_id {
+                (site.pos, destination)
+            } else {
+                (destination, site.pos)
+            };
+            out.push((a, b, sim.sites.terrain(id, neigh_id), quality));
         }
     }
     out
 }
 
-pub(crate) fn map_view_items(sim: &Simulation, viewport: Extents) -> Vec<MapItem> {
-    let sites = sim
-        .sites
+pub(crate) fn map_view_territory(sim: &Simulation, viewport: Extents) -> Vec<MapTerritory> {
+    let factions: Vec<AgentId> = sim
+        .agents
+        .entries
         .iter()
-        .filter(|(_, site)| viewport.contains(site.pos))
-        .filter_map(|(site_id, site)| {
-            // Skip sites that have a location (and thus a party)
-            if site.location.is_some() {
-                return None;
-            }
-            Some(MapItem {
-                id: ObjectId(ObjectHandle::Site(site_id)),
-                kind: MapItemKind::Site,
-                name: String::default(),
-                image: "",
+        .filter(|(_, agent)| agent.flags.get(AgentFlag::IsFaction))
+        .map(|(id, _)| id)
+        .collect();
+    sim.sites
+        .within(viewport)
+        .filter_map(|(_, site)| {
+            let faction = site.controller?;
+            let colour = factions.iter().position(|&x| x == faction)?;
+            Some(MapTerritory {
                 pos: site.pos,
-                size: 1.,
-                layer: 0,
+                faction: ObjectId(ObjectHandle::Entity(sim.agents[faction].entity)),
+                colour: colour as u8,
             })
+        })
+        .collect()
+}
+
+/// The items on the map within the viewport. Given the sight of an observing
+/// faction, only what it sees is shown, and the sites it explored before as
+/// it remembers them.
+pub(crate) fn map_view_items(
+    sim: &Simulation,
+    viewport: Extents,
+    filter: MapFilter,
+    sight: Option<&Sight>,
+) -> Vec<MapItem> {
+    let sees = |pos: V2| sight.is_none_or(|sight| sight.sees(pos));
+
+    let sites = sim
+        .sites
+        .within(viewport)
+        // Skip sites that have a location (and thus a party)
+        .filter(|(_, site)| site.location.is_none() && sees(site.pos))
+        .map(|(site_id, _)| site_item(sim, site_id));
+
+    let remembered = sight
+        .into_iter()
+        .flat_map(|sight| sim.visibility.remembered(sight.faction))
+        .filter(|&(site, _)| {
+            let pos = sim.sites[site].pos;
+            viewport.contains(pos) && !sees(pos)
+        })
+        .map(|(_, item)| MapItem {
+            remembered: true,
+            ..item.clone()
         });
 
+    let (min, max) = viewport.corners();
     let parties = sim
-        .parties
-        .values()
-        .filter(|party| viewport.contains(party.pos))
-        .map(|party| {
-            let entity = &sim.entities[party.entity];
-            MapItem {
-                id: ObjectId(ObjectHandle::Entity(party.entity)),
-                kind: MapItemKind::Party,
-                name: entity.name.clone(),
-                image: party.image,
-                pos: party.pos,
-                size: party.size,
-                layer: party.layer,
-            }
-        });
+        .party_grid
+        .query(min, max)
+        .into_iter()
+        .filter_map(|id| sim.parties.get(id))
+        .filter(|party| viewport.contains(party.pos) && sees(party.pos))
+        .filter(|party| filter.shows(sim, party))
+        // Groups are drawn as their leader alone, and escorted parties as
+        // their escort
+        .filter(|party| {
+            sim.entities[party.entity].party.is_none_or(|id| {
+                sim.groups.leader_of(id).is_none() && sim.escorts.escort_of(id).is_none()
+            })
+        })
+        .map(|party| party_item(sim, party));
 
-    let mut items: Vec<_> = sites.chain(parties).collect();
+    let mut items: Vec<_> = sites.chain(remembered).chain(parties).collect();
     items.sort_by_key(|item| item.layer);
     items
 }
 
-pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Object> {
+/// How a site looks on the map: its settlement, if any, or a bare marker
+pub(crate) fn site_item(sim: &Simulation, site_id: SiteId) -> MapItem {
+    let site = &sim.sites[site_id];
+    if let Some(location) = site.location {
+        let party = sim.locations[location].party;
+        return party_item(sim, &sim.parties[party]);
+    }
+    MapItem {
+        id: ObjectId(ObjectHandle::Site(site_id)),
+        kind: MapItemKind::Site,
+        name: String::default(),
+        note: String::default(),
+        image: Cow::Borrowed(""),
+        pos: site.pos,
+        size: 1.,
+        layer: 0,
+        remembered: false,
+    }
+}
+
+fn party_item(sim: &Simulation, party: &PartyData) -> MapItem {
+    let entity = &sim.entities[party.entity];
+    let followers = entity.party.map_or(0, |id| {
+        sim.groups.members(id).len() + sim.escorts.passengers(id).len()
+    });
+    let name = match followers {
+        0 => entity.name.clone(),
+        1 => format!("{} and 1 other", entity.name),
+        n => format!("{} and {n} others", entity.name),
+    };
+    MapItem {
+        id: ObjectId(ObjectHandle::Entity(party.entity)),
+        kind: MapItemKind::Party,
+        name,
+        note: entity.note.clone(),
+        image: Cow::Borrowed(party.image),
+        pos: party.pos,
+        size: party.size * (1. + GROUP_SIZE_STEP * followers as f32),
+        layer: party.layer,
+        remembered: false,
+    }
+}
+
+// How much larger a group is drawn for each party following the leader
+const GROUP_SIZE_STEP: f32 = 0.25;
+
+pub struct RouteMeasurement {
+    pub waypoints: Vec<V2>,
+    pub distance: f32,
+    // Days of travel at the pace of a person on foot
+    pub days_on_foot: f32,
+}
+
+/// A party a faction owns, as listed by `Simulation::parties_of`
+pub struct OwnedParty {
+    pub id: ObjectId,
+    pub pos: V2,
+    // Going nowhere, with nothing to do
+    pub idle: bool,
+}
+
+impl Simulation {
+    /// The parties on the move owned by a faction, always listed in the same order
+    pub fn parties_of(&self, faction: ObjectId) -> Vec<OwnedParty> {
+        let ObjectHandle::Entity(faction) = faction.0 else {
+            return vec![];
+        };
+        let Some(faction) = self.entities.get(faction).and_then(|data| data.agent) else {
+            return vec![];
+        };
+        self.entities
+            .iter()
+            .filter(|(_, data)| {
+                data.agent.is_some_and(|agent| {
+                    crate::chronicle::faction_of(&self.agents, agent) == Some(faction)
+                })
+            })
+            .filter_map(|(entity, data)| {
+                let party = &self.parties[data.party?];
+                if party.movement_speed <= 0. {
+                    return None;
+                }
+                let busy = data
+                    .behavior
+                    .and_then(|behavior| self.beahviors.get(behavior))
+                    .is_some_and(|behavior| !behavior.plan.is_empty());
+                Some(OwnedParty {
+                    id: ObjectId(ObjectHandle::Entity(entity)),
+                    pos: party.pos,
+                    idle: party.movement.target.is_none() && !busy,
+                })
+            })
+            .collect()
+    }
+
+    /// Measures the road route between two map objects (sites or parties)
+    pub fn measure_route(&self, from: ObjectId, to: ObjectId) -> Option<RouteMeasurement> {
+        let coord_of = |id: ObjectId| match id.0 {
+            ObjectHandle::Site(site) => Some(GridCoord::at(site)),
+            ObjectHandle::Entity(entity) => self
+                .entities
+                .get(entity)
+                .and_then(|data| data.party)
+                .map(|party| self.parties[party].position),
+            _ => None,
+        };
+        let start = coord_of(from)?;
+        let end = coord_of(to)?;
+
+        let mut waypoints = vec![pos_of_grid_coordinate(&self.sites, start)];
+        if !start.is_colinear(end) {
+            let (steps, _) = self
+                .sites
+                .astar(start.closest_endpoint(), end.closest_endpoint())?;
+            waypoints.extend(steps.into_iter().map(|site| self.sites[site].pos));
+        }
+        waypoints.push(pos_of_grid_coordinate(&self.sites, end));
+        waypoints.dedup();
+
+        let distance: f32 = waypoints.windows(2).map(|w| w[0].distance(w[1])).sum();
+        let ticks = distance / (PERSON_MOVEMENT_SPEED * BASE_SPEED);
+        Some(RouteMeasurement {
+            waypoints,
+            distance,
+            days_on_foot: ticks / Date::TICKS_IN_DAY as f32,
+        })
+    }
+
+    /// How long a party would take to reach a map object (a site or a party)
+    /// if ordered there now, by the way and at the pace it would travel
+    pub fn estimate_travel(&self, subject: ObjectId, target: ObjectId) -> Option<Duration> {
+        let party_of = |entity| self.entities.get(entity).and_then(|data| data.party);
+        let ObjectHandle::Entity(subject) = subject.0 else {
+            return None;
+        };
+        let party = party_of(subject)?;
+        let start = self.parties[party].position;
+        let end = match target.0 {
+            ObjectHandle::Site(site) => GridCoord::at(site),
+            ObjectHandle::Entity(entity) => self.parties[party_of(entity)?].position,
+            _ => return None,
+        };
+
+        let path = if start == end {
+            vec![]
+        } else if start.is_colinear(end) {
+            vec![end]
+        } else {
+            let no_passages = BTreeSet::new();
+            let known = crate::exploration::known_to(self, party).unwrap_or(&no_passages);
+            let (steps, _) = self.sites.astar_with(
+                start.closest_endpoint(),
+                end.closest_endpoint(),
+                known,
+                self.groups.mobility(&self.parties, party),
+            )?;
+            path_through(start, steps, end)
+        };
+        crate::pace::Pace::of(self).travel_time(party, path)
+    }
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct ObjectCache {
+    revision: u64,
+    entries: BTreeMap<ObjectId, Option<Object>>,
+}
+
+pub(crate) fn extract_objects(sim: &mut Simulation, ids: &[ObjectId]) -> Vec<Option<Object>> {
+    let mut cache = std::mem::take(&mut sim.object_cache);
+    if cache.revision != sim.view_revision {
+        cache.entries.clear();
+        cache.revision = sim.view_revision;
+    }
+    // Forget objects that are no longer requested (eg: closed windows), and
+    // those of parties on the move, whose speed and arrival change by the tick
+    cache
+        .entries
+        .retain(|&id, _| ids.contains(&id) && !is_moving(sim, id));
+
+    let objects = ids
+        .iter()
+        .map(|&id| {
+            cache
+                .entries
+                .entry(id)
+                .or_insert_with(|| extract_object(sim, id))
+                .clone()
+        })
+        .collect();
+
+    sim.object_cache = cache;
+    objects
+}
+
+// Whether the object is a party travelling, on its own or with its group
+fn is_moving(sim: &Simulation, id: ObjectId) -> bool {
+    let ObjectHandle::Entity(entity) = id.0 else {
+        return false;
+    };
+    let Some(party) = sim.entities.get(entity).and_then(|data| data.party) else {
+        return false;
+    };
+    let leader = sim.groups.leader_of(party).unwrap_or(party);
+    !sim.parties[leader].movement.path.is_empty()
+}
+
+fn extract_object(sim: &Simulation, id: ObjectId) -> Option<Object> {
     let mut obj = Object::new();
     obj.set("id", id);
 
@@ -101,6 +543,25 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                 date.calendar_year()
             );
             obj.set("date", date);
+            obj.set("money_supply", format!("{:1.0}$", sim.currency.supply()));
+            obj.set("inflation", format!("{:1.2}", sim.currency.index()));
+            if let Some(player) = sim.players.active() {
+                obj.set("player", &sim.entities[sim.agents[player].entity].name);
+            }
+        }
+
+        ObjectHandle::Advisor => {
+            let suggestions: Vec<_> = crate::advisor::suggestions(sim)
+                .into_iter()
+                .map(|suggestion| {
+                    let mut obj = Object::new();
+                    obj.set("kind", suggestion.kind.name());
+                    obj.set("text", suggestion.text);
+                    obj.set("profit", format!("{:1.0}$", suggestion.profit));
+                    obj
+                })
+                .collect();
+            obj.set("suggestions", suggestions);
         }
 
         ObjectHandle::Entity(entity_id) => {
@@ -108,10 +569,219 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
 
             obj.set("name", &entity.name);
             obj.set("kind", entity.kind_name);
+            obj.set("note", &entity.note);
+            if let Some(lore) = lore_view(sim, entity_id) {
+                obj.set("lore", lore);
+            }
+
+            // Most recent first
+            const MAX_HISTORY: usize = 50;
+            let history: Vec<_> = sim
+                .chronicle
+                .history_of(entity_id)
+                .rev()
+                .take(MAX_HISTORY)
+                .map(|entry| {
+                    let mut item = Object::new();
+                    item.set(
+                        "date",
+                        format!(
+                            "{}/{}/{}",
+                            entry.date.calendar_day(),
+                            entry.date.calendar_month(),
+                            entry.date.calendar_year()
+                        ),
+                    );
+                    item.set("text", &entry.text);
+                    if let Some(battle) = entry.battle {
+                        item.set("battle", ObjectHandle::Battle(battle));
+                    }
+                    item
+                })
+                .collect();
+            obj.set("history", history);
 
             if let Some(agent_id) = entity.agent {
                 let agent_data = &sim.agents[agent_id];
                 obj.set("cash", format!("{:1.0}$", agent_data.cash));
+                if agent_data.tax_revenue > 0. {
+                    obj.set("tax_revenue", format!("{:1.0}$", agent_data.tax_revenue));
+                }
+
+                if let Some(guild) = crate::guilds::member_of(sim, agent_id) {
+                    obj.set("guild", &sim.entities[sim.agents[guild].entity].name);
+                }
+                if let Some(guild) = sim.guilds.entries.get(&agent_id) {
+                    let towns: Vec<_> = sim
+                        .guilds
+                        .towns_of(agent_id)
+                        .map(|town| sim.entities[sim.locations[town].entity].name.as_str())
+                        .collect();
+                    obj.set("towns", towns.join(", "));
+                    obj.set("dues", format!("{:1.0}%", guild.policy.dues * 100.));
+                    obj.set("min_profit", format!("{:1.0}$", guild.policy.min_profit));
+                    let privileges: Vec<_> = guild
+                        .privileges
+                        .keys()
+                        .map(|&faction| sim.entities[sim.agents[faction].entity].name.as_str())
+                        .collect();
+                    if !privileges.is_empty() {
+                        obj.set("privileges", privileges.join(", "));
+                    }
+                }
+
+                if agent_data.flags.get(AgentFlag::IsFaction) {
+                    obj.set("tax_rate", format!("{:1.1}%", agent_data.tax_rate * 100.));
+                    obj.set("toll_rate", format!("{:1.1}%", agent_data.toll_rate * 100.));
+                    obj.set("ledger", ObjectId(ObjectHandle::Faction(agent_id)));
+                    let played = sim.players.controls(agent_id);
+                    obj.set("playable", !played);
+                    obj.set("led_by", if played { "A player" } else { "The computer" });
+                    if agent_data.toll_revenue > 0. {
+                        obj.set("toll_revenue", format!("{:1.0}$", agent_data.toll_revenue));
+                    }
+                    let personality = &agent_data.personality;
+                    obj.set(
+                        "aggressiveness",
+                        format!("{:1.2}", personality.aggressiveness),
+                    );
+                    obj.set("trade_focus", format!("{:1.2}", personality.trade_focus));
+                    obj.set(
+                        "expansion_appetite",
+                        format!("{:1.2}", personality.expansion_appetite),
+                    );
+                    obj.set(
+                        "income_handicap",
+                        format!("{:1.2}", personality.income_handicap),
+                    );
+
+                    let opinions: Vec<_> = sim
+                        .relations
+                        .held_by(agent_id)
+                        .filter_map(|(towards, opinion)| {
+                            let towards = sim.agents.entries.get(towards)?;
+                            let mut obj = Object::new();
+                            obj.set("name", &sim.entities[towards.entity].name);
+                            obj.set("opinion", format!("{opinion:1.0}"));
+                            Some(obj)
+                        })
+                        .collect();
+                    obj.set("opinions", opinions);
+
+                    let intel: Vec<_> = sim
+                        .intel
+                        .reports_of(agent_id)
+                        .map(|(location, date)| {
+                            let location = &sim.locations[location];
+                            let mut obj = Object::new();
+                            obj.set("name", &sim.entities[location.entity].name);
+                            obj.set(
+                                "date",
+                                format!(
+                                    "{}/{}/{}",
+                                    date.calendar_day(),
+                                    date.calendar_month(),
+                                    date.calendar_year()
+                                ),
+                            );
+                            obj
+                        })
+                        .collect();
+                    obj.set("intel", intel);
+
+                    let embargoes: Vec<_> = sim
+                        .relations
+                        .embargoes_by(agent_id)
+                        .map(|embargo| {
+                            let mut obj = Object::new();
+                            let target = embargo
+                                .target
+                                .and_then(|id| sim.agents.entries.get(id))
+                                .map(|agent| sim.entities[agent.entity].name.as_str())
+                                .unwrap_or("Everyone");
+                            let good = embargo
+                                .good
+                                .map(|id| sim.good_types[id].name)
+                                .unwrap_or("All goods");
+                            obj.set("target", target);
+                            obj.set("good", good);
+                            obj
+                        })
+                        .collect();
+                    obj.set("embargoes", embargoes);
+
+                    let edicts: Vec<_> = sim
+                        .famines
+                        .edicts_of(agent_id)
+                        .map(|edict| {
+                            let mut obj = Object::new();
+                            obj.set("name", edict.name());
+                            obj
+                        })
+                        .collect();
+                    obj.set("edicts", edicts);
+
+                    let passages: Vec<_> = sim
+                        .exploration
+                        .known_by(agent_id)
+                        .filter_map(|(a, b)| {
+                            let kind = sim.sites.passage(a, b)?;
+                            let mut obj = Object::new();
+                            obj.set("kind", kind.name());
+                            obj.set("place", crate::exploration::whereabouts(sim, a, b));
+                            Some(obj)
+                        })
+                        .collect();
+                    if !passages.is_empty() {
+                        obj.set("passages", passages);
+                    }
+                }
+
+                if let Some(character) = sim.characters.get(agent_id) {
+                    obj.set(
+                        "age",
+                        format!("{} years", sim.date.years_since(character.born)),
+                    );
+                    if !character.traits.is_empty() {
+                        let traits: Vec<_> = character.traits.iter().map(|t| t.name()).collect();
+                        obj.set("traits", traits.join(", "));
+                    }
+                    obj.set("trade_skill", format!("{:1.0}%", character.trade * 100.));
+                    obj.set("travel_skill", format!("{:1.0}%", character.travel * 100.));
+                    let relative = |relation: &str, agent: AgentId| {
+                        let mut item = Object::new();
+                        item.set("relation", relation);
+                        item.set("name", &sim.entities[sim.agents[agent].entity].name);
+                        if let Some(age) = sim.characters.age(sim.date, agent) {
+                            item.set("age", age.to_string());
+                        }
+                        item
+                    };
+                    let family: Vec<_> = sim
+                        .characters
+                        .parent(agent_id)
+                        .map(|parent| relative("Parent", parent))
+                        .into_iter()
+                        .chain(
+                            sim.characters
+                                .children(agent_id)
+                                .map(|child| relative("Child", child)),
+                        )
+                        .collect();
+                    if !family.is_empty() {
+                        obj.set("family", family);
+                    }
+                }
+
+                let titles: Vec<_> = sim
+                    .ambitions
+                    .titles_of(agent_id)
+                    .filter_map(|location| sim.locations.get(location))
+                    .map(|location| sim.entities[location.entity].name.as_str())
+                    .collect();
+                if !titles.is_empty() {
+                    obj.set("titles", titles.join(", "));
+                }
 
                 struct Field {
                     tag: &'static str,
@@ -139,8 +809,126 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                 }
             }
 
-            if let Some(party) = entity.party {
-                let party = &sim.parties[party];
+            if let Some(behavior) = entity.behavior
+                && let Goal::Ambition { ambition, .. } = sim.beahviors[behavior].goal
+            {
+                obj.set("ambition", ambition.describe(sim));
+            }
+            if let Some(behavior) = entity.behavior
+                && let Some(contract) = sim.beahviors[behavior].contract
+                && let Some(contract) = sim.contracts.entries.get(contract)
+            {
+                let destination = sim.locations[contract.location].entity;
+                obj.set(
+                    "contract",
+                    format!(
+                        "{:1.0} {} to {} for {:1.0}$",
+                        contract.quantity,
+                        sim.good_types[contract.good].name.to_lowercase(),
+                        sim.entities[destination].name,
+                        contract.reward
+                    ),
+                );
+            }
+            if let Some(behavior) = entity.behavior
+                && let Goal::Migrate(migration) = sim.beahviors[behavior].goal
+                && let Some(destination) = sim.locations.get(migration.destination)
+            {
+                obj.set(
+                    "migrating_to",
+                    sim.entities[destination.entity].name.as_str(),
+                );
+                let pop = &sim.tokens.types[migration.pop];
+                obj.set("migrants", format!("{} {}", migration.size, pop.name));
+            }
+
+            if let Some(party_id) = entity.party {
+                let party = &sim.parties[party_id];
+                obj.set("mobile", party.movement_speed > 0.);
+                if party.movement_speed > 0. {
+                    let pace = crate::pace::Pace::of(sim);
+                    let leader = sim.groups.leader_of(party_id).unwrap_or(party_id);
+                    obj.set("speed", format!("{:1.2}", pace.speed(leader)));
+                    let modifiers = crate::pace::describe(&pace.modifiers(party_id));
+                    if !modifiers.is_empty() {
+                        obj.set("pace", modifiers.join(", "));
+                    }
+                    obj.set("forced_march", party.forced_march);
+                    let path = &sim.parties[leader].movement.path;
+                    if !path.is_empty()
+                        && let Some(eta) = pace.travel_time(leader, path.iter())
+                    {
+                        obj.set("eta", format!("{:1.1} days", eta.days()));
+                    }
+                }
+                if let Some(troops) = party.troops {
+                    let list: Vec<String> = sim
+                        .tokens
+                        .all_tokens_of_category(troops, TokenCategory::Military)
+                        .filter(|token| token.data.size > 0)
+                        .map(|token| format!("{} {}", token.data.size, token.typ.name))
+                        .collect();
+                    obj.set("troops", list.join(", "));
+                    let strength = crate::combat::strength(sim, party);
+                    obj.set("strength", format!("{strength:1.0}"));
+                    obj.set("armed", strength > 0.);
+                }
+                if party.movement.blocked {
+                    obj.set("movement", "Cannot reach destination");
+                }
+                match sim.groups.leader_of(party_id) {
+                    Some(leader) => {
+                        let leader = sim.parties[leader].entity;
+                        obj.set("group", sim.entities[leader].name.as_str());
+                        obj.set("grouped", true);
+                    }
+                    None if !sim.groups.members(party_id).is_empty() => {
+                        let members: Vec<&str> = sim
+                            .groups
+                            .members(party_id)
+                            .iter()
+                            .map(|&member| sim.entities[sim.parties[member].entity].name.as_str())
+                            .collect();
+                        obj.set("group", format!("Leading {}", members.join(", ")));
+                        obj.set("grouped", true);
+                    }
+                    None => {}
+                }
+                if let Some(escort) = sim.escorts.escort_of(party_id) {
+                    let escort = sim.parties[escort].entity;
+                    obj.set(
+                        "escort",
+                        format!("Escorted by {}", sim.entities[escort].name),
+                    );
+                    obj.set("escorted", true);
+                } else if !sim.escorts.passengers(party_id).is_empty() {
+                    let passengers: Vec<&str> = sim
+                        .escorts
+                        .passengers(party_id)
+                        .iter()
+                        .map(|&passenger| sim.entities[sim.parties[passenger].entity].name.as_str())
+                        .collect();
+                    obj.set("escort", format!("Escorting {}", passengers.join(", ")));
+                    obj.set("escorted", true);
+                }
+                if let Some(behavior) = entity.behavior {
+                    obj.set("stance", sim.beahviors[behavior].stance.name());
+                }
+                let waypoints = party.movement.waypoints.len();
+                if let Some(reaction) = party.movement.reaction
+                    && let Some(target) = sim.parties.get(reaction.target)
+                {
+                    let target = sim.entities[target.entity].name.as_str();
+                    if reaction.fleeing {
+                        obj.set("orders", format!("Fleeing to {target}"));
+                    } else {
+                        obj.set("orders", format!("Chasing {target}"));
+                    }
+                } else if party.movement.patrol {
+                    obj.set("orders", format!("Patrolling {} waypoints", waypoints + 1));
+                } else if waypoints > 0 {
+                    obj.set("orders", format!("{waypoints} more waypoints"));
+                }
                 obj.set(
                     "good_stock",
                     sim.good_types
@@ -157,16 +945,53 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                         })
                         .collect::<Vec<_>>(),
                 );
+                if party.carry_capacity > 0. {
+                    let load = party.good_stock.weight(&sim.good_types);
+                    obj.set("cargo", format!("{load:1.0}/{:1.0}", party.carry_capacity));
+                }
+                if party.carry_capacity > 0.
+                    && let Some(location) = transfer::location_under(sim, party_id)
+                {
+                    let market = &sim.locations[location].market;
+                    let mut exchange = Object::new();
+                    exchange.set(
+                        "location",
+                        sim.entities[sim.locations[location].entity].name.as_str(),
+                    );
+                    exchange.set("loadable", transfer::can_load(sim, party_id, location));
+                    exchange.set(
+                        "goods",
+                        sim.good_types
+                            .iter()
+                            .map(|(good_id, good_data)| {
+                                let mut obj = Object::new();
+                                obj.set("tag", good_data.tag);
+                                obj.set("name", good_data.name);
+                                obj.set("carried", format!("{:1.0}", party.good_stock[good_id]));
+                                obj.set("stock", format!("{:1.0}", market.goods[good_id].stock));
+                                obj
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                    obj.set("transfer", exchange);
+                }
             }
 
-            if let Some(location) = entity.location {
-                let location = &sim.locations[location];
+            if let Some(location_id) = entity.location {
+                let location = &sim.locations[location_id];
                 let mut entry = Object::new();
                 entry.set("population", location.population.to_string());
+                entry.set(
+                    "garrison",
+                    format!("{:1.0}", crate::combat::garrison_strength(sim, location_id)),
+                );
                 entry.set(
                     "prosperity",
                     format!("{:1.2}%", (location.prosperity * 100.0)),
                 );
+                if let Some(target) = sim.outlook.target(location_id) {
+                    entry.set("prosperity_target", format!("{:1.2}%", target * 100.));
+                }
 
                 entry.set(
                     "food",
@@ -176,6 +1001,48 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                     ),
                 );
                 entry.set("income", format!("{:1.0}$", location.market.income));
+                entry.set("wages", format!("{:1.0}$", location.market.wages));
+                entry.set(
+                    "travellers",
+                    sim.hospitality.travellers(location_id).to_string(),
+                );
+                entry.set(
+                    "hospitality",
+                    format!("{:1.0}$", sim.hospitality.earnings(location_id)),
+                );
+                entry.set("employed", format!("{:1.0}", location.market.employed));
+                entry.set(
+                    "unemployment",
+                    format!("{:1.0}%", location.market.unemployment() * 100.),
+                );
+                entry.set(
+                    "food_satisfaction",
+                    format!("{:1.0}%", location.market.food_satisfaction() * 100.),
+                );
+                entry.set(
+                    "harvest",
+                    format!("{:1.0}%", sim.famines.harvest(location.site) * 100.),
+                );
+                let modifiers = crate::modifiers::describe(
+                    &sim.good_types,
+                    &crate::famine::conditions_at(sim, location),
+                );
+                if !modifiers.is_empty() {
+                    entry.set("market_modifiers", modifiers.join(", "));
+                }
+                let starving = sim.famines.is_starving(location_id);
+                entry.set("famine", if starving { "Starving" } else { "None" });
+                let growth = sim.demography.last_change(location_id).unwrap_or(0.);
+                entry.set("growth", format!("{:+1.1}%", growth * 100.));
+                let lord = sim
+                    .ambitions
+                    .lord_of(&sim.agents, location_id)
+                    .map(|lord| sim.entities[sim.agents[lord].entity].name.as_str());
+                entry.set("lord", lord.unwrap_or("None"));
+                entry.set("managed", sim.autonomy.is_managed(location_id));
+                if let Some(guild) = sim.guilds.of_town(location_id) {
+                    entry.set("guild", &sim.entities[sim.agents[guild].entity].name);
+                }
 
                 let pops: Vec<_> = sim
                     .tokens
@@ -184,11 +1051,33 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                         let mut obj = Object::new();
                         obj.set("name", tok.typ.name);
                         obj.set("size", format!("{}", tok.data.size));
+                        obj.set("modifiers", describe_modifiers(sim.date, tok.data));
                         obj
                     })
                     .collect();
                 entry.set("pops", pops);
 
+                let mut demography = Object::new();
+                let population = location.population.max(1) as f64;
+                let classes = sim
+                    .tokens
+                    .all_tokens_of_category(location.tokens, TokenCategory::Pop)
+                    .map(|tok| (tok.typ.name.to_string(), tok.data.size as f64 / population));
+                demography.set("classes", breakdown(classes));
+                if let Some(profile) = sim.demography.profile(location_id) {
+                    let ages = crate::demography::AGE_GROUPS
+                        .iter()
+                        .zip(profile.ages)
+                        .map(|(name, share)| (name.to_string(), share));
+                    demography.set("ages", breakdown(ages));
+                    let cultures = profile
+                        .cultures
+                        .iter()
+                        .map(|(name, &share)| (name.clone(), share));
+                    demography.set("cultures", breakdown(cultures));
+                }
+                entry.set("demography", demography);
+
                 let buildings: Vec<_> = sim
                     .tokens
                     .all_tokens_of_category(location.tokens, TokenCategory::Building)
@@ -196,11 +1085,85 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                         let mut obj = Object::new();
                         obj.set("name", tok.typ.name);
                         obj.set("size", format!("{}", tok.data.size));
+                        obj.set("modifiers", describe_modifiers(sim.date, tok.data));
+                        let throughput = location.market.throughput.get(tok.data.typ);
+                        obj.set(
+                            "throughput",
+                            format!("{:1.0}%", throughput.copied().unwrap_or(0.) * 100.),
+                        );
                         obj
                     })
                     .collect();
                 entry.set("buildings", buildings);
 
+                let constructions: Vec<_> = sim
+                    .constructions
+                    .at(location_id)
+                    .map(|project| {
+                        let typ = &sim.tokens.types[project.typ];
+                        let days = typ.construction.as_ref().map_or(1, |cost| cost.days.max(1));
+                        let mut obj = Object::new();
+                        obj.set("name", typ.name);
+                        obj.set(
+                            "progress",
+                            format!("{:1.0}%", project.days_done as f64 / days as f64 * 100.),
+                        );
+                        obj.set(
+                            "status",
+                            if project.stalled {
+                                "Stalled"
+                            } else {
+                                "Building"
+                            },
+                        );
+                        obj.set(
+                            "started",
+                            format!(
+                                "{}/{}/{}",
+                                project.started.calendar_day(),
+                                project.started.calendar_month(),
+                                project.started.calendar_year()
+                            ),
+                        );
+                        obj
+                    })
+                    .collect();
+                entry.set("constructions", constructions);
+
+                let buildable: Vec<_> = sim
+                    .tokens
+                    .types
+                    .iter()
+                    .filter(|(_, typ)| typ.construction.is_some())
+                    .filter_map(|(id, typ)| {
+                        let mut obj = Object::new();
+                        obj.set("name", typ.name);
+                        obj.set("tag", sim.tags.token_types.reverse_lookup(&id)?);
+                        Some(obj)
+                    })
+                    .collect();
+                entry.set("buildable", buildable);
+
+                let contracts: Vec<_> = sim
+                    .contracts
+                    .posted_by(location_id)
+                    .map(|contract| {
+                        let mut obj = Object::new();
+                        obj.set("good", sim.good_types[contract.good].name);
+                        obj.set("quantity", format!("{:1.0}", contract.quantity));
+                        obj.set("reward", format!("{:1.0}$", contract.reward));
+                        let carrier = contract
+                            .carrier
+                            .and_then(|carrier| sim.entities.get(carrier))
+                            .map_or("Open", |carrier| carrier.name.as_str());
+                        obj.set("carrier", carrier);
+                        obj.set("days_left", contract.days_left.to_string());
+                        obj
+                    })
+                    .collect();
+                entry.set("contracts", contracts);
+                entry.set("deposits", deposits_view(sim, location.site));
+
                 let market_goods: Vec<_> = location
                     .market
                     .goods
@@ -209,7 +1172,12 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                         let mut entry = Object::new();
                         let typ = &sim.good_types[id];
                         entry.set("name", typ.name);
+                        entry.set("tag", typ.tag);
+                        entry.set("category", typ.category.name());
                         entry.set("stock", format!("{:1.1}", good.stock));
+                        let target = sim.stockpiles.target(location_id, id);
+                        entry.set("stockpile", format!("{target:1.0}"));
+                        entry.set("wasted", format!("{:1.1}", good.wasted));
                         {
                             let mark = if good.stock_delta >= 0. { "+" } else { "" };
                             entry.set("stock_delta", format!("{mark}{:1.1}", good.stock_delta));
@@ -229,12 +1197,49 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
 
                         entry.set("price", format!("{:1.2}$", good.price));
                         entry.set("target_price", format!("{:1.2}$", good.target_price));
+                        entry.set("trend", format!("{:+1.1}%", good.price_trend() * 100.));
+                        entry.set(
+                            "price_history",
+                            good.price_history.iter().copied().collect::<Vec<_>>(),
+                        );
                         entry
                     })
                     .collect();
 
                 entry.set("market_goods", market_goods);
 
+                let categories: Vec<_> = GoodCategory::iter()
+                    .filter_map(|category| {
+                        let totals = location.market.category_totals(&sim.good_types, category)?;
+                        let mut entry = Object::new();
+                        entry.set("name", category.name());
+                        entry.set("supply", format!("{:1.1}", totals.supply));
+                        entry.set("demand", format!("{:1.1}", totals.demand));
+                        entry.set("stock", format!("{:1.1}", totals.stock));
+                        entry.set(
+                            "satisfaction",
+                            format!("{:1.1}%", totals.satisfaction() * 100.),
+                        );
+                        Some(entry)
+                    })
+                    .collect();
+                entry.set("market_categories", categories);
+
+                {
+                    let influences = &sim.sites[location.site].influences;
+                    let blends = &sim.influence_blends;
+                    let market = influences.strength(
+                        crate::sites::InfluenceKind::Market,
+                        blends.get(crate::sites::InfluenceKind::Market),
+                    );
+                    let security = influences.strength(
+                        crate::sites::InfluenceKind::Security,
+                        blends.get(crate::sites::InfluenceKind::Security),
+                    );
+                    entry.set("market_access", format!("{market:1.0}"));
+                    entry.set("security", format!("{security:1.0}"));
+                }
+
                 entry.set("influences", {
                     let influences = &sim.sites[location.site].influences;
                     influences
@@ -245,6 +1250,7 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                                 "kind",
                                 match typ.kind {
                                     crate::sites::InfluenceKind::Market => "Market",
+                                    crate::sites::InfluenceKind::Security => "Security",
                                 },
                             );
                             {
@@ -258,6 +1264,38 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
                         .collect::<Vec<_>>()
                 });
 
+                let trade_routes: Vec<_> = sim
+                    .routes
+                    .of_location(location_id)
+                    .filter_map(|(_, route)| {
+                        let outgoing = route.source == location_id;
+                        let partner = if outgoing {
+                            route.destination
+                        } else {
+                            route.source
+                        };
+                        let partner = sim.locations.get(partner)?;
+                        let mut obj = Object::new();
+                        obj.set("good", sim.good_types[route.good].name);
+                        obj.set("direction", if outgoing { "Export" } else { "Import" });
+                        obj.set("partner", &sim.entities[partner.entity].name);
+                        obj.set("throughput", format!("{:1.1}", route.throughput));
+                        obj.set("travel", format!("{:1.1} days", route.travel_days));
+                        obj.set("caravans", route.caravans.len().to_string());
+                        obj.set(
+                            "established",
+                            format!(
+                                "{}/{}/{}",
+                                route.established.calendar_day(),
+                                route.established.calendar_month(),
+                                route.established.calendar_year()
+                            ),
+                        );
+                        Some(obj)
+                    })
+                    .collect();
+                entry.set("trade_routes", trade_routes);
+
                 obj.set("location", entry);
             }
 
@@ -275,6 +1313,8 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
 
                             let name = match kind {
                                 PressureType::Farmer => "Farmer",
+                                PressureType::Merchant => "Merchant",
+                                PressureType::Unrest => "Unrest",
                             };
                             item.set("name", name);
                             item.set("amount", format!("{amount:1.0}"));
@@ -287,10 +1327,152 @@ pub(super) fn extract_object(sim: &mut Simulation, id: ObjectId) -> Option<Objec
             }
         }
 
-        ObjectHandle::Site(_) => {
-            obj.set("kind", "Site");
+        ObjectHandle::Site(site) => {
+            let kind = match sim.sites.get(site) {
+                Some(_) if sim.sites.is_sea(site) => "Open sea",
+                Some(_) if sim.sites.is_port(site) => "Port",
+                _ => "Site",
+            };
+            obj.set("kind", kind);
+            if let Some(faction) = sim.sites.get(site).and_then(|site| site.controller) {
+                obj.set("controller", &sim.entities[sim.agents[faction].entity].name);
+            }
+            obj.set("deposits", deposits_view(sim, site));
+            let settled = sim.sites.get(site).and_then(|site| site.location);
+            if let Some(location) = settled.and_then(|id| sim.locations.get(id))
+                && let Some(lore) = lore_view(sim, location.entity)
+            {
+                obj.set("lore", lore);
+            }
+        }
+
+        ObjectHandle::Faction(faction) => {
+            let agent = sim.agents.entries.get(faction)?;
+            obj.set("name", &sim.entities[agent.entity].name);
+            let Some(latest) = sim.ledger.latest(faction) else {
+                return Some(obj);
+            };
+            obj.set("population", latest.population.to_string());
+            obj.set("gdp", format!("{:1.0}$", latest.gdp));
+            obj.set("treasury", format!("{:1.0}$", latest.treasury));
+            obj.set("trade_balance", format!("{:+1.0}$", latest.trade_balance));
+            obj.set("settlements", latest.settlements.to_string());
+
+            let series = |field: fn(&crate::ledger::FactionStats) -> f64| {
+                sim.ledger
+                    .history(faction)
+                    .map(|stats| field(stats) as f32)
+                    .collect::<Vec<_>>()
+            };
+            obj.set("population_history", series(|x| x.population as f64));
+            obj.set("gdp_history", series(|x| x.gdp));
+            obj.set("treasury_history", series(|x| x.treasury));
+            obj.set("trade_balance_history", series(|x| x.trade_balance));
+        }
+
+        ObjectHandle::Battle(battle) => {
+            let report = sim.chronicle.battle(battle)?;
+            let [attacker, defender] = &report.sides;
+            obj.set(
+                "name",
+                format!("{} against {}", attacker.name, defender.name),
+            );
+            obj.set(
+                "date",
+                format!(
+                    "{}/{}/{}",
+                    report.date.calendar_day(),
+                    report.date.calendar_month(),
+                    report.date.calendar_year()
+                ),
+            );
+            obj.set("attacker", &attacker.name);
+            obj.set("defender", &defender.name);
+            let winner = if report.attacker_won {
+                attacker
+            } else {
+                defender
+            };
+            obj.set("winner", &winner.name);
+
+            let modifiers = |side: &crate::combat::Side| {
+                crate::combat::describe(&side.modifiers)
+                    .into_iter()
+                    .map(|text| {
+                        let mut item = Object::new();
+                        item.set("text", text);
+                        item
+                    })
+                    .collect::<Vec<_>>()
+            };
+            obj.set("attacker_modifiers", modifiers(attacker));
+            obj.set("defender_modifiers", modifiers(defender));
+
+            let rounds: Vec<_> = report
+                .rounds
+                .iter()
+                .enumerate()
+                .map(|(idx, round)| {
+                    let mut item = Object::new();
+                    item.set("round", (idx + 1).to_string());
+                    item.set("attacker_strength", format!("{:1.0}", round.strengths[0]));
+                    item.set("defender_strength", format!("{:1.0}", round.strengths[1]));
+                    item.set("attacker_losses", round.casualties[0].to_string());
+                    item.set("defender_losses", round.casualties[1].to_string());
+                    item
+                })
+                .collect();
+            obj.set("rounds", rounds);
         }
     }
 
     Some(obj)
 }
+
+fn lore_view(sim: &Simulation, entity: EntityId) -> Option<Object> {
+    let legend = sim.lore.get(entity)?;
+    let mut obj = Object::new();
+    if let Some(etymology) = &legend.etymology {
+        obj.set("etymology", etymology);
+    }
+    if let Some(founding) = &legend.founding {
+        obj.set("founding", founding);
+    }
+    Some(obj)
+}
+
+fn deposits_view(sim: &Simulation, site: SiteId) -> Vec<Object> {
+    let Some(site) = sim.sites.get(site) else {
+        return vec![];
+    };
+    site.rgo
+        .deposits
+        .iter()
+        .map(|deposit| {
+            let mut obj = Object::new();
+            obj.set("name", sim.good_types[deposit.good].name);
+            obj.set("rate", format!("{:1.2}", deposit.rate * deposit.level));
+            obj.set("capacity", format!("{}", deposit.capacity));
+            obj.set("workers", format!("{:1.0}", deposit.workers));
+            obj.set("level", format!("{:1.0}%", deposit.level * 100.));
+            obj
+        })
+        .collect()
+}
+
+// Parts of a whole, listed with their shares and as a series to chart them
+fn breakdown(parts: impl Iterator<Item = (String, f64)>) -> Object {
+    let mut obj = Object::new();
+    let mut list = vec![];
+    let mut shares = vec![];
+    for (name, share) in parts {
+        let mut part = Object::new();
+        part.set("name", name);
+        part.set("share", format!("{:1.0}%", share * 100.));
+        list.push(part);
+        shares.push(share as f32);
+    }
+    obj.set("parts", list);
+    obj.set("shares", shares);
+    obj
+}
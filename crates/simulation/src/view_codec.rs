@@ -0,0 +1,599 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use slotmap::KeyData;
+
+use crate::espionage::CovertAction;
+use crate::object::*;
+use crate::simulation::V2;
+use crate::sites::Terrain;
+use crate::view::*;
+
+// Compact binary encoding of SimView, delta-compressed against the
+// previously encoded view. Encoder and decoder must see the same sequence
+// of views, so each keeps its own copy of the last one.
+
+const SAME: u8 = 0;
+const CHANGED: u8 = 1;
+
+#[derive(Default)]
+pub struct ViewEncoder {
+    previous: SimView,
+}
+
+impl ViewEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn encode(&mut self, view: &SimView) -> Vec<u8> {
+        let mut w = Writer::default();
+
+        let lines_changed = view.map_lines != self.previous.map_lines;
+        w.u8(if lines_changed { CHANGED } else { SAME });
+        if lines_changed {
+            w.uint(view.map_lines.len() as u64);
+            for &(a, b, terrain, quality) in &view.map_lines {
+                w.v2(a);
+                w.v2(b);
+                w.terrain(terrain);
+                w.u8(quality);
+            }
+        }
+
+        w.delta_list(&view.map_items, &self.previous.map_items, |w, item| {
+            w.object_id(item.id);
+            w.u8(match item.kind {
+                MapItemKind::Site => 0,
+                MapItemKind::Party => 1,
+            });
+            w.str(&item.name);
+            w.str(&item.note);
+            w.str(&item.image);
+            w.v2(item.pos);
+            w.f32(item.size);
+            w.u8(item.layer);
+            w.u8(item.remembered as u8);
+        });
+
+        w.delta_list(
+            &view.map_territory,
+            &self.previous.map_territory,
+            |w, territory| {
+                w.v2(territory.pos);
+                w.object_id(territory.faction);
+                w.u8(territory.colour);
+            },
+        );
+
+        w.delta_list(&view.objects, &self.previous.objects, |w, obj| match obj {
+            Some(obj) => {
+                w.u8(1);
+                w.object(obj);
+            }
+            None => w.u8(0),
+        });
+
+        // Events and warnings never repeat between views, so they are not delta-compressed
+        w.uint(view.events.len() as u64);
+        for event in &view.events {
+            match event {
+                SimEvent::NewDay => w.u8(0),
+                SimEvent::EntityCreated { id } => {
+                    w.u8(1);
+                    w.object_id(*id);
+                }
+                SimEvent::TradeResolved { party, location } => {
+                    w.u8(2);
+                    w.object_id(*party);
+                    w.object_id(*location);
+                }
+                SimEvent::CovertActionResolved {
+                    actor,
+                    target,
+                    action,
+                    succeeded,
+                    discovered,
+                } => {
+                    w.u8(3);
+                    w.object_id(*actor);
+                    w.object_id(*target);
+                    w.u8(match action {
+                        CovertAction::ScoutMarket => 0,
+                        CovertAction::StealTreasury => 1,
+                        CovertAction::InciteUnrest => 2,
+                    });
+                    w.u8(*succeeded as u8);
+                    w.u8(*discovered as u8);
+                }
+                SimEvent::FamineChanged { location, starving } => {
+                    w.u8(4);
+                    w.object_id(*location);
+                    w.u8(*starving as u8);
+                }
+                SimEvent::PathBlocked { party } => {
+                    w.u8(5);
+                    w.object_id(*party);
+                }
+                SimEvent::FactionEliminated { faction } => {
+                    w.u8(6);
+                    w.object_id(*faction);
+                }
+                SimEvent::Battle {
+                    winner,
+                    loser,
+                    destroyed,
+                } => {
+                    w.u8(7);
+                    w.object_id(*winner);
+                    w.object_id(*loser);
+                    w.u8(*destroyed as u8);
+                }
+            }
+        }
+
+        w.uint(view.warnings.len() as u64);
+        for warning in &view.warnings {
+            match warning {
+                SimWarning::UndefinedTag { kind, tag } => {
+                    w.u8(0);
+                    w.tag_kind(*kind);
+                    w.str(tag);
+                }
+                SimWarning::LocationWithoutParty { name } => {
+                    w.u8(1);
+                    w.str(name);
+                }
+                SimWarning::DuplicateTag { kind, tag } => {
+                    w.u8(2);
+                    w.tag_kind(*kind);
+                    w.str(tag);
+                }
+                SimWarning::NotABuilding { tag } => {
+                    w.u8(3);
+                    w.str(tag);
+                }
+                SimWarning::NotPermitted => w.u8(4),
+                SimWarning::NotAFaction { tag } => {
+                    w.u8(5);
+                    w.str(tag);
+                }
+                SimWarning::BrokenInvariant { tick, diagnostic } => {
+                    w.u8(6);
+                    w.uint(*tick);
+                    w.str(diagnostic);
+                }
+            }
+        }
+
+        self.previous = view.clone();
+        w.0
+    }
+}
+
+#[derive(Default)]
+pub struct ViewDecoder {
+    previous: SimView,
+}
+
+impl ViewDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns None if the bytes are malformed or were not encoded against
+    /// the same previous view as this decoder holds.
+    pub fn decode(&mut self, bytes: &[u8]) -> Option<SimView> {
+        let mut r = Reader { bytes, cursor: 0 };
+
+        let map_lines = match r.u8()? {
+            SAME => self.previous.map_lines.clone(),
+            CHANGED => {
+                let len = r.len()?;
+                let mut lines = Vec::with_capacity(len);
+                for _ in 0..len {
+                    lines.push((r.v2()?, r.v2()?, r.terrain()?, r.u8()?));
+                }
+                lines
+            }
+            _ => return None,
+        };
+
+        let map_items = r.delta_list(&self.previous.map_items, |r| {
+            Some(MapItem {
+                id: r.object_id()?,
+                kind: match r.u8()? {
+                    0 => MapItemKind::Site,
+                    1 => MapItemKind::Party,
+                    _ => return None,
+                },
+                name: r.string()?,
+                note: r.string()?,
+                image: Cow::Owned(r.string()?),
+                pos: r.v2()?,
+                size: r.f32()?,
+                layer: r.u8()?,
+                remembered: r.u8()? != 0,
+            })
+        })?;
+
+        let map_territory = r.delta_list(&self.previous.map_territory, |r| {
+            Some(MapTerritory {
+                pos: r.v2()?,
+                faction: r.object_id()?,
+                colour: r.u8()?,
+            })
+        })?;
+
+        let objects = r.delta_list(&self.previous.objects, |r| match r.u8()? {
+            0 => Some(None),
+            1 => Some(Some(r.object()?)),
+            _ => None,
+        })?;
+
+        let len = r.len()?;
+        let mut events = Vec::with_capacity(len);
+        for _ in 0..len {
+            let event = match r.u8()? {
+                0 => SimEvent::NewDay,
+                1 => SimEvent::EntityCreated { id: r.object_id()? },
+                2 => SimEvent::TradeResolved {
+                    party: r.object_id()?,
+                    location: r.object_id()?,
+                },
+                3 => SimEvent::CovertActionResolved {
+                    actor: r.object_id()?,
+                    target: r.object_id()?,
+                    action: match r.u8()? {
+                        0 => CovertAction::ScoutMarket,
+                        1 => CovertAction::StealTreasury,
+                        2 => CovertAction::InciteUnrest,
+                        _ => return None,
+                    },
+                    succeeded: r.u8()? != 0,
+                    discovered: r.u8()? != 0,
+                },
+                4 => SimEvent::FamineChanged {
+                    location: r.object_id()?,
+                    starving: r.u8()? != 0,
+                },
+                5 => SimEvent::PathBlocked {
+                    party: r.object_id()?,
+                },
+                6 => SimEvent::FactionEliminated {
+                    faction: r.object_id()?,
+                },
+                7 => SimEvent::Battle {
+                    winner: r.object_id()?,
+                    loser: r.object_id()?,
+                    destroyed: r.u8()? != 0,
+                },
+                _ => return None,
+            };
+            events.push(event);
+        }
+
+        let len = r.len()?;
+        let mut warnings = Vec::with_capacity(len);
+        for _ in 0..len {
+            let warning = match r.u8()? {
+                0 => SimWarning::UndefinedTag {
+                    kind: r.tag_kind()?,
+                    tag: r.string()?,
+                },
+                1 => SimWarning::LocationWithoutParty { name: r.string()? },
+                2 => SimWarning::DuplicateTag {
+                    kind: r.tag_kind()?,
+                    tag: r.string()?,
+                },
+                3 => SimWarning::NotABuilding { tag: r.string()? },
+                4 => SimWarning::NotPermitted,
+                5 => SimWarning::NotAFaction { tag: r.string()? },
+                6 => SimWarning::BrokenInvariant {
+                    tick: r.uint()?,
+                    diagnostic: r.string()?,
+                },
+                _ => return None,
+            };
+            warnings.push(warning);
+        }
+
+        if r.cursor != bytes.len() {
+            return None;
+        }
+
+        let view = SimView {
+            map_lines,
+            map_items,
+            map_territory,
+            objects,
+            events,
+            warnings,
+        };
+        self.previous = view.clone();
+        Some(view)
+    }
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, x: u8) {
+        self.0.push(x);
+    }
+
+    // LEB128
+    fn uint(&mut self, mut x: u64) {
+        loop {
+            let byte = (x & 0x7f) as u8;
+            x >>= 7;
+            if x == 0 {
+                self.0.push(byte);
+                return;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    fn f32(&mut self, x: f32) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn v2(&mut self, v: V2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    fn object_id(&mut self, id: ObjectId) {
+        match id.0 {
+            ObjectHandle::Null => self.u8(0),
+            ObjectHandle::Global => self.u8(1),
+            ObjectHandle::Site(id) => {
+                self.u8(2);
+                self.uint(slotmap::Key::data(&id).as_ffi());
+            }
+            ObjectHandle::Entity(id) => {
+                self.u8(3);
+                self.uint(slotmap::Key::data(&id).as_ffi());
+            }
+            ObjectHandle::Advisor => self.u8(4),
+            ObjectHandle::Faction(id) => {
+                self.u8(5);
+                self.uint(slotmap::Key::data(&id).as_ffi());
+            }
+            ObjectHandle::Battle(id) => {
+                self.u8(6);
+                self.uint(id as u64);
+            }
+        }
+    }
+
+    fn terrain(&mut self, terrain: Terrain) {
+        self.u8(match terrain {
+            Terrain::Road => 0,
+            Terrain::Trail => 1,
+            Terrain::Forest => 2,
+            Terrain::Ford => 3,
+            Terrain::MountainPass => 4,
+            Terrain::River => 5,
+            Terrain::Sea => 6,
+        });
+    }
+
+    fn tag_kind(&mut self, kind: TagKind) {
+        self.u8(match kind {
+            TagKind::Site => 0,
+            TagKind::Agent => 1,
+            TagKind::Good => 2,
+            TagKind::TokenType => 3,
+            TagKind::Template => 4,
+        });
+    }
+
+    fn object(&mut self, obj: &Object) {
+        self.uint(obj.0.len() as u64);
+        for (tag, value) in &obj.0 {
+            self.str(tag);
+            match value {
+                Value::Id(id) => {
+                    self.u8(0);
+                    self.object_id(*id);
+                }
+                Value::Flag(flag) => {
+                    self.u8(1);
+                    self.u8(*flag as u8);
+                }
+                Value::String(str) => {
+                    self.u8(2);
+                    self.str(str);
+                }
+                Value::Child(child) => {
+                    self.u8(3);
+                    self.object(child);
+                }
+                Value::List(items) => {
+                    self.u8(4);
+                    self.uint(items.len() as u64);
+                    for item in items {
+                        self.object(item);
+                    }
+                }
+                Value::Series(values) => {
+                    self.u8(5);
+                    self.uint(values.len() as u64);
+                    for &value in values {
+                        self.f32(value);
+                    }
+                }
+            }
+        }
+    }
+
+    // Items equal to the one at the same index in the previous list are
+    // written as a single marker byte
+    fn delta_list<T: PartialEq>(
+        &mut self,
+        items: &[T],
+        previous: &[T],
+        mut write: impl FnMut(&mut Self, &T),
+    ) {
+        self.uint(items.len() as u64);
+        for (idx, item) in items.iter().enumerate() {
+            if previous.get(idx) == Some(item) {
+                self.u8(SAME);
+            } else {
+                self.u8(CHANGED);
+                write(self, item);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.cursor.checked_add(n)?;
+        let slice = self.bytes.get(self.cursor..end)?;
+        self.cursor = end;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|x| x[0])
+    }
+
+    fn uint(&mut self) -> Option<u64> {
+        let mut out = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            out |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(out);
+            }
+        }
+        None
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let len = usize::try_from(self.uint()?).ok()?;
+        // Every element takes at least one byte, reject absurd lengths early
+        if len > self.bytes.len() - self.cursor {
+            return None;
+        }
+        Some(len)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        let bytes = self.take(4)?;
+        Some(f32::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn v2(&mut self) -> Option<V2> {
+        Some(V2::new(self.f32()?, self.f32()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    fn object_id(&mut self) -> Option<ObjectId> {
+        let handle = match self.u8()? {
+            0 => ObjectHandle::Null,
+            1 => ObjectHandle::Global,
+            2 => ObjectHandle::Site(KeyData::from_ffi(self.uint()?).into()),
+            3 => ObjectHandle::Entity(KeyData::from_ffi(self.uint()?).into()),
+            4 => ObjectHandle::Advisor,
+            5 => ObjectHandle::Faction(KeyData::from_ffi(self.uint()?).into()),
+            6 => ObjectHandle::Battle(self.uint()? as usize),
+            _ => return None,
+        };
+        Some(ObjectId(handle))
+    }
+
+    fn terrain(&mut self) -> Option<Terrain> {
+        match self.u8()? {
+            0 => Some(Terrain::Road),
+            1 => Some(Terrain::Trail),
+            2 => Some(Terrain::Forest),
+            3 => Some(Terrain::Ford),
+            4 => Some(Terrain::MountainPass),
+            5 => Some(Terrain::River),
+            6 => Some(Terrain::Sea),
+            _ => None,
+        }
+    }
+
+    fn tag_kind(&mut self) -> Option<TagKind> {
+        match self.u8()? {
+            0 => Some(TagKind::Site),
+            1 => Some(TagKind::Agent),
+            2 => Some(TagKind::Good),
+            3 => Some(TagKind::TokenType),
+            4 => Some(TagKind::Template),
+            _ => None,
+        }
+    }
+
+    fn object(&mut self) -> Option<Object> {
+        let len = self.len()?;
+        let mut fields = BTreeMap::new();
+        for _ in 0..len {
+            let tag = self.string()?;
+            let value = match self.u8()? {
+                0 => Value::Id(self.object_id()?),
+                1 => Value::Flag(self.u8()? != 0),
+                2 => Value::String(self.string()?),
+                3 => Value::Child(self.object()?),
+                4 => {
+                    let len = self.len()?;
+                    let mut items = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        items.push(self.object()?);
+                    }
+                    Value::List(items)
+                }
+                5 => {
+                    let len = self.len()?;
+                    let mut values = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        values.push(self.f32()?);
+                    }
+                    Value::Series(values)
+                }
+                _ => return None,
+            };
+            fields.insert(tag, value);
+        }
+        Some(Object(fields))
+    }
+
+    fn delta_list<T: Clone>(
+        &mut self,
+        previous: &[T],
+        mut read: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let len = self.len()?;
+        let mut items = Vec::with_capacity(len);
+        for idx in 0..len {
+            let item = match self.u8()? {
+                SAME => previous.get(idx)?.clone(),
+                CHANGED => read(self)?,
+                _ => return None,
+            };
+            items.push(item);
+        }
+        Some(items)
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::chronicle::faction_of;
+use crate::simulation::*;
+use crate::sites::SiteId;
+use crate::view::MapItem;
+
+// Distance a party on the move scouts around it, and a settlement
+const PARTY_SIGHT: f32 = 6.;
+const SETTLEMENT_SIGHT: f32 = 10.;
+
+/// The sites each faction has explored, as they looked when last in sight
+#[derive(Default, Clone)]
+pub(crate) struct Memories {
+    seen: BTreeMap<AgentId, BTreeMap<SiteId, MapItem>>,
+}
+
+impl Memories {
+    pub fn remembered(&self, faction: AgentId) -> impl Iterator<Item = (SiteId, &MapItem)> + '_ {
+        self.seen
+            .get(&faction)
+            .into_iter()
+            .flatten()
+            .map(|(&site, item)| (site, item))
+    }
+
+    pub fn forget_faction(&mut self, faction: AgentId) {
+        self.seen.remove(&faction);
+    }
+}
+
+/// What a faction sees at present: all within scouting range of its parties
+/// and settlements
+pub(crate) struct Sight {
+    pub faction: AgentId,
+    eyes: Vec<(V2, f32)>,
+}
+
+impl Sight {
+    pub fn of(sim: &Simulation, faction: AgentId) -> Self {
+        let eyes = sim
+            .parties
+            .values()
+            .filter(|party| faction_of_party(sim, party) == Some(faction))
+            .map(|party| eye(sim, party))
+            .collect();
+        Self { faction, eyes }
+    }
+
+    // The sight of every faction with parties or settlements, in one pass
+    // over the parties
+    fn of_all(sim: &Simulation) -> Vec<Self> {
+        let mut eyes: BTreeMap<AgentId, Vec<(V2, f32)>> = BTreeMap::new();
+        for party in sim.parties.values() {
+            if let Some(faction) = faction_of_party(sim, party)
+                && sim.agents[faction].flags.get(AgentFlag::IsFaction)
+            {
+                eyes.entry(faction).or_default().push(eye(sim, party));
+            }
+        }
+        eyes.into_iter()
+            .map(|(faction, eyes)| Self { faction, eyes })
+            .collect()
+    }
+
+    pub fn sees(&self, pos: V2) -> bool {
+        self.eyes
+            .iter()
+            .any(|&(eye, range)| eye.distance(pos) <= range)
+    }
+}
+
+// Where a party looks out from, and how far it sees
+fn eye(sim: &Simulation, party: &PartyData) -> (V2, f32) {
+    let pos = crate::tick::pos_of_grid_coordinate(&sim.sites, party.position);
+    let range = if party.location.is_some() {
+        SETTLEMENT_SIGHT
+    } else {
+        PARTY_SIGHT
+    };
+    (pos, range)
+}
+
+fn faction_of_party(sim: &Simulation, party: &PartyData) -> Option<AgentId> {
+    let agent = sim.entities[party.entity].agent?;
+    faction_of(&sim.agents, agent)
+}
+
+/// Has every faction remember the sites in its sight as they look now
+pub(crate) fn tick(sim: &mut Simulation) {
+    for sight in Sight::of_all(sim) {
+        observe(sim, &sight);
+    }
+}
+
+// Remembers the sites in sight of a faction as they look now, given the map
+// item of each
+fn observe(sim: &mut Simulation, sight: &Sight) {
+    let mut sites = BTreeSet::new();
+    for &(eye, range) in &sight.eyes {
+        sites.extend(
+            sim.sites
+                .within(Extents::around(eye, range))
+                .filter(|(_, site)| eye.distance(site.pos) <= range)
+                .map(|(id, _)| id),
+        );
+    }
+    let in_sight: Vec<(SiteId, MapItem)> = sites
+        .into_iter()
+        .map(|id| (id, crate::view::site_item(sim, id)))
+        .collect();
+    let seen = sim.visibility.seen.entry(sight.faction).or_default();
+    for (site, item) in in_sight {
+        seen.insert(site, item);
+    }
+}
@@ -0,0 +1,273 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use util::spatial_grid::SpatialGrid;
+
+use crate::simulation::*;
+use crate::sites::*;
+
+/// Parameters for a procedurally generated site graph
+#[derive(Clone, Copy, Debug)]
+pub struct WorldGenParams {
+    /// Half the side of the square map, in world units
+    pub size: f32,
+    /// Average number of sites per 10x10 world units
+    pub density: f32,
+    pub seed: u64,
+}
+
+impl Default for WorldGenParams {
+    fn default() -> Self {
+        Self {
+            size: 30.,
+            density: 1.,
+            seed: 0,
+        }
+    }
+}
+
+// Sites less fertile than this are left as bare waypoints
+const WAYPOINT_FERTILITY: f64 = 0.2;
+// Connections through land less fertile than this are overgrown
+const FOREST_FERTILITY: f64 = 0.35;
+
+pub(crate) fn generate(sim: &mut Simulation, params: &WorldGenParams) {
+    let mut rng = SmallRng::seed_from_u64(params.seed);
+
+    let spacing = min_distance(params);
+    let points = poisson_disk(&mut rng, params, spacing);
+    let edges = relative_neighbourhood(&points, spacing);
+
+    let wheat = sim.tags.goods.lookup("wheat");
+    let lumber = sim.tags.goods.lookup("lumber");
+    let noise = ValueNoise::new(params.seed);
+
+    let mut ids = Vec::with_capacity(points.len());
+    for (idx, &pos) in points.iter().enumerate() {
+        let fertility = noise.fertility(pos);
+
+        let mut rates = vec![];
+        if fertility >= WAYPOINT_FERTILITY {
+            if let Some(wheat) = wheat {
+                rates.push((wheat, 0.6 + 1.8 * fertility));
+            }
+            if let Some(lumber) = lumber {
+                rates.push((lumber, 0.4 + 1.2 * (1. - fertility)));
+            }
+        }
+        let rgo = SiteRGO::with_rates(rates, 5_000, lumber);
+
+        let tag = format!("site_{idx}");
+        let id = sim.sites.define(tag.as_str(), pos, rgo);
+        sim.tags.sites.insert(tag, id);
+        ids.push(id);
+    }
+
+    for (a, b) in edges {
+        let (pa, pb) = (points[a], points[b]);
+        let middle = V2 {
+            x: (pa.x + pb.x) / 2.,
+            y: (pa.y + pb.y) / 2.,
+        };
+        // Ways between barren waypoints are no more than trails, and those
+        // through the wilds run through forest
+        let terrain = if noise.fertility(pa).max(noise.fertility(pb)) < WAYPOINT_FERTILITY {
+            Terrain::Trail
+        } else if noise.fertility(middle) < FOREST_FERTILITY {
+            Terrain::Forest
+        } else {
+            Terrain::Road
+        };
+        sim.sites.connect(ids[a], ids[b], terrain);
+    }
+}
+
+// Dense poisson disk packings place about one point per 1.5 r^2
+fn min_distance(params: &WorldGenParams) -> f32 {
+    (100. / (1.5 * params.density.max(0.01))).sqrt()
+}
+
+// Dart throwing: candidates closer than the minimum distance to an accepted
+// point are rejected, until too many attempts in a row fail
+fn poisson_disk(rng: &mut SmallRng, params: &WorldGenParams, min_distance: f32) -> Vec<V2> {
+    const MAX_FAILURES: usize = 500;
+    let size = params.size.max(1.);
+
+    let mut points: Vec<V2> = vec![];
+    // Only the points in the cells around a candidate can be too close to it
+    let mut grid: SpatialGrid<usize> = SpatialGrid::new(min_distance);
+    let mut failures = 0;
+    while failures < MAX_FAILURES {
+        let candidate = V2::new(rng.gen_range(-size..=size), rng.gen_range(-size..=size));
+        let (min, max) = square_around(candidate, min_distance);
+        let near = grid.query(min, max);
+        if near
+            .iter()
+            .all(|&idx| points[idx].distance(candidate) >= min_distance)
+        {
+            grid.insert_point(points.len(), (candidate.x, candidate.y));
+            points.push(candidate);
+            failures = 0;
+        } else {
+            failures += 1;
+        }
+    }
+    points
+}
+
+// Sectors the surroundings of a point are split in, narrow enough that a
+// point in one blocks any further out in the same sector
+const SECTORS: usize = 12;
+
+// Two points are connected unless a third one is closer to both of them
+// than they are to each other. Such a third point lies nearer the first than
+// the second does, so each point only looks as far around as it takes to
+// find a point in every sector.
+fn relative_neighbourhood(points: &[V2], spacing: f32) -> Vec<(usize, usize)> {
+    let mut grid = SpatialGrid::new(spacing);
+    for (idx, point) in points.iter().enumerate() {
+        grid.insert_point(idx, (point.x, point.y));
+    }
+    let bounds = points.iter().fold(
+        (V2::new(f32::MAX, f32::MAX), V2::new(f32::MIN, f32::MIN)),
+        |(min, max), p| {
+            (
+                V2::new(min.x.min(p.x), min.y.min(p.y)),
+                V2::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+
+    let mut edges = vec![];
+    for (a, &pa) in points.iter().enumerate() {
+        let mut radius = spacing;
+        let near = loop {
+            radius *= 2.;
+            let (min, max) = square_around(pa, radius);
+            let near: Vec<usize> = grid
+                .query(min, max)
+                .into_iter()
+                .filter(|&c| c != a && points[c].distance(pa) <= radius)
+                .collect();
+            if is_enclosed(pa, radius, &near, points, bounds) {
+                break near;
+            }
+        };
+        for &b in near.iter().filter(|&&b| b > a) {
+            let distance = pa.distance(points[b]);
+            let blocked = near
+                .iter()
+                .filter(|&&c| c != b)
+                .any(|&c| pa.distance(points[c]).max(points[b].distance(points[c])) < distance);
+            if !blocked {
+                edges.push((a, b));
+            }
+        }
+    }
+    edges
+}
+
+// Whether every sector around a point holds one of the points near it, or
+// reaches no further than the radius within the bounds of all points
+fn is_enclosed(center: V2, radius: f32, near: &[usize], points: &[V2], bounds: (V2, V2)) -> bool {
+    let width = std::f32::consts::TAU / SECTORS as f32;
+    let sector_of = |p: V2| {
+        let angle = (p.y - center.y)
+            .atan2(p.x - center.x)
+            .rem_euclid(std::f32::consts::TAU);
+        ((angle / width) as usize).min(SECTORS - 1)
+    };
+    let mut held = [false; SECTORS];
+    for &idx in near {
+        held[sector_of(points[idx])] = true;
+    }
+
+    // The furthest reach of a sector within the bounds is where one of its
+    // edges leaves them, or a corner of them it takes in
+    let (min, max) = bounds;
+    let corners = [min, V2::new(max.x, min.y), max, V2::new(min.x, max.y)];
+    let exit = |angle: f32| {
+        let (dx, dy) = (angle.cos(), angle.sin());
+        let along = |from: f32, lo: f32, hi: f32, d: f32| {
+            if d > 0. {
+                (hi - from) / d
+            } else if d < 0. {
+                (lo - from) / d
+            } else {
+                f32::INFINITY
+            }
+        };
+        along(center.x, min.x, max.x, dx).min(along(center.y, min.y, max.y, dy))
+    };
+    (0..SECTORS).all(|sector| {
+        held[sector] || {
+            let start = sector as f32 * width;
+            let reach = corners
+                .iter()
+                .filter(|&&corner| sector_of(corner) == sector)
+                .map(|&corner| corner.distance(center))
+                .fold(exit(start).max(exit(start + width)), f32::max);
+            reach <= radius
+        }
+    })
+}
+
+fn square_around(center: V2, half_side: f32) -> ((f32, f32), (f32, f32)) {
+    (
+        (center.x - half_side, center.y - half_side),
+        (center.x + half_side, center.y + half_side),
+    )
+}
+
+struct ValueNoise {
+    seed: u64,
+}
+
+impl ValueNoise {
+    // World units between lattice points of the coarsest octave
+    const SCALE: f32 = 20.;
+
+    fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    // Two octaves of value noise, in the [0, 1] range
+    fn fertility(&self, pos: V2) -> f64 {
+        let coarse = self.sample(pos.x / Self::SCALE, pos.y / Self::SCALE, 0);
+        let fine = self.sample(2. * pos.x / Self::SCALE, 2. * pos.y / Self::SCALE, 1);
+        (coarse * 2. + fine) / 3.
+    }
+
+    fn sample(&self, x: f32, y: f32, octave: u64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+        let (ix, iy) = (x0 as i64, y0 as i64);
+
+        let corner = |dx: i64, dy: i64| self.hash(ix + dx, iy + dy, octave);
+        let top = lerp(corner(0, 0), corner(1, 0), tx);
+        let bottom = lerp(corner(0, 1), corner(1, 1), tx);
+        lerp(top, bottom, ty)
+    }
+
+    // Deterministic value in [0, 1] for a lattice point
+    fn hash(&self, x: i64, y: i64, octave: u64) -> f64 {
+        let mut h = self.seed ^ 0x9e37_79b9_7f4a_7c15;
+        for v in [x as u64, y as u64, octave] {
+            h ^= v
+                .wrapping_add(0x9e37_79b9_7f4a_7c15)
+                .wrapping_add(h << 6)
+                .wrapping_add(h >> 2);
+            h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            h ^= h >> 31;
+        }
+        (h >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn smoothstep(t: f32) -> f64 {
+    let t = t as f64;
+    t * t * (3. - 2. * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
@@ -5,6 +5,7 @@ use strum::{EnumCount, IntoEnumIterator};
 
 pub trait EnumMapKey: IntoEnumIterator + EnumCount + Copy + Into<usize> {}
 
+#[derive(Clone)]
 pub struct EnumMap<K: EnumMapKey, V, const N: usize> {
     key_type: PhantomData<K>,
     data: ArrayVec<V, N>,
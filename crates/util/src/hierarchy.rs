@@ -2,6 +2,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use crate::arena::{Arena, ArenaSafe};
 
+#[derive(Clone)]
 pub struct Hierarchy<P, C> {
     parent_to_child: BTreeSet<(P, Entry<C>)>,
     child_to_parent: BTreeMap<C, P>,
@@ -59,6 +60,11 @@ where
         self.child_to_parent.get(&child).copied()
     }
 
+    /// All (child, parent) pairs
+    pub fn iter(&self) -> impl Iterator<Item = (C, P)> + '_ {
+        self.child_to_parent.iter().map(|(&c, &p)| (c, p))
+    }
+
     pub fn remove_child(&mut self, child: C) {
         if let Some(parent) = self.child_to_parent.remove(&child) {
             self.parent_to_child.remove(&(parent, Entry::new(child)));
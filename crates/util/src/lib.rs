@@ -2,4 +2,6 @@ pub mod arena;
 pub mod enum_map;
 pub mod hierarchy;
 pub mod one_to_one_map;
+pub mod ring_buffer;
+pub mod spatial_grid;
 pub mod tally;
@@ -1,5 +1,6 @@
 use slotmap::{Key, SecondaryMap};
 
+#[derive(Clone)]
 pub struct OneToOneMap<A, B>
 where
     A: Key,
@@ -0,0 +1,58 @@
+/// Keeps the last `capacity` pushed values, overwriting the oldest one when full
+#[derive(Clone, Default)]
+pub struct RingBuffer<T> {
+    items: Vec<T>,
+    // Index of the oldest item, once the buffer is full
+    head: usize,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    /// Storage is only allocated on the first push
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            head: 0,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.items.len() < self.capacity {
+            self.items.push(value);
+        } else {
+            self.items[self.head] = value;
+            self.head = (self.head + 1) % self.capacity;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn oldest(&self) -> Option<&T> {
+        self.items.get(self.head)
+    }
+
+    pub fn newest(&self) -> Option<&T> {
+        let idx = (self.head + self.items.len()).checked_sub(1)? % self.items.len();
+        self.items.get(idx)
+    }
+
+    /// From the oldest to the newest value
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        let (newer, older) = self.items.split_at(self.head);
+        older.iter().chain(newer.iter())
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+type Cell = (i32, i32);
+
+/// Buckets keys by the square cells of the plane their bounds overlap, so
+/// that what lies in an area is found without going through everything
+#[derive(Clone)]
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    cells: BTreeMap<Cell, Vec<K>>,
+    // First and last cell each key spans
+    spans: BTreeMap<K, (Cell, Cell)>,
+}
+
+impl<K> Default for SpatialGrid<K> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CELL_SIZE)
+    }
+}
+
+impl<K> SpatialGrid<K> {
+    pub const DEFAULT_CELL_SIZE: f32 = 10.;
+
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            cells: BTreeMap::new(),
+            spans: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.spans.clear();
+    }
+
+    // Float to int casts saturate, so unbounded areas span the whole grid
+    fn cell_of(&self, (x, y): (f32, f32)) -> Cell {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+impl<K: Copy + Ord> SpatialGrid<K> {
+    /// Places a key over an area, moving it if it was already in the grid
+    pub fn insert(&mut self, key: K, min: (f32, f32), max: (f32, f32)) {
+        let span = (self.cell_of(min), self.cell_of(max));
+        if self.spans.get(&key) == Some(&span) {
+            return;
+        }
+        self.remove(key);
+        let ((x0, y0), (x1, y1)) = span;
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push(key);
+            }
+        }
+        self.spans.insert(key, span);
+    }
+
+    pub fn insert_point(&mut self, key: K, pos: (f32, f32)) {
+        self.insert(key, pos, pos);
+    }
+
+    pub fn remove(&mut self, key: K) {
+        let Some(((x0, y0), (x1, y1))) = self.spans.remove(&key) else {
+            return;
+        };
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(keys) = self.cells.get_mut(&(x, y)) {
+                    keys.retain(|&other| other != key);
+                    if keys.is_empty() {
+                        self.cells.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The keys in the cells an area overlaps, each once and in order. Some
+    /// may lie just outside the area, within the cells at its edges.
+    pub fn query(&self, min: (f32, f32), max: (f32, f32)) -> Vec<K> {
+        let ((x0, y0), (x1, y1)) = (self.cell_of(min), self.cell_of(max));
+        if x1 < x0 || y1 < y0 {
+            return vec![];
+        }
+        let area = (x1 as i64 - x0 as i64 + 1).saturating_mul(y1 as i64 - y0 as i64 + 1);
+        let mut out: Vec<K> = if area > self.cells.len() as i64 {
+            // Larger areas are cheaper to check cell by occupied cell
+            self.cells
+                .iter()
+                .filter(|&(&(x, y), _)| x >= x0 && x <= x1 && y >= y0 && y <= y1)
+                .flat_map(|(_, keys)| keys.iter().copied())
+                .collect()
+        } else {
+            (x0..=x1)
+                .flat_map(|x| (y0..=y1).map(move |y| (x, y)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flat_map(|keys| keys.iter().copied())
+                .collect()
+        };
+        out.sort();
+        out.dedup();
+        out
+    }
+}
@@ -1,3 +1,43 @@
+use game::WorldGenParams;
+
+// Any of --seed, --size or --density switches to a generated map, and
+// --history sets the years run ahead of play
+fn parse_args() -> (Option<WorldGenParams>, u64) {
+    let mut params = WorldGenParams::default();
+    let mut generated = false;
+    let mut history = 0;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        if arg == "--history" {
+            match value.and_then(|v| v.parse().ok()) {
+                Some(years) => history = years,
+                None => eprintln!("Invalid value for {arg}"),
+            }
+            continue;
+        }
+        let parsed = match arg.as_str() {
+            "--seed" => value.and_then(|v| v.parse().ok()).map(|v| params.seed = v),
+            "--size" => value.and_then(|v| v.parse().ok()).map(|v| params.size = v),
+            "--density" => value
+                .and_then(|v| v.parse().ok())
+                .map(|v| params.density = v),
+            _ => {
+                eprintln!("Unknown argument {arg}");
+                continue;
+            }
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid value for {arg}");
+        }
+        generated = true;
+    }
+
+    (generated.then_some(params), history)
+}
+
 fn main() {
-    game::start();
+    let (world, history) = parse_args();
+    game::start_with(world, history);
 }